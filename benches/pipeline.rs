@@ -0,0 +1,30 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use eliza::bench;
+use eliza::script::Script;
+
+fn pipeline(c: &mut Criterion) {
+    let script = Script::from_file("scripts/doctor.json").unwrap();
+
+    c.bench_function("compile_rule", |b| {
+        b.iter(|| bench::compile_rule(black_box("(.*)my (.*)(@family)(.*)"), &script))
+    });
+    c.bench_function("reflect", |b| {
+        b.iter(|| {
+            bench::reflect(
+                black_box("i am sure you were with my mother and me"),
+                &script,
+            )
+        })
+    });
+    c.bench_function("populate_keystack", |b| {
+        b.iter(|| {
+            bench::populate_keystack(
+                black_box("well, i remember my mother always said i can't dream of computers"),
+                &script,
+            )
+        })
+    });
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
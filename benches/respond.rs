@@ -0,0 +1,27 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use eliza::Eliza;
+
+const SHORT: &str = "Hello.";
+const LONG: &str = "Well, my boyfriend made me come here, and he says that I am depressed much of \
+                    the time, but I think that the real problem is that nobody at work listens to \
+                    anything that I have to say about the way things are done.";
+const KEYWORD_DENSE: &str = "I remember my mother always said that everybody was like me, \
+                             because I can't dream of computers and I am sorry, perhaps.";
+
+fn respond(c: &mut Criterion) {
+    let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+    e.seed(1);
+
+    let mut group = c.benchmark_group("respond");
+    for (name, input) in [
+        ("short", SHORT),
+        ("long", LONG),
+        ("keyword_dense", KEYWORD_DENSE),
+    ] {
+        group.bench_function(name, |b| b.iter(|| e.respond(black_box(input))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, respond);
+criterion_main!(benches);
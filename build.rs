@@ -0,0 +1,19 @@
+//Generates the C header for the 'ffi' module, so that it never drifts from the Rust definitions
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out = std::env::var("OUT_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=ELIZA_UPDATE_HEADER");
+
+        let bindings = cbindgen::generate(&dir).expect("Failed to generate C bindings");
+        bindings.write_to_file(format!("{}/eliza.h", out));
+
+        //The copy in the source tree is only refreshed on request, so that builds never modify it
+        if std::env::var_os("ELIZA_UPDATE_HEADER").is_some() {
+            bindings.write_to_file(format!("{}/include/eliza.h", dir));
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Hosts ELIZA as a Discord bot.
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> DISCORD_TOKEN=... cargo run --example discord --features discord -- scripts/doctor.json
+//! ```
+//!
+//! The bot needs the 'Message Content' privileged intent enabled in the Discord developer portal.
+//!
+use eliza::integrations::discord::DiscordBot;
+use eliza::pool::ElizaPool;
+use eliza::script::Script;
+use serenity::prelude::*;
+use std::env;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let token = env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
+    let location = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "scripts/doctor.json".to_string());
+    let script = Script::from_file(&location).expect("Failed to load script");
+
+    let pool = ElizaPool::new(script).idle_timeout(Duration::from_secs(30 * 60));
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT;
+
+    let mut client = Client::builder(&token, intents)
+        .event_handler(DiscordBot::new(pool))
+        .await
+        .expect("Failed to create client");
+
+    if let Err(e) = client.start().await {
+        eprintln!("Client error: {}", e);
+    }
+}
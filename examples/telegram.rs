@@ -0,0 +1,37 @@
+//! Hosts ELIZA as a Telegram bot.
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> TELOXIDE_TOKEN=... cargo run --example telegram --features telegram -- scripts/doctor.json
+//! ```
+//!
+//! Updates are received by long polling, unless `WEBHOOK_ADDRESS` and `WEBHOOK_URL` are set.
+//!
+use eliza::integrations::telegram::{Mode, TelegramBot};
+use eliza::pool::ElizaPool;
+use eliza::script::Script;
+use std::env;
+use std::time::Duration;
+use teloxide::Bot;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let location = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "scripts/doctor.json".to_string());
+    let script = Script::from_file(&location).expect("Failed to load script");
+
+    let mode = match (env::var("WEBHOOK_ADDRESS"), env::var("WEBHOOK_URL")) {
+        (Ok(address), Ok(url)) => Mode::Webhook {
+            address: address.parse().expect("Invalid WEBHOOK_ADDRESS"),
+            url,
+        },
+        _ => Mode::LongPolling,
+    };
+
+    let pool = ElizaPool::new(script).idle_timeout(Duration::from_secs(30 * 60));
+    if let Err(e) = TelegramBot::new(pool).run(Bot::from_env(), mode).await {
+        eprintln!("Bot error: {}", e);
+    }
+}
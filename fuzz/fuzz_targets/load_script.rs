@@ -0,0 +1,22 @@
+//! Feeds arbitrary bytes to the script loader, which should reject a bad script rather than
+//! panic. Scripts that do load are put to work, to exercise their rules (and especially the
+//! capture ids in their reassembly rules).
+#![no_main]
+use eliza::Eliza;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let script = match std::str::from_utf8(data) {
+        Ok(script) => script,
+        Err(_) => return,
+    };
+
+    if let Ok(mut e) = Eliza::from_str(script) {
+        e.seed(0);
+        e.greet();
+        for input in ["Hello", "I remember my mother", "you are not very nice, but why?"] {
+            e.respond(input);
+        }
+        e.farewell();
+    }
+});
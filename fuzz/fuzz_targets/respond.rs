@@ -0,0 +1,18 @@
+//! Feeds arbitrary text to the DOCTOR, which should never panic however odd the input.
+#![no_main]
+use eliza::script::Script;
+use eliza::Eliza;
+use libfuzzer_sys::fuzz_target;
+use std::sync::{Arc, OnceLock};
+
+static DOCTOR: OnceLock<Arc<Script>> = OnceLock::new();
+
+fuzz_target!(|input: &str| {
+    let script = DOCTOR.get_or_init(|| {
+        Arc::new(Script::from_slice(include_bytes!("../../scripts/doctor.json")).unwrap())
+    });
+
+    let mut e = Eliza::from_shared(Arc::clone(script));
+    e.seed(0);
+    e.respond(input);
+});
@@ -0,0 +1,75 @@
+#ifndef ELIZA_H
+#define ELIZA_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An ELIZA instance.
+//
+// This struct is created by the `new()` method. See its documentation for more.
+//
+// `Eliza` is `Send` and `Sync`, so may be shared between threads (e.g. behind a `Mutex` in a web
+// server). Cloning an instance forks the conversation: the clone carries on from the same memory,
+// rule usage, affect and random state, but from then on the two conversations are independent.
+// The scripts themselves are shared between clones rather than copied, so a clone costs only as
+// much as the conversation so far.
+typedef struct Eliza Eliza;
+
+// Initialise ELIZA with the script at the specified location.
+//
+// Returns `NULL` if the script is invalid, or the location is not valid UTF-8. The handle must
+// be released with `eliza_free()`.
+//
+// # Safety
+//
+// `script_path` must be a valid, nul-terminated string.
+struct Eliza *eliza_new(const char *script_path);
+
+// Returns a greeting from the script.
+//
+// Returns `NULL` if the handle is `NULL`.
+//
+// # Safety
+//
+// `handle` must have been returned by `eliza_new()`, and not yet freed.
+char *eliza_greet(const struct Eliza *handle);
+
+// Responds to the user's input.
+//
+// Input that is not valid UTF-8 is converted lossily. Returns `NULL` if either argument is
+// `NULL`.
+//
+// # Safety
+//
+// `handle` must have been returned by `eliza_new()`, and not yet freed. `input` must be a valid,
+// nul-terminated string.
+char *eliza_respond(struct Eliza *handle, const char *input);
+
+// Returns a farewell from the script.
+//
+// Returns `NULL` if the handle is `NULL`.
+//
+// # Safety
+//
+// `handle` must have been returned by `eliza_new()`, and not yet freed.
+char *eliza_farewell(const struct Eliza *handle);
+
+// Releases an ELIZA handle. Passing `NULL` does nothing.
+//
+// # Safety
+//
+// `handle` must have been returned by `eliza_new()`, and not already freed.
+void eliza_free(struct Eliza *handle);
+
+// Releases a string returned by ELIZA. Passing `NULL` does nothing.
+//
+// # Safety
+//
+// `s` must have been returned by one of the functions in this module, and not already freed.
+void eliza_string_free(char *s);
+
+#endif  /* ELIZA_H */
@@ -0,0 +1,173 @@
+//! The `eliza_script!` macro, which embeds an ELIZA script in a binary and checks it at compile
+//! time.
+//!
+//! This crate is re-exported by `eliza` when its `macros` feature is enabled, and is not meant to
+//! be used on its own:
+//!
+//! ```rust,ignore
+//! let script: &'static eliza::script::Script = eliza::eliza_script!("scripts/doctor.json");
+//! ```
+//!
+//! The path is relative to the `Cargo.toml` of the crate using the macro. A script that can't be
+//! read, isn't `json`, or has any of the following mistakes fails to compile:
+//! * A decomposition rule that is not a valid regex.
+//! * A `GOTO` naming a keyword that does not exist.
+//! * A keyword that is defined more than once.
+//!
+//! The script is parsed the first time the expression is evaluated, and shared from then on.
+//!
+use proc_macro::TokenStream;
+use serde_json::Value;
+use std::path::Path;
+
+//The runtime's own reading of GOTOs and decomposition rules, so that the two cannot disagree
+#[path = "../../src/syntax.rs"]
+mod syntax;
+
+#[proc_macro]
+pub fn eliza_script(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(tokens) => tokens,
+        Err(e) => compile_error(&e),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    //Format example: '"scripts/doctor.json"', a plain string literal without escapes
+    let literal = input.to_string();
+    let location = literal
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .filter(|l| !l.contains(['"', '\\']))
+        .ok_or("Expected the path to a script, e.g. eliza_script!(\"scripts/doctor.json\")")?;
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    let path = Path::new(&root).join(location);
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let problems = problems(&json)?;
+    if !problems.is_empty() {
+        return Err(format!("{} is invalid: {}", location, problems.join("; ")));
+    }
+
+    //Including the script, rather than its contents, has the compiler rebuild when it changes
+    format!(
+        r#"{{
+            static SCRIPT: ::std::sync::OnceLock<::eliza::script::Script> =
+                ::std::sync::OnceLock::new();
+            SCRIPT.get_or_init(|| {{
+                ::eliza::script::Script::from_str(include_str!({:?}))
+                    .expect("The embedded script is invalid")
+            }})
+        }}"#,
+        path.display().to_string()
+    )
+    .parse()
+    .map_err(|e| format!("{:?}", e))
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("compile_error!({:?})", message).parse().unwrap()
+}
+
+//Describes each mistake in the script that can be found without the `eliza` crate, or returns
+//`Err` if the script isn't a json object
+fn problems(json: &str) -> Result<Vec<String>, String> {
+    let script: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let keywords = match script.get("keywords") {
+        Some(Value::Array(keywords)) => keywords.as_slice(),
+        Some(_) => return Err("keywords must be an array".to_string()),
+        None => return Err("missing field `keywords`".to_string()),
+    };
+
+    let keys: Vec<&str> = keywords
+        .iter()
+        .map(|k| k.get("key").and_then(Value::as_str).unwrap_or_default())
+        .collect();
+
+    //As at runtime, the NONE keyword is kept apart from any keyword for the word 'none'
+    let folded = |key: &str| match key {
+        "NONE" => key.to_string(),
+        _ => key.to_lowercase(),
+    };
+    let mut problems = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        if keys[..i].iter().any(|k| folded(k) == folded(key)) {
+            problems.push(format!(
+                "Keyword '{}' (keywords[{}]) is defined more than once",
+                key, i
+            ));
+        }
+    }
+
+    for (i, k) in keywords.iter().enumerate() {
+        let rules = k.get("rules").and_then(Value::as_array);
+        for (j, r) in rules.into_iter().flatten().enumerate() {
+            let decomposition = r
+                .get("decomposition_rule")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            if let Err(e) = syntax::decomposition_regex(decomposition) {
+                problems.push(format!(
+                    "Invalid decomposition rule for key '{}' (keywords[{}].rules[{}]): {}",
+                    keys[i], i, j, e
+                ));
+            }
+
+            let reassembly = r.get("reassembly_rules").and_then(Value::as_array);
+            let reassembly = reassembly.into_iter().flatten().filter_map(Value::as_str);
+            for goto in reassembly.filter_map(syntax::is_goto) {
+                if syntax::goto_index(keys.iter().copied(), &goto).is_none() {
+                    problems.push(format!(
+                        "GOTO for key '{}' (keywords[{}].rules[{}]) names a keyword that doesn't \
+                         exist: '{}'",
+                        keys[i], i, j, goto
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_problems() {
+        let doctor = std::fs::read_to_string("../scripts/doctor.json").unwrap();
+        assert_eq!(Ok(Vec::new()), problems(&doctor));
+
+        let problems = problems(
+            r#"{"keywords": [
+                {"key": "hello", "rank": 0, "rules": [
+                    {"decomposition_rule": "(.*", "reassembly_rules": ["Hi."]},
+                    {"decomposition_rule": "@{hi|hey}(.*)", "reassembly_rules": ["GOTO bye"]}
+                ]},
+                {"key": "Hello", "rank": 0, "rules": []}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(3, problems.len(), "{:?}", problems);
+        assert_eq!(
+            "Keyword 'Hello' (keywords[1]) is defined more than once",
+            problems[0]
+        );
+        assert!(problems[1].contains("(keywords[0].rules[0])"));
+        assert!(problems[2].ends_with("names a keyword that doesn't exist: 'bye'"));
+
+        let none = r#"{"keywords": [{"key": "NONE", "rank": 0, "rules": []},
+            {"key": "none", "rank": 0, "rules": []}]}"#;
+        assert_eq!(Ok(Vec::new()), super::problems(none));
+
+        //As at runtime, a GOTO names a keyword exactly as it is spelt
+        let goto = r#"{"keywords": [{"key": "Bye", "rank": 0, "rules": [
+            {"decomposition_rule": "(.*)", "reassembly_rules": ["GOTO bye", "GOTO  Bye"]}]}]}"#;
+        assert_eq!(1, super::problems(goto).unwrap().len());
+
+        assert!(super::problems("{").is_err());
+        assert!(super::problems("{}").is_err());
+    }
+}
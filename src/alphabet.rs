@@ -1,129 +1,99 @@
-//! Contains helpful constants and functions.
+//! Character classes, which decide the characters that names within a script are made of.
+//!
+//! ELIZA picks names out of a script's rules - the synonym in `(.*)my (.* @family)` and the
+//! capture id in `Tell me more about your $2.` - by reading the characters that follow the `@` or
+//! `$`, for as long as they belong to a `CharClass`. Capture ids are always Unicode
+//! alphanumeric, while the characters of synonym names may be chosen by the script (see
+//! `Script::word_chars`), so that names such as `@well-being` or `@glücklich` are possible.
+//!
+//! ```rust
+//! use eliza::alphabet::CharClass;
+//!
+//! let class = CharClass::Any(vec![CharClass::Letters, CharClass::Chars("-".to_string())]);
+//! assert_eq!("well-being", class.take("well-being)(.*)"));
+//! assert_eq!("glücklich", class.scrub("(@glücklich)"));
+//! ```
 //!
-const ALPHABET_LOWER: [char; 26] = [
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
-    't', 'u', 'v', 'w', 'x', 'y', 'z',
-];
-
-const ALPHABET_UPPER: [char; 26] = [
-    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
-    'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
-];
-
-const NUMERIC: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
-
-pub const STANDARD: Standard = Standard;
-pub const ALPHANUMERIC: Alphanumeric = Alphanumeric;
-
-pub trait Alphabet {
-    /// Attempts to find the position of the character in the alphabet.
-    ///
-    fn find_position(&self, c: char) -> Option<usize>;
-
-    /// Returns a letter from within the alphabet at a specific index.
-    ///
-    /// Will return None if the index is out of bounds
-    fn get_letter(&self, index: usize, is_uppercase: bool) -> Option<char>;
-
-    /// Performs a modulo on an index so that its value references a position within the alphabet.
-    /// This function handles negative wrap around modulo as rust does not natievly support it.
-    ///
-    fn modulo(&self, i: isize) -> usize {
-        (((i % self.length() as isize) + self.length() as isize) % self.length() as isize) as usize
-    }
-
-    /// Will check if the text contains valid alphabetic symbols only.
-    ///
-    fn is_valid(&self, text: &str) -> bool;
-
-    /// Will scrub non-alphabetic characters from the text and return the scrubed version.
-    ///
-    fn scrub(&self, text: &str) -> String {
-        text.chars()
-            .filter(|&c| self.find_position(c).is_some())
-            .collect()
-    }
 
-    /// Returns the length of the alphabet
-    ///
-    fn length(&self) -> usize;
+/// A set of characters, defined by Unicode categories or by listing the characters themselves.
+///
+/// In a script, a `CharClass` is written as one of `"letters"`, `"digits"`, `"alphanumeric"`,
+/// `{"chars" : "..."}` or `{"any" : [...]}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    /// Unicode letters, in any script.
+    Letters,
+    /// Unicode numeric characters.
+    Digits,
+    /// Unicode letters and numeric characters.
+    Alphanumeric,
+    /// Exactly the given characters.
+    Chars(String),
+    /// Characters belonging to any of the given classes.
+    Any(Vec<CharClass>),
 }
 
-pub struct Standard;
-impl Alphabet for Standard {
-    fn find_position(&self, c: char) -> Option<usize> {
-        ALPHABET_LOWER
-            .iter()
-            .position(|&a| a == c)
-            .or(ALPHABET_UPPER.iter().position(|&a| a == c))
+impl CharClass {
+    /// The characters that synonym names are made of, unless a script says otherwise: letters,
+    /// digits, hyphens, apostrophes and underscores.
+    pub fn word() -> CharClass {
+        CharClass::Any(vec![
+            CharClass::Alphanumeric,
+            CharClass::Chars(String::from("-'_")),
+        ])
     }
 
-    fn get_letter(&self, index: usize, is_uppercase: bool) -> Option<char> {
-        if index > self.length() {
-            return None;
-        }
-
-        match is_uppercase {
-            true => Some(ALPHABET_UPPER[index]),
-            false => Some(ALPHABET_LOWER[index]),
+    /// Returns true if the character belongs to the class.
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharClass::Letters => c.is_alphabetic(),
+            CharClass::Digits => c.is_numeric(),
+            CharClass::Alphanumeric => c.is_alphanumeric(),
+            CharClass::Chars(chars) => chars.contains(c),
+            CharClass::Any(classes) => classes.iter().any(|class| class.contains(c)),
         }
     }
 
-    fn is_valid(&self, text: &str) -> bool {
-        for c in text.chars() {
-            if self.find_position(c).is_none() {
-                return false;
-            }
-        }
-
-        true
+    /// Returns true if every character of the text belongs to the class.
+    pub fn is_valid(&self, text: &str) -> bool {
+        text.chars().all(|c| self.contains(c))
     }
 
-    fn length(&self) -> usize {
-        26
+    /// Removes the characters that don't belong to the class from the text.
+    pub fn scrub(&self, text: &str) -> String {
+        text.chars().filter(|&c| self.contains(c)).collect()
     }
-}
-
-pub struct Alphanumeric;
-impl Alphabet for Alphanumeric {
-    fn find_position(&self, c: char) -> Option<usize> {
-        if let Some(pos) = STANDARD.find_position(c) {
-            return Some(pos);
-        }
 
-        if let Some(pos) = NUMERIC.iter().position(|&n| n == c) {
-            return Some(pos + 26);
+    /// Returns the start of the text, up to the first character that doesn't belong to the class.
+    pub fn take<'a>(&self, text: &'a str) -> &'a str {
+        match text.char_indices().find(|&(_, c)| !self.contains(c)) {
+            Some((end, _)) => &text[..end],
+            None => text,
         }
-
-        None
     }
+}
 
-    fn get_letter(&self, index: usize, is_uppercase: bool) -> Option<char> {
-        if index > self.length() {
-            return None;
-        }
-
-        if index > 25 {
-            return Some(NUMERIC[index - 26]);
-        }
-
-        match is_uppercase {
-            true => Some(ALPHABET_UPPER[index]),
-            false => Some(ALPHABET_LOWER[index]),
-        }
-    }
-
-    fn is_valid(&self, text: &str) -> bool {
-        for c in text.chars() {
-            if self.find_position(c).is_none() {
-                return false;
-            }
-        }
-
-        true
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_class() {
+        let word = CharClass::word();
+        assert_eq!("well-being", word.take("well-being)"));
+        assert_eq!("don't", word.take("don't worry"));
+        assert_eq!("sueño", word.take("sueño)(.*)"));
+        assert!(word.is_valid("family_2"));
+        assert!(!word.is_valid("(.*)"));
     }
 
-    fn length(&self) -> usize {
-        36
+    #[test]
+    fn script_classes() {
+        let class: CharClass =
+            serde_json::from_str(r#"{"any": ["letters", {"chars": "."}]}"#).unwrap();
+        assert_eq!("e.g", class.take("e.g, this"));
+        assert_eq!("ab", CharClass::Letters.scrub("a1b2"));
+        assert_eq!("12", CharClass::Digits.scrub("a1b2"));
     }
 }
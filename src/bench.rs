@@ -0,0 +1,38 @@
+//! Entry points to the stages of ELIZA's matching pipeline, so that the benchmarks in `benches/`
+//! can measure each stage on its own.
+//!
+//! Only available with the `bench` feature. This module is not part of the public API, and may
+//! change at any time.
+//!
+use crate::script::Script;
+use regex::Regex;
+
+/// Compiles a decomposition rule, given the script's synonyms.
+pub fn compile_rule(decomposition: &str, script: &Script) -> Option<Regex> {
+    crate::Decomposition::compile(
+        decomposition,
+        &script.synonyms,
+        &script.word_chars(),
+        script.normalizer(),
+    )
+    .map(|d| d.regex)
+}
+
+/// Reflects the input, as is done to a capture before it is used in a response.
+pub fn reflect(input: &str, script: &Script) -> String {
+    crate::reflect(input, &script.reflections, script.normalizer())
+}
+
+/// Splits the input into phrases, and finds the first phrase with keywords along with the number
+/// of keywords it has.
+pub fn populate_keystack(input: &str, script: &Script) -> (Option<String>, usize) {
+    let phrases = script.splitter().split(input, &script.delimiters());
+    let known = script.fuzzy_keywords.then(|| crate::known_words(script));
+    let (phrase, keystack) = crate::populate_keystack(
+        phrases,
+        &script.keywords,
+        script.normalizer(),
+        known.as_ref(),
+    );
+    (phrase, keystack.len())
+}
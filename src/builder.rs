@@ -0,0 +1,442 @@
+//! Configures an ELIZA instance before the conversation begins.
+//!
+//! `Eliza::from_file()` and friends remain the simplest way to start a conversation. When more
+//! than a script is needed, an `ElizaBuilder` gathers the options in one place:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::script::Script;
+//! use eliza::{Eliza, Strategy};
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let mut e = Eliza::builder()
+//!     .script(script)
+//!     .seed(42)
+//!     .memory_capacity(10)
+//!     .selection(Strategy::Random)
+//!     .build()
+//!     .unwrap();
+//!
+//! println!("{}", e.respond("Men are all alike."));
+//! # }
+//! ```
+//!
+//! Scripts themselves can be built in code with a `ScriptBuilder`, which suits bots that are
+//! generated by another program, and tests:
+//!
+//! ```rust
+//! use eliza::builder::ScriptBuilder;
+//! use eliza::Eliza;
+//!
+//! let script = ScriptBuilder::new()
+//!     .greeting("Hi")
+//!     .fallback("Go on.")
+//!     .keyword("mother", 2, |k| {
+//!         k.rule("(.*)my mother(.*)", ["Tell me more about your mother."])
+//!     })
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut e = Eliza::builder().script(script).build().unwrap();
+//! assert_eq!("Tell me more about your mother.", e.respond("I like my mother"));
+//! ```
+//!
+use crate::clock::Clock;
+use crate::event::EventLog;
+use crate::sanitize::Sanitization;
+use crate::script::{FallbackOrder, Keyword, Reflection, Rule, Script, Synonym, Transform};
+use crate::{ContentFilter, Eliza, GreetingOrder, Limits, Recall, Strategy};
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+
+/// A builder for `Eliza`, created by `Eliza::builder()`.
+///
+/// Only a script is required. Everything else defaults to the behaviour of `Eliza::from_file()`.
+#[derive(Default)]
+pub struct ElizaBuilder {
+    scripts: Vec<Arc<Script>>,
+    seed: Option<u64>,
+    memory_capacity: Option<usize>,
+    history_capacity: Option<usize>,
+    recall: Recall,
+    selection: Strategy,
+    greeting_order: GreetingOrder,
+    clock: Option<Arc<dyn Clock>>,
+    first_choice: bool,
+    forgetful: bool,
+    limits: Limits,
+    content_filter: Option<ContentFilter>,
+    sanitization: Sanitization,
+    event_log: Option<EventLog>,
+}
+
+impl ElizaBuilder {
+    /// Adds a script to the chain of scripts. The first script added is the primary script, and
+    /// any others are fallback scripts (see `Eliza::from_files()`).
+    pub fn script(mut self, mut script: Script) -> ElizaBuilder {
+        script.expand_synonyms();
+        script.resolve_gotos();
+        script.compile_rules();
+        self.scripts.push(Arc::new(script));
+        self
+    }
+
+    /// Adds a script that is shared with other instances to the chain of scripts.
+    pub fn shared_script(mut self, script: Arc<Script>) -> ElizaBuilder {
+        self.scripts.push(script);
+        self
+    }
+
+    /// Seeds ELIZA's random choices (see `Eliza::seed()`).
+    pub fn seed(mut self, seed: u64) -> ElizaBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Limits the number of responses ELIZA may hold in memory, forgetting the oldest response
+    /// when a new one would exceed the limit. By default memory is unlimited.
+    pub fn memory_capacity(mut self, capacity: usize) -> ElizaBuilder {
+        self.memory_capacity = Some(capacity);
+        self
+    }
+
+    /// Sets how many of the most recent exchanges ELIZA keeps in her history (see
+    /// `Eliza::history()`). By default she keeps ten.
+    pub fn history_capacity(mut self, capacity: usize) -> ElizaBuilder {
+        self.history_capacity = Some(capacity);
+        self
+    }
+
+    /// Limits the length of anything ELIZA echoes back from the user's input, in characters.
+    /// Longer captures are shortened with an ellipsis, between words where possible, so that a
+    /// pasted paragraph isn't repeated in full. By default captures are not limited.
+    pub fn max_capture_len(mut self, max: usize) -> ElizaBuilder {
+        self.limits.capture = Some(max);
+        self
+    }
+
+    /// Limits the length of ELIZA's responses from keyword rules, in characters, shortening
+    /// longer responses with an ellipsis. By default responses are not limited.
+    pub fn max_response_len(mut self, max: usize) -> ElizaBuilder {
+        self.limits.response = Some(max);
+        self
+    }
+
+    /// Limits how many of the input's phrases ELIZA searches for keywords, ignoring any after the
+    /// first `max`, so that input with hundreds of commas can't make her work through hundreds of
+    /// phrases. Defaults to 100.
+    pub fn max_phrases(mut self, max: usize) -> ElizaBuilder {
+        self.limits.phrases = max;
+        self
+    }
+
+    /// Limits how many keywords ELIZA keeps on the keystack, dropping the lowest ranked beyond
+    /// the first `max`. Defaults to 50.
+    pub fn max_keystack(mut self, max: usize) -> ElizaBuilder {
+        self.limits.keystack = max;
+        self
+    }
+
+    /// Sets when ELIZA may bring up a response she saved in memory (see `Recall`). By default she
+    /// does so whenever she finds no keyword.
+    pub fn recall(mut self, recall: Recall) -> ElizaBuilder {
+        self.recall = recall;
+        self
+    }
+
+    /// Sets how ELIZA chooses between the reassembly rules of a matching decomposition rule.
+    pub fn selection(mut self, selection: Strategy) -> ElizaBuilder {
+        self.selection = selection;
+        self
+    }
+
+    /// Sets how ELIZA chooses between the greetings of her script, and between its farewells.
+    pub fn greeting_order(mut self, order: GreetingOrder) -> ElizaBuilder {
+        self.greeting_order = order;
+        self
+    }
+
+    /// Sets the clock that ELIZA reads the time of day from (see `Eliza::clock()`).
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> ElizaBuilder {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Enables or disables ELIZA's memory (see `Eliza::memorise()`).
+    pub fn memorise(mut self, enabled: bool) -> ElizaBuilder {
+        self.forgetful = !enabled;
+        self
+    }
+
+    /// Makes ELIZA always choose the first of her options (see `Eliza::first_choice()`).
+    pub fn first_choice(mut self, enabled: bool) -> ElizaBuilder {
+        self.first_choice = enabled;
+        self
+    }
+
+    /// Sets how ELIZA treats control characters and terminal escape sequences in the user's
+    /// input, which she would otherwise echo back (see `sanitize::Sanitization`). By default
+    /// they are stripped.
+    pub fn sanitization(mut self, sanitization: Sanitization) -> ElizaBuilder {
+        self.sanitization = sanitization;
+        self
+    }
+
+    /// Refuses any input for which the filter returns true (see `Eliza::content_filter()`).
+    pub fn content_filter<F>(mut self, filter: F) -> ElizaBuilder
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.content_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Writes the decisions ELIZA makes each turn to the sink (see `Eliza::event_log()`).
+    pub fn event_log<W: Write + Send + 'static>(mut self, sink: W) -> ElizaBuilder {
+        self.event_log = Some(EventLog::new(sink));
+        self
+    }
+
+    /// Creates the ELIZA instance.
+    ///
+    /// Will return `Err` if no script was given, or any of the scripts are invalid.
+    pub fn build(self) -> Result<Eliza, Box<dyn Error>> {
+        let mut scripts = self.scripts.into_iter();
+        let script = scripts.next().ok_or("At least one script is required")?;
+        script.check()?;
+
+        let mut e = Eliza::from_shared(script);
+        for script in scripts {
+            script.check()?;
+            e.fallback_scripts.push(script);
+        }
+
+        if let Some(seed) = self.seed {
+            e.seed(seed);
+        }
+        e.memory_capacity = self.memory_capacity;
+        if let Some(capacity) = self.history_capacity {
+            e.history_capacity = capacity;
+        }
+        e.recall = self.recall;
+        e.selection = self.selection;
+        e.greeting_order = self.greeting_order;
+        e.clock = self.clock;
+        e.first_choice = self.first_choice;
+        e.forgetful = self.forgetful;
+        e.limits = self.limits;
+        e.content_filter = self.content_filter;
+        e.sanitization = self.sanitization;
+        e.event_log = self.event_log;
+
+        Ok(e)
+    }
+}
+
+/// A builder for `Script`, for constructing scripts in code rather than loading them.
+///
+/// Everything is optional, so a builder that is given nothing builds an empty script.
+#[derive(Default)]
+pub struct ScriptBuilder {
+    script: Script,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> ScriptBuilder {
+        ScriptBuilder::default()
+    }
+
+    /// Adds a greeting, one of which ELIZA says to begin a conversation.
+    pub fn greeting(mut self, text: &str) -> ScriptBuilder {
+        self.script.greetings.push(text.to_string());
+        self
+    }
+
+    /// Adds a farewell, one of which ELIZA says to end a conversation.
+    pub fn farewell(mut self, text: &str) -> ScriptBuilder {
+        self.script.farewells.push(text.to_string());
+        self
+    }
+
+    /// Adds a fallback, for when ELIZA can't find any other response.
+    pub fn fallback(mut self, text: &str) -> ScriptBuilder {
+        self.script.fallbacks.push(text.to_string());
+        self
+    }
+
+    /// Sets how ELIZA chooses between the fallbacks (see `FallbackOrder`).
+    pub fn fallback_order(mut self, order: FallbackOrder) -> ScriptBuilder {
+        self.script.fallback_order = Some(order);
+        self
+    }
+
+    /// Adds a banned word or phrase, which ELIZA refuses to engage with.
+    pub fn banned(mut self, term: &str) -> ScriptBuilder {
+        self.script.banned.push(term.to_string());
+        self
+    }
+
+    /// Adds a response for input that contains a banned term.
+    pub fn banned_response(mut self, text: &str) -> ScriptBuilder {
+        self.script.banned_responses.push(text.to_string());
+        self
+    }
+
+    /// Adds a nudge, for prompting a user who has gone quiet (see `Eliza::nudge()`).
+    pub fn nudge(mut self, text: &str) -> ScriptBuilder {
+        self.script.nudges.push(text.to_string());
+        self
+    }
+
+    /// Adds a transform, which replaces any of the equivalents in the user's input with the word
+    /// before ELIZA looks for keywords.
+    pub fn transform<'a, I>(mut self, word: &str, equivalents: I) -> ScriptBuilder
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.script.transforms.push(Transform {
+            word: word.to_string(),
+            equivalents: equivalents.into_iter().map(String::from).collect(),
+            ..Transform::default()
+        });
+        self
+    }
+
+    /// Adds a synonym, which decomposition rules may refer to as `@word`.
+    pub fn synonym<'a, I>(mut self, word: &str, equivalents: I) -> ScriptBuilder
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.script.synonyms.push(Synonym {
+            word: word.to_string(),
+            equivalents: equivalents.into_iter().map(String::from).collect(),
+        });
+        self
+    }
+
+    /// Adds a reflection, which swaps the word for its inverse in anything ELIZA echoes back -
+    /// and the inverse for the word, if `twoway` is set.
+    pub fn reflection(mut self, word: &str, inverse: &str, twoway: bool) -> ScriptBuilder {
+        self.script.reflections.push(Reflection {
+            word: word.to_string(),
+            inverse: inverse.to_string(),
+            twoway,
+        });
+        self
+    }
+
+    /// Adds a keyword of the given rank, whose rules are added by the closure.
+    pub fn keyword<F>(mut self, key: &str, rank: i32, rules: F) -> ScriptBuilder
+    where
+        F: FnOnce(KeywordBuilder) -> KeywordBuilder,
+    {
+        let builder = KeywordBuilder {
+            keyword: Keyword {
+                key: key.to_string(),
+                rank,
+                tags: Vec::new(),
+                rules: Vec::new(),
+            },
+        };
+        self.script.keywords.push(rules(builder).keyword);
+        self
+    }
+
+    /// Creates the script.
+    ///
+    /// Will return `Err` if the script has any of the mistakes that `Script::validate()` checks
+    /// for, such as an invalid regex or a `GOTO` to a keyword that doesn't exist.
+    pub fn build(self) -> Result<Script, Box<dyn Error>> {
+        let mut script = self.script;
+        script.validate()?;
+        script.expand_synonyms();
+        script.resolve_gotos();
+        script.compile_rules();
+        Ok(script)
+    }
+}
+
+/// Adds the rules of a keyword, for `ScriptBuilder::keyword()`.
+pub struct KeywordBuilder {
+    keyword: Keyword,
+}
+
+impl KeywordBuilder {
+    /// Tags the keyword, so that decomposition rules naming the tag (e.g. `#family`) match it.
+    pub fn tag(mut self, tag: &str) -> KeywordBuilder {
+        self.keyword.tags.push(tag.to_string());
+        self
+    }
+
+    /// Adds a decomposition rule, along with the reassembly rules ELIZA may respond with when it
+    /// matches.
+    pub fn rule<'a, I>(self, decomposition: &str, reassembly: I) -> KeywordBuilder
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.push(false, decomposition, reassembly)
+    }
+
+    /// Adds a decomposition rule whose responses ELIZA saves for later, rather than saying them
+    /// straight away (see `Eliza::memorise()`).
+    pub fn memorised_rule<'a, I>(self, decomposition: &str, reassembly: I) -> KeywordBuilder
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.push(true, decomposition, reassembly)
+    }
+
+    fn push<'a, I>(mut self, memorise: bool, decomposition: &str, reassembly: I) -> KeywordBuilder
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.keyword.rules.push(Rule {
+            memorise,
+            decomposition_rule: decomposition.to_string(),
+            reassembly_rules: reassembly.into_iter().map(String::from).collect(),
+            ..Rule::default()
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_script() {
+        let script = ScriptBuilder::new()
+            .greeting("Hello")
+            .farewell("Bye")
+            .fallback("Go on.")
+            .transform("mom", ["mum", "mommy"])
+            .synonym("family", ["mom", "dad"])
+            .reflection("my", "your", true)
+            .keyword("mom", 2, |k| {
+                k.memorised_rule("(.*)my mom(.*)", ["Earlier you mentioned your mom."])
+                    .rule("(.*)my (.*@family)(.*)", ["Tell me about your $2."])
+            })
+            .keyword("dad", 1, |k| k.rule("(.*)", ["GOTO mom"]))
+            .build()
+            .unwrap();
+        assert_eq!(2, script.keywords[0].rules.len());
+        assert!(script.keywords[0].rules[0].memorise);
+
+        let mut e = Eliza::from_scripts(vec![script]).unwrap();
+        e.first_choice(true);
+        assert_eq!("Hello", e.greet());
+        assert_eq!("Tell me about your mom.", e.respond("I miss my mum"));
+        assert_eq!(
+            "Tell me about your dad.",
+            e.respond("dad says my dad is fine")
+        );
+
+        let err = ScriptBuilder::new()
+            .keyword("dad", 1, |k| k.rule("(.*)", ["GOTO mom"]))
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("'mom'"), "{}", err);
+    }
+}
@@ -0,0 +1,164 @@
+//! Tells ELIZA the time of day, for greetings and farewells that mention it.
+//!
+//! A greeting or farewell may include `{part_of_day}`, which becomes `morning`, `afternoon`,
+//! `evening` or `night`, and `{weekday}`, which becomes the name of the day, such as `Monday`:
+//!
+//! ```json,no_run
+//! "greetings" : ["Good {part_of_day}, what brings you here?"],
+//! "farewells" : ["Enjoy the rest of your {weekday}."]
+//! ```
+//!
+//! ELIZA reads the time from a `Clock`. By default this is the system clock in UTC, and hosts may
+//! give their own offset from UTC, or a clock of their own - one backed by a time zone database,
+//! say, or a fixed time for tests:
+//!
+//! ```rust
+//! use eliza::builder::ScriptBuilder;
+//! use eliza::clock::{Clock, LocalTime, Weekday};
+//! use eliza::Eliza;
+//!
+//! struct Friday;
+//!
+//! impl Clock for Friday {
+//!     fn now(&self) -> LocalTime {
+//!         LocalTime { hour: 19, weekday: Weekday::Friday }
+//!     }
+//! }
+//!
+//! let script = ScriptBuilder::new()
+//!     .greeting("Good {part_of_day}. How was your {weekday}?")
+//!     .build()
+//!     .unwrap();
+//! let e = Eliza::builder().script(script).clock(Friday).build().unwrap();
+//! assert_eq!("Good evening. How was your Friday?", e.greet());
+//! ```
+//!
+use std::fmt;
+
+/// A day of the week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+//The days of the week, in order from the day of the Unix epoch
+const FROM_EPOCH: [Weekday; 7] = [
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+];
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The time of day where the user is, as far as ELIZA needs to know it.
+///
+/// * **hour**: The hour of the day, from 0 to 23.
+/// * **weekday**: The day of the week.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub hour: u8,
+    pub weekday: Weekday,
+}
+
+impl LocalTime {
+    /// Returns the time of day a number of seconds after the Unix epoch, which is in UTC unless
+    /// the seconds have been offset to a time zone. Hosts without a system clock may use this to
+    /// build their own `Clock`.
+    pub fn from_unix(secs: i64) -> LocalTime {
+        let days = secs.div_euclid(86_400);
+        LocalTime {
+            hour: (secs.rem_euclid(86_400) / 3600) as u8,
+            weekday: FROM_EPOCH[days.rem_euclid(7) as usize],
+        }
+    }
+
+    /// Returns the part of the day: `morning` from 5am, `afternoon` from noon, `evening` from 6pm
+    /// and `night` from 10pm.
+    pub fn part_of_day(&self) -> &'static str {
+        match self.hour {
+            5..=11 => "morning",
+            12..=17 => "afternoon",
+            18..=21 => "evening",
+            _ => "night",
+        }
+    }
+
+    //Swaps each placeholder in the text for the time it names
+    pub(crate) fn fill(&self, text: &str) -> String {
+        text.replace("{part_of_day}", self.part_of_day())
+            .replace("{weekday}", &self.weekday.to_string())
+    }
+}
+
+/// A source of the time, which ELIZA consults whenever she greets or farewells the user.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> LocalTime;
+}
+
+/// The system clock, at a fixed offset from UTC. The default offset is zero, i.e. UTC itself.
+///
+/// Not a `Clock` when targeting `wasm32-unknown-unknown`, which has no system clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemClock {
+    pub utc_offset_minutes: i32,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Clock for SystemClock {
+    fn now(&self) -> LocalTime {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        LocalTime::from_unix(secs + i64::from(self.utc_offset_minutes) * 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_times() {
+        assert_eq!(
+            LocalTime {
+                hour: 0,
+                weekday: Weekday::Thursday
+            },
+            LocalTime::from_unix(0)
+        );
+        //2024-02-29 was a Thursday, and 1969-12-31 a Wednesday
+        assert_eq!(
+            LocalTime {
+                hour: 13,
+                weekday: Weekday::Thursday
+            },
+            LocalTime::from_unix(1_709_212_000)
+        );
+        assert_eq!(Weekday::Wednesday, LocalTime::from_unix(-1).weekday);
+        assert_eq!(23, LocalTime::from_unix(-1).hour);
+
+        let time = LocalTime {
+            hour: 7,
+            weekday: Weekday::Monday,
+        };
+        assert_eq!(
+            "Good morning, happy Monday!",
+            time.fill("Good {part_of_day}, happy {weekday}!")
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Compares two versions of a script, so that changes to a large script can be reviewed by what
+//! they mean rather than by eyeballing `json`.
+//!
+//! Keywords are matched by their key, rules by their decomposition rule and synonyms by their
+//! word, so reordering a script changes nothing. Other parts of the script, such as the greetings
+//! or reflections, are only reported as changed as a whole.
+//!
+//! ```rust
+//! use eliza::script::Script;
+//!
+//! let old = Script::from_str(r#"{"greetings": ["Hi"], "farewells": [], "fallbacks": [],
+//!     "transforms": [], "synonyms": [], "reflections": [], "keywords": []}"#).unwrap();
+//! let mut new = old.clone();
+//! new.greetings.push("Hello".to_string());
+//!
+//! let diff = old.diff(&new);
+//! assert_eq!(vec!["greetings"], diff.changed_sections);
+//! println!("{}", diff);
+//! ```
+//!
+use crate::script::{Keyword, Rule, Script, Synonym};
+use serde_json::Value;
+use std::fmt;
+
+/// The differences between two scripts, from the first to the second.
+///
+/// * **added_keywords**, **removed_keywords**: The keys of keywords only in one of the scripts.
+/// * **changed_keywords**: Keywords in both scripts, but with a different rank or rules.
+/// * **added_synonyms**, **removed_synonyms**: The words of synonyms only in one of the scripts.
+/// * **changed_synonyms**: Synonyms in both scripts, but with different equivalents.
+/// * **changed_sections**: The names of any other parts of the script that differ, such as
+///   `greetings` or `delimiters`.
+///
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScriptDiff {
+    pub added_keywords: Vec<String>,
+    pub removed_keywords: Vec<String>,
+    pub changed_keywords: Vec<KeywordDiff>,
+    pub added_synonyms: Vec<String>,
+    pub removed_synonyms: Vec<String>,
+    pub changed_synonyms: Vec<SynonymDiff>,
+    pub changed_sections: Vec<String>,
+}
+
+/// The differences between two versions of a keyword.
+///
+/// * **rank**: The old and new rank, if the rank changed.
+/// * **added_tags**, **removed_tags**: The tags only in one version.
+/// * **added_rules**, **removed_rules**: The decomposition rules only in one version.
+/// * **changed_rules**: Decomposition rules in both versions, whose reassembly rules or other
+///   settings differ.
+///
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeywordDiff {
+    pub key: String,
+    pub rank: Option<(i32, i32)>,
+    pub added_tags: Vec<String>,
+    pub removed_tags: Vec<String>,
+    pub added_rules: Vec<String>,
+    pub removed_rules: Vec<String>,
+    pub changed_rules: Vec<String>,
+}
+
+/// The differences between two versions of a synonym.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SynonymDiff {
+    pub word: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Script {
+    /// Compares this script with another, reporting what would change were this script replaced
+    /// by the other.
+    pub fn diff(&self, other: &Script) -> ScriptDiff {
+        let mut diff = ScriptDiff::default();
+
+        for old in &self.keywords {
+            match other.keywords.iter().find(|k| k.key == old.key) {
+                Some(new) => diff.changed_keywords.extend(keyword_diff(old, new)),
+                None => diff.removed_keywords.push(old.key.clone()),
+            }
+        }
+        diff.added_keywords = added(&other.keywords, &self.keywords, |k| &k.key);
+
+        for old in &self.synonyms {
+            match other.synonyms.iter().find(|s| s.word == old.word) {
+                Some(new) => diff.changed_synonyms.extend(synonym_diff(old, new)),
+                None => diff.removed_synonyms.push(old.word.clone()),
+            }
+        }
+        diff.added_synonyms = added(&other.synonyms, &self.synonyms, |s| &s.word);
+
+        //Every other part of the script is compared as a whole
+        if let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        {
+            let mut sections: Vec<&String> = old.keys().chain(new.keys()).collect();
+            sections.sort();
+            sections.dedup();
+            diff.changed_sections = sections
+                .into_iter()
+                .filter(|s| *s != "keywords" && *s != "synonyms")
+                .filter(|s| old.get(*s) != new.get(*s))
+                .cloned()
+                .collect();
+        }
+
+        diff
+    }
+}
+
+impl ScriptDiff {
+    /// Returns true if the scripts are equivalent.
+    pub fn is_empty(&self) -> bool {
+        *self == ScriptDiff::default()
+    }
+}
+
+impl fmt::Display for ScriptDiff {
+    /// Formats the differences one per line, marking each as added (`+`), removed (`-`) or
+    /// changed (`~`).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in &self.added_keywords {
+            writeln!(f, "+ keyword '{}'", key)?;
+        }
+        for key in &self.removed_keywords {
+            writeln!(f, "- keyword '{}'", key)?;
+        }
+        for k in &self.changed_keywords {
+            match k.rank {
+                Some((old, new)) => writeln!(f, "~ keyword '{}' (rank {} -> {})", k.key, old, new)?,
+                None => writeln!(f, "~ keyword '{}'", k.key)?,
+            }
+            for tag in &k.added_tags {
+                writeln!(f, "    + tag '#{}'", tag)?;
+            }
+            for tag in &k.removed_tags {
+                writeln!(f, "    - tag '#{}'", tag)?;
+            }
+            for rule in &k.added_rules {
+                writeln!(f, "    + rule '{}'", rule)?;
+            }
+            for rule in &k.removed_rules {
+                writeln!(f, "    - rule '{}'", rule)?;
+            }
+            for rule in &k.changed_rules {
+                writeln!(f, "    ~ rule '{}'", rule)?;
+            }
+        }
+
+        for word in &self.added_synonyms {
+            writeln!(f, "+ synonym '{}'", word)?;
+        }
+        for word in &self.removed_synonyms {
+            writeln!(f, "- synonym '{}'", word)?;
+        }
+        for s in &self.changed_synonyms {
+            writeln!(f, "~ synonym '{}'", s.word)?;
+            for e in &s.added {
+                writeln!(f, "    + '{}'", e)?;
+            }
+            for e in &s.removed {
+                writeln!(f, "    - '{}'", e)?;
+            }
+        }
+
+        for section in &self.changed_sections {
+            writeln!(f, "~ {}", section)?;
+        }
+
+        Ok(())
+    }
+}
+
+//Returns the names of the items in `new` that aren't in `old`
+fn added<T>(new: &[T], old: &[T], name: fn(&T) -> &String) -> Vec<String> {
+    new.iter()
+        .filter(|n| !old.iter().any(|o| name(o) == name(n)))
+        .map(|n| name(n).clone())
+        .collect()
+}
+
+fn keyword_diff(old: &Keyword, new: &Keyword) -> Option<KeywordDiff> {
+    let mut diff = KeywordDiff {
+        key: old.key.clone(),
+        rank: Some((old.rank, new.rank)).filter(|(o, n)| o != n),
+        added_tags: new
+            .tags
+            .iter()
+            .filter(|t| !old.tags.contains(t))
+            .cloned()
+            .collect(),
+        removed_tags: old
+            .tags
+            .iter()
+            .filter(|t| !new.tags.contains(t))
+            .cloned()
+            .collect(),
+        ..KeywordDiff::default()
+    };
+
+    //A keyword may have several rules with the same decomposition rule (e.g. one to memorise),
+    //which are matched in the order they appear
+    for (i, rule) in old.rules.iter().enumerate() {
+        match nth_rule(&new.rules, rule, occurrence(&old.rules, i)) {
+            Some(r) if r != rule => diff.changed_rules.push(rule.decomposition_rule.clone()),
+            Some(_) => {}
+            None => diff.removed_rules.push(rule.decomposition_rule.clone()),
+        }
+    }
+    for (i, rule) in new.rules.iter().enumerate() {
+        if nth_rule(&old.rules, rule, occurrence(&new.rules, i)).is_none() {
+            diff.added_rules.push(rule.decomposition_rule.clone());
+        }
+    }
+
+    let unchanged = diff.rank.is_none()
+        && diff.added_tags.is_empty()
+        && diff.removed_tags.is_empty()
+        && diff.added_rules.is_empty()
+        && diff.removed_rules.is_empty()
+        && diff.changed_rules.is_empty();
+    match unchanged {
+        true => None,
+        false => Some(diff),
+    }
+}
+
+//Returns how many rules before the i-th have the same decomposition rule
+fn occurrence(rules: &[Rule], i: usize) -> usize {
+    rules[..i]
+        .iter()
+        .filter(|r| r.decomposition_rule == rules[i].decomposition_rule)
+        .count()
+}
+
+//Returns the n-th rule with the same decomposition rule as the given rule, counting from zero
+fn nth_rule<'a>(rules: &'a [Rule], rule: &Rule, n: usize) -> Option<&'a Rule> {
+    rules
+        .iter()
+        .filter(|r| r.decomposition_rule == rule.decomposition_rule)
+        .nth(n)
+}
+
+fn synonym_diff(old: &Synonym, new: &Synonym) -> Option<SynonymDiff> {
+    let diff = SynonymDiff {
+        word: old.word.clone(),
+        added: new
+            .equivalents
+            .iter()
+            .filter(|e| !old.equivalents.contains(e))
+            .cloned()
+            .collect(),
+        removed: old
+            .equivalents
+            .iter()
+            .filter(|e| !new.equivalents.contains(e))
+            .cloned()
+            .collect(),
+    };
+
+    match diff.added.is_empty() && diff.removed.is_empty() {
+        true => None,
+        false => Some(diff),
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyword_changes() {
+        let old = Script::from_file("scripts/doctor.json").unwrap();
+        let mut new = old.clone();
+        new.keywords.reverse();
+        assert!(old.diff(&new).is_empty(), "{}", old.diff(&new));
+
+        new.keywords.retain(|k| k.key != "sorry");
+        new.keywords.push(Keyword {
+            key: "cat".to_string(),
+            ..Keyword::default()
+        });
+        let my = new.keywords.iter_mut().find(|k| k.key == "my").unwrap();
+        my.rank += 1;
+        my.tags.push("possessive".to_string());
+        my.rules[0].reassembly_rules.push("Your $2?".to_string());
+        my.rules.pop();
+        new.synonyms[1].equivalents.pop();
+        new.synonyms[1].equivalents.push("trust".to_string());
+        new.fallbacks.clear();
+
+        let diff = old.diff(&new);
+        assert_eq!(vec!["cat"], diff.added_keywords);
+        assert_eq!(vec!["sorry"], diff.removed_keywords);
+        assert_eq!(1, diff.changed_keywords.len());
+        let my = &diff.changed_keywords[0];
+        assert_eq!(Some((2, 3)), my.rank);
+        assert_eq!(1, my.changed_rules.len());
+        assert_eq!(1, my.removed_rules.len());
+        assert!(my.added_rules.is_empty());
+        assert_eq!(vec!["possessive"], my.added_tags);
+        assert_eq!(vec!["trust"], diff.changed_synonyms[0].added);
+        assert_eq!(vec!["wish"], diff.changed_synonyms[0].removed);
+        assert_eq!(vec!["fallbacks"], diff.changed_sections);
+
+        assert!(diff.to_string().contains("~ keyword 'my' (rank 2 -> 3)\n"));
+        assert!(diff.to_string().contains("    + tag '#possessive'\n"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Pits two ELIZA instances against each other, in the spirit of the 1972 encounter between
+//! PARRY and the DOCTOR.
+//!
+//! Left to talk amongst themselves, two scripts quickly reveal rules that misfire or loop, which
+//! makes a duel a handy stress test for a script.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::duel::duel;
+//! use eliza::Eliza;
+//!
+//! let mut doctor = Eliza::from_file("scripts/doctor.json").unwrap();
+//! let mut pirate = Eliza::from_file("scripts/pirate.json").unwrap();
+//! doctor.seed(1);
+//! pirate.seed(2);
+//!
+//! for line in duel(&mut doctor, &mut pirate, 10) {
+//!     println!("{}: {}", line.speaker, line.text);
+//! }
+//! # }
+//! ```
+//!
+use crate::{Eliza, ResponseSource};
+
+/// A single line of a duel's transcript.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Line {
+    /// Who spoke the line, where `0` opened the duel and `1` replied.
+    pub speaker: usize,
+    /// What was said.
+    pub text: String,
+    /// Where the response came from, or None for the opening greeting.
+    pub source: Option<ResponseSource>,
+}
+
+/// Has `first` greet `second`, then lets them respond to one another for the given number of
+/// turns.
+///
+/// The transcript includes the greeting, so holds one more line than there are turns.
+pub fn duel(first: &mut Eliza, second: &mut Eliza, turns: usize) -> Vec<Line> {
+    let mut transcript = vec![Line {
+        speaker: 0,
+        text: first.greet(),
+        source: None,
+    }];
+
+    for turn in 0..turns {
+        let speaker = (turn + 1) % 2;
+        let eliza = match speaker {
+            0 => &mut *first,
+            _ => &mut *second,
+        };
+
+        let res = eliza.respond_detailed(&transcript[turn].text);
+        transcript.push(Line {
+            speaker,
+            text: res.text,
+            source: Some(res.source),
+        });
+    }
+
+    transcript
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+
+    fn seeded(location: &str, seed: u64) -> Eliza {
+        let mut e = Eliza::from_file(location).unwrap();
+        e.seed(seed);
+        e
+    }
+
+    #[test]
+    fn turns_alternate() {
+        let mut doctor = seeded("scripts/doctor.json", 1);
+        let mut pirate = seeded("scripts/pirate.json", 2);
+        let transcript = duel(&mut doctor, &mut pirate, 5);
+
+        assert_eq!(6, transcript.len());
+        assert_eq!(None, transcript[0].source);
+        let speakers: Vec<usize> = transcript.iter().map(|l| l.speaker).collect();
+        assert_eq!(vec![0, 1, 0, 1, 0, 1], speakers);
+    }
+
+    #[test]
+    fn seeded_duels_repeat() {
+        let run = || {
+            let mut first = seeded("scripts/doctor.json", 7);
+            let mut second = seeded("scripts/doctor.json", 8);
+            duel(&mut first, &mut second, 20)
+        };
+
+        assert_eq!(run(), run());
+    }
+}
@@ -0,0 +1,88 @@
+//! Errors that ELIZA may report.
+//!
+//! Functions that load scripts return `Box<dyn Error>`, as they may also fail for reasons such as
+//! I/O. Where the script itself is at fault, the boxed error is an `ElizaError`, which may be
+//! recovered with `downcast_ref()`:
+//!
+//! ```rust
+//! use eliza::error::ElizaError;
+//! use eliza::script::Script;
+//!
+//! let err = Script::from_str(r#"{"greetings": ["Hello", 1]}"#).unwrap_err();
+//! match err.downcast_ref::<ElizaError>() {
+//!     Some(ElizaError::Parse { path, .. }) => assert_eq!("greetings[1]", path),
+//!     _ => panic!("Expected a parse error"),
+//! }
+//! ```
+//!
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElizaError {
+    /// The script is not valid json, or does not have the structure of a script.
+    ///
+    /// `path` locates the offending element, such as `keywords[42].rules[3].reassembly_rules`,
+    /// and is empty if the json itself is malformed. `line`, `column` and `offset` (in bytes) are
+    /// the position at which the problem was found.
+    Parse {
+        path: String,
+        message: String,
+        line: usize,
+        column: usize,
+        offset: usize,
+    },
+    /// A rule of the script couldn't be used while responding (see `Eliza::try_respond()`).
+    Rule(RuleWarning),
+}
+
+/// A rule that ELIZA had to pass over while responding, because of a mistake in the script.
+///
+/// * **keyword**: The keyword the rule belongs to.
+/// * **rule**: The decomposition rule.
+/// * **message**: What was wrong, e.g. a reassembly rule referring to a capture that the
+///   decomposition rule doesn't have.
+///
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleWarning {
+    pub keyword: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rule '{}' of key '{}': {}",
+            self.rule, self.keyword, self.message
+        )
+    }
+}
+
+impl fmt::Display for ElizaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElizaError::Parse {
+                path,
+                message,
+                line,
+                column,
+                offset,
+            } => {
+                if !path.is_empty() {
+                    write!(f, "{}: ", path)?;
+                }
+
+                write!(
+                    f,
+                    "{} at line {} column {} (byte offset {})",
+                    message, line, column, offset
+                )
+            }
+            ElizaError::Rule(warning) => warning.fmt(f),
+        }
+    }
+}
+
+impl Error for ElizaError {}
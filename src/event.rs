@@ -0,0 +1,154 @@
+//! Records the decisions ELIZA makes each turn, for offline analysis of large conversation
+//! corpora.
+//!
+//! Once an event log is given to ELIZA, every response she gives is also written to it as a
+//! `TurnEvent`: one line of `json` per turn, which suits tools that read JSON Lines.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::Eliza;
+//! use std::fs::File;
+//!
+//! let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+//! e.event_log(File::create("turns.jsonl").unwrap());
+//! e.respond("I remember my mother.");
+//! # }
+//! ```
+//!
+//! Each line looks like so:
+//!
+//! ```json,no_run
+//! {"turn":1,"input":"I remember my mother.","transformed":"i remember my mother.",
+//!  "phrase":"i remember my mother","keystack":["remember","my"],"layer":0,
+//!  "text":"Do you often think of your mother?","source":"keyword","keyword":"remember",
+//!  "rule":"(.*)i remember(.+)","confidence":0.708}
+//! ```
+//!
+use crate::Response;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// The pipeline decisions of a single turn, along with ELIZA's response.
+///
+/// * **turn**: The number of the turn, counting from one.
+/// * **input**: The user's input, as given.
+/// * **transformed**: The input once case folded and transformed, or None if ELIZA asked a
+///   follow-up question without looking at the input.
+/// * **phrase**: The phrase ELIZA searched for a response, if any phrase had keywords.
+/// * **keystack**: The keywords found in the phrase, highest ranked first.
+/// * **layer**: The script in the chain that these were found with, where the primary script is
+///   `0`. When no script could respond, this is the last script tried.
+/// * The details of the response, as reported by `Eliza::respond_detailed()`.
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TurnEvent {
+    pub turn: usize,
+    pub input: String,
+    pub transformed: Option<String>,
+    pub phrase: Option<String>,
+    pub keystack: Vec<String>,
+    pub layer: usize,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+//The decisions made while searching for a response, before the response itself is known
+#[derive(Default)]
+pub(crate) struct Trace {
+    pub transformed: Option<String>,
+    pub phrase: Option<String>,
+    pub keystack: Vec<String>,
+    pub layer: usize,
+}
+
+impl Trace {
+    pub fn into_event(self, turn: usize, input: &str, response: Response) -> TurnEvent {
+        TurnEvent {
+            turn,
+            input: input.to_string(),
+            transformed: self.transformed,
+            phrase: self.phrase,
+            keystack: self.keystack,
+            layer: self.layer,
+            response,
+        }
+    }
+}
+
+//Where turn events are written. Clones of an ELIZA instance share the one sink.
+#[derive(Clone)]
+pub(crate) struct EventLog(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl EventLog {
+    pub fn new<W: Write + Send + 'static>(sink: W) -> EventLog {
+        EventLog(Arc::new(Mutex::new(Box::new(sink))))
+    }
+
+    //Writes the event as a line of json. Failing to log is no reason to stop the conversation,
+    //so errors are only reported.
+    pub fn write(&self, event: &TurnEvent) {
+        let mut sink = self.0.lock().unwrap();
+        let result = serde_json::to_writer(&mut *sink, event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| sink.write_all(b"\n"))
+            .and_then(|_| sink.flush());
+
+        if let Err(e) = result {
+            warn!("Failed to write turn event: {}", e);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use crate::Eliza;
+    use serde_json::Value;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    //A sink that can be read once ELIZA has written to it
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn turn_events() {
+        let buffer = Buffer::default();
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.first_choice(true);
+        e.event_log(buffer.clone());
+        e.respond("Well, my boyfriend made me come here.");
+        e.respond("Hmm");
+
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<Value> = log
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(2, events.len());
+
+        assert_eq!(1, events[0]["turn"]);
+        assert_eq!("Well, my boyfriend made me come here.", events[0]["input"]);
+        assert_eq!(
+            "well, my boyfriend made me come here.",
+            events[0]["transformed"]
+        );
+        assert_eq!("my boyfriend made me come here", events[0]["phrase"]);
+        assert_eq!(serde_json::json!(["my"]), events[0]["keystack"]);
+        assert_eq!("keyword", events[0]["source"]);
+        assert_eq!("Your boyfriend made you come here?", events[0]["text"]);
+
+        assert_eq!(2, events[1]["turn"]);
+        assert_eq!(Value::Null, events[1]["phrase"]);
+        assert_eq!("memory", events[1]["source"]);
+    }
+}
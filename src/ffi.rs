@@ -0,0 +1,184 @@
+//! A C interface, so that ELIZA can be embedded in C and C++ programs such as games.
+//!
+//! This module is only available when the crate is built with the `ffi` feature. The matching
+//! header is kept at `include/eliza.h`, and is generated into the build's `OUT_DIR` on every
+//! build. Building with `ELIZA_UPDATE_HEADER=1` set refreshes the copy in `include/` as well.
+//!
+//! ```c
+//! #include "eliza.h"
+//!
+//! Eliza *eliza = eliza_new("scripts/doctor.json");
+//! if (eliza != NULL) {
+//!     char *response = eliza_respond(eliza, "Men are all alike.");
+//!     printf("%s\n", response);
+//!
+//!     eliza_string_free(response);
+//!     eliza_free(eliza);
+//! }
+//! ```
+//!
+//! Strings returned by ELIZA are owned by the caller, and must be released with
+//! `eliza_string_free()`.
+//!
+use crate::Eliza;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+/// Initialise ELIZA with the script at the specified location.
+///
+/// Returns `NULL` if the script is invalid, or the location is not valid UTF-8. The handle must
+/// be released with `eliza_free()`.
+///
+/// # Safety
+///
+/// `script_path` must be a valid, nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_new(script_path: *const c_char) -> *mut Eliza {
+    if script_path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path = match CStr::from_ptr(script_path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    match Eliza::from_file(path) {
+        Ok(eliza) => Box::into_raw(Box::new(eliza)),
+        Err(e) => {
+            error!("Failed to load {}: {}", path, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns a greeting from the script.
+///
+/// Returns `NULL` if the handle is `NULL`.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `eliza_new()`, and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_greet(handle: *const Eliza) -> *mut c_char {
+    match handle.as_ref() {
+        Some(eliza) => into_c_string(eliza.greet()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Responds to the user's input.
+///
+/// Input that is not valid UTF-8 is converted lossily. Returns `NULL` if either argument is
+/// `NULL`.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `eliza_new()`, and not yet freed. `input` must be a valid,
+/// nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_respond(handle: *mut Eliza, input: *const c_char) -> *mut c_char {
+    if input.is_null() {
+        return ptr::null_mut();
+    }
+
+    match handle.as_mut() {
+        Some(eliza) => {
+            let input = CStr::from_ptr(input).to_string_lossy();
+            into_c_string(eliza.respond(&input))
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+/// Returns a farewell from the script.
+///
+/// Returns `NULL` if the handle is `NULL`.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `eliza_new()`, and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_farewell(handle: *const Eliza) -> *mut c_char {
+    match handle.as_ref() {
+        Some(eliza) => into_c_string(eliza.farewell()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Releases an ELIZA handle. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `eliza_new()`, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_free(handle: *mut Eliza) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Releases a string returned by ELIZA. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `s` must have been returned by one of the functions in this module, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn eliza_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+//Scripts may legitimately contain nul bytes, which C strings can't represent
+fn into_c_string(s: String) -> *mut c_char {
+    let s = s.replace('\0', "");
+    CString::new(s).unwrap().into_raw() //safe as nul bytes were removed above
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/eliza.h"));
+        let checked_in = include_str!("../include/eliza.h");
+        assert!(
+            generated == checked_in,
+            "include/eliza.h is out of date, rebuild with ELIZA_UPDATE_HEADER=1"
+        );
+    }
+
+    #[test]
+    fn conversation() {
+        let path = CString::new("scripts/doctor.json").unwrap();
+        let input = CString::new("Men are all alike.").unwrap();
+
+        unsafe {
+            let eliza = eliza_new(path.as_ptr());
+            assert!(!eliza.is_null());
+
+            let response = eliza_respond(eliza, input.as_ptr());
+            assert_eq!("In what way?", CStr::from_ptr(response).to_str().unwrap());
+
+            eliza_string_free(response);
+            eliza_free(eliza);
+        }
+    }
+
+    #[test]
+    fn null_arguments() {
+        let missing = CString::new("scripts/missing.json").unwrap();
+
+        unsafe {
+            assert!(eliza_new(ptr::null()).is_null());
+            assert!(eliza_new(missing.as_ptr()).is_null());
+            assert!(eliza_respond(ptr::null_mut(), ptr::null()).is_null());
+            assert!(eliza_greet(ptr::null()).is_null());
+            eliza_free(ptr::null_mut());
+            eliza_string_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! The `elizadata.js` of Norbert Landsteiner's elizabot.js, which declares a script as JavaScript
+//! variables:
+//!
+//! ```text,no_run
+//! var elizaInitials = ["How do you do.  Please tell me your problem."];
+//! var elizaFinals = ["Goodbye.  Thank you for talking to me."];
+//! var elizaQuits = ["bye", "goodbye"];
+//! var elizaPres = ["dont", "don't", "recollect", "remember"];
+//! var elizaPosts = ["am", "are", "your", "my"];
+//! var elizaSynons = { "family": ["mother", "mom", "father", "dad"] };
+//! var elizaKeywords = [
+//!   ["xnone", 0, [
+//!     ["*", ["I'm not sure I understand you fully.", "Please go on."]]
+//!   ]],
+//!   ["remember", 5, [
+//!     ["* i remember *", ["Do you often think of (2) ?", "goto what"]]
+//!   ]]
+//! ];
+//! var elizaPostTransforms = [];
+//! ```
+//!
+//! The pairs of `elizaPres` are read as transforms and those of `elizaPosts` as reflections, where
+//! two pairs swapping the same words are a reflection that works both ways. Keywords are read as
+//! Hayden's are (see the `hayden` module), with `xnone` becoming the `NONE` keyword.
+//!
+//! Only literals are read, written as in `json` but for comments, trailing commas and single
+//! quoted strings. ELIZA has no quit words, and no regex of her own to apply to a response, so
+//! `elizaQuits` and `elizaPostTransforms` are skipped with a warning. Scripts are written with an
+//! empty `elizaQuits` and `elizaPostTransforms`, as elizabot.js expects them.
+//!
+use super::Format;
+use crate::script::{Keyword, Rule, Script, Synonym, NONE_KEYWORD};
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::fmt::Write;
+
+//The keyword that elizabot uses when the input has no keywords
+const XNONE: &str = "xnone";
+
+/// Reads an `elizadata.js`.
+///
+/// Will return `Err` if `elizaKeywords` is missing, or if a variable isn't a literal of the shape
+/// that elizabot.js expects.
+pub fn parse(text: &str) -> Result<Script, Box<dyn Error>> {
+    let strings = |name: &str| -> Result<Vec<String>, Box<dyn Error>> {
+        match variable(text, name)? {
+            Some(value) => Ok(serde_json::from_value(value)
+                .map_err(|e| format!("{} must be a list of strings: {}", name, e))?),
+            None => Ok(Vec::new()),
+        }
+    };
+    let pairs = |name: &str| -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let words = strings(name)?;
+        if words.len() % 2 == 1 {
+            return Err(format!("{} must hold pairs of words", name).into());
+        }
+        Ok(words
+            .chunks(2)
+            .map(|p| (p[0].clone(), p[1].clone()))
+            .collect())
+    };
+
+    let mut script = Script {
+        greetings: strings("elizaInitials")?,
+        farewells: strings("elizaFinals")?,
+        reflections: super::reflections(pairs("elizaPosts")?),
+        ..Script::default()
+    };
+    for (from, to) in pairs("elizaPres")? {
+        super::substitute(&mut script.transforms, &from, &to);
+    }
+    for name in ["elizaQuits", "elizaPostTransforms"] {
+        //These needn't be literals, as they aren't read
+        match variable(text, name) {
+            Ok(None) => (),
+            Ok(Some(value)) if value == json!([]) => (),
+            _ => warn!("Skipping {}, which ELIZA has no use for", name),
+        }
+    }
+
+    if let Some(synonyms) = variable(text, "elizaSynons")? {
+        let synonyms: Map<String, Value> = serde_json::from_value(synonyms)
+            .map_err(|e| format!("elizaSynons must be an object: {}", e))?;
+        for (word, equivalents) in synonyms {
+            script.synonyms.push(Synonym {
+                equivalents: serde_json::from_value(equivalents)
+                    .map_err(|e| format!("Synonym '{}' must be a list of strings: {}", word, e))?,
+                word,
+            });
+        }
+    }
+
+    let keywords: Vec<(String, i32, Vec<Value>)> =
+        serde_json::from_value(variable(text, "elizaKeywords")?.ok_or("Missing elizaKeywords")?)
+            .map_err(|e| format!("Each of elizaKeywords must be [key, rank, rules]: {}", e))?;
+    for (key, rank, rules) in keywords {
+        let mut keyword = Keyword {
+            key: match key.as_str() {
+                XNONE => NONE_KEYWORD.to_string(),
+                _ => key,
+            },
+            rank,
+            ..Keyword::default()
+        };
+        for rule in rules {
+            let (pattern, reassembly): (String, Vec<String>) = match rule {
+                Value::Array(mut parts) if parts.len() >= 2 => {
+                    parts.truncate(2);
+                    serde_json::from_value(Value::Array(parts))?
+                }
+                _ => {
+                    return Err(format!(
+                        "Each rule of keyword '{}' must be [decomposition, reassembly]",
+                        keyword.key
+                    )
+                    .into())
+                }
+            };
+            let (memorise, decomposition) = super::hayden_decomposition(&pattern)?;
+            keyword.rules.push(Rule {
+                memorise,
+                decomposition_rule: decomposition,
+                reassembly_rules: reassembly
+                    .iter()
+                    .map(|r| super::hayden_reassembly(r))
+                    .collect(),
+                ..Rule::default()
+            });
+        }
+        script.keywords.push(keyword);
+    }
+
+    script.tidy();
+    Ok(script)
+}
+
+/// Writes an `elizadata.js`.
+///
+/// Will return `Err` if the script has parts that elizabot.js can't hold, or rules that can't be
+/// written as its patterns, such as a decomposition rule with a regex of its own.
+pub fn write(script: &Script) -> Result<String, Box<dyn Error>> {
+    let parts = [
+        "greetings",
+        "farewells",
+        "fallbacks",
+        "transforms",
+        "synonyms",
+        "reflections",
+        "keywords",
+    ];
+    super::check_parts(script, Format::Elizabot, &parts, false)?;
+
+    let mut pres = Vec::new();
+    for t in &script.transforms {
+        if t.priority != 0 {
+            return Err(format!(
+                "Transform '{}' can't be written as an elizabot substitution, as it has a priority",
+                t.word
+            )
+            .into());
+        }
+        for equivalent in &t.equivalents {
+            pres.extend([equivalent.as_str(), t.word.as_str()]);
+        }
+    }
+    let posts: Vec<&str> = super::reflection_pairs(script)
+        .into_iter()
+        .flat_map(|(word, inverse)| [word, inverse])
+        .collect();
+    let synonyms: Map<String, Value> = script
+        .synonyms
+        .iter()
+        .map(|s| (s.word.clone(), json!(s.equivalents)))
+        .collect();
+
+    let mut keywords = Vec::new();
+    for k in super::with_fallbacks(script, Format::Elizabot)? {
+        let mut rules = Vec::new();
+        for r in &k.rules {
+            let reassembly = r
+                .reassembly_rules
+                .iter()
+                .map(|assem| super::hayden_text(assem, &k.key))
+                .collect::<Result<Vec<_>, _>>()?;
+            rules.push(json!([super::hayden_pattern(r)?, reassembly]));
+        }
+        let key = match k.is_none_keyword() {
+            true => XNONE,
+            false => &k.key,
+        };
+        keywords.push(json!([key, k.rank, rules]));
+    }
+
+    let mut text = String::new();
+    let variables = [
+        ("elizaInitials", json!(script.greetings)),
+        ("elizaFinals", json!(script.farewells)),
+        ("elizaQuits", json!([])),
+        ("elizaPres", json!(pres)),
+        ("elizaPosts", json!(posts)),
+        ("elizaSynons", Value::Object(synonyms)),
+        ("elizaKeywords", json!(keywords)),
+        ("elizaPostTransforms", json!([])),
+    ];
+    for (name, value) in &variables {
+        writeln!(
+            text,
+            "var {} = {};\n",
+            name,
+            serde_json::to_string_pretty(value)?
+        )?;
+    }
+    text.pop();
+
+    Ok(text)
+}
+
+//Reads the literal assigned to a variable, if the script declares it
+fn variable(text: &str, name: &str) -> Result<Option<Value>, Box<dyn Error>> {
+    let mut search = 0;
+    while let Some(i) = text[search..].find(name).map(|i| search + i) {
+        search = i + name.len();
+        let before = text[..i].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+            continue;
+        }
+
+        let rest = text[search..].trim_start();
+        if let Some(value) = rest.strip_prefix('=').filter(|r| !r.starts_with('=')) {
+            let mut literal = Literal { text: value, at: 0 };
+            return match literal.value() {
+                Ok(value) => Ok(Some(value)),
+                Err(e) => Err(format!("{}: {}", name, e).into()),
+            };
+        }
+    }
+
+    Ok(None)
+}
+
+//Reads a JavaScript literal, as json but for comments, trailing commas and single quotes
+struct Literal<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl<'a> Literal<'a> {
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip();
+        match self.peek() {
+            Some('[') => {
+                self.at += 1;
+                let mut items = Vec::new();
+                while !self.close(']')? {
+                    items.push(self.value()?);
+                    self.comma(']')?;
+                }
+                Ok(Value::Array(items))
+            }
+            Some('{') => {
+                self.at += 1;
+                let mut fields = Map::new();
+                while !self.close('}')? {
+                    let key = match self.peek() {
+                        Some('"') | Some('\'') => self.string()?,
+                        _ => self.identifier()?,
+                    };
+                    self.skip();
+                    if self.peek() != Some(':') {
+                        return Err(self.mistake("Expected ':'"));
+                    }
+                    self.at += 1;
+                    fields.insert(key, self.value()?);
+                    self.comma('}')?;
+                }
+                Ok(Value::Object(fields))
+            }
+            Some('"') | Some('\'') => self.string().map(Value::String),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let number = self.take(|c| c == '-' || c == '.' || c.is_ascii_digit());
+                serde_json::from_str(number).map_err(|_| self.mistake("Invalid number"))
+            }
+            Some(_) => match self.identifier()?.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "null" => Ok(Value::Null),
+                _ => Err(self.mistake("Only literals can be read")),
+            },
+            None => Err(self.mistake("Unexpected end of script")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap_or('"');
+        self.at += 1;
+        let mut string = String::new();
+        let mut chars = self.text[self.at..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                _ if c == quote => {
+                    self.at += i + 1;
+                    return Ok(string);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        string.push(c.ok_or_else(|| self.mistake("Invalid escape"))?);
+                    }
+                    Some(c) => string.push(c),
+                    None => break,
+                },
+                _ => string.push(c),
+            }
+        }
+        Err(self.mistake("Unterminated string"))
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        match self.take(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+            "" => Err(self.mistake("Unexpected character")),
+            identifier => Ok(identifier.to_string()),
+        }
+    }
+
+    //Returns true, and moves past it, if the list or object is closed
+    fn close(&mut self, close: char) -> Result<bool, String> {
+        self.skip();
+        match self.peek() {
+            Some(c) if c == close => {
+                self.at += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(self.mistake("Unexpected end of script")),
+        }
+    }
+
+    fn comma(&mut self, close: char) -> Result<(), String> {
+        self.skip();
+        match self.peek() {
+            Some(',') => {
+                self.at += 1;
+                Ok(())
+            }
+            Some(c) if c == close => Ok(()),
+            _ => Err(self.mistake(&format!("Expected ',' or '{}'", close))),
+        }
+    }
+
+    //Skips whitespace and comments
+    fn skip(&mut self) {
+        loop {
+            let rest = self.text[self.at..].trim_start();
+            self.at = self.text.len() - rest.len();
+            if rest.starts_with("//") {
+                self.at += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                self.at += comment.find("*/").map_or(rest.len(), |end| end + 4);
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn take(&mut self, include: impl Fn(char) -> bool) -> &'a str {
+        let rest = &self.text[self.at..];
+        let end = rest.find(|c| !include(c)).unwrap_or(rest.len());
+        self.at += end;
+        &rest[..end]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.at..].chars().next()
+    }
+
+    fn mistake(&self, problem: &str) -> String {
+        let line = self.text[..self.at].lines().count().max(1);
+        format!("{} at line {} of the literal", problem, line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"// elizadata.js
+var elizaInitials = [
+"How do you do.  Please tell me your problem.",
+// a comment between items
+'Is something troubling you ?',
+];
+
+var elizaFinals = ["Goodbye.  Thank you for talking to me."];
+var elizaQuits = ["bye", "goodbye"];
+var elizaPres = ["dont", "don't", "recollect", "remember"];
+var elizaPosts = ["am", "are", "your", "my", "my", "your"];
+var elizaSynons = {
+"family": ["mother", "mom", "father", "dad"],
+belief: ["feel", "think"] /* an unquoted key */
+};
+
+var elizaKeywords = [
+["xnone", 0, [
+ ["*", [
+     "I'm not sure I understand you fully.",
+     "Please go on."
+  ]]
+]],
+["remember", 5, [
+ ["* i remember *", [
+     "Do you often think of (2) ?",
+     "goto what"
+  ]],
+ ["$ * my *", ["Lets discuss further why your (2)."]]
+]],
+["what", 0, [
+ ["*", ["Why do you ask ?"]]
+]]
+];
+
+var elizaPostTransforms = [
+	/ old old/g, " old",
+];
+"#;
+
+    #[test]
+    fn read() {
+        let script = parse(SCRIPT).unwrap();
+        assert_eq!("Is something troubling you ?", script.greetings[1]);
+        assert_eq!("don't", script.transforms[0].word);
+        assert_eq!(2, script.reflections.len());
+        assert!(script.reflections[1].twoway);
+        assert_eq!(2, script.synonyms.len());
+        assert_eq!(NONE_KEYWORD, script.keywords[0].key);
+
+        let remember = &script.keywords[1];
+        assert_eq!(5, remember.rank);
+        assert_eq!("(.*)i remember(.*)", remember.rules[0].decomposition_rule);
+        assert_eq!(
+            vec!("Do you often think of $2 ?", "GOTO what"),
+            remember.rules[0].reassembly_rules
+        );
+        assert!(remember.rules[1].memorise);
+        assert!(script.validate().is_ok());
+
+        assert!(parse("var elizaInitials = [];").is_err());
+        assert!(parse("var elizaKeywords = [[\"x\", 0, [[\"*\"]]]];").is_err());
+        assert!(parse("var elizaKeywords = [\"unterminated];").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let script = parse(SCRIPT).unwrap();
+        let written = write(&script).unwrap();
+        assert_eq!(
+            serde_json::to_value(&script).unwrap(),
+            serde_json::to_value(parse(&written).unwrap()).unwrap()
+        );
+        assert_eq!(written, write(&parse(&written).unwrap()).unwrap());
+    }
+}
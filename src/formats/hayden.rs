@@ -0,0 +1,279 @@
+//! Scripts for Charles Hayden's Java ELIZA, in which each line is tagged with what it holds:
+//!
+//! ```text,no_run
+//! initial: How do you do.  Please tell me your problem.
+//! final: Goodbye.  Thank you for talking to me.
+//! pre: dont don't
+//! post: am are
+//! synon: family mother father sister brother
+//! key: remember 5
+//!   decomp: * i remember *
+//!     reasmb: Do you often think of (2) ?
+//!     reasmb: goto what
+//!   decomp: $ * my *
+//!     reasmb: Lets discuss further why your (2).
+//! key: xnone
+//!   decomp: *
+//!     reasmb: Please go on.
+//! ```
+//!
+//! `initial` and `final` lines are the greetings and farewells. A `pre` substitution is read as a
+//! transform and a `post` substitution as a reflection, where two `post` lines swapping the same
+//! words are a reflection that works both ways. Each `*` and `@synonym` in a `decomp` is a capture
+//! numbered as Hayden numbers them, and a `$` before the pattern means the rule is memorised. The
+//! `xnone` keyword becomes the `NONE` keyword.
+//!
+//! ELIZA has no quit words, so `quit` lines are skipped with a warning. A script is written with
+//! its fallbacks as the rules of `xnone`, but can't hold other parts such as nudges or tests.
+//!
+use super::Format;
+use crate::script::{Keyword, Rule, Script, Synonym, NONE_KEYWORD};
+use std::error::Error;
+use std::fmt::Write;
+
+//The keyword that Hayden uses when the input has no keywords
+const XNONE: &str = "xnone";
+
+/// Reads a Hayden script.
+///
+/// Will return `Err`, giving the line, if a line isn't tagged with something that Hayden scripts
+/// hold or is out of place, such as a `reasmb` line before any `decomp`.
+pub fn parse(text: &str) -> Result<Script, Box<dyn Error>> {
+    let mut script = Script::default();
+    let mut posts = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mistake = |problem: &str| format!("Line {}: {}: '{}'", n + 1, problem, line);
+        let (tag, value) = line
+            .split_once(':')
+            .map(|(t, v)| (t.trim(), v.trim()))
+            .ok_or_else(|| mistake("Expected a tag, such as 'key:'"))?;
+        let mut words = value.split_whitespace();
+        match tag {
+            "initial" => script.greetings.push(value.to_string()),
+            "final" => script.farewells.push(value.to_string()),
+            "quit" => warn!("Skipping quit word '{}', as ELIZA has no quit words", value),
+            "pre" | "post" => {
+                let (from, to) = match (words.next(), words.collect::<Vec<_>>().join(" ")) {
+                    (Some(from), to) if !to.is_empty() => (from.to_string(), to),
+                    _ => return Err(mistake("Expected a word and its substitute").into()),
+                };
+                match tag {
+                    "pre" => super::substitute(&mut script.transforms, &from, &to),
+                    _ => posts.push((from, to)),
+                }
+            }
+            "synon" => {
+                let word = words.next().ok_or_else(|| mistake("Expected a synonym"))?;
+                script.synonyms.push(Synonym {
+                    word: word.to_string(),
+                    equivalents: words.map(str::to_string).collect(),
+                });
+            }
+            "key" => {
+                let key = match words.next() {
+                    Some(XNONE) => NONE_KEYWORD,
+                    Some(key) => key,
+                    None => return Err(mistake("Expected a keyword").into()),
+                };
+                let rank = match words.next() {
+                    Some(rank) => rank.parse().map_err(|_| mistake("Invalid rank"))?,
+                    None => 0,
+                };
+                script.keywords.push(Keyword {
+                    key: key.to_string(),
+                    rank,
+                    ..Keyword::default()
+                });
+            }
+            "decomp" => {
+                let keyword = script
+                    .keywords
+                    .last_mut()
+                    .ok_or_else(|| mistake("Expected a key before the decomp"))?;
+                let (memorise, decomposition) =
+                    super::hayden_decomposition(value).map_err(|e| mistake(&e.to_string()))?;
+                keyword.rules.push(Rule {
+                    memorise,
+                    decomposition_rule: decomposition,
+                    ..Rule::default()
+                });
+            }
+            "reasmb" => script
+                .keywords
+                .last_mut()
+                .and_then(|k| k.rules.last_mut())
+                .ok_or_else(|| mistake("Expected a decomp before the reasmb"))?
+                .reassembly_rules
+                .push(super::hayden_reassembly(value)),
+            _ => return Err(mistake("Unknown tag").into()),
+        }
+    }
+
+    script.reflections = super::reflections(posts);
+    script.tidy();
+    Ok(script)
+}
+
+/// Writes a Hayden script.
+///
+/// Will return `Err` if the script has parts that Hayden scripts can't hold, or rules that can't
+/// be written as Hayden patterns, such as a decomposition rule with a regex of its own.
+pub fn write(script: &Script) -> Result<String, Box<dyn Error>> {
+    let parts = [
+        "greetings",
+        "farewells",
+        "fallbacks",
+        "transforms",
+        "synonyms",
+        "reflections",
+        "keywords",
+    ];
+    super::check_parts(script, Format::Hayden, &parts, false)?;
+
+    let mut text = String::new();
+    for greeting in &script.greetings {
+        writeln!(text, "initial: {}", greeting)?;
+    }
+    for farewell in &script.farewells {
+        writeln!(text, "final: {}", farewell)?;
+    }
+    for t in &script.transforms {
+        for equivalent in &t.equivalents {
+            if t.priority != 0 || equivalent.contains(char::is_whitespace) {
+                return Err(format!(
+                    "Transform '{}' can't be written as Hayden substitutions, which replace a \
+                     single word in no particular order",
+                    t.word
+                )
+                .into());
+            }
+            writeln!(text, "pre: {} {}", equivalent, t.word)?;
+        }
+    }
+    for (word, inverse) in super::reflection_pairs(script) {
+        writeln!(text, "post: {} {}", word, inverse)?;
+    }
+    for s in &script.synonyms {
+        if s.equivalents
+            .iter()
+            .any(|e| e.contains(char::is_whitespace))
+        {
+            return Err(format!(
+                "Synonym '{}' can't be written as a Hayden synonym, which lists single words",
+                s.word
+            )
+            .into());
+        }
+        writeln!(text, "synon: {} {}", s.word, s.equivalents.join(" "))?;
+    }
+
+    for k in super::with_fallbacks(script, Format::Hayden)? {
+        let key = match k.is_none_keyword() {
+            true => XNONE,
+            false => &k.key,
+        };
+        match k.rank {
+            0 => writeln!(text, "key: {}", key)?,
+            rank => writeln!(text, "key: {} {}", key, rank)?,
+        }
+        for r in &k.rules {
+            writeln!(text, "  decomp: {}", super::hayden_pattern(r)?)?;
+            for assem in &r.reassembly_rules {
+                writeln!(text, "    reasmb: {}", super::hayden_text(assem, &k.key)?)?;
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "initial: How do you do.  Please tell me your problem.
+final: Goodbye.  Thank you for talking to me.
+quit: bye
+pre: dont don't
+pre: recollect remember
+post: am are
+post: you I
+post: I you
+synon: family mother mom father dad
+key: xnone
+  decomp: *
+    reasmb: I'm not sure I understand you fully.
+    reasmb: Please go on.
+key: remember 5
+  decomp: * i remember *
+    reasmb: Do you often think of (2) ?
+    reasmb: goto what
+key: my 2
+  decomp: $ * my *
+    reasmb: Lets discuss further why your (2).
+  decomp: * my* @family *
+    reasmb: Tell me more about your family.
+    reasmb: Who else in your family (4) ?
+key: what
+  decomp: *
+    reasmb: Why do you ask ?
+";
+
+    #[test]
+    fn read() {
+        let script = parse(SCRIPT).unwrap();
+        assert_eq!(
+            vec!("Goodbye.  Thank you for talking to me."),
+            script.farewells
+        );
+        assert_eq!("don't", script.transforms[0].word);
+        assert_eq!(vec!("dont"), script.transforms[0].equivalents);
+        assert_eq!(2, script.reflections.len());
+        assert!(script.reflections[1].twoway);
+        assert_eq!(NONE_KEYWORD, script.keywords[0].key);
+
+        let remember = &script.keywords[1];
+        assert_eq!(5, remember.rank);
+        assert_eq!("(.*)i remember(.*)", remember.rules[0].decomposition_rule);
+        assert_eq!(
+            vec!("Do you often think of $2 ?", "GOTO what"),
+            remember.rules[0].reassembly_rules
+        );
+
+        let my = &script.keywords[2];
+        assert!(my.rules[0].memorise);
+        assert_eq!("(.*)my(.*)(@family)(.*)", my.rules[1].decomposition_rule);
+        assert!(script.validate().is_ok());
+
+        assert!(parse("key: x\n    reasmb: Hello").is_err());
+        assert!(parse("key x").is_err());
+        assert!(parse("colour: blue").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let script = parse(SCRIPT).unwrap();
+        let written = write(&script).unwrap();
+        assert_eq!(
+            serde_json::to_value(&script).unwrap(),
+            serde_json::to_value(parse(&written).unwrap()).unwrap()
+        );
+        assert_eq!(written, write(&parse(&written).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn unwritable() {
+        let mut script = parse(SCRIPT).unwrap();
+        script.keywords[1].rules[0].decomposition_rule = "(.*)[0-9]+".to_string();
+        assert!(write(&script).is_err());
+
+        let mut script = parse(SCRIPT).unwrap();
+        script.fallbacks.push("Go on.".to_string());
+        assert!(write(&script).is_err());
+    }
+}
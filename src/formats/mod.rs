@@ -0,0 +1,691 @@
+//! Reads and writes scripts in formats other than ELIZA's own `json`, so that collections of
+//! scripts written for other ELIZAs can be migrated in one step, as the `convert` subcommand does.
+//!
+//! * `hayden`: the text scripts of Charles Hayden's Java ELIZA, with lines such as
+//!   `key: remember 5`, `decomp: * i remember *` and `reasmb: Do you often think of (2) ?`.
+//! * `elizabot`: the `elizadata.js` of Norbert Landsteiner's elizabot.js.
+//! * `slip`: Weizenbaum's 1966 scripts, written as lists in the manner of SLIP, such as
+//!   `(REMEMBER 5 ((0 YOU REMEMBER 0) (DO YOU OFTEN THINK OF 4)))`.
+//! * `yaml` and `toml`: ELIZA's own script, in another notation. Only available with the features
+//!   of the same name.
+//!
+//! ```rust
+//! use eliza::formats::Format;
+//!
+//! let hayden = "initial: How do you do.\nkey: sorry\n  decomp: *\n    reasmb: Please don't apologise.\n";
+//! let script = Format::Hayden.parse(hayden).unwrap();
+//! assert_eq!("(.*)", script.keywords[0].rules[0].decomposition_rule);
+//! assert_eq!(hayden, Format::Hayden.write(&script).unwrap());
+//! ```
+//!
+//! A script is read into ELIZA's rules as closely as they allow, and the loaders' usual leniency
+//! applies. Writing a script in a format that can't hold all of it returns `Err` naming what
+//! would be lost, rather than leaving it out. See each module for how its format is read.
+//!
+pub mod elizabot;
+pub mod hayden;
+pub mod slip;
+
+use crate::script::{Keyword, Reflection, Rule, Script, Transform, NONE_KEYWORD};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A format that scripts may be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Hayden,
+    Elizabot,
+    Slip,
+}
+
+impl Format {
+    /// Every format, in the order they are listed in usage messages.
+    pub const ALL: [Format; 6] = [
+        Format::Json,
+        Format::Yaml,
+        Format::Toml,
+        Format::Hayden,
+        Format::Elizabot,
+        Format::Slip,
+    ];
+
+    /// Guesses the format of a file from its extension: `json`, `yaml` or `yml`, `toml`, `script`
+    /// or `txt` for Hayden, `js` for elizabot and `slip`.
+    pub fn from_extension(path: &str) -> Option<Format> {
+        let extension = path.rsplit_once('.')?.1.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "script" | "txt" => Some(Format::Hayden),
+            "js" => Some(Format::Elizabot),
+            "slip" => Some(Format::Slip),
+            _ => None,
+        }
+    }
+
+    /// Reads a script written in this format.
+    ///
+    /// Will return `Err` if the script is invalid, or if the crate was built without the feature
+    /// for the format.
+    pub fn parse(self, text: &str) -> Result<Script, Box<dyn Error>> {
+        match self {
+            Format::Json => Script::from_str(text),
+            Format::Yaml => yaml::parse(text),
+            Format::Toml => toml::parse(text),
+            Format::Hayden => hayden::parse(text),
+            Format::Elizabot => elizabot::parse(text),
+            Format::Slip => slip::parse(text),
+        }
+    }
+
+    /// Writes a script in this format.
+    ///
+    /// Will return `Err` if the format can't hold some part of the script, or if the crate was
+    /// built without the feature for the format.
+    pub fn write(self, script: &Script) -> Result<String, Box<dyn Error>> {
+        match self {
+            Format::Json => Script::format(&serde_json::to_vec(script)?, false),
+            Format::Yaml => yaml::write(script),
+            Format::Toml => toml::write(script),
+            Format::Hayden => hayden::write(script),
+            Format::Elizabot => elizabot::write(script),
+            Format::Slip => slip::write(script),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Format, String> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|f| f.to_string() == name)
+            .ok_or_else(|| format!("Unsupported format '{}'", name))
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Hayden => "hayden",
+            Format::Elizabot => "elizabot",
+            Format::Slip => "slip",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(feature = "yaml")]
+mod yaml {
+    use crate::script::Script;
+    use std::error::Error;
+
+    pub(super) fn parse(text: &str) -> Result<Script, Box<dyn Error>> {
+        let mut script: Script = serde_yaml::from_str(text)?;
+        script.tidy();
+        Ok(script)
+    }
+
+    pub(super) fn write(script: &Script) -> Result<String, Box<dyn Error>> {
+        Ok(serde_yaml::to_string(script)?)
+    }
+}
+
+#[cfg(not(feature = "yaml"))]
+mod yaml {
+    use crate::script::Script;
+    use std::error::Error;
+
+    pub(super) fn parse(_text: &str) -> Result<Script, Box<dyn Error>> {
+        Err("eliza was built without the 'yaml' feature".into())
+    }
+
+    pub(super) fn write(_script: &Script) -> Result<String, Box<dyn Error>> {
+        Err("eliza was built without the 'yaml' feature".into())
+    }
+}
+
+#[cfg(feature = "toml")]
+mod toml {
+    use crate::script::Script;
+    use std::error::Error;
+
+    pub(super) fn parse(text: &str) -> Result<Script, Box<dyn Error>> {
+        let mut script: Script = ::toml::from_str(text)?;
+        script.tidy();
+        Ok(script)
+    }
+
+    pub(super) fn write(script: &Script) -> Result<String, Box<dyn Error>> {
+        Ok(::toml::to_string(script)?)
+    }
+}
+
+#[cfg(not(feature = "toml"))]
+mod toml {
+    use crate::script::Script;
+    use std::error::Error;
+
+    pub(super) fn parse(_text: &str) -> Result<Script, Box<dyn Error>> {
+        Err("eliza was built without the 'toml' feature".into())
+    }
+
+    pub(super) fn write(_script: &Script) -> Result<String, Box<dyn Error>> {
+        Err("eliza was built without the 'toml' feature".into())
+    }
+}
+
+//Refuses a script with parts that a format can't hold, such as nudges or a keyword's tags, so
+//that nothing is lost without saying so
+fn check_parts(
+    script: &Script,
+    format: Format,
+    parts: &[&str],
+    tags: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut unsupported: Vec<String> = match serde_json::to_value(script)? {
+        Value::Object(all) => all
+            .into_iter()
+            .filter(|(name, part)| !parts.contains(&name.as_str()) && !is_empty(part))
+            .map(|(name, _)| name)
+            .collect(),
+        _ => return Err("A script must serialize to a json object".into()),
+    };
+
+    for k in &script.keywords {
+        if !tags && !k.tags.is_empty() {
+            unsupported.push(format!("tags of keyword '{}'", k.key));
+        }
+        for r in &k.rules {
+            let context = r.reassembly_context.iter().any(Option::is_some);
+            let follow_ups = r.follow_ups.iter().any(Option::is_some);
+            if !r.conditions.is_empty() || !r.affect.is_empty() || context || follow_ups {
+                unsupported.push(format!(
+                    "conditions, affect, contexts or follow-ups of rule '{}' (keyword '{}')",
+                    r.decomposition_rule, k.key
+                ));
+            }
+        }
+    }
+
+    match unsupported.is_empty() {
+        true => Ok(()),
+        false => Err(format!(
+            "The {} format can't hold the script's {}",
+            format,
+            unsupported.join(", ")
+        )
+        .into()),
+    }
+}
+
+fn is_empty(part: &Value) -> bool {
+    match part {
+        Value::Array(items) => items.is_empty(),
+        Value::Bool(set) => !set,
+        Value::Null => true,
+        _ => false,
+    }
+}
+
+//A part of a decomposition rule, as the other formats write them
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    //Any number of words, including none
+    Any,
+    //Exactly this many words
+    Words(usize),
+    Word(String),
+    Synonym(String),
+    Tag(String),
+    Alternatives(Vec<String>),
+}
+
+//Reads a decomposition rule as a sequence of parts, each with whether it is captured, or returns
+//None if the rule is a regex that the other formats have no way to write
+fn parts(decomposition: &str) -> Option<Vec<(Part, bool)>> {
+    let mut rest = decomposition.strip_prefix('^').unwrap_or(decomposition);
+    rest = rest.strip_suffix('$').unwrap_or(rest);
+
+    let mut parts = Vec::new();
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix(' ') {
+            rest = r.strip_prefix('?').unwrap_or(r);
+        } else if let Some(r) = rest.strip_prefix('(') {
+            let end = group_end(r)?;
+            parts.push((group(&r[..end])?, true));
+            rest = &r[end + 1..];
+        } else if rest.starts_with(['@', '#']) {
+            let end = rest[1..]
+                .find(|c: char| !is_word_char(c) && c != '{' && c != '|' && c != '}')
+                .map_or(rest.len(), |i| i + 1);
+            parts.push((group(&rest[..end])?, false));
+            rest = &rest[end..];
+        } else {
+            let (word, r) = literal(rest)?;
+            parts.push((Part::Word(word), false));
+            rest = r;
+        }
+    }
+
+    Some(parts)
+}
+
+//Returns where the group ends, given the text after its opening parenthesis
+fn group_end(group: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in group.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+//Reads the inside of a group, or a synonym, tag or inline alternation written outside of one
+fn group(inner: &str) -> Option<Part> {
+    let words = |inner: &str| -> Option<usize> {
+        let n = inner
+            .strip_prefix(r"\S+(?: \S+){")?
+            .strip_suffix('}')?
+            .parse::<usize>()
+            .ok()?;
+        Some(n + 1)
+    };
+
+    match inner {
+        ".*" | ".+" | ".*?" | ".+?" => Some(Part::Any),
+        r"\S+" => Some(Part::Words(1)),
+        _ if inner.starts_with(r"\S+") => words(inner).map(Part::Words),
+        _ if inner.starts_with("@{") && inner.ends_with('}') => Some(Part::Alternatives(
+            inner[2..inner.len() - 1]
+                .split('|')
+                .map(str::to_string)
+                .collect(),
+        )),
+        _ if inner.len() > 1 && inner[1..].chars().all(is_word_char) => match inner {
+            _ if inner.starts_with('@') => Some(Part::Synonym(inner[1..].to_string())),
+            _ if inner.starts_with('#') => Some(Part::Tag(inner[1..].to_string())),
+            _ => literal(inner).map(|(w, _)| Part::Word(w)),
+        },
+        _ => match literal(inner)? {
+            (word, "") => Some(Part::Word(word)),
+            _ => None,
+        },
+    }
+}
+
+//Reads a literal word from the start of a rule, undoing any escapes
+fn literal(rule: &str) -> Option<(String, &str)> {
+    let mut word = String::new();
+    let mut chars = rule.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            '\\' => {
+                chars.next();
+                match chars.next() {
+                    Some((_, escaped)) if escaped.is_ascii_punctuation() => word.push(escaped),
+                    _ => return None,
+                }
+            }
+            _ if is_word_char(c) || c == '\'' || c == ',' || c == '-' => {
+                word.push(c);
+                chars.next();
+            }
+            ' ' | '(' | '@' | '#' => {
+                return Some((word, &rule[i..])).filter(|(w, _)| !w.is_empty())
+            }
+            _ => return None,
+        }
+    }
+    Some((word, "")).filter(|(w, _)| !w.is_empty())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+//Writes the pattern of a Hayden or elizabot decomposition rule as a regex, in which each '*' and
+//'@synonym' is a capture, so that their reassembly rules may still number them
+fn hayden_decomposition(pattern: &str) -> Result<(bool, String), Box<dyn Error>> {
+    let (memorise, pattern) = match pattern.trim().strip_prefix('$') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, pattern.trim()),
+    };
+
+    let mut regex = String::new();
+    let mut after_word = false;
+    let mut synonyms = 0;
+    for word in pattern.split_whitespace() {
+        for (i, piece) in word.split('*').enumerate() {
+            if i > 0 {
+                regex.push_str("(.*)");
+                after_word = false;
+            }
+            if piece.is_empty() {
+                continue;
+            }
+
+            if after_word {
+                regex.push(' ');
+            }
+            match piece.strip_prefix('@') {
+                Some(name) => {
+                    synonyms += 1;
+                    regex.push_str(&format!("(@{})", name));
+                }
+                None => regex.push_str(&regex::escape(piece)),
+            }
+            after_word = true;
+        }
+    }
+
+    match synonyms {
+        0 | 1 => Ok((memorise, regex)),
+        _ => Err(format!(
+            "Decomposition '{}' names more than one synonym, which ELIZA can't match",
+            pattern
+        )
+        .into()),
+    }
+}
+
+//Writes a decomposition rule as a Hayden or elizabot pattern, in which only the '*'s and
+//'@synonym's are numbered
+fn hayden_pattern(rule: &Rule) -> Result<String, Box<dyn Error>> {
+    let unwritable = || {
+        format!(
+            "Decomposition rule '{}' can't be written as a pattern of '*'s and words",
+            rule.decomposition_rule
+        )
+    };
+    let parts = parts(&rule.decomposition_rule).ok_or_else(unwritable)?;
+
+    let mut pattern = String::new();
+    if rule.memorise {
+        pattern.push_str("$ ");
+    }
+    for (i, (part, captured)) in parts.iter().enumerate() {
+        if i > 0 {
+            pattern.push(' ');
+        }
+        match (part, captured) {
+            (Part::Any, true) => pattern.push('*'),
+            (Part::Synonym(name), true) => pattern.push_str(&format!("@{}", name)),
+            (Part::Word(word), false) => pattern.push_str(word),
+            _ => return Err(unwritable().into()),
+        }
+    }
+
+    Ok(pattern)
+}
+
+//Reads a Hayden or elizabot reassembly rule, in which '(2)' is the second capture and
+//'goto key' is a GOTO
+fn hayden_reassembly(text: &str) -> String {
+    let text = text.trim();
+    if let Some(key) = text
+        .get(..5)
+        .filter(|g| g.eq_ignore_ascii_case("goto "))
+        .map(|_| text[5..].trim())
+    {
+        return format!("GOTO {}", key);
+    }
+
+    let mut reassembly = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('(') {
+        reassembly.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        match after.find(')') {
+            Some(end) if end > 0 && after[..end].chars().all(|c| c.is_ascii_digit()) => {
+                reassembly.push('$');
+                reassembly.push_str(&after[..end]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                reassembly.push('(');
+                rest = after;
+            }
+        }
+    }
+    reassembly.push_str(rest);
+
+    reassembly
+}
+
+//Writes a reassembly rule as Hayden and elizabot do
+fn hayden_text(reassembly: &str, key: &str) -> Result<String, Box<dyn Error>> {
+    if let Some(goto) = crate::syntax::is_goto(reassembly) {
+        return Ok(format!("goto {}", goto));
+    }
+
+    let mut text = String::with_capacity(reassembly.len());
+    let mut rest = reassembly;
+    while let Some(i) = rest.find('$') {
+        text.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        if digits == 0 {
+            return Err(format!(
+                "Reassembly rule '{}' (keyword '{}') can only refer to captures by number",
+                reassembly, key
+            )
+            .into());
+        }
+        text.push_str(&format!("({})", &after[..digits]));
+        rest = &after[digits..];
+    }
+    text.push_str(rest);
+
+    Ok(text)
+}
+
+//Reads a substitution, such as Hayden's 'pre: dont don't', as a transform, merging it with any
+//other transform to the same words
+fn substitute(transforms: &mut Vec<Transform>, from: &str, to: &str) {
+    match transforms.iter_mut().find(|t| t.word == to) {
+        Some(t) => t.equivalents.push(from.to_string()),
+        None => transforms.push(Transform {
+            word: to.to_string(),
+            equivalents: vec![from.to_string()],
+            priority: 0,
+        }),
+    }
+}
+
+//Reads the pairs of words that reflect into each other, such as 'am' and 'are', where a pair
+//given both ways round is a single reflection that works both ways
+fn reflections(pairs: Vec<(String, String)>) -> Vec<Reflection> {
+    let mut reflections: Vec<Reflection> = Vec::new();
+    for (word, inverse) in pairs {
+        match reflections
+            .iter_mut()
+            .find(|r| !r.twoway && r.word == inverse && r.inverse == word)
+        {
+            Some(r) => r.twoway = true,
+            None => reflections.push(Reflection {
+                word,
+                inverse,
+                twoway: false,
+            }),
+        }
+    }
+    reflections
+}
+
+//The pairs of words that each reflection swaps
+fn reflection_pairs(script: &Script) -> Vec<(&str, &str)> {
+    let mut pairs = Vec::new();
+    for r in &script.reflections {
+        pairs.push((r.word.as_str(), r.inverse.as_str()));
+        if r.twoway {
+            pairs.push((r.inverse.as_str(), r.word.as_str()));
+        }
+    }
+    pairs
+}
+
+//The keywords to write for a script whose format has no fallbacks, where the fallbacks become the
+//rules of a NONE keyword, as the other ELIZAs have
+fn with_fallbacks(script: &Script, format: Format) -> Result<Vec<Keyword>, Box<dyn Error>> {
+    let mut keywords = script.keywords.clone();
+    if script.fallbacks.is_empty() {
+        return Ok(keywords);
+    }
+    if script.none_keyword().is_some() {
+        return Err(format!(
+            "The {} format can't hold both the script's fallbacks and its NONE keyword",
+            format
+        )
+        .into());
+    }
+
+    keywords.push(Keyword {
+        key: NONE_KEYWORD.to_string(),
+        rules: vec![Rule {
+            decomposition_rule: "(.*)".to_string(),
+            reassembly_rules: script.fallbacks.clone(),
+            ..Rule::default()
+        }],
+        ..Keyword::default()
+    });
+    Ok(keywords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decomposition_parts() {
+        assert_eq!(
+            Some(vec!(
+                (Part::Any, true),
+                (Part::Word("i".to_string()), false),
+                (Part::Word("remember".to_string()), false),
+                (Part::Any, true)
+            )),
+            parts("(.*)i remember(.+)")
+        );
+        assert_eq!(
+            Some(vec!(
+                (Part::Words(3), true),
+                (Part::Word("don't".to_string()), true),
+                (Part::Tag("family".to_string()), true),
+                (
+                    Part::Alternatives(vec!("want".to_string(), "need".to_string())),
+                    true
+                ),
+                (Part::Synonym("belief".to_string()), false)
+            )),
+            parts(r"^(\S+(?: \S+){2}) (don\'t) (#family) (@{want|need}) @belief$")
+        );
+        assert_eq!(None, parts("(.*@family)(.*)"));
+        assert_eq!(None, parts("[a-z]+"));
+    }
+
+    #[test]
+    fn hayden_rules() {
+        assert_eq!(
+            (false, "(.*)my(.*)(@family)(.*)".to_string()),
+            hayden_decomposition("* my* @family *").unwrap()
+        );
+        assert_eq!(
+            (true, "(.*)your(.*)".to_string()),
+            hayden_decomposition("$ * your *").unwrap()
+        );
+        assert!(hayden_decomposition("* @belief @family *").is_err());
+
+        assert_eq!("Why $2 ?", hayden_reassembly("Why (2) ?"));
+        assert_eq!("Why $2 (really)?", hayden_reassembly("Why (2) (really)?"));
+        assert_eq!("GOTO what", hayden_reassembly("goto what"));
+        assert_eq!("Why (2) ?", hayden_text("Why $2 ?", "why").unwrap());
+        assert_eq!("goto what", hayden_text("GOTO what", "why").unwrap());
+        assert!(hayden_text("Tell me about $@family", "my").is_err());
+    }
+
+    #[test]
+    fn format_names() {
+        for format in Format::ALL.iter() {
+            assert_eq!(Ok(*format), format.to_string().parse());
+        }
+        assert!("xml".parse::<Format>().is_err());
+        assert_eq!(
+            Some(Format::Elizabot),
+            Format::from_extension("elizadata.js")
+        );
+        assert_eq!(Some(Format::Yaml), Format::from_extension("doctor.YML"));
+        assert_eq!(None, Format::from_extension("doctor"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let formats: &[Format] = &[
+            Format::Json,
+            #[cfg(feature = "yaml")]
+            Format::Yaml,
+            #[cfg(feature = "toml")]
+            Format::Toml,
+        ];
+        //json is written canonically, with its keywords sorted by rank
+        let sorted = |mut script: Script| {
+            script
+                .keywords
+                .sort_by(|a, b| b.rank.cmp(&a.rank).then_with(|| a.key.cmp(&b.key)));
+            serde_json::to_value(&script).unwrap()
+        };
+        let scripts = [
+            include_str!("../../scripts/doctor.json"),
+            include_str!("../../scripts/pirate.json"),
+        ];
+        for text in scripts.iter() {
+            let script = Script::from_str(text).unwrap();
+            for format in formats {
+                let written = format.write(&script).unwrap();
+                let read = format.parse(&written).unwrap();
+                assert_eq!(sorted(script.clone()), sorted(read), "{}", format);
+            }
+        }
+
+        #[cfg(not(feature = "yaml"))]
+        assert!(Format::Yaml.write(&Script::default()).is_err());
+    }
+
+    #[test]
+    fn unsupported_parts() {
+        let mut script = Script::default();
+        assert!(check_parts(&script, Format::Hayden, &[], false).is_ok());
+
+        script.nudges.push("Still there?".to_string());
+        script.keywords.push(Keyword {
+            key: "mother".to_string(),
+            tags: vec!["family".to_string()],
+            ..Keyword::default()
+        });
+        let e = check_parts(&script, Format::Hayden, &["keywords"], false).unwrap_err();
+        assert_eq!(
+            "The hayden format can't hold the script's nudges, tags of keyword 'mother'",
+            e.to_string()
+        );
+    }
+}
@@ -0,0 +1,729 @@
+//! Weizenbaum's 1966 scripts, written as lists in the manner of SLIP, as in the DOCTOR script
+//! printed with his paper:
+//!
+//! ```text,no_run
+//! (HOW DO YOU DO.  PLEASE TELL ME YOUR PROBLEM)
+//! START
+//! (SORRY ((0) (PLEASE DON'T APOLIGIZE) (APOLOGIES ARE NOT NECESSARY)))
+//! (DONT = DON'T)
+//! (REMEMBER 5 ((0 YOU REMEMBER 0) (DO YOU OFTEN THINK OF 4) (=WHAT)))
+//! (MY = YOUR 2 ((0 YOUR 0 (/FAMILY) 0) (TELL ME MORE ABOUT YOUR FAMILY)))
+//! (MOTHER DLIST(/FAMILY))
+//! (WHAT ((0) (WHY DO YOU ASK)))
+//! (NONE ((0) (I AM NOT SURE I UNDERSTAND YOU FULLY)))
+//! (MEMORY MY (0 YOUR 0 = LETS DISCUSS FURTHER WHY YOUR 3))
+//! ()
+//! ```
+//!
+//! The first list is the greeting. A keyword's substitution, such as `MY = YOUR`, is read as a
+//! transform, and as Weizenbaum's decomposition rules are written for the substituted input, the
+//! keyword's rules are kept under the substituted word. Each element of a decomposition rule is a
+//! capture, so that a reassembly rule's numbers still refer to them: `0` is any number of words,
+//! another number that many words, `(/FAMILY)` any keyword tagged with `DLIST(/FAMILY)` and
+//! `(*WANT NEED)` either word. `(=WHAT)` is a `GOTO`, and the rules of `MEMORY` are memorised.
+//!
+//! A reassembly rule of `(NEWKEY)` is left out, so that ELIZA moves on to the next rule and keyword
+//! as Weizenbaum's did. ELIZA has no way to reassemble the input and then go to another keyword,
+//! so `PRE` rules are skipped with a warning.
+//!
+//! A script is written with its fallbacks as the rules of `NONE`, and its synonyms written out
+//! wherever a rule uses them. It may have a single greeting, but can't hold farewells,
+//! reflections or other parts such as nudges or tests, nor memorise the rules of more than one
+//! keyword.
+//!
+use super::{Format, Part};
+use crate::script::{Keyword, Rule, Script, NONE_KEYWORD};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+
+//A list or atom of a script
+#[derive(Debug, Clone, PartialEq)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+//A keyword's list in a script, before its substitution is applied
+#[derive(Debug, Default)]
+struct Entry {
+    name: String,
+    substitute: Option<String>,
+    rank: i32,
+    tags: Vec<String>,
+    rules: Vec<Rule>,
+}
+
+/// Reads a 1966 script.
+///
+/// Will return `Err` if the lists are unbalanced, or if a keyword's list holds something other
+/// than its substitution, rank, `DLIST` and rules.
+pub fn parse(text: &str) -> Result<Script, Box<dyn Error>> {
+    let mut lists = read(text)?.into_iter().peekable();
+    let greeting = match lists.next() {
+        Some(Sexp::List(words)) => text_of(&words, false)?,
+        _ => return Err("A script must begin with a greeting".into()),
+    };
+    if lists.peek() == Some(&Sexp::Atom("START".to_string())) {
+        lists.next();
+    }
+
+    let mut entries = Vec::new();
+    let mut memory: Option<(String, Vec<Rule>)> = None;
+    for list in lists {
+        let items = match list {
+            Sexp::List(items) if items.is_empty() => break,
+            Sexp::List(items) => items,
+            Sexp::Atom(atom) => return Err(format!("Expected a keyword's list: '{}'", atom).into()),
+        };
+        match items.first() {
+            Some(Sexp::Atom(memo)) if memo == "MEMORY" => memory = Some(memory_rules(&items)?),
+            _ => entries.push(entry(&items)?),
+        }
+    }
+
+    //Keywords are named in GOTOs and MEMORY as they are in the input, before substitution
+    let keys: HashMap<String, String> = entries
+        .iter()
+        .filter(|e| !e.rules.is_empty() || !e.tags.is_empty())
+        .map(|e| (e.name.clone(), key(e)))
+        .collect();
+    let key_of = |name: &str| keys.get(name).cloned().unwrap_or_else(|| name.to_string());
+
+    let mut script = Script::default();
+    if !greeting.is_empty() {
+        script.greetings.push(greeting);
+    }
+    for mut e in entries {
+        if let Some(substitute) = &e.substitute {
+            super::substitute(&mut script.transforms, &e.name, substitute);
+        }
+
+        let key = key(&e);
+        for r in &mut e.rules {
+            for assem in &mut r.reassembly_rules {
+                if let Some(goto) = crate::syntax::is_goto(assem) {
+                    *assem = format!("GOTO {}", key_of(&goto));
+                }
+            }
+        }
+        //A keyword that goes to the one it is substituted with is just the substitution
+        let to_itself = |r: &Rule| r.reassembly_rules == [format!("GOTO {}", key)];
+        e.rules.retain(|r| !to_itself(r));
+
+        match script.keywords.iter_mut().find(|k| k.key == key) {
+            Some(k) => {
+                k.rank = k.rank.max(e.rank);
+                k.tags.extend(e.tags);
+                k.rules.extend(e.rules);
+            }
+            None if e.substitute.is_none() || !e.rules.is_empty() || !e.tags.is_empty() => {
+                script.keywords.push(Keyword {
+                    key,
+                    rank: e.rank,
+                    tags: e.tags,
+                    rules: e.rules,
+                })
+            }
+            None => (),
+        }
+    }
+
+    if let Some((name, rules)) = memory {
+        let key = key_of(&name);
+        match script.keywords.iter_mut().find(|k| k.key == key) {
+            Some(k) => k.rules.extend(rules),
+            None => {
+                return Err(format!("MEMORY names a keyword that doesn't exist: '{}'", name).into())
+            }
+        }
+    }
+
+    script.tidy();
+    Ok(script)
+}
+
+//The key of an entry's keyword, which is the word it is substituted with, if it has one
+fn key(entry: &Entry) -> String {
+    entry
+        .substitute
+        .clone()
+        .unwrap_or_else(|| entry.name.clone())
+}
+
+//Reads a keyword's list, such as '(MY = YOUR 2 DLIST(/FAMILY) ((0 YOUR 0) (WHY YOUR 3)))'
+fn entry(items: &[Sexp]) -> Result<Entry, Box<dyn Error>> {
+    let name = match items.first() {
+        Some(Sexp::Atom(name)) => name_of(name),
+        _ => return Err("Each keyword's list must begin with the keyword".into()),
+    };
+    let mistake = |problem: &str| format!("{} in the list of keyword '{}'", problem, name);
+
+    let mut entry = Entry {
+        name: name.clone(),
+        ..Entry::default()
+    };
+    let mut items = items[1..].iter();
+    while let Some(item) = items.next() {
+        match item {
+            Sexp::Atom(eq) if eq == "=" => match items.next() {
+                Some(Sexp::Atom(substitute)) => entry.substitute = Some(name_of(substitute)),
+                _ => return Err(mistake("Expected a substitute after '='").into()),
+            },
+            Sexp::Atom(dlist) if dlist == "DLIST" => match items.next() {
+                Some(Sexp::List(tags)) => {
+                    for tag in tags {
+                        match tag {
+                            Sexp::Atom(tag) => {
+                                entry.tags.push(tag.trim_start_matches('/').to_lowercase())
+                            }
+                            _ => return Err(mistake("Expected a tag").into()),
+                        }
+                    }
+                }
+                _ => return Err(mistake("Expected the tags of a DLIST").into()),
+            },
+            Sexp::Atom(rank) => {
+                entry.rank = rank.parse().map_err(|_| mistake("Expected a rank"))?;
+            }
+            //A list of just a GOTO stands in for all of the keyword's rules
+            Sexp::List(goto) if goto_of(goto).is_some() => entry.rules.push(Rule {
+                decomposition_rule: decomposition(&[Sexp::Atom("0".to_string())])?,
+                reassembly_rules: goto_of(goto).into_iter().collect(),
+                ..Rule::default()
+            }),
+            Sexp::List(rule) => match rule.split_first() {
+                Some((Sexp::List(pattern), reassembly)) => {
+                    let mut rules = Vec::new();
+                    for assem in reassembly {
+                        match assem {
+                            Sexp::List(assem) => rules.extend(reassembly_of(assem, &name)?),
+                            Sexp::Atom(_) => {
+                                return Err(mistake("Expected a reassembly rule").into())
+                            }
+                        }
+                    }
+                    entry.rules.push(Rule {
+                        decomposition_rule: decomposition(pattern)?,
+                        reassembly_rules: rules,
+                        ..Rule::default()
+                    });
+                }
+                _ => return Err(mistake("Expected a decomposition rule").into()),
+            },
+        }
+    }
+
+    Ok(entry)
+}
+
+//Reads the rules of MEMORY, such as '(MEMORY MY (0 YOUR 0 = LETS DISCUSS FURTHER WHY YOUR 3))'
+fn memory_rules(items: &[Sexp]) -> Result<(String, Vec<Rule>), Box<dyn Error>> {
+    let name = match items.get(1) {
+        Some(Sexp::Atom(name)) => name_of(name),
+        _ => return Err("MEMORY must name a keyword".into()),
+    };
+
+    let mut rules = Vec::new();
+    for item in &items[2..] {
+        let rule = match item {
+            Sexp::List(rule) => rule,
+            Sexp::Atom(_) => return Err("Each rule of MEMORY must be a list".into()),
+        };
+        let split = rule
+            .iter()
+            .position(|s| *s == Sexp::Atom("=".to_string()))
+            .ok_or("Each rule of MEMORY must be a decomposition, '=' and reassembly")?;
+        rules.push(Rule {
+            memorise: true,
+            decomposition_rule: decomposition(&rule[..split])?,
+            reassembly_rules: reassembly_of(&rule[split + 1..], "MEMORY")?
+                .into_iter()
+                .collect(),
+            ..Rule::default()
+        });
+    }
+
+    Ok((name, rules))
+}
+
+//Writes the elements of a decomposition rule as a regex in which each element is a capture
+fn decomposition(pattern: &[Sexp]) -> Result<String, Box<dyn Error>> {
+    let mut regex = String::from("^");
+    let mut any_before = false;
+    for (i, element) in pattern.iter().enumerate() {
+        let (group, any) = match element {
+            Sexp::Atom(n) if n == "0" => ("(.*?)".to_string(), true),
+            Sexp::Atom(n) if n.chars().all(|c| c.is_ascii_digit()) => match n.parse::<usize>()? {
+                1 => (r"(\S+)".to_string(), false),
+                n => (format!(r"(\S+(?: \S+){{{}}})", n - 1), false),
+            },
+            Sexp::Atom(word) => (format!("({})", regex::escape(&word.to_lowercase())), false),
+            Sexp::List(list) => match list.first() {
+                Some(Sexp::Atom(tag)) if list.len() == 1 && tag.starts_with('/') => {
+                    (format!("(#{})", tag[1..].to_lowercase()), false)
+                }
+                Some(Sexp::Atom(first)) if first.starts_with('*') => {
+                    let words = text_of(list, false)?;
+                    let words: Vec<String> = words[1..]
+                        .to_lowercase()
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect();
+                    (format!("(@{{{}}})", words.join("|")), false)
+                }
+                _ => {
+                    return Err(
+                        format!("Unknown element of a decomposition rule: {:?}", list).into(),
+                    )
+                }
+            },
+        };
+
+        if i > 0 {
+            regex.push_str(if any || any_before { " ?" } else { " " });
+        }
+        regex.push_str(&group);
+        any_before = any;
+    }
+    regex.push('$');
+
+    Ok(regex)
+}
+
+//Reads a reassembly rule, or returns None for one that ELIZA has no equivalent of
+fn reassembly_of(assem: &[Sexp], name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    if let Some(goto) = goto_of(assem) {
+        return Ok(Some(goto));
+    }
+    match assem.first() {
+        Some(Sexp::Atom(newkey)) if newkey == "NEWKEY" && assem.len() == 1 => Ok(None),
+        Some(Sexp::Atom(pre)) if pre == "PRE" => {
+            warn!(
+                "Skipping a PRE rule of keyword '{}', which ELIZA can't follow",
+                name
+            );
+            Ok(None)
+        }
+        _ => text_of(assem, true).map(Some),
+    }
+}
+
+//Reads a GOTO, such as '(=WHAT)'
+fn goto_of(list: &[Sexp]) -> Option<String> {
+    match list {
+        [Sexp::Atom(goto)] if goto.len() > 1 && goto.starts_with('=') => {
+            Some(format!("GOTO {}", name_of(&goto[1..])))
+        }
+        _ => None,
+    }
+}
+
+//Joins a list of words into text, where each number is a capture if they are to be reassembled
+fn text_of(words: &[Sexp], reassembly: bool) -> Result<String, Box<dyn Error>> {
+    let mut text = Vec::new();
+    for word in words {
+        match word {
+            Sexp::Atom(n) if reassembly && n.chars().all(|c| c.is_ascii_digit()) => {
+                text.push(format!("${}", n))
+            }
+            Sexp::Atom(word) => text.push(word.clone()),
+            Sexp::List(_) => return Err(format!("Expected words, not a list: {:?}", words).into()),
+        }
+    }
+    Ok(text.join(" "))
+}
+
+//Keys are read in lower case, but for NONE
+fn name_of(atom: &str) -> String {
+    match atom {
+        NONE_KEYWORD => atom.to_string(),
+        _ => atom.to_lowercase(),
+    }
+}
+
+//Reads the lists and atoms of a script
+fn read(text: &str) -> Result<Vec<Sexp>, Box<dyn Error>> {
+    let mut stack: Vec<Vec<Sexp>> = vec![Vec::new()];
+    let mut atom = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c == '(' || c == ')' || c.is_whitespace() {
+            if !atom.is_empty() {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push(Sexp::Atom(std::mem::take(&mut atom)));
+            }
+        } else {
+            atom.push(c);
+        }
+
+        match c {
+            '(' => stack.push(Vec::new()),
+            ')' => match stack.pop() {
+                Some(list) if !stack.is_empty() => stack.last_mut().unwrap().push(Sexp::List(list)),
+                _ => return Err("A list is closed that was never opened".into()),
+            },
+            _ => (),
+        }
+    }
+
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap_or_default()),
+        _ => Err("A list is opened that is never closed".into()),
+    }
+}
+
+/// Writes a 1966 script.
+///
+/// Will return `Err` if the script has parts that 1966 scripts can't hold, or rules that can't
+/// be written as lists, such as a decomposition rule with a regex of its own.
+pub fn write(script: &Script) -> Result<String, Box<dyn Error>> {
+    let parts = [
+        "greetings",
+        "fallbacks",
+        "transforms",
+        "synonyms",
+        "keywords",
+    ];
+    super::check_parts(script, Format::Slip, &parts, true)?;
+    if script.greetings.len() > 1 {
+        return Err("The slip format can't hold more than one greeting".into());
+    }
+
+    //Each substitution as the word and its substitute
+    let mut substitutions = Vec::new();
+    for t in &script.transforms {
+        for equivalent in &t.equivalents {
+            if t.priority != 0 || !is_atom(equivalent) || !is_atom(&t.word) {
+                return Err(format!(
+                    "Transform '{}' can't be written as slip substitutions, which replace a \
+                     single word with another in no particular order",
+                    t.word
+                )
+                .into());
+            }
+            substitutions.push((equivalent.as_str(), t.word.as_str()));
+        }
+    }
+
+    //A keyword is written in the list of a word substituted with it, as Weizenbaum wrote them,
+    //unless the substitutions and keywords can't then be kept in order
+    let keywords = super::with_fallbacks(script, Format::Slip)?;
+    let mut merged: HashMap<&str, usize> = HashMap::new();
+    for (i, k) in keywords.iter().enumerate() {
+        if let Some(&(word, _)) = substitutions.iter().find(|(_, s)| *s == k.key) {
+            merged.entry(word).or_insert(i);
+        }
+    }
+    let mut lists: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let mut next = 0;
+    for (s, &(word, _)) in substitutions.iter().enumerate() {
+        match merged.get(word) {
+            Some(&k) if k >= next => {
+                for earlier in next..k {
+                    merged.retain(|_, &mut m| m != earlier);
+                    lists.push((None, Some(earlier)));
+                }
+                lists.push((Some(s), Some(k)));
+                next = k + 1;
+            }
+            _ => lists.push((Some(s), None)),
+        }
+    }
+    lists.extend((next..keywords.len()).map(|k| (None, Some(k))));
+
+    let mut names: HashMap<&str, &str> = HashMap::new();
+    for &(s, k) in &lists {
+        if let (Some(s), Some(k)) = (s, k) {
+            names.insert(&keywords[k].key, substitutions[s].0);
+        }
+    }
+    let name = |key: &str| names.get(key).copied().unwrap_or(key).to_uppercase();
+
+    let mut text = String::new();
+    let greeting = script
+        .greetings
+        .first()
+        .map_or(Ok(String::new()), |g| atoms(g, false))?;
+    writeln!(text, "({})", greeting)?;
+    writeln!(text, "START")?;
+
+    let mut memory = None;
+    for (s, k) in lists {
+        let keyword = match (s, k) {
+            (Some(s), None) => {
+                let (word, substitute) = substitutions[s];
+                writeln!(
+                    text,
+                    "({} = {})",
+                    word.to_uppercase(),
+                    substitute.to_uppercase()
+                )?;
+                continue;
+            }
+            (_, Some(k)) => &keywords[k],
+            (None, None) => continue,
+        };
+
+        let mut list = name(&keyword.key);
+        if let Some(s) = s {
+            write!(list, " = {}", substitutions[s].1.to_uppercase())?;
+        }
+        if keyword.rank != 0 {
+            write!(list, " {}", keyword.rank)?;
+        }
+        if !keyword.tags.is_empty() {
+            write!(list, " DLIST(/{})", keyword.tags.join(" ").to_uppercase())?;
+        }
+
+        for r in &keyword.rules {
+            let (pattern, captures) = pattern(r, script)?;
+            if r.memorise {
+                if memory.as_ref().is_some_and(|(key, _)| *key != keyword.key) {
+                    return Err(
+                        "The slip format can't memorise the rules of more than one keyword".into(),
+                    );
+                }
+                let rules = &mut memory
+                    .get_or_insert_with(|| (keyword.key.clone(), Vec::new()))
+                    .1;
+                for assem in &r.reassembly_rules {
+                    rules.push(format!(
+                        "({} = {})",
+                        pattern,
+                        reassembly(assem, &captures, &name)?
+                    ));
+                }
+                continue;
+            }
+
+            write!(list, "\n\t(({})", pattern)?;
+            for assem in &r.reassembly_rules {
+                write!(list, "\n\t\t({})", reassembly(assem, &captures, &name)?)?;
+            }
+            list.push(')');
+        }
+        writeln!(text, "({})", list)?;
+    }
+
+    if let Some((key, rules)) = memory {
+        writeln!(text, "(MEMORY {}", name(&key))?;
+        for rule in rules {
+            writeln!(text, "\t{}", rule)?;
+        }
+        writeln!(text, ")")?;
+    }
+    writeln!(text, "()")?;
+
+    Ok(text)
+}
+
+//Writes a decomposition rule as a list of elements, along with the element that each of its
+//captures became
+fn pattern(rule: &Rule, script: &Script) -> Result<(String, Vec<usize>), Box<dyn Error>> {
+    let unwritable = || {
+        format!(
+            "Decomposition rule '{}' can't be written as a list of words",
+            rule.decomposition_rule
+        )
+    };
+    let parts = super::parts(&rule.decomposition_rule).ok_or_else(unwritable)?;
+
+    let mut elements = Vec::new();
+    let mut captures = Vec::new();
+    for (i, (part, captured)) in parts.into_iter().enumerate() {
+        if captured {
+            captures.push(i + 1);
+        }
+        elements.push(match part {
+            Part::Any => "0".to_string(),
+            Part::Words(n) => n.to_string(),
+            Part::Word(word) if is_atom(&word) && word.parse::<usize>().is_err() => {
+                word.to_uppercase()
+            }
+            Part::Tag(tag) => format!("(/{})", tag.to_uppercase()),
+            Part::Alternatives(words) => format!("(*{})", words.join(" ").to_uppercase()),
+            Part::Synonym(name) => {
+                let synonym = script
+                    .synonyms
+                    .iter()
+                    .find(|s| s.word.eq_ignore_ascii_case(&name))
+                    .ok_or_else(unwritable)?;
+                let words = std::iter::once(&synonym.word).chain(&synonym.equivalents);
+                let words: Vec<&str> = words.map(String::as_str).collect();
+                format!("(*{})", words.join(" ").to_uppercase())
+            }
+            Part::Word(_) => return Err(unwritable().into()),
+        });
+    }
+
+    Ok((elements.join(" "), captures))
+}
+
+//Writes a reassembly rule as a list of words, numbering each capture by its element
+fn reassembly(
+    assem: &str,
+    captures: &[usize],
+    name: &dyn Fn(&str) -> String,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(goto) = crate::syntax::is_goto(assem) {
+        return Ok(format!("={}", name(&goto)));
+    }
+
+    let mut words = Vec::new();
+    for word in assem.split_whitespace() {
+        let capture = word.strip_prefix('$').map(|rest| {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            (rest[..digits].parse::<usize>().ok(), &rest[digits..])
+        });
+        match capture {
+            Some((Some(n), rest)) if n > 0 && !rest.contains('$') && is_atom(word) => {
+                let element = captures.get(n - 1).ok_or_else(|| {
+                    format!("Reassembly rule '{}' refers to a missing capture", assem)
+                })?;
+                words.push(element.to_string());
+                if !rest.is_empty() {
+                    words.push(rest.to_string());
+                }
+            }
+            None if is_atom(word) && word.parse::<usize>().is_err() => words.push(word.to_string()),
+            _ => {
+                return Err(format!(
+                    "Reassembly rule '{}' can't be written as a list of words and captures",
+                    assem
+                )
+                .into())
+            }
+        }
+    }
+
+    Ok(words.join(" "))
+}
+
+//Writes text as the words of a list
+fn atoms(text: &str, reassembly: bool) -> Result<String, Box<dyn Error>> {
+    match text
+        .split_whitespace()
+        .all(|w| is_atom(w) && (!reassembly || w.parse::<usize>().is_err()))
+    {
+        true => Ok(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+        false => Err(format!("'{}' can't be written as a list of words", text).into()),
+    }
+}
+
+fn is_atom(word: &str) -> bool {
+    !word.is_empty()
+        && !word.contains(|c: char| c == '(' || c == ')' || c == '=' || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "(HOW DO YOU DO.  PLEASE TELL ME YOUR PROBLEM)
+START
+(SORRY ((0) (PLEASE DON'T APOLIGIZE) (APOLOGIES ARE NOT NECESSARY)))
+(DONT = DON'T)
+(REMEMBER 5 ((0 YOU REMEMBER 0) (DO YOU OFTEN THINK OF 4) (=WHAT))
+    ((0) (NEWKEY)))
+(DREAMT 4 ((0 YOU DREAMT 0) (REALLY, 4)))
+(DREAMED = DREAMT 4 (=DREAMT))
+(ME = YOU)
+(YOUR = MY)
+(MY = YOUR 2 ((0 YOUR 0 (/FAMILY) 0) (TELL ME MORE ABOUT YOUR FAMILY) (YOUR 4 ?))
+    ((0 YOUR 1 (*SAD UNHAPPY) 0) (WHY IS YOUR 3 4)))
+(MOTHER DLIST(/FAMILY))
+(FATHER DLIST(/FAMILY))
+(YOU'RE = I'M ((0 I'M 0) (PRE (I ARE 3) (=YOU))))
+(WHAT ((0) (WHY DO YOU ASK)))
+(NONE ((0) (I AM NOT SURE I UNDERSTAND YOU FULLY)))
+(MEMORY MY (0 YOUR 0 = LETS DISCUSS FURTHER WHY YOUR 3)
+    (0 YOUR 0 = EARLIER YOU SAID YOUR 3))
+()
+";
+
+    #[test]
+    fn read() {
+        let script = parse(SCRIPT).unwrap();
+        assert_eq!(
+            vec!("HOW DO YOU DO. PLEASE TELL ME YOUR PROBLEM"),
+            script.greetings
+        );
+        let transforms: Vec<(&str, &str)> = script
+            .transforms
+            .iter()
+            .flat_map(|t| {
+                t.equivalents
+                    .iter()
+                    .map(move |e| (e.as_str(), t.word.as_str()))
+            })
+            .collect();
+        assert_eq!(
+            vec!(
+                ("dont", "don't"),
+                ("dreamed", "dreamt"),
+                ("me", "you"),
+                ("your", "my"),
+                ("my", "your"),
+                ("you're", "i'm")
+            ),
+            transforms
+        );
+
+        let keys: Vec<&str> = script.keywords.iter().map(|k| k.key.as_str()).collect();
+        assert_eq!(
+            vec!("sorry", "remember", "dreamt", "your", "mother", "father", "i'm", "what", "NONE"),
+            keys
+        );
+
+        let remember = &script.keywords[1];
+        assert_eq!(
+            r"^(.*?) ?(you) (remember) ?(.*?)$",
+            remember.rules[0].decomposition_rule
+        );
+        assert_eq!(
+            vec!("DO YOU OFTEN THINK OF $4", "GOTO what"),
+            remember.rules[0].reassembly_rules
+        );
+        assert!(remember.rules[1].reassembly_rules.is_empty());
+        assert_eq!(1, script.keywords[2].rules.len());
+
+        let your = &script.keywords[3];
+        assert_eq!(2, your.rank);
+        assert_eq!(
+            r"^(.*?) ?(your) ?(.*?) ?(#family) ?(.*?)$",
+            your.rules[0].decomposition_rule
+        );
+        assert_eq!(
+            r"^(.*?) ?(your) (\S+) (@{sad|unhappy}) ?(.*?)$",
+            your.rules[1].decomposition_rule
+        );
+        assert!(your.rules[2].memorise);
+        assert_eq!(vec!("family"), script.keywords[4].tags);
+        assert!(script.keywords[6].rules[0].reassembly_rules.is_empty());
+        assert!(script.validate().is_ok());
+
+        assert!(parse("(HELLO) START (SORRY ((0) (NO))").is_err());
+        assert!(parse("(HELLO) START (SORRY 0 0 ((0) (NO)))) ()").is_err());
+        assert!(parse("(HELLO) START (SORRY ((0) (NO))) (MEMORY MINE (0 = WHY)) ()").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let script = parse(SCRIPT).unwrap();
+        let written = write(&script).unwrap();
+        assert!(written.contains("(MY = YOUR 2\n"), "{}", written);
+        assert_eq!(
+            serde_json::to_value(&script).unwrap(),
+            serde_json::to_value(parse(&written).unwrap()).unwrap()
+        );
+        assert_eq!(written, write(&parse(&written).unwrap()).unwrap());
+
+        let mut script = parse(SCRIPT).unwrap();
+        script.farewells.push("GOODBYE".to_string());
+        assert!(write(&script).is_err());
+    }
+}
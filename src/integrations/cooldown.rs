@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+//Limits how often ELIZA will reply within each session, so a bot can't be used to flood a channel
+pub(crate) struct Cooldown {
+    interval: Duration,
+    last_reply: HashMap<String, Instant>,
+}
+
+impl Cooldown {
+    pub(crate) fn new(interval: Duration) -> Cooldown {
+        Cooldown {
+            interval,
+            last_reply: HashMap::new(),
+        }
+    }
+
+    //Returns true if the session may be replied to, in which case the reply is recorded
+    pub(crate) fn ready(&mut self, id: &str) -> bool {
+        let interval = self.interval;
+        self.last_reply.retain(|_, t| t.elapsed() < interval);
+
+        if self.last_reply.contains_key(id) {
+            return false;
+        }
+
+        self.last_reply.insert(id.to_string(), Instant::now());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldown() {
+        let mut cooldown = Cooldown::new(Duration::from_secs(60));
+        assert!(cooldown.ready("alice"));
+        assert!(!cooldown.ready("alice"));
+        assert!(cooldown.ready("bob"));
+
+        let mut cooldown = Cooldown::new(Duration::from_millis(0));
+        assert!(cooldown.ready("alice"));
+        assert!(cooldown.ready("alice"));
+    }
+}
@@ -0,0 +1,177 @@
+//! A Discord bot, built on [serenity](https://docs.rs/serenity).
+//!
+//! `DiscordBot` is a serenity `EventHandler`, holding a conversation with each user in each
+//! channel. By default ELIZA replies to direct messages, and to messages that mention the bot.
+//!
+//! ```rust,no_run
+//! # async fn run() {
+//! use eliza::integrations::discord::{DiscordBot, Trigger};
+//! use eliza::pool::ElizaPool;
+//! use eliza::script::Script;
+//! use serenity::prelude::*;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let bot = DiscordBot::new(ElizaPool::new(script)).trigger(Trigger::Mentions);
+//!
+//! let intents = GatewayIntents::GUILD_MESSAGES
+//!     | GatewayIntents::DIRECT_MESSAGES
+//!     | GatewayIntents::MESSAGE_CONTENT;
+//! let mut client = Client::builder("token", intents)
+//!     .event_handler(bot)
+//!     .await
+//!     .unwrap();
+//! client.start().await.unwrap();
+//! # }
+//! ```
+//!
+//! A runnable version of the above can be found in `examples/discord.rs`.
+//!
+use crate::integrations::cooldown::Cooldown;
+use crate::pool::ElizaPool;
+use serenity::async_trait;
+use serenity::model::channel::Message;
+use serenity::model::gateway::Ready;
+use serenity::model::id::UserId;
+use serenity::prelude::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The messages ELIZA will reply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Only messages in a server channel that mention the bot.
+    Mentions,
+    /// Only direct messages to the bot.
+    DirectMessages,
+    /// Both mentions and direct messages.
+    Both,
+}
+
+/// A serenity event handler that converses with Discord users.
+///
+/// This struct is created by the `new()` method. See its documentation for more.
+pub struct DiscordBot {
+    pool: Mutex<ElizaPool>,
+    cooldown: Mutex<Cooldown>,
+    trigger: Trigger,
+    user: Mutex<Option<UserId>>,
+}
+
+impl DiscordBot {
+    /// Creates a bot whose conversations are held in the pool.
+    ///
+    /// By default the bot replies to both mentions and direct messages, at most once a second
+    /// within each conversation.
+    pub fn new(pool: ElizaPool) -> DiscordBot {
+        DiscordBot {
+            pool: Mutex::new(pool),
+            cooldown: Mutex::new(Cooldown::new(Duration::from_secs(1))),
+            trigger: Trigger::Both,
+            user: Mutex::new(None),
+        }
+    }
+
+    /// Sets the messages the bot will reply to.
+    pub fn trigger(mut self, trigger: Trigger) -> DiscordBot {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Sets the minimum time between replies within a conversation. Messages that arrive sooner
+    /// are ignored.
+    pub fn cooldown(mut self, interval: Duration) -> DiscordBot {
+        self.cooldown = Mutex::new(Cooldown::new(interval));
+        self
+    }
+
+    //Returns ELIZA's reply to the message, if it warrants one
+    fn reply(&self, msg: &Message) -> Option<String> {
+        if msg.author.bot {
+            return None;
+        }
+
+        let user = (*self.user.lock().unwrap())?; //Not yet connected
+        let direct = msg.guild_id.is_none();
+        if !triggered(self.trigger, direct, msg.mentions_user_id(user)) {
+            return None;
+        }
+
+        let id = session_id(msg.channel_id.get(), msg.author.id.get());
+        if !self.cooldown.lock().unwrap().ready(&id) {
+            debug!("Ignoring message in session {}, it is cooling down", id);
+            return None;
+        }
+
+        let input = strip_mention(&msg.content, user.get());
+        match self.pool.lock().unwrap().respond(&id, &input) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                warn!("Unable to respond in session {}: {}", id, e);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for DiscordBot {
+    async fn ready(&self, _ctx: Context, ready: Ready) {
+        info!("Connected to Discord as {}", ready.user.name);
+        *self.user.lock().unwrap() = Some(ready.user.id);
+    }
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        if let Some(response) = self.reply(&msg) {
+            if let Err(e) = msg.channel_id.say(&ctx.http, response).await {
+                warn!("Failed to send message to {}: {}", msg.channel_id, e);
+            }
+        }
+    }
+}
+
+fn triggered(trigger: Trigger, direct: bool, mentioned: bool) -> bool {
+    match trigger {
+        Trigger::Mentions => !direct && mentioned,
+        Trigger::DirectMessages => direct,
+        Trigger::Both => direct || mentioned,
+    }
+}
+
+//Each user has their own conversation within each channel
+fn session_id(channel: u64, user: u64) -> String {
+    format!("{}:{}", channel, user)
+}
+
+//Mentions may appear as either '<@id>' or '<@!id>', and are meaningless to ELIZA
+fn strip_mention(content: &str, user: u64) -> String {
+    content
+        .replace(&format!("<@{}>", user), "")
+        .replace(&format!("<@!{}>", user), "")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triggers() {
+        assert!(triggered(Trigger::Mentions, false, true));
+        assert!(!triggered(Trigger::Mentions, true, false));
+        assert!(!triggered(Trigger::Mentions, false, false));
+        assert!(triggered(Trigger::DirectMessages, true, false));
+        assert!(!triggered(Trigger::DirectMessages, false, true));
+        assert!(triggered(Trigger::Both, true, false));
+        assert!(triggered(Trigger::Both, false, true));
+        assert!(!triggered(Trigger::Both, false, false));
+    }
+
+    #[test]
+    fn mentions_are_stripped() {
+        assert_eq!("Hello there", strip_mention("<@42> Hello there", 42));
+        assert_eq!("Hello there", strip_mention("Hello there <@!42>", 42));
+        assert_eq!("Hello <@7>", strip_mention("Hello <@7>", 42));
+        assert_eq!("1:42", session_id(1, 42));
+    }
+}
@@ -0,0 +1,14 @@
+//! Ready-made glue for hosting ELIZA on chat platforms.
+//!
+//! Each integration is only available when the crate is built with the feature of the same name.
+//! They all keep a conversation per user in an `ElizaPool`, so the pool's idle timeout and session
+//! limit apply as usual.
+//!
+#[cfg(feature = "discord")]
+mod cooldown;
+#[cfg(feature = "discord")]
+pub mod discord;
+#[cfg(feature = "slack")]
+pub mod slack;
+#[cfg(feature = "telegram")]
+pub mod telegram;
@@ -0,0 +1,297 @@
+//! A Slack app, receiving messages through the [Events API](https://api.slack.com/apis/events-api).
+//!
+//! `SlackApp` is independent of any web framework: hand it the body and signature headers of each
+//! request Slack sends to your events endpoint, and it returns what to do next. ELIZA replies when
+//! the app is mentioned, and to direct messages (subscribe to the `app_mention` and `message.im`
+//! events). Each Slack thread has its own conversation, and ELIZA always replies within it.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::integrations::slack::{SlackAction, SlackApp};
+//! use eliza::pool::ElizaPool;
+//! use eliza::script::Script;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let app = SlackApp::new("signing secret", ElizaPool::new(script));
+//!
+//! # let (timestamp, signature, body) = ("", "", b"");
+//! //For each request, using the 'X-Slack-Request-Timestamp' and 'X-Slack-Signature' headers
+//! match app.handle(timestamp, signature, body) {
+//!     Ok(SlackAction::Challenge(challenge)) => { /* Respond with the challenge as text */ }
+//!     Ok(SlackAction::Reply { channel, thread_ts, text }) => { /* Call 'chat.postMessage' */ }
+//!     Ok(SlackAction::Ignore) => { /* Respond with 200 OK */ }
+//!     Err(e) => { /* Respond with 401 Unauthorized or 400 Bad Request */ }
+//! }
+//! # }
+//! ```
+//!
+use crate::pool::ElizaPool;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::Value;
+use sha2::Sha256;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//Slack recommends rejecting requests older than this, to guard against replay attacks
+const MAX_AGE_SECS: u64 = 5 * 60;
+
+/// What to do in response to a request from Slack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlackAction {
+    /// Slack is verifying the endpoint; respond with the challenge as the body.
+    Challenge(String),
+    /// Post the text to the channel with `chat.postMessage`, within the given thread.
+    Reply {
+        channel: String,
+        thread_ts: String,
+        text: String,
+    },
+    /// Nothing needs to be done beyond acknowledging the request.
+    Ignore,
+}
+
+/// The reasons a request from Slack may be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlackError {
+    /// The request was not signed with the app's signing secret.
+    InvalidSignature,
+    /// The request is too old, and may be a replay of an earlier one.
+    Stale,
+    /// The request body could not be understood.
+    InvalidBody(String),
+}
+
+impl fmt::Display for SlackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlackError::InvalidSignature => write!(f, "The request signature is invalid"),
+            SlackError::Stale => write!(f, "The request timestamp is too old"),
+            SlackError::InvalidBody(e) => write!(f, "The request body is invalid: {}", e),
+        }
+    }
+}
+
+impl Error for SlackError {}
+
+/// Converses with Slack users.
+///
+/// This struct is created by the `new()` method. See its documentation for more.
+pub struct SlackApp {
+    signing_secret: Vec<u8>,
+    pool: Mutex<ElizaPool>,
+    mention: Regex,
+}
+
+impl SlackApp {
+    /// Creates an app whose conversations are held in the pool.
+    ///
+    /// The signing secret can be found on the app's 'Basic Information' page.
+    pub fn new(signing_secret: &str, pool: ElizaPool) -> SlackApp {
+        SlackApp {
+            signing_secret: signing_secret.as_bytes().to_vec(),
+            pool: Mutex::new(pool),
+            mention: Regex::new(r"<@[A-Z0-9]+>").unwrap(), //safe as the pattern is fixed
+        }
+    }
+
+    /// Handles a request to the events endpoint.
+    ///
+    /// Will return `Err` if the request did not come from Slack, or can't be understood.
+    pub fn handle(
+        &self,
+        timestamp: &str,
+        signature: &str,
+        body: &[u8],
+    ) -> Result<SlackAction, SlackError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.verify(timestamp, signature, body, now)?;
+
+        let body: Value =
+            serde_json::from_slice(body).map_err(|e| SlackError::InvalidBody(e.to_string()))?;
+        match body["type"].as_str() {
+            Some("url_verification") => match body["challenge"].as_str() {
+                Some(challenge) => Ok(SlackAction::Challenge(challenge.to_string())),
+                None => Err(SlackError::InvalidBody("Missing challenge".to_string())),
+            },
+            Some("event_callback") => Ok(self.event(&body["event"])),
+            _ => Ok(SlackAction::Ignore),
+        }
+    }
+
+    //Checks the request was signed by Slack, as described at https://api.slack.com/authentication/verifying-requests-from-slack
+    fn verify(
+        &self,
+        timestamp: &str,
+        signature: &str,
+        body: &[u8],
+        now: u64,
+    ) -> Result<(), SlackError> {
+        let sent: u64 = timestamp
+            .parse()
+            .map_err(|_| SlackError::InvalidSignature)?;
+        if now.saturating_sub(sent) > MAX_AGE_SECS {
+            return Err(SlackError::Stale);
+        }
+
+        let expected = signature
+            .strip_prefix("v0=")
+            .and_then(|s| hex::decode(s).ok())
+            .ok_or(SlackError::InvalidSignature)?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.signing_secret)
+            .map_err(|_| SlackError::InvalidSignature)?;
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        mac.verify_slice(&expected)
+            .map_err(|_| SlackError::InvalidSignature)
+    }
+
+    fn event(&self, event: &Value) -> SlackAction {
+        //Channel messages that mention the app arrive as both 'message' and 'app_mention' events,
+        //so only reply to the former in direct messages
+        let relevant = match event["type"].as_str() {
+            Some("app_mention") => true,
+            Some("message") => event["channel_type"] == "im",
+            _ => false,
+        };
+
+        //Ignore edits, deletions and messages from bots (including ourselves!)
+        if !relevant || event.get("subtype").is_some() || event.get("bot_id").is_some() {
+            return SlackAction::Ignore;
+        }
+
+        let (channel, ts, text) = match (
+            event["channel"].as_str(),
+            event["ts"].as_str(),
+            event["text"].as_str(),
+        ) {
+            (Some(channel), Some(ts), Some(text)) => (channel, ts, text),
+            _ => return SlackAction::Ignore,
+        };
+
+        //A message that isn't in a thread starts one
+        let thread_ts = event["thread_ts"].as_str().unwrap_or(ts);
+        let id = format!("{}:{}", channel, thread_ts);
+        let input = self.mention.replace_all(text, "");
+
+        match self.pool.lock().unwrap().respond(&id, input.trim()) {
+            Ok(text) => SlackAction::Reply {
+                channel: channel.to_string(),
+                thread_ts: thread_ts.to_string(),
+                text,
+            },
+            Err(e) => {
+                warn!("Unable to respond in thread {}: {}", id, e);
+                SlackAction::Ignore
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::script::Script;
+    use serde_json::json;
+
+    fn app() -> SlackApp {
+        let script = Script::from_file("scripts/doctor.json").unwrap();
+        SlackApp::new("8f742231b10e8888abcd99yyyzzz85a5", ElizaPool::new(script))
+    }
+
+    //Signs a request in the same way as Slack
+    fn sign(timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"8f742231b10e8888abcd99yyyzzz85a5").unwrap();
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn signatures() {
+        let app = app();
+        let body = b"token=xyzz0WbapA4vBCDEFasx0q6G";
+        let signature = sign("1531420618", body);
+
+        assert_eq!(
+            Ok(()),
+            app.verify("1531420618", &signature, body, 1531420618)
+        );
+        assert_eq!(
+            Err(SlackError::Stale),
+            app.verify("1531420618", &signature, body, 1531420618 + 301)
+        );
+        assert_eq!(
+            Err(SlackError::InvalidSignature),
+            app.verify("1531420618", &signature, b"token=tampered", 1531420618)
+        );
+        assert_eq!(
+            Err(SlackError::InvalidSignature),
+            app.verify("1531420618", "v0=nothex", body, 1531420618)
+        );
+    }
+
+    #[test]
+    fn challenge() {
+        let app = app();
+        let body = json!({ "type": "url_verification", "challenge": "3eZbrw1aBm2r" }).to_string();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+
+        assert_eq!(
+            Ok(SlackAction::Challenge("3eZbrw1aBm2r".to_string())),
+            app.handle(
+                &timestamp,
+                &sign(&timestamp, body.as_bytes()),
+                body.as_bytes()
+            )
+        );
+    }
+
+    #[test]
+    fn threads() {
+        let app = app();
+        let mut mention = json!({
+            "type": "app_mention",
+            "channel": "C1",
+            "ts": "100.1",
+            "text": "<@U0LAN0Z89> Men are all alike.",
+        });
+
+        assert_eq!(
+            SlackAction::Reply {
+                channel: "C1".to_string(),
+                thread_ts: "100.1".to_string(),
+                text: "In what way?".to_string(),
+            },
+            app.event(&mention)
+        );
+
+        let mut direct = json!({
+            "type": "message",
+            "channel_type": "im",
+            "channel": "D1",
+            "ts": "200.1",
+            "thread_ts": "100.1",
+            "text": "Hello",
+        });
+        assert!(matches!(app.event(&direct), SlackAction::Reply { .. }));
+
+        //Replies from bots (including ELIZA herself) are ignored
+        direct["bot_id"] = json!("B1");
+        assert_eq!(SlackAction::Ignore, app.event(&direct));
+
+        //As are channel messages, which are handled by the mention instead
+        mention["type"] = json!("message");
+        assert_eq!(SlackAction::Ignore, app.event(&mention));
+    }
+}
@@ -0,0 +1,125 @@
+//! A Telegram bot, built on [teloxide](https://docs.rs/teloxide).
+//!
+//! Each Telegram chat has its own conversation. The `/start` command begins a fresh conversation
+//! with ELIZA's greeting, and `/stop` ends it with her farewell. Updates may be received by long
+//! polling, which needs no public address, or by webhook.
+//!
+//! ```rust,no_run
+//! # async fn run() {
+//! use eliza::integrations::telegram::{Mode, TelegramBot};
+//! use eliza::pool::ElizaPool;
+//! use eliza::script::Script;
+//! use teloxide::Bot;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let bot = TelegramBot::new(ElizaPool::new(script));
+//! bot.run(Bot::from_env(), Mode::LongPolling).await.unwrap();
+//! # }
+//! ```
+//!
+use crate::pool::ElizaPool;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use teloxide::prelude::*;
+use teloxide::update_listeners::webhooks;
+
+/// How the bot receives updates from Telegram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Repeatedly asks Telegram for new updates.
+    LongPolling,
+    /// Listens on `address` for updates, which Telegram is told to send to `url`.
+    ///
+    /// The url must be public and served over HTTPS, e.g. via a reverse proxy to the address.
+    Webhook { address: SocketAddr, url: String },
+}
+
+/// Converses with Telegram users.
+///
+/// This struct is created by the `new()` method. See its documentation for more.
+#[derive(Clone)]
+pub struct TelegramBot {
+    pool: Arc<Mutex<ElizaPool>>,
+}
+
+impl TelegramBot {
+    /// Creates a bot whose conversations are held in the pool.
+    pub fn new(pool: ElizaPool) -> TelegramBot {
+        TelegramBot {
+            pool: Arc::new(Mutex::new(pool)),
+        }
+    }
+
+    /// Replies to messages until the process is stopped.
+    ///
+    /// Will return `Err` if the webhook can't be registered with Telegram.
+    pub async fn run(self, bot: Bot, mode: Mode) -> Result<(), Box<dyn Error>> {
+        let handler = move |bot: Bot, msg: Message| {
+            let eliza = self.clone();
+            async move {
+                if let Some(text) = msg.text().and_then(|t| eliza.reply(msg.chat.id.0, t)) {
+                    bot.send_message(msg.chat.id, text).await?;
+                }
+                Ok(())
+            }
+        };
+
+        match mode {
+            Mode::LongPolling => {
+                info!("Polling Telegram for updates...");
+                teloxide::repl(bot, handler).await;
+            }
+            Mode::Webhook { address, url } => {
+                info!("Listening for Telegram updates on {}", address);
+                let options = webhooks::Options::new(address, url.parse()?);
+                let listener = webhooks::axum(bot.clone(), options).await?;
+                teloxide::repl_with_listener(bot, handler, listener).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Returns ELIZA's reply to a message in the chat, if it warrants one
+    fn reply(&self, chat: i64, text: &str) -> Option<String> {
+        let id = chat.to_string();
+        let mut pool = self.pool.lock().unwrap();
+
+        //In group chats, commands may be addressed to a specific bot, e.g. '/start@eliza_bot'
+        let command = text.split('@').next().unwrap_or(text).trim();
+        let result = match command {
+            "/start" => pool.start(&id),
+            "/stop" => return pool.end(&id),
+            _ => pool.respond(&id, text),
+        };
+
+        match result {
+            Ok(response) => Some(response),
+            Err(e) => {
+                warn!("Unable to respond in chat {}: {}", id, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::script::Script;
+
+    #[test]
+    fn commands() {
+        let script = Script::from_file("scripts/doctor.json").unwrap();
+        let bot = TelegramBot::new(ElizaPool::new(script));
+
+        assert!(bot.reply(1, "/start").is_some());
+        assert_eq!(
+            Some("In what way?".to_string()),
+            bot.reply(1, "Men are all alike.")
+        );
+        assert!(bot.reply(1, "/stop@eliza_bot").is_some());
+        assert_eq!(None, bot.reply(1, "/stop"));
+    }
+}
@@ -7,13 +7,13 @@
 //! ## The Algorithm
 //!
 //! 1. Attempt to transform each word in the user's input, so the text is easier to process.
-//! 2. Disassemble the input into phrases, and return the first phrase that contains a keyword(s).
+//! 2. Disassemble the input into phrases (see `splitter`), and return the first phrase that contains a keyword(s).
 //! 3. For each keyword found, attempt to match the phrase with an associated decomposition rule.
 //! 4. If the decomposition rule is valid for that phrase, select one of the associated
-//! reassembly rules to form a response based on contextual information from the phrase.
+//!    reassembly rules to form a response based on contextual information from the phrase.
 //! 5. If none of the keyword/rule pairs are true for that phrase, attempt to retrieve a 'memory'
-//! (a response that was assembled earlier in conversation, but was stored instead) or, use a
-//! general 'fallback' statement.
+//!    (a response that was assembled earlier in conversation, but was stored instead) or, use a
+//!    general 'fallback' statement.
 //!
 //! ## References
 //!
@@ -26,560 +26,3926 @@ extern crate serde_derive;
 #[macro_use]
 extern crate log;
 
-mod alphabet;
+//Enters a tracing span, named by the literal, until the end of the enclosing block. Without the
+//`tracing` feature, this does nothing.
+macro_rules! span {
+    ($span:ident = $name:literal $($fields:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let $span = tracing::debug_span!($name $($fields)*).entered();
+    };
+}
+
+//Records the value of a field that was left empty when its span was entered
+macro_rules! record {
+    ($span:ident, $field:literal, $value:expr) => {
+        #[cfg(feature = "tracing")]
+        $span.record($field, $value);
+    };
+}
+
+pub mod alphabet;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+pub mod builder;
+pub mod clock;
+pub mod diff;
+pub mod duel;
+pub mod error;
+pub mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod formats;
+#[cfg(any(feature = "discord", feature = "slack", feature = "telegram"))]
+pub mod integrations;
+mod normalize;
+pub mod patch;
+pub mod pool;
+pub mod sanitize;
 pub mod script; //Making script public so that its documentation may be viewed on doc.rs
+#[cfg(feature = "server")]
+pub mod server;
+pub mod snapshot;
+pub mod splitter;
+pub mod store;
+mod syntax;
+#[cfg(all(
+    feature = "tcp",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub mod tcp;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transcript;
+pub mod typing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "watch")]
+pub mod watch;
 
-use crate::alphabet::Alphabet;
-use crate::script::{Keyword, Reflection, Script, Synonym, Transform};
+/// Embeds a script in the binary, checking it at compile time so that an invalid regex or a
+/// `GOTO` to a missing keyword fails the build. Evaluates to a `&'static Script`.
+///
+/// Only available with the `macros` feature. The path is relative to the `Cargo.toml` of the
+/// crate using the macro:
+///
+/// ```rust,ignore
+/// let mut e = eliza::Eliza::from_scripts(vec![eliza::eliza_script!("scripts/doctor.json").clone()])?;
+/// ```
+#[cfg(feature = "macros")]
+pub use eliza_macros::eliza_script;
+
+use crate::alphabet::CharClass;
+use crate::builder::ElizaBuilder;
+use crate::clock::Clock;
+use crate::error::{ElizaError, RuleWarning};
+use crate::event::{EventLog, Trace};
+use crate::normalize::{Normalizer, Phrase};
+use crate::sanitize::Sanitization;
+use crate::script::{
+    CaseFolding, FallbackOrder, Keyword, Reflection, Rule, Script, Synonym, Transform,
+};
+use crate::snapshot::{Boost, Snapshot};
+use crate::syntax::{inline_alternations, is_goto};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
 use regex::{Captures, Regex};
-use std::collections::{HashMap, VecDeque};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
+use std::io::{Read, Write};
+use std::iter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// An ELIZA instance.
 ///
 /// This struct is created by the `new()` method. See its documentation for more.
+///
+/// `Eliza` is `Send` and `Sync`, so may be shared between threads (e.g. behind a `Mutex` in a web
+/// server). Cloning an instance forks the conversation: the clone carries on from the same memory,
+/// rule usage, affect and random state, but from then on the two conversations are independent.
+/// The scripts themselves are shared between clones rather than copied, so a clone costs only as
+/// much as the conversation so far.
 #[derive(Default)]
 pub struct Eliza {
-    script: Script,
+    script: Arc<Script>,
+    fallback_scripts: Vec<Arc<Script>>,
+    overlaid: bool,
     memory: VecDeque<String>,
-    rule_usage: HashMap<String, usize>,
+    memory_turns: VecDeque<usize>,
+    follow_ups: VecDeque<String>,
+    rule_usage: HashMap<RuleId, usize>,
+    usage_names: OnceLock<HashMap<String, usize>>,
+    affect: HashMap<String, i32>,
+    rng: Mutex<Option<StdRng>>,
+    first_choice: bool,
+    memory_capacity: Option<usize>,
+    recall: Recall,
+    selection: Strategy,
+    forgetful: bool,
+    limits: Limits,
+    turns: usize,
+    last_keyword: Option<String>,
+    history: VecDeque<Exchange>,
+    history_capacity: usize,
+    used: Vec<RuleId>,
+    saved: Saved,
+    undo: Option<Undo>,
+    content_filter: Option<ContentFilter>,
+    sanitization: Sanitization,
+    warnings: Vec<RuleWarning>,
+    greeting_order: GreetingOrder,
+    greetings_said: AtomicUsize,
+    farewells_said: AtomicUsize,
+    clock: Option<Arc<dyn Clock>>,
+    fallback_streak: usize,
+    boosts: HashMap<String, Boost>,
+    event_log: Option<EventLog>,
+}
+
+impl Clone for Eliza {
+    fn clone(&self) -> Eliza {
+        Eliza {
+            script: Arc::clone(&self.script),
+            fallback_scripts: self.fallback_scripts.clone(),
+            overlaid: self.overlaid,
+            memory: self.memory.clone(),
+            memory_turns: self.memory_turns.clone(),
+            follow_ups: self.follow_ups.clone(),
+            rule_usage: self.rule_usage.clone(),
+            usage_names: self.usage_names.clone(),
+            affect: self.affect.clone(),
+            rng: Mutex::new(self.rng.lock().unwrap().clone()),
+            first_choice: self.first_choice,
+            memory_capacity: self.memory_capacity,
+            recall: self.recall,
+            selection: self.selection,
+            forgetful: self.forgetful,
+            limits: self.limits,
+            turns: self.turns,
+            last_keyword: self.last_keyword.clone(),
+            history: self.history.clone(),
+            history_capacity: self.history_capacity,
+            used: self.used.clone(),
+            saved: self.saved.clone(),
+            undo: self.undo.clone(),
+            content_filter: self.content_filter.clone(),
+            sanitization: self.sanitization,
+            warnings: self.warnings.clone(),
+            greeting_order: self.greeting_order,
+            greetings_said: AtomicUsize::new(self.greetings_said.load(Ordering::Relaxed)),
+            farewells_said: AtomicUsize::new(self.farewells_said.load(Ordering::Relaxed)),
+            clock: self.clock.clone(),
+            fallback_streak: self.fallback_streak,
+            boosts: self.boosts.clone(),
+            event_log: self.event_log.clone(),
+        }
+    }
+}
+
+/// How ELIZA chooses between the greetings of her script, and between its farewells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GreetingOrder {
+    /// A greeting at random (see `Eliza::seed()`).
+    #[default]
+    Random,
+    /// Each greeting in turn, in the order they appear in the script, starting again from the
+    /// first once all have been used. Suits kiosks, where the opener should be predictable.
+    Sequential,
+}
+
+/// How ELIZA chooses between the reassembly rules of a decomposition rule that matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// The rule that has been used the least, taking the earliest in the script when several
+    /// have been used equally. This is how Weizenbaum's ELIZA cycles through her responses.
+    #[default]
+    LeastUsed,
+    /// A rule at random (see `Eliza::seed()`).
+    Random,
+}
+
+/// When ELIZA may bring up a response she saved in memory, in place of a fallback.
+///
+/// * **min_turns**: How many turns must pass after a response is saved before it is brought up.
+///   With zero, a response saved while answering an input may be given in answer to that same
+///   input.
+/// * **probability**: The chance, from 0 to 1, that ELIZA uses her memory when she may. When she
+///   doesn't, she gives a fallback and keeps the memory for later.
+/// * **after_fallbacks**: How many fallbacks in a row ELIZA must give before she turns to memory.
+///
+/// By default ELIZA uses her memory whenever she finds no keyword. Weizenbaum's ELIZA was more
+/// reticent, only recalling the user's words at random, so that she didn't seem to be waiting to
+/// spring them - a `probability` of `0.25` is close to the original.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recall {
+    pub min_turns: usize,
+    pub probability: f64,
+    pub after_fallbacks: usize,
+}
+
+impl Default for Recall {
+    fn default() -> Recall {
+        Recall {
+            min_turns: 0,
+            probability: 1.0,
+            after_fallbacks: 0,
+        }
+    }
+}
+
+//How many exchanges ELIZA keeps in her history, unless told otherwise
+const HISTORY_CAPACITY: usize = 10;
+
+//The placeholder in a reassembly rule for ELIZA's previous response
+const LAST_RESPONSE: &str = "{last_response}";
+
+//The placeholder in a fallback for the user's input, and the most of it, in characters, that is
+//echoed unless captures are limited otherwise
+const INPUT: &str = "$input";
+const MAX_INPUT_ECHO: usize = 60;
+
+//What ELIZA needs to take back her most recent response: the exchange itself, any exchange it
+//pushed out of her history, the state it changed as it was before, and the reassembly rules she
+//used
+#[derive(Debug, Clone)]
+struct Undo {
+    exchange: Exchange,
+    evicted: Option<Exchange>,
+    saved: Saved,
+    last_keyword: Option<String>,
+    fallback_streak: usize,
+    used: Vec<RuleId>,
+}
+
+//The parts of ELIZA's state that a turn has changed, as they were before it. Each is only copied
+//when the turn first changes it, so that most turns copy nothing
+#[derive(Debug, Clone, Default)]
+struct Saved {
+    memory: Option<(VecDeque<String>, VecDeque<usize>)>,
+    follow_ups: Option<VecDeque<String>>,
+    affect: Option<HashMap<String, i32>>,
+}
+
+//A host's own check of the user's input, which returns true if ELIZA should refuse the input
+pub(crate) type ContentFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+//Identifies a reassembly rule by the layer of its script, the index of its keyword and
+//decomposition rule, and its own index
+type RuleId = (usize, usize, usize, usize);
+
+//The longest captures and responses that ELIZA will give, in characters, beyond which they are
+//truncated with an ellipsis, and the most phrases and keywords she will consider in an input
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    capture: Option<usize>,
+    response: Option<usize>,
+    phrases: usize,
+    keystack: usize,
+}
+
+//How many phrases of an input ELIZA searches for keywords, unless told otherwise
+const MAX_PHRASES: usize = 100;
+
+//How many keywords ELIZA keeps on the keystack, unless told otherwise
+const MAX_KEYSTACK: usize = 50;
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            capture: None,
+            response: None,
+            phrases: MAX_PHRASES,
+            keystack: MAX_KEYSTACK,
+        }
+    }
+}
+
+/// Where a response came from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseSource {
+    /// Assembled from a keyword's decomposition and reassembly rules.
+    Keyword,
+    /// Assembled earlier in the conversation, but saved to memory for later use.
+    Memory,
+    /// A follow-up question, queued by the rule that produced the previous response.
+    FollowUp,
+    /// A general fallback statement, or a response from the rules of the `NONE` keyword (see
+    /// `script::Keyword`), as no keyword rules could be applied.
+    Fallback,
+    /// A refusal, as the input contained a banned term or was refused by the content filter.
+    Filtered,
+}
+
+/// A response from ELIZA, along with details of how it was found.
+///
+/// * **text**: The response itself.
+/// * **source**: Whether the response came from a keyword rule, memory, a follow-up or a
+///   fallback.
+/// * **keyword**: The keyword that produced the response (if any).
+/// * **rule**: The decomposition rule that produced the response (if any).
+/// * **confidence**: A rough measure, from `0.0` to `1.0`, of how well ELIZA understood the
+///   input. Fallbacks score zero, and memories and follow-ups score `0.25`, while responses from
+///   a keyword score at least `0.5`, more for higher ranked keywords and for decomposition rules
+///   that match more of the input with their own words. Host applications may use it to decide
+///   when to hand the conversation over to a person or another engine.
+/// * **warnings**: The rules that ELIZA had to pass over while responding, because of mistakes in
+///   the script, such as a reassembly rule referring to a capture that doesn't exist. ELIZA
+///   carries on regardless, so these are the only sign of such mistakes besides the log.
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub text: String,
+    pub source: ResponseSource,
+    pub keyword: Option<String>,
+    pub rule: Option<String>,
+    pub confidence: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<RuleWarning>,
+}
+
+impl Response {
+    fn from(text: String, source: ResponseSource) -> Response {
+        let confidence = match source {
+            ResponseSource::Keyword => 0.5,
+            ResponseSource::Memory | ResponseSource::FollowUp => 0.25,
+            ResponseSource::Fallback | ResponseSource::Filtered => 0.0,
+        };
+
+        Response {
+            text,
+            source,
+            keyword: None,
+            rule: None,
+            confidence,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// An input and ELIZA's response to it, as kept in her history (see `Eliza::history()`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    pub input: String,
+    pub response: String,
+}
+
+/// A response that ELIZA could give, as found by `Eliza::respond_candidates()`.
+///
+/// * **text**: The response itself.
+/// * **keyword**: The keyword whose rule produced the response.
+/// * **rule**: The decomposition rule that produced the response.
+/// * **usage**: How many times the reassembly rule has been used. ELIZA prefers the least used.
+/// * **memorise**: Whether ELIZA would save the response to memory, rather than give it now.
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub text: String,
+    pub keyword: String,
+    pub rule: String,
+    pub usage: usize,
+    pub memorise: bool,
 }
 
 impl Eliza {
     /// Initialise ELIZA with a script.
     ///
-    /// Will return `Err` if the script at the specified location is invalid.
+    /// Will return `Err` if the script at the specified location is invalid. Only available with
+    /// the `std-fs` feature, which is enabled by default, and not when targeting
+    /// `wasm32-unknown-unknown`, which has no file system.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
     pub fn from_file(location: &str) -> Result<Eliza, Box<dyn Error>> {
-        let e = Eliza {
-            script: {
-                info!("Loading {}", location);
-                Script::from_file(location)?
-            },
-            memory: VecDeque::new(),
-            rule_usage: HashMap::new(),
-        };
+        info!("Loading {}", location);
+        Ok(Eliza::from_shared(Arc::new(Script::from_file(location)?)))
+    }
 
-        Ok(e)
+    /// Initialise ELIZA with a script, loaded without blocking the async runtime.
+    ///
+    /// Only available with the `async` feature. Will return `Err` if the script at the specified
+    /// location is invalid.
+    #[cfg(feature = "async")]
+    pub async fn from_file_async(location: &str) -> Result<Eliza, Box<dyn Error + Send + Sync>> {
+        info!("Loading {}", location);
+        let script = Script::from_file_async(location).await?;
+        Ok(Eliza::from_shared(Arc::new(script)))
     }
 
-    pub fn from_str(script: &str) -> Result<Eliza, Box<dyn Error>> {
-        let e = Eliza {
-            script: {
-                info!("Loading script...");
-                Script::from_str(script)?
-            },
+    /// Initialise ELIZA with a script read from any reader.
+    ///
+    /// Will return `Err` if the script could not be read, or is invalid.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Eliza, Box<dyn Error>> {
+        info!("Loading script from reader...");
+        Ok(Eliza::from_shared(Arc::new(Script::from_reader(reader)?)))
+    }
+
+    /// Initialise ELIZA with a script held in memory as bytes.
+    ///
+    /// Will return `Err` if the script is invalid. See `Script::from_slice()` for more.
+    pub fn from_slice(bytes: &[u8]) -> Result<Eliza, Box<dyn Error>> {
+        info!("Loading script from bytes...");
+        Ok(Eliza::from_shared(Arc::new(Script::from_slice(bytes)?)))
+    }
+
+    /// Initialise ELIZA with the bundled DOCTOR script.
+    ///
+    /// Only available with the `builtin-doctor` feature. See `Script::doctor()` for more.
+    #[cfg(feature = "builtin-doctor")]
+    pub fn doctor() -> Eliza {
+        Eliza::from_shared(Arc::new(Script::doctor()))
+    }
+
+    /// Returns a builder, for when ELIZA needs configuring beyond her script (see
+    /// `builder::ElizaBuilder`).
+    pub fn builder() -> ElizaBuilder {
+        ElizaBuilder::default()
+    }
+
+    /// Initialise ELIZA with a script that is shared with other instances.
+    ///
+    /// Loading a script once and sharing it is much cheaper than loading it for every
+    /// conversation, which matters when hosting many conversations at once.
+    pub fn from_shared(script: Arc<Script>) -> Eliza {
+        Eliza {
+            script,
+            fallback_scripts: Vec::new(),
+            overlaid: false,
             memory: VecDeque::new(),
+            memory_turns: VecDeque::new(),
+            follow_ups: VecDeque::new(),
             rule_usage: HashMap::new(),
-        };
+            usage_names: OnceLock::new(),
+            affect: HashMap::new(),
+            rng: Mutex::new(None),
+            first_choice: false,
+            memory_capacity: None,
+            recall: Recall::default(),
+            selection: Strategy::LeastUsed,
+            forgetful: false,
+            limits: Limits::default(),
+            turns: 0,
+            last_keyword: None,
+            history: VecDeque::new(),
+            history_capacity: HISTORY_CAPACITY,
+            used: Vec::new(),
+            saved: Saved::default(),
+            undo: None,
+            content_filter: None,
+            sanitization: Sanitization::Strip,
+            warnings: Vec::new(),
+            greeting_order: GreetingOrder::Random,
+            greetings_said: AtomicUsize::new(0),
+            farewells_said: AtomicUsize::new(0),
+            clock: None,
+            fallback_streak: 0,
+            boosts: HashMap::new(),
+            event_log: None,
+        }
+    }
+
+    /// Initialise ELIZA with an ordered chain of scripts.
+    ///
+    /// The first script is the primary script, used for greetings, farewells and fallbacks. If it
+    /// can't produce a response for some input, each of the following scripts is tried in turn
+    /// before ELIZA resorts to memory or a fallback statement.
+    ///
+    /// Will return `Err` if no locations are given, or any of the scripts are invalid. Not
+    /// available without the `std-fs` feature, or when targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn from_files(locations: &[&str]) -> Result<Eliza, Box<dyn Error>> {
+        let mut scripts = Vec::new();
+        for location in locations {
+            info!("Loading {}", location);
+            scripts.push(Script::from_file(location)?);
+        }
+
+        Eliza::from_scripts(scripts)
+    }
+
+    /// Initialise ELIZA with an ordered chain of already loaded scripts.
+    ///
+    /// See `from_files()` for how the chain is used. Will return `Err` if `scripts` is empty.
+    pub fn from_scripts(scripts: Vec<Script>) -> Result<Eliza, Box<dyn Error>> {
+        let mut scripts = scripts.into_iter().map(|mut script| {
+            script.resolve_gotos();
+            script.compile_rules();
+            Arc::new(script)
+        });
+        let mut e = Eliza::from_shared(scripts.next().ok_or("At least one script is required")?);
+        e.fallback_scripts = scripts.collect();
 
         Ok(e)
     }
 
-    /// Randomly selects a greeting statement from the `greetings` list in the script.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(script: &str) -> Result<Eliza, Box<dyn Error>> {
+        info!("Loading script...");
+        Ok(Eliza::from_shared(Arc::new(Script::from_str(script)?)))
+    }
+
+    /// Selects a greeting statement from the `greetings` list in the script, at random unless
+    /// told otherwise (see `GreetingOrder`). Any mention of the time of day is filled in from
+    /// ELIZA's clock (see `clock`).
     ///
     pub fn greet(&self) -> String {
-        match self.script.rand_greet() {
-            Some(greet) => greet.to_string(),
-            None => {
-                warn!("Eliza has no greetings to use");
-                String::from("Hello, I am Eliza.") //If greetings are empty, have default
-            }
-        }
+        self.timely(greeting(
+            self.choose_opener(self.lines(|s| &s.greetings), &self.greetings_said),
+        ))
+    }
+
+    /// Selects the n-th greeting statement of the script, counting from zero. Greetings are
+    /// counted round again from the first once n passes the last.
+    pub fn greet_nth(&self, n: usize) -> String {
+        self.timely(greeting(nth(self.lines(|s| &s.greetings), n)))
     }
 
-    /// Randomly selects a farewell statement from the `farewell` list in the script.
+    /// Selects a farewell statement from the `farewell` list in the script, at random unless told
+    /// otherwise (see `GreetingOrder`). Any mention of the time of day is filled in, as it is for
+    /// greetings.
     ///
     pub fn farewell(&self) -> String {
-        match self.script.rand_farewell() {
-            Some(farwell) => farwell.to_string(),
+        self.timely(farewell(
+            self.choose_opener(self.lines(|s| &s.farewells), &self.farewells_said),
+        ))
+    }
+
+    /// Selects the n-th farewell statement of the script, counting from zero. Farewells are
+    /// counted round again from the first once n passes the last.
+    pub fn farewell_nth(&self, n: usize) -> String {
+        self.timely(farewell(nth(self.lines(|s| &s.farewells), n)))
+    }
+
+    /// Sets the clock that ELIZA reads the time of day from (see `clock`). By default she uses
+    /// the system clock, in UTC, except when targeting `wasm32-unknown-unknown`, which has no
+    /// system clock - there, the time is only mentioned if a clock is set. Clones of this
+    /// instance share the clock.
+    pub fn clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Some(Arc::new(clock));
+    }
+
+    //Fills in any mention of the time of day, only reading the clock if there is one
+    fn timely(&self, text: String) -> String {
+        if !text.contains('{') {
+            return text;
+        }
+
+        let now = match &self.clock {
+            Some(clock) => clock.now(),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            None => clock::SystemClock::default().now(),
+            #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+            None => return text,
+        };
+        now.fill(&text)
+    }
+
+    /// Prompts a user who has gone quiet, for host applications to call when no input has
+    /// arrived for a while.
+    ///
+    /// ELIZA brings up something she has saved in memory if she can, as the user's own words are
+    /// the likeliest to draw them back in. Otherwise she selects one of the `nudges` in the
+    /// script at random. A nudge is not a response, so the conversation's turns are unchanged.
+    ///
+    /// The `min_turns` of ELIZA's `Recall` applies to nudges too, counting from her last
+    /// response. Its `probability` and `after_fallbacks` don't, as the user has said nothing for
+    /// her to fall back on.
+    pub fn nudge(&mut self) -> String {
+        self.undo = None;
+        if self.memory_due(self.turns) {
+            if let Some(mem) = self.pop_memory() {
+                info!("Nudging with memory");
+                return mem;
+            }
+        }
+
+        match self.choose(&self.settings(0).nudges) {
+            Some(nudge) => nudge.to_string(),
             None => {
-                warn!("Eliza has no farewells to use");
-                String::from("Goodbye.") //If farewells are empty, have default
+                warn!("Eliza has no nudges to use");
+                String::from("Are you still there?") //If nudges are empty, have default
             }
         }
     }
 
     /// Responds to a given input string based on the internal ELIZA script.
     ///
+    /// Responding is pure computation - it performs no I/O and never blocks - so it is safe to
+    /// call directly from async code without resorting to `spawn_blocking`.
     pub fn respond(&mut self, input: &str) -> String {
-        //Convert the input to lowercase and transform words before populating the keystack
-        let mut response: Option<String> = None;
-        let phrases = get_phrases(&transform(&input.to_lowercase(), &self.script.transforms));
-        let (active_phrase, mut keystack) = populate_keystack(phrases, &self.script.keywords);
+        self.respond_detailed(input).text
+    }
 
-        if let Some(phrase) = active_phrase {
-            response = self.get_response(&phrase, &mut keystack);
+    /// Responds to a given input string, yielding the response a word at a time so that a
+    /// frontend can render it as though it were being typed.
+    ///
+    /// Each word keeps the space that follows it, so joining the words gives back the response.
+    pub fn respond_stream(&mut self, input: &str) -> impl Iterator<Item = String> {
+        let response = self.respond(input);
+        let words: Vec<String> = response.split_inclusive(' ').map(String::from).collect();
+        words.into_iter()
+    }
+
+    /// Responds to a given input string, unless a mistake in the script got in the way.
+    ///
+    /// Where `respond()` passes over a rule it can't use and carries on, this returns the first
+    /// such rule as an `ElizaError::Rule`, so that script bugs can be caught in production. The
+    /// conversation carries on as it would have with `respond()` either way. Hosts wanting the
+    /// response as well as any mistakes may use `respond_detailed()`, which reports both.
+    pub fn try_respond(&mut self, input: &str) -> Result<Response, ElizaError> {
+        let mut response = self.respond_detailed(input);
+        match response.warnings.is_empty() {
+            true => Ok(response),
+            false => Err(ElizaError::Rule(response.warnings.remove(0))),
         }
+    }
 
-        if let Some(res) = response {
-            res
-        } else if let Some(mem) = self.memory.pop_front() {
-            //Attempt to use something in memory, otherwise use fallback trick
-            info!("Using memory");
-            mem
-        } else {
-            info!("Using fallback statement");
-            self.fallback()
+    /// Responds to a given input string, reporting how the response was found.
+    ///
+    /// See `Response` for the details that are reported.
+    pub fn respond_detailed(&mut self, input: &str) -> Response {
+        span!(
+            span = "respond",
+            source = tracing::field::Empty,
+            keyword = tracing::field::Empty
+        );
+        self.saved = Saved::default();
+        self.used.clear();
+        self.warnings.clear();
+
+        let mut trace = self.event_log.as_ref().map(|_| Trace::default());
+        let mut response = self.find_response(input, trace.as_mut());
+        response.warnings = std::mem::take(&mut self.warnings);
+        let fallback_streak = self.fallback_streak;
+        match response.source {
+            ResponseSource::Keyword => self.fallback_streak = 0,
+            ResponseSource::Fallback => self.fallback_streak += 1,
+            _ => {}
         }
+        self.turns += 1;
+        let exchange = Exchange {
+            input: input.to_string(),
+            response: response.text.clone(),
+        };
+        //With no history kept, the exchange is never pushed, so it can't be evicted and restored
+        let mut evicted = None;
+        if self.history_capacity > 0 {
+            self.history.push_back(exchange.clone());
+            while self.history.len() > self.history_capacity {
+                evicted = self.history.pop_front();
+            }
+        }
+        self.undo = Some(Undo {
+            exchange,
+            evicted,
+            saved: std::mem::take(&mut self.saved),
+            last_keyword: std::mem::replace(&mut self.last_keyword, response.keyword.clone()),
+            fallback_streak,
+            used: std::mem::take(&mut self.used),
+        });
+
+        if let (Some(log), Some(trace)) = (&self.event_log, trace) {
+            log.write(&trace.into_event(self.turns, input, response.clone()));
+        }
+
+        record!(span, "source", tracing::field::debug(response.source));
+        record!(span, "keyword", response.keyword.as_deref());
+        response
     }
 
-    fn fallback(&self) -> String {
-        match self.script.rand_fallback() {
-            Some(fallback) => fallback.to_string(),
-            None => {
-                warn!("Eliza has no fallbacks to use");
-                String::from("Go on.") //A fallback for the fallback - har har
+    /// Takes back ELIZA's most recent response, as though the input had never been given,
+    /// returning the exchange that was undone. Any memory she used or saved, any follow-up
+    /// question she asked or queued, the usage of the reassembly rules she chose, her affect, and
+    /// the conversation's turns and history are all restored to how they were before she
+    /// responded.
+    ///
+    /// Only the most recent response can be undone, so a second call returns `None`, as does a
+    /// call before ELIZA has responded. Nudging ELIZA, swapping her script or restoring a
+    /// snapshot also prevents the response from being undone. Random choices are not taken back.
+    pub fn undo(&mut self) -> Option<Exchange> {
+        let undo = self.undo.take()?;
+        if let Some((memory, memory_turns)) = undo.saved.memory {
+            self.memory = memory;
+            self.memory_turns = memory_turns;
+        }
+        if let Some(follow_ups) = undo.saved.follow_ups {
+            self.follow_ups = follow_ups;
+        }
+        if let Some(affect) = undo.saved.affect {
+            self.affect = affect;
+        }
+        self.last_keyword = undo.last_keyword;
+        self.fallback_streak = undo.fallback_streak;
+        self.turns -= 1;
+
+        self.usage_names.take();
+        for id in undo.used {
+            if let Some(usage) = self.rule_usage.get_mut(&id) {
+                *usage -= 1;
+                if *usage == 0 {
+                    self.rule_usage.remove(&id);
+                }
             }
         }
+
+        if self.history_capacity > 0 {
+            self.history.pop_back();
+        }
+        if let Some(evicted) = undo.evicted {
+            self.history.push_front(evicted);
+        }
+
+        Some(undo.exchange)
     }
 
-    fn get_response(&mut self, phrase: &str, keystack: &mut VecDeque<Keyword>) -> Option<String> {
-        let mut response: Option<String> = None;
+    /// Writes the decisions ELIZA makes each turn to the sink, as one line of `json` per turn
+    /// (see `event::TurnEvent`). Clones of this instance will write to the same sink.
+    ///
+    /// Failures to write are logged, but don't interrupt the conversation.
+    pub fn event_log<W: Write + Send + 'static>(&mut self, sink: W) {
+        self.event_log = Some(EventLog::new(sink));
+    }
 
-        //Search for a response while the keystack is not empty
-        'search: while response.is_none() && !keystack.is_empty() {
-            let next = keystack.pop_front().unwrap(); //safe due to prior check
-
-            //For each rule set, attempt to decompose phrase then reassemble a response
-            'decompostion: for r in next.rules {
-                //Get all regex permutations of the decomposition rule (dependent upon synonyms)
-                let regexes = permutations(&r.decomposition_rule, &self.script.synonyms);
-                for re in regexes {
-                    if let Some(cap) = re.captures(phrase) {
-                        //A match was found: find the best reassembly rule to use
-                        if let Some(assem) =
-                            self.get_reassembly(&r.decomposition_rule, &r.reassembly_rules)
-                        {
-                            if let Some(goto) = is_goto(&assem) {
-                                //The best rule was a goto, push associated key entry to stack
-                                if let Some(entry) =
-                                    self.script.keywords.iter().find(|a| a.key == goto)
-                                {
-                                    //Push to front of keystack and skip to it
-                                    info!(
-                                        "Using GOTO '{}' for key '{}' and decomp rule '{}'",
-                                        goto, next.key, r.decomposition_rule
-                                    );
-                                    keystack.push_front(entry.clone());
-                                    break 'decompostion;
-                                } else {
-                                    error!("No such keyword: {}", goto);
-                                    continue; //Something wrong with this GOTO
-                                }
-                            }
+    /// Refuses any input for which the filter returns true, as though it contained one of the
+    /// script's `banned` terms, so that hosts can plug in their own moderation. The filter is
+    /// given the input once it has been sanitized (see `Sanitization`), so that control characters
+    /// can't hide a term from it, but before ELIZA has changed it in any other way. Clones of this
+    /// instance share the filter.
+    pub fn content_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.content_filter = Some(Arc::new(filter));
+    }
 
-                            //Attempt to assemble given the capture groups
-                            response = assemble(&assem, &cap, &self.script.reflections);
-                            if response.is_some() {
-                                if r.memorise {
-                                    //We'll save this response for later...
-                                    info!("Saving response that matched key '{}' and decomp rule '{}'", next.key, r.decomposition_rule);
-                                    self.memory.push_back(response.unwrap());
-                                    response = None;
-                                } else {
-                                    //We found a response, exit
-                                    info!(
-                                        "Found response for key '{}' and decomp rule '{}'",
-                                        next.key, r.decomposition_rule
-                                    );
-                                    break 'search;
-                                }
-                            }
+    //Finds the response to the input, noting the decisions made along the way in the trace
+    fn find_response(&mut self, input: &str, mut trace: Option<&mut Trace>) -> Response {
+        //Control characters are dealt with first, so they can't hide a banned term by splitting it
+        let sanitized = self.sanitization.apply(input);
+        let input = sanitized.as_ref();
+
+        //Banned input is refused before any keyword is searched for, so it is never echoed back
+        if self.is_banned(input) {
+            info!("Refusing banned input");
+            return Response::from(self.banned_response(), ResponseSource::Filtered);
+        }
+
+        //A follow-up question is asked whatever the input
+        if !self.follow_ups.is_empty() {
+            self.save_follow_ups();
+        }
+        if let Some(question) = self.follow_ups.pop_front() {
+            info!("Asking follow-up question");
+            return Response::from(question, ResponseSource::FollowUp);
+        }
+
+        //Work down the chain of scripts until one of them can respond
+        for layer in 0..=self.fallback_scripts.len() {
+            let (active_phrase, mut keystack) = self.keystack(layer, input);
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.transformed = Some(self.transformed(layer, input));
+                trace.phrase = active_phrase.clone();
+                let keywords = &self.layer(layer).keywords;
+                trace.keystack = keystack.iter().map(|&k| keywords[k].key.clone()).collect();
+                trace.layer = layer;
+            }
+
+            if let Some(phrase) = active_phrase {
+                if let Some(res) = self.get_response(layer, &phrase, &mut keystack) {
+                    return res;
+                }
+            }
+        }
+
+        //Attempt to use something in memory, otherwise use fallback trick
+        if let Some(mem) = self.recall() {
+            info!("Using memory");
+            Response::from(mem, ResponseSource::Memory)
+        } else if let Some(res) = self.none_response(input) {
+            info!("Using rules of the NONE keyword");
+            res
+        } else {
+            info!("Using fallback statement");
+            Response::from(self.fallback(input), ResponseSource::Fallback)
+        }
+    }
+
+    /// Returns every response that ELIZA could give to the input, in the order she would consider
+    /// them, without using any of them.
+    ///
+    /// Candidates come from the rules of every keyword in the input, highest ranked keyword
+    /// first, and a `GOTO` is followed in place of the reassembly rule. Within each decomposition
+    /// rule, candidates are ordered least used first. ELIZA's memory and fallbacks are not
+    /// included, so an empty list means she would resort to one of them. Nor is any follow-up
+    /// question, which ELIZA would ask in place of all of these.
+    pub fn respond_candidates(&self, input: &str) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        for layer in 0..=self.fallback_scripts.len() {
+            let (phrase, mut keystack) = self.keystack(layer, input);
+            let phrase = match phrase {
+                Some(phrase) => phrase,
+                None => continue,
+            };
+
+            let script = self.layer(layer);
+            let settings = self.settings(layer);
+            let normalizer = settings.normalizer();
+            let normalized = normalizer.phrase(&phrase);
+            let mut visited: HashSet<usize> = HashSet::new();
+            while let Some(index) = keystack.pop_front() {
+                //A keyword may be reached more than once through GOTOs
+                if !visited.insert(index) {
+                    continue;
+                }
+
+                let k = &script.keywords[index];
+                let mut gotos: Vec<usize> = Vec::new();
+                for (i, r) in k.rules.iter().enumerate() {
+                    let viable = (!r.memorise || !self.forgetful)
+                        && r.conditions
+                            .iter()
+                            .all(|c| c.holds(self.affect(&c.variable)));
+                    let decomposition = match script.decomposition(&r.decomposition_rule) {
+                        Some(decomposition) if viable => decomposition,
+                        _ => continue,
+                    };
+                    let cap = match decomposition.regex.captures(&normalized.text) {
+                        Some(cap) => cap,
+                        None => continue,
+                    };
+
+                    let word = decomposition.word(&cap, normalizer);
+                    let captures = decomposition.captures(&normalized, &phrase, &cap);
+                    let mut found: Vec<Candidate> = Vec::new();
+                    let reassembly = r.reassembly_rules.iter().enumerate();
+                    for (a, assem) in reassembly.filter(|&(a, _)| self.in_context(r, a)) {
+                        if let Some(goto) = is_goto(assem) {
+                            gotos.extend(script.goto_index(&goto));
+                        } else if let Some(text) = substitute_synonym(
+                            assem,
+                            &r.decomposition_rule,
+                            word.as_deref(),
+                            &settings.word_chars(),
+                            normalizer,
+                        )
+                        .and_then(|assem| {
+                            assemble(
+                                &assem,
+                                &captures,
+                                &settings.reflections,
+                                normalizer,
+                                self.limits,
+                            )
+                        })
+                        .and_then(|text| recall(text, self.last_response()))
+                        {
+                            let id = (layer, index, i, a);
+                            found.push(Candidate {
+                                text,
+                                keyword: k.key.clone(),
+                                rule: r.decomposition_rule.clone(),
+                                usage: self.rule_usage.get(&id).copied().unwrap_or(0),
+                                memorise: r.memorise,
+                            });
                         }
                     }
+
+                    found.sort_by_key(|c| c.usage);
+                    candidates.extend(found);
+                }
+
+                //Keywords named by a GOTO are tried before the rest of the keystack
+                for goto in gotos.into_iter().rev() {
+                    keystack.push_front(goto);
                 }
             }
         }
 
-        response
+        candidates
     }
 
-    fn get_reassembly(&mut self, id: &str, rules: &[String]) -> Option<String> {
-        let mut best_rule: Option<String> = None;
-        let mut count: Option<usize> = None;
+    /// Swaps the primary script mid-conversation.
+    ///
+    /// Memory is kept, as is the usage count of any reassembly rule that still exists in one of
+    /// the scripts - usage for rules that no longer exist is cleared.
+    ///
+    /// Will return `Err` (and keep the current script) if the new script is invalid.
+    pub fn set_script(&mut self, mut script: Script) -> Result<(), Box<dyn Error>> {
+        script.check()?;
+        script.resolve_gotos();
+        script.compile_rules();
 
-        //rules are prepended with an id to make them unique within that domain
-        //(e.g. deconstruction rules could share similar looking assembly rules)
-        for rule in rules {
-            let key = String::from(id) + rule;
-            match self.rule_usage.contains_key(&key) {
-                true => {
-                    //If it has already been used, get its usage count
-                    let usage = self.rule_usage[&key];
-                    if let Some(c) = count {
-                        if usage < c {
-                            //The usage is less than the running total
-                            best_rule = Some(rule.clone());
-                            count = Some(usage);
-                        }
+        //Rules are counted by their place in the script, which the new script may change
+        let usage = self.rule_usage().clone();
+        self.script = Arc::new(script);
+        self.overlaid = false;
+        self.set_usage(&usage);
+        self.undo = None;
+        Ok(())
+    }
+
+    /// Teaches ELIZA a keyword mid-conversation, for bots that learn triggers of their own. The
+    /// keyword replaces any keyword in the primary script with the same key, whose rule usage is
+    /// forgotten.
+    ///
+    /// The script is copied rather than changed, so instances sharing it are unaffected (see
+    /// `Eliza::from_shared()`).
+    ///
+    /// Will return `Err` (and keep the current script) if the keyword has any of the mistakes
+    /// that `Script::validate()` checks for, such as an invalid regex or a `GOTO` to a keyword
+    /// that doesn't exist.
+    pub fn add_keyword(&mut self, keyword: Keyword) -> Result<(), Box<dyn Error>> {
+        let mut script = Script::clone(&self.script);
+        let replaced = script.keywords.iter().position(|k| k.key == keyword.key);
+        if let Some(i) = replaced {
+            script.keywords.remove(i);
+        }
+
+        let index = script.keywords.len();
+        script.keywords.push(keyword);
+        if let Some(problem) = script.keyword_problems(&[index]).into_iter().next() {
+            return Err(problem.into());
+        }
+
+        self.replace_script(script, replaced);
+        Ok(())
+    }
+
+    /// Removes a keyword from the primary script mid-conversation, returning it, and forgets the
+    /// usage of its rules. Like `add_keyword()`, this doesn't affect instances sharing the script.
+    ///
+    /// Will return `Err` if the script has no such keyword, or another keyword has a `GOTO` to
+    /// it.
+    pub fn remove_keyword(&mut self, key: &str) -> Result<Keyword, Box<dyn Error>> {
+        let mut script = Script::clone(&self.script);
+        let index = script
+            .keywords
+            .iter()
+            .position(|k| k.key == key)
+            .ok_or_else(|| format!("No such keyword: '{}'", key))?;
+        let keyword = script.keywords.remove(index);
+        if script.is_goto_target(key) {
+            return Err(format!("Keyword '{}' is named by a GOTO", key).into());
+        }
+
+        self.replace_script(script, Some(index));
+        Ok(keyword)
+    }
+
+    //Swaps the primary script for a copy with a keyword added or removed. The usage of the removed
+    //keyword's rules is forgotten, and the keywords after it are counted from their new places.
+    fn replace_script(&mut self, mut script: Script, removed: Option<usize>) {
+        script.resolve_gotos();
+        script.compile_rules();
+        self.script = Arc::new(script);
+
+        if let Some(removed) = removed {
+            self.usage_names.take();
+            let usage = std::mem::take(&mut self.rule_usage);
+            self.rule_usage = usage
+                .into_iter()
+                .filter_map(|((layer, k, r, a), n)| {
+                    if layer > 0 || k < removed {
+                        Some(((layer, k, r, a), n))
+                    } else if k == removed {
+                        None
                     } else {
-                        //The count has yet to be updated, this is the best usage so far
-                        best_rule = Some(rule.clone());
-                        count = Some(usage);
+                        Some(((layer, k - 1, r, a), n))
+                    }
+                })
+                .collect();
+        }
+        self.undo = None;
+    }
+
+    //Puts a script of the conversation's own in front of the script it shares with others (see
+    //`pool::ElizaPool::personalize()`). The overlay holds little more than keywords, so the
+    //input is read with the shared script's settings. Rule usage carries over, as it does for
+    //`set_script()`.
+    pub(crate) fn overlay(&mut self, overlay: Script, shared: Arc<Script>) {
+        let usage = self.rule_usage().clone();
+        self.script = Arc::new(overlay);
+        self.fallback_scripts = vec![shared];
+        self.overlaid = true;
+        self.set_usage(&usage);
+        self.undo = None;
+    }
+
+    /// Branches the conversation, so that tools can explore what ELIZA would say were the user to
+    /// answer differently, without disturbing the conversation itself.
+    ///
+    /// The fork starts with a copy of ELIZA's memory, rule usage, history, affect and random
+    /// state, so it responds just as the original would. The scripts are shared rather than
+    /// copied, so forking costs no more than the conversation's own state. Unlike a clone, the
+    /// fork doesn't write to the original's event log (see `Eliza::event_log()`), which should
+    /// only record what the user was actually told.
+    pub fn fork(&self) -> Eliza {
+        let mut fork = self.clone();
+        fork.event_log = None;
+        fork
+    }
+
+    /// Captures the state of the conversation, which may be saved so that the conversation can
+    /// survive a restart (see `snapshot::Snapshot`). Like `fork()`, it doesn't include the
+    /// scripts.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: snapshot::VERSION,
+            memory: self.memory.iter().cloned().collect(),
+            memory_turns: self.memory_turns.iter().copied().collect(),
+            follow_ups: self.follow_ups.iter().cloned().collect(),
+            rule_usage: self.rule_usage().clone(),
+            affect: self.affect.clone(),
+            turns: self.turns,
+            last_keyword: self.last_keyword.clone(),
+            history: self.history.iter().cloned().collect(),
+            fallback_streak: self.fallback_streak,
+            boosts: self.boosts.clone(),
+        }
+    }
+
+    /// Carries on a conversation from a snapshot, replacing the state of the current one. The
+    /// snapshot is migrated from the version it was saved in first (see `Snapshot::migrate()`).
+    ///
+    /// The usage of any reassembly rule that isn't in the scripts is ignored, as it is by
+    /// `set_script()`.
+    ///
+    /// Will return `Err` (and keep the current state) if the snapshot was saved in a newer
+    /// version of the format than this release understands.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), Box<dyn Error>> {
+        self.restore_migrated(snapshot.migrate()?);
+        Ok(())
+    }
+
+    //Restores a snapshot that is already in the current version of the format
+    pub(crate) fn restore_migrated(&mut self, snapshot: Snapshot) {
+        self.memory_turns = snapshot.memory_turns.into();
+        while self.memory_turns.len() > snapshot.memory.len() {
+            self.memory_turns.pop_front();
+        }
+        self.memory = snapshot.memory.into();
+        if let Some(capacity) = self.memory_capacity {
+            while self.memory.len() > capacity {
+                self.pop_memory();
+            }
+        }
+        self.follow_ups = snapshot.follow_ups.into();
+        self.set_usage(&snapshot.rule_usage);
+        self.affect = snapshot.affect;
+        self.boosts = snapshot.boosts;
+        self.turns = snapshot.turns;
+        self.last_keyword = snapshot.last_keyword;
+        self.history = snapshot.history.into();
+        self.fallback_streak = snapshot.fallback_streak;
+        while self.history.len() > self.history_capacity {
+            self.history.pop_front();
+        }
+        self.undo = None;
+    }
+
+    //Counts the reassembly rules that usage counts from `rule_usage()` refer to, forgetting the
+    //usage of any rules that are no longer in the scripts. Counts saved before rules were named
+    //by their keyword are given to every rule that reads alike.
+    fn set_usage(&mut self, usage: &HashMap<String, usize>) {
+        self.usage_names.take();
+        let mut by_id = HashMap::new();
+
+        for layer in 0..=self.fallback_scripts.len() {
+            for (k, keyword) in self.layer(layer).keywords.iter().enumerate() {
+                for (r, rule) in keyword.rules.iter().enumerate() {
+                    for (a, assem) in rule.reassembly_rules.iter().enumerate() {
+                        let text = rule.decomposition_rule.clone() + assem;
+                        let name = usage_name(&keyword.key, &text);
+                        if let Some(&n) = usage.get(&name).or_else(|| usage.get(&text)) {
+                            by_id.insert((layer, k, r, a), n);
+                        }
                     }
-                }
-                false => {
-                    //The rule has never been used before - this has precedence
-                    best_rule = Some(rule.clone());
-                    self.rule_usage.insert(key, 0);
-                    break;
                 }
             }
         }
+        self.rule_usage = by_id;
+    }
+
+    /// Returns the current value of one of ELIZA's affect variables, such as `anger` or `fear`.
+    ///
+    /// Variables start at zero, and are adjusted by the `affect` of each rule that is used. See
+    /// `script::Condition` for more.
+    pub fn affect(&self, variable: &str) -> i32 {
+        self.affect.get(variable).copied().unwrap_or(0)
+    }
+
+    /// Returns the most recent exchanges of the conversation, oldest first. Only the last ten are
+    /// kept, unless the builder says otherwise (see `ElizaBuilder::history_capacity()`).
+    pub fn history(&self) -> &VecDeque<Exchange> {
+        &self.history
+    }
+
+    //Returns ELIZA's previous response, for reassembly rules that quote it
+    fn last_response(&self) -> Option<&str> {
+        self.history.back().map(|e| e.response.as_str())
+    }
+
+    /// Returns the responses ELIZA has saved for later, oldest first.
+    pub fn memory(&self) -> &VecDeque<String> {
+        &self.memory
+    }
+
+    /// Returns how many times each reassembly rule has been used, which ELIZA relies upon to
+    /// vary her responses.
+    ///
+    /// Each rule is identified by its keyword and a space, then its decomposition rule followed
+    /// directly by the reassembly rule itself, e.g. `remember (.*)i remember(.+)Do you often
+    /// think of $2?`. Rules that have never been considered are absent. Should a keyword hold
+    /// more than one rule that reads alike, the greatest of their counts is given.
+    pub fn rule_usage(&self) -> &HashMap<String, usize> {
+        self.usage_names.get_or_init(|| {
+            let mut usage = HashMap::new();
+            for (&id, &n) in &self.rule_usage {
+                if let Some(name) = self.usage_name(id) {
+                    let count = usage.entry(name).or_insert(0);
+                    *count = n.max(*count);
+                }
+            }
+            usage
+        })
+    }
+
+    //Returns how `rule_usage()` identifies a reassembly rule, if it is still in the scripts
+    fn usage_name(&self, (layer, k, r, a): RuleId) -> Option<String> {
+        if layer > self.fallback_scripts.len() {
+            return None;
+        }
+
+        let keyword = self.layer(layer).keywords.get(k)?;
+        let rule = keyword.rules.get(r)?;
+        let text = rule.decomposition_rule.clone() + rule.reassembly_rules.get(a)?;
+        Some(usage_name(&keyword.key, &text))
+    }
+
+    /// Forgets everything the user has told ELIZA, so that a deletion request can be honoured
+    /// without ending the conversation.
+    ///
+    /// ELIZA's memory, follow-up questions, history and affect are cleared, along with the
+    /// keyword of her last response and anything needed to undo it. The usage of each reassembly
+    /// rule, which holds nothing the user said, is kept so that ELIZA doesn't start repeating
+    /// herself, as is the number of turns. Anything already written to the event log (see
+    /// `Eliza::event_log()`) is for the host to delete.
+    pub fn forget_user_data(&mut self) {
+        self.memory.clear();
+        self.memory_turns.clear();
+        self.follow_ups.clear();
+        self.history.clear();
+        self.affect.clear();
+        self.last_keyword = None;
+        self.undo = None;
+    }
+
+    /// Raises the rank of a keyword by `delta` for the next `decay_turns` responses, so that an
+    /// application can steer ELIZA towards a topic for a while. A negative `delta` lowers the
+    /// rank instead.
+    ///
+    /// The boost fades in equal steps as the conversation goes on, and is gone after
+    /// `decay_turns` responses. Boosting the keyword again replaces any boost it already has,
+    /// and a `decay_turns` of zero removes it. Ranks only decide which keywords ELIZA tries first,
+    /// so a boost doesn't change the confidence of her responses.
+    ///
+    /// The key is compared as the scripts compare keywords, so `Mother` boosts `mother`. Returns
+    /// false, and boosts nothing, if none of the scripts has the keyword.
+    pub fn boost_keyword(&mut self, key: &str, delta: i32, decay_turns: usize) -> bool {
+        let mut keys = Vec::new();
+        for layer in 0..=self.fallback_scripts.len() {
+            let normalizer = self.settings(layer).normalizer();
+            let key = normalizer.normalize(key);
+            let keywords = self.layer(layer).keywords.iter();
+            let found = keywords.filter(|k| normalizer.normalize(&k.key) == key);
+            keys.extend(found.map(|k| k.key.clone()));
+        }
+
+        let turns = self.turns;
+        self.boosts.retain(|_, b| b.until > turns);
+        for key in &keys {
+            if decay_turns == 0 {
+                self.boosts.remove(key);
+            } else {
+                let boost = Boost {
+                    delta,
+                    turns: decay_turns,
+                    until: turns + decay_turns,
+                };
+                self.boosts.insert(key.clone(), boost);
+            }
+        }
+
+        !keys.is_empty()
+    }
+
+    //Returns the rank of a keyword, as boosted for the current turn
+    fn rank(&self, keyword: &Keyword) -> i32 {
+        match self.boosts.get(&keyword.key) {
+            Some(b) if b.until > self.turns => {
+                let remaining = (b.until - self.turns) as i64;
+                let delta = i64::from(b.delta) * remaining / b.turns as i64;
+                keyword.rank.saturating_add(delta as i32)
+            }
+            _ => keyword.rank,
+        }
+    }
+
+    /// Forgets how many times each reassembly rule has been used, so that ELIZA starts again from
+    /// the first reassembly rule of each decomposition rule.
+    pub fn reset_usage(&mut self) {
+        self.usage_names.take();
+        self.rule_usage.clear();
+    }
+
+    /// Seeds ELIZA's random choices of greetings, farewells and fallbacks, so that conversations
+    /// can be repeated exactly.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Mutex::new(Some(StdRng::seed_from_u64(seed)));
+    }
+
+    /// Makes ELIZA always choose the first of her options: the first greeting, farewell and
+    /// fallback in the script, and the first reassembly rule of any decomposition rule that
+    /// matches.
+    ///
+    /// Unlike `seed()`, this makes responses predictable without replaying the conversation, so
+    /// tests can assert exactly what ELIZA will say to any input. Note that ELIZA no longer cycles
+    /// through a rule's reassembly rules while this is enabled.
+    pub fn first_choice(&mut self, enabled: bool) {
+        self.first_choice = enabled;
+    }
+
+    /// Enables or disables ELIZA's memory, which is enabled by default.
+    ///
+    /// Without memory, rules that would `memorise` a response for later are skipped, so the
+    /// input is matched against the rules that follow them instead. Anything already in memory
+    /// is forgotten.
+    pub fn memorise(&mut self, enabled: bool) {
+        self.forgetful = !enabled;
+        if self.forgetful {
+            self.memory.clear();
+            self.memory_turns.clear();
+        }
+    }
+
+    //Takes the oldest response from memory, if the recall policy allows ELIZA to use it now
+    fn recall(&mut self) -> Option<String> {
+        if self.memory.is_empty() {
+            return None;
+        }
+
+        let recall = self.recall;
+        if !self.memory_due(self.turns + 1)
+            || self.fallback_streak < recall.after_fallbacks
+            || !self.chance(recall.probability)
+        {
+            info!("Keeping memory for later");
+            return None;
+        }
+
+        self.save_memory();
+        self.pop_memory()
+    }
+
+    //Copies the memory as it was before the turn, the first time the turn changes it
+    fn save_memory(&mut self) {
+        if self.saved.memory.is_none() {
+            self.saved.memory = Some((self.memory.clone(), self.memory_turns.clone()));
+        }
+    }
+
+    //Copies the follow-up questions as they were before the turn, the first time the turn
+    //changes them
+    fn save_follow_ups(&mut self) {
+        if self.saved.follow_ups.is_none() {
+            self.saved.follow_ups = Some(self.follow_ups.clone());
+        }
+    }
+
+    //Returns true if enough turns have passed by the given turn since the oldest response in
+    //memory was saved for the recall policy to allow it to be used
+    fn memory_due(&self, turn: usize) -> bool {
+        let saved = match self.memory_turns.len() < self.memory.len() {
+            true => 0,
+            false => self.memory_turns.front().copied().unwrap_or(0),
+        };
+        turn.saturating_sub(saved) >= self.recall.min_turns
+    }
+
+    //Saves a response for later, forgetting the oldest responses if memory is full
+    fn push_memory(&mut self, text: String) {
+        self.memory.push_back(text);
+        self.memory_turns.push_back(self.turns + 1);
+        if let Some(capacity) = self.memory_capacity {
+            //Forget the oldest responses first
+            while self.memory.len() > capacity {
+                self.pop_memory();
+            }
+        }
+    }
+
+    //Takes the oldest response from memory. The turns on which responses were saved are kept for
+    //the newest responses, as snapshots from before they were recorded have none; older responses
+    //count as saved long ago
+    fn pop_memory(&mut self) -> Option<String> {
+        if self.memory_turns.len() >= self.memory.len() {
+            self.memory_turns.pop_front();
+        }
+        self.memory.pop_front()
+    }
+
+    //Returns true with the given probability, using ELIZA's seeded random state if she has one
+    fn chance(&self, probability: f64) -> bool {
+        //Certain outcomes draw nothing from the random state, so they don't disturb seeded choices
+        let p = probability.clamp(0.0, 1.0);
+        if p >= 1.0 {
+            return true;
+        } else if p.is_nan() || p <= 0.0 {
+            return false;
+        }
+
+        match self.rng.lock().unwrap().as_mut() {
+            Some(rng) => rng.gen_bool(p),
+            None => rand::thread_rng().gen_bool(p),
+        }
+    }
+
+    //Returns true if the conversation so far meets the context of the rule's reassembly rule
+    fn in_context(&self, rule: &Rule, assem: usize) -> bool {
+        rule.reassembly_context
+            .get(assem)
+            .and_then(Option::as_ref)
+            .is_none_or(|c| c.holds(self.last_keyword.as_deref(), self.turns + 1, &self.affect))
+    }
+
+    //Chooses a greeting or farewell, as the greeting order asks, given a count of those said
+    fn choose_opener<'a>(&self, options: &'a [String], said: &AtomicUsize) -> Option<&'a String> {
+        match self.greeting_order {
+            GreetingOrder::Random => self.choose(options),
+            GreetingOrder::Sequential => nth(options, said.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+
+    //Randomly selects one of the options, using the seeded generator if there is one
+    fn choose<'a, T>(&self, options: &'a [T]) -> Option<&'a T> {
+        if self.first_choice {
+            return options.first();
+        }
+
+        match self.rng.lock().unwrap().as_mut() {
+            Some(rng) => options.choose(rng),
+            None => options.choose(&mut rand::thread_rng()),
+        }
+    }
+
+    //Finds the first phrase of the input with keywords for a script in the chain, along with the
+    //keywords it has, highest ranked first
+    fn keystack(&self, layer: usize, input: &str) -> (Option<String>, VecDeque<usize>) {
+        span!(span = "keystack", layer, keywords = tracing::field::Empty);
+        let keywords = &self.layer(layer).keywords;
+        let script = self.settings(layer);
+        let mut phrases = script
+            .splitter()
+            .split(&self.transformed(layer, input), &script.delimiters());
+        if phrases.len() > self.limits.phrases {
+            //Only the first phrases are searched, which would very likely hold a keyword anyway
+            warn!(
+                "Ignoring all but {} of {} phrases",
+                self.limits.phrases,
+                phrases.len()
+            );
+            phrases.truncate(self.limits.phrases);
+        }
+
+        let known = script.fuzzy_keywords.then(|| known_words(script));
+        let fuzzy = known.as_ref();
+        let (mut phrase, mut keystack) =
+            populate_keystack(phrases, keywords, script.normalizer(), fuzzy);
+
+        //Narrow the phrase at each conjunction, strongest first, while a part has keywords
+        for conjunction in &script.conjunctions {
+            let parts = match &phrase {
+                Some(phrase) => split_conjunction(phrase, conjunction, script.normalizer()),
+                None => break,
+            };
+            if let (Some(part), stack) =
+                populate_keystack(parts, keywords, script.normalizer(), fuzzy)
+            {
+                phrase = Some(part);
+                keystack = stack;
+            }
+        }
+
+        //Boosted keywords are moved up the keystack, or down it if their boost is negative
+        if !self.boosts.is_empty() {
+            let ranks: Vec<i32> = keystack.iter().map(|&k| self.rank(&keywords[k])).collect();
+            let mut ranked: Vec<(i32, usize)> = ranks.into_iter().zip(keystack).collect();
+            ranked.sort_by_key(|&(rank, _)| std::cmp::Reverse(rank));
+            keystack = ranked.into_iter().map(|(_, k)| k).collect();
+        }
+
+        if keystack.len() > self.limits.keystack {
+            //The keystack is ordered by rank, so the keywords lost are the least important
+            warn!(
+                "Ignoring all but {} of {} keywords",
+                self.limits.keystack,
+                keystack.len()
+            );
+            keystack.truncate(self.limits.keystack);
+        }
+
+        record!(span, "keywords", keystack.len());
+        (phrase, keystack)
+    }
+
+    //Sanitizes the input, handles emoji, case folds it and transforms words, ready for the
+    //keystack to be populated
+    fn transformed(&self, layer: usize, input: &str) -> String {
+        let script = self.settings(layer);
+        let folding = script.case_folding();
+        let input = self.sanitization.apply(input);
+        let input = match &script.emoji {
+            Some(policy) => Cow::Owned(policy.apply(&input)),
+            None => input,
+        };
+
+        let folded = folding.folded(&input);
+        transform(&folded, &script.transforms, folding).into_owned()
+    }
+
+    //Returns a script from the chain, where the primary script is layer 0
+    fn layer(&self, layer: usize) -> &Arc<Script> {
+        match layer {
+            0 => &self.script,
+            n => &self.fallback_scripts[n - 1],
+        }
+    }
+
+    //Returns the script whose settings a layer of the chain reads the input with. An overlay has
+    //no settings of its own, so uses those of the shared script behind it.
+    fn settings(&self, layer: usize) -> &Arc<Script> {
+        match layer {
+            0 if self.overlaid => &self.fallback_scripts[0],
+            n => self.layer(n),
+        }
+    }
+
+    //Returns the greetings, farewells or fallbacks of the primary script, or those of the shared
+    //script behind an overlay that has none of its own
+    fn lines(&self, pick: fn(&Script) -> &Vec<String>) -> &[String] {
+        match pick(&self.script) {
+            own if own.is_empty() && self.overlaid => pick(&self.fallback_scripts[0]),
+            own => own,
+        }
+    }
+
+    //Returns true if the input contains a banned term of any of the scripts, or the content
+    //filter refuses it
+    fn is_banned(&self, input: &str) -> bool {
+        let banned = (0..=self.fallback_scripts.len()).any(|layer| {
+            let script = self.layer(layer);
+            if script.banned.is_empty() {
+                return false;
+            }
+
+            let normalizer = script.normalizer();
+            let text = normalizer.normalize(input);
+            script
+                .banned
+                .iter()
+                .any(|term| contains_term(&text, &normalizer.normalize(term)))
+        });
+
+        banned || self.content_filter.as_ref().is_some_and(|f| f(input))
+    }
+
+    //Chooses a banned response from the first script in the chain that has any, as a fallback
+    //script may ban terms that the primary script has no response for
+    fn banned_response(&self) -> String {
+        let responses = (0..=self.fallback_scripts.len())
+            .map(|layer| &self.settings(layer).banned_responses)
+            .find(|responses| !responses.is_empty());
+        match responses.and_then(|responses| self.choose(responses)) {
+            Some(response) => response.to_string(),
+            None => {
+                warn!("Eliza has no banned responses to use");
+                String::from("Let's keep this conversation civil.")
+            }
+        }
+    }
+
+    //Chooses a fallback for the input, echoing the input in any fallback that asks for it. If
+    //the input has nothing to echo, only the other fallbacks are considered.
+    fn fallback(&self, input: &str) -> String {
+        let echo = self.echo(input);
+        let fallbacks: Cow<'_, [String]> = match echo {
+            Some(_) => Cow::Borrowed(self.lines(|s| &s.fallbacks)),
+            None => self
+                .lines(|s| &s.fallbacks)
+                .iter()
+                .filter(|f| !f.contains(INPUT))
+                .cloned()
+                .collect(),
+        };
+        let fallback = match self.settings(0).fallback_order() {
+            FallbackOrder::Random => self.choose(&fallbacks),
+            FallbackOrder::Escalating => fallbacks
+                .get(self.fallback_streak)
+                .or_else(|| fallbacks.last()),
+        };
+
+        match fallback {
+            Some(fallback) if fallback.contains(INPUT) => {
+                fallback.replace(INPUT, echo.as_deref().unwrap_or_default())
+            }
+            Some(fallback) => fallback.to_string(),
+            None => {
+                warn!("Eliza has no fallbacks to use");
+                String::from("Go on.") //A fallback for the fallback - har har
+            }
+        }
+    }
+
+    //Responds to the input with the rules of the first script in the chain with a NONE keyword
+    //that matches it. The response is still a fallback, as no keyword was found in the input.
+    fn none_response(&mut self, input: &str) -> Option<Response> {
+        for layer in 0..=self.fallback_scripts.len() {
+            let none = match self.layer(layer).none_keyword() {
+                Some(none) => none,
+                None => continue,
+            };
+
+            let script = self.settings(layer);
+            let phrases = script
+                .splitter()
+                .split(&self.transformed(layer, input), &script.delimiters());
+            let phrase = phrases.into_iter().find(|p| !p.trim().is_empty());
+            let phrase = phrase.unwrap_or_default();
+            if let Some(mut res) = self.get_response(layer, &phrase, &mut VecDeque::from([none])) {
+                res.source = ResponseSource::Fallback;
+                res.confidence = 0.0;
+                return Some(res);
+            }
+        }
+
+        None
+    }
+
+    //Returns the first phrase of the input, reflected and shortened as a capture would be, for
+    //fallbacks to echo
+    fn echo(&self, input: &str) -> Option<String> {
+        if !self
+            .lines(|s| &s.fallbacks)
+            .iter()
+            .any(|f| f.contains(INPUT))
+        {
+            return None;
+        }
+
+        let script = self.settings(0);
+        let phrases = script
+            .splitter()
+            .split(&self.transformed(0, input), &script.delimiters());
+        let phrase = phrases.iter().map(|p| p.trim()).find(|p| !p.is_empty())?;
+        let reflected = reflect(phrase, &script.reflections, script.normalizer());
+        let max = self.limits.capture.unwrap_or(MAX_INPUT_ECHO);
+        Some(truncate(&reflected, max).into_owned())
+    }
+
+    fn get_response(
+        &mut self,
+        layer: usize,
+        phrase: &str,
+        keystack: &mut VecDeque<usize>,
+    ) -> Option<Response> {
+        let mut response: Option<Response> = None;
+        let mut gotos = 0;
+
+        //The script is shared, so that its rules can be used while ELIZA updates her state. The
+        //phrase is the same for every rule, so is only normalized once.
+        let script = Arc::clone(self.layer(layer));
+        let settings = Arc::clone(self.settings(layer));
+        let normalizer = settings.normalizer();
+        let word_chars = settings.word_chars();
+        let normalized = normalizer.phrase(phrase);
+
+        //Search for a response while the keystack is not empty
+        'search: while response.is_none() && !keystack.is_empty() {
+            let index = keystack.pop_front().unwrap(); //safe due to prior check
+            let next = &script.keywords[index];
+
+            //For each rule set, attempt to decompose phrase then reassemble a response. Rules
+            //that can't match are found together, rather than tried one by one.
+            let possible = script.possible_rules(next, &normalized.text);
+            let rules = next.rules.iter().enumerate();
+            'decompostion: for (i, r) in rules.filter(|&(i, _)| possible[i]) {
+                //Rules for memory are skipped when ELIZA has none, in favour of the rules after them
+                if r.memorise && self.forgetful {
+                    continue;
+                }
+
+                //Some rules may only be used while ELIZA is in the right frame of mind
+                if !r
+                    .conditions
+                    .iter()
+                    .all(|c| c.holds(self.affect(&c.variable)))
+                {
+                    continue;
+                }
+
+                span!(
+                    span = "decompose",
+                    keyword = %next.key,
+                    rule = %r.decomposition_rule,
+                    matched = false
+                );
+
+                //The rule is matched along with every equivalent of its synonym (if it has one)
+                let decomposition = match script.decomposition(&r.decomposition_rule) {
+                    Some(decomposition) => decomposition,
+                    None => {
+                        self.warn(next, r, "the decomposition rule is invalid");
+                        continue;
+                    }
+                };
+                if let Some(cap) = decomposition.regex.captures(&normalized.text) {
+                    let word = decomposition.word(&cap, normalizer);
+                    //A match was found: find the best reassembly rule to use
+                    record!(span, "matched", true);
+                    let rules: Vec<usize> = (0..r.reassembly_rules.len())
+                        .filter(|&a| self.in_context(r, a))
+                        .collect();
+                    if let Some(a) = self.get_reassembly((layer, index, i), &rules) {
+                        let assem = r.reassembly_rules[a].as_str();
+                        if let Some(goto) = is_goto(assem) {
+                            //A chain of more GOTOs than there are keywords is a loop
+                            if gotos == script.keywords.len() {
+                                error!("GOTOs for key '{}' loop: '{}'", next.key, goto);
+                                self.warn(next, r, &format!("GOTOs loop: '{}'", goto));
+                                continue;
+                            }
+
+                            //The best rule was a goto, push associated key entry to stack
+                            if let Some(entry) = script.goto_index(&goto) {
+                                //Push to front of keystack and skip to it
+                                info!(
+                                    "Using GOTO '{}' for key '{}' and decomp rule '{}'",
+                                    goto, next.key, r.decomposition_rule
+                                );
+                                keystack.push_front(entry);
+                                gotos += 1;
+                                break 'decompostion;
+                            } else {
+                                error!("No such keyword: {}", goto);
+                                let message = format!("GOTO names no keyword: '{}'", goto);
+                                self.warn(next, r, &message);
+                                continue; //Something wrong with this GOTO
+                            }
+                        }
+
+                        //Attempt to assemble given the synonym and capture groups
+                        span!(_assembly = "assemble", rule = %assem);
+                        let captures = decomposition.captures(&normalized, phrase, &cap);
+                        let assemble_ = |assem: &str| {
+                            substitute_synonym(
+                                assem,
+                                &r.decomposition_rule,
+                                word.as_deref(),
+                                &word_chars,
+                                normalizer,
+                            )
+                            .and_then(|assem| {
+                                assemble(
+                                    &assem,
+                                    &captures,
+                                    &settings.reflections,
+                                    normalizer,
+                                    self.limits,
+                                )
+                            })
+                        };
+                        let assembled = assemble_(assem);
+                        let follow_up = r.follow_ups.get(a).and_then(Option::as_ref);
+                        let follow_up = follow_up.map(|f| (f, assemble_(f)));
+
+                        //A rule that can't be assembled is a mistake in the script
+                        if assembled.is_none() {
+                            self.warn(next, r, &unassembled(assem));
+                        }
+                        if let Some((f, None)) = &follow_up {
+                            self.warn(next, r, &unassembled(f));
+                        }
+
+                        let last_response = self.last_response();
+                        let follow_up = follow_up
+                            .and_then(|(_, f)| f)
+                            .and_then(|f| recall(f, last_response));
+                        if let Some(text) = assembled.and_then(|a| recall(a, last_response)) {
+                            if !r.affect.is_empty() && self.saved.affect.is_none() {
+                                self.saved.affect = Some(self.affect.clone());
+                            }
+                            for (variable, delta) in &r.affect {
+                                *self.affect.entry(variable.clone()).or_insert(0) += delta;
+                            }
+
+                            if r.memorise {
+                                //We'll save this response for later...
+                                info!(
+                                    "Saving response that matched key '{}' and decomp rule '{}'",
+                                    next.key, r.decomposition_rule
+                                );
+                                self.save_memory();
+                                self.push_memory(text);
+                            } else {
+                                //We found a response, exit
+                                info!(
+                                    "Found response for key '{}' and decomp rule '{}'",
+                                    next.key, r.decomposition_rule
+                                );
+                                if follow_up.is_some() {
+                                    self.save_follow_ups();
+                                }
+                                self.follow_ups.extend(follow_up);
+                                response = Some(Response {
+                                    text,
+                                    source: ResponseSource::Keyword,
+                                    keyword: Some(next.key.clone()),
+                                    rule: Some(r.decomposition_rule.clone()),
+                                    confidence: confidence(
+                                        next.rank,
+                                        &normalized.text,
+                                        &cap,
+                                        decomposition.synonym,
+                                    ),
+                                    warnings: Vec::new(),
+                                });
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        response
+    }
+
+    //Notes a rule that had to be passed over because of a mistake in the script
+    fn warn(&mut self, keyword: &Keyword, rule: &Rule, message: &str) {
+        self.warnings.push(RuleWarning {
+            keyword: keyword.key.clone(),
+            rule: rule.decomposition_rule.clone(),
+            message: message.to_string(),
+        });
+    }
+
+    //Chooses between the reassembly rules of a decomposition rule, given the layer, keyword and
+    //index of the decomposition rule, and the indices of its reassembly rules that may be used
+    fn get_reassembly(&mut self, id: (usize, usize, usize), rules: &[usize]) -> Option<usize> {
+        if self.first_choice || self.selection == Strategy::Random {
+            return self.choose(rules).copied();
+        }
+
+        let (layer, keyword, decomposition) = id;
+        let mut best_rule: Option<usize> = None;
+        self.usage_names.take();
+        let mut count: Option<usize> = None;
+
+        //Each reassembly rule is identified by its indices, so rules that read alike in different
+        //decomposition rules or keywords are counted apart
+        for &rule in rules {
+            let key = (layer, keyword, decomposition, rule);
+            match self.rule_usage.contains_key(&key) {
+                true => {
+                    //If it has already been used, get its usage count
+                    let usage = self.rule_usage[&key];
+                    if let Some(c) = count {
+                        if usage < c {
+                            //The usage is less than the running total
+                            best_rule = Some(rule);
+                            count = Some(usage);
+                        }
+                    } else {
+                        //The count has yet to be updated, this is the best usage so far
+                        best_rule = Some(rule);
+                        count = Some(usage);
+                    }
+                }
+                false => {
+                    //The rule has never been used before - this has precedence
+                    best_rule = Some(rule);
+                    self.rule_usage.insert(key, 0);
+                    break;
+                }
+            }
+        }
+
+        //For whatever rule we use (if any), increment its usage count
+        if let Some(rule) = best_rule {
+            let key = (layer, keyword, decomposition, rule);
+            if let Some(usage) = self.rule_usage.get_mut(&key) {
+                *usage += 1;
+                self.used.push(key);
+            }
+        }
+
+        best_rule
+    }
+}
+
+fn transform<'a>(
+    input: &'a str,
+    transforms: &'a [Transform],
+    folding: CaseFolding,
+) -> Cow<'a, str> {
+    //Try the equivalents of the highest priority transforms first, then the longest equivalents
+    let mut equivalents: Vec<(Cow<'_, str>, Cow<'_, str>, i32)> = transforms
+        .iter()
+        .flat_map(|t| {
+            t.equivalents
+                .iter()
+                .map(move |e| (folding.folded(e), folding.folded(&t.word), t.priority))
+        })
+        .filter(|(e, _, _)| !e.is_empty())
+        .collect();
+    equivalents
+        .sort_by_key(|(e, _, priority)| (std::cmp::Reverse(*priority), std::cmp::Reverse(e.len())));
+
+    //Replace whole words in a single pass, so that no replacement is transformed again. The
+    //input is only copied once something in it is replaced.
+    let mut transformed: Option<String> = None;
+    let mut i = 0;
+    'input: while let Some(c) = input[i..].chars().next() {
+        let rest = &input[i..];
+        for (equivalent, word, _) in &equivalents {
+            if rest.starts_with(&**equivalent)
+                && !splits_word(input[..i].chars().next_back(), equivalent.chars().next())
+                && !splits_word(
+                    rest[equivalent.len()..].chars().next(),
+                    equivalent.chars().next_back(),
+                )
+            {
+                transformed
+                    .get_or_insert_with(|| {
+                        let mut copy = String::with_capacity(input.len());
+                        copy.push_str(&input[..i]);
+                        copy
+                    })
+                    .push_str(word);
+                i += equivalent.len();
+                continue 'input;
+            }
+        }
+
+        if let Some(transformed) = transformed.as_mut() {
+            transformed.push(c);
+        }
+        i += c.len_utf8();
+    }
+
+    match transformed {
+        Some(transformed) => Cow::Owned(transformed),
+        None => Cow::Borrowed(input),
+    }
+}
+
+//Returns true if the term appears in the text as whole words
+fn contains_term(text: &str, term: &str) -> bool {
+    !term.is_empty()
+        && text.match_indices(term).any(|(i, _)| {
+            !splits_word(text[..i].chars().next_back(), term.chars().next())
+                && !splits_word(
+                    text[i + term.len()..].chars().next(),
+                    term.chars().next_back(),
+                )
+        })
+}
+
+//Returns true if replacing text that ends with `edge` would split it from its `neighbour`, as
+//the two belong to the same word
+fn splits_word(neighbour: Option<char>, edge: Option<char>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '\'';
+    neighbour.is_some_and(is_word) && edge.is_some_and(is_word)
+}
+
+//Fuzzy matching, if on, holds the words that are never taken for a misspelt keyword
+fn populate_keystack(
+    phrases: Vec<String>,
+    keywords: &[Keyword],
+    normalizer: Normalizer,
+    fuzzy: Option<&HashSet<String>>,
+) -> (Option<String>, VecDeque<usize>) {
+    //The keystack holds the index of each keyword, rather than a copy of it
+    let mut keystack: Vec<usize> = Vec::new();
+    let mut active_phrase: Option<String> = None;
+
+    for phrase in phrases {
+        if active_phrase.is_some() {
+            //A phrase with keywords was found, break as we don't care about other phrases
+            break;
+        }
+
+        //Misspelt keywords found by fuzzy matching are corrected, so decomposition rules match
+        let mut corrections = Vec::new();
+        let mut found_keyword = false;
+        for word in phrase.split_whitespace() {
+            //Punctuation stays with the phrase for captures, but mustn't hide a keyword
+            let word = trim_punctuation(word);
+            let normalized = normalizer.word(word);
+            let exact = keywords
+                .iter()
+                .position(|k| !k.is_none_keyword() && normalizer.word(&k.key) == normalized);
+
+            let found = match exact {
+                None if fuzzy.is_some_and(|known| !known.contains(normalized.as_ref())) => {
+                    closest_keyword(&normalized, keywords, normalizer).inspect(|&k| {
+                        let start = word_offset(&phrase, word);
+                        corrections.push((
+                            start..start + word.len(),
+                            normalizer.normalize(&keywords[k].key).into_owned(),
+                        ));
+                    })
+                }
+                found => found,
+            };
+            if let Some(k) = found {
+                keystack.push(k);
+                found_keyword = true;
+            }
+        }
+
+        if found_keyword {
+            let mut active = phrase;
+            for (range, key) in corrections.into_iter().rev() {
+                active.replace_range(range, &key);
+            }
+            active_phrase = Some(active);
+        }
+    }
+
+    //sort the keystack with highest rank first
+    keystack.sort_by_key(|&k| std::cmp::Reverse(keywords[k].rank));
+
+    (active_phrase, VecDeque::from(keystack))
+}
+
+//The name of the group that a decomposition rule's synonym is compiled into
+const SYNONYM_GROUP: &str = "syn";
+
+//A decomposition rule compiled into a single regex, in which the rule's synonym (if it has one) is
+//an alternation of the synonym and its equivalents, e.g. '(.*)my (?P<syn>family|mother|father)'
+#[derive(Debug)]
+pub(crate) struct Decomposition {
+    regex: Regex,
+    //The index of the synonym's group, which isn't one of the rule's own captures
+    synonym: Option<usize>,
+    //The synonym and its equivalents, as written in the script
+    words: Vec<String>,
+}
+
+impl Decomposition {
+    pub(crate) fn compile(
+        decomposition: &str,
+        synonyms: &[Synonym],
+        word_chars: &CharClass,
+        normalizer: Normalizer,
+    ) -> Option<Decomposition> {
+        let inlined = inline_alternations(decomposition);
+        if inlined.matches('@').count() > 1 {
+            error!(
+                "Decomposition rules are limited to one synonym conversion: '{}'",
+                decomposition
+            );
+            return None;
+        }
+
+        //The input has been normalized, so the rule must be too
+        let mut words = Vec::new();
+        let pattern = match synonym_name(&inlined, word_chars) {
+            //Format example: '(.*) my (.* @family)', where the synonym may be in any language
+            Some(name) => {
+                words.push(name.to_string());
+                if let Some(synonym) = synonyms
+                    .iter()
+                    .find(|s| normalizer.normalize(&s.word) == normalizer.normalize(name))
+                {
+                    words.extend(synonym.equivalents.iter().cloned());
+                }
+
+                let i = inlined.find('@').unwrap_or_default();
+                let alternatives: Vec<String> =
+                    words.iter().map(|w| normalizer.pattern(w)).collect();
+                format!(
+                    "{}(?P<{}>{}){}",
+                    normalizer.pattern(&inlined[..i]),
+                    SYNONYM_GROUP,
+                    alternatives.join("|"),
+                    normalizer.pattern(&inlined[i + 1 + name.len()..])
+                )
+            }
+            None => normalizer.pattern(&inlined),
+        };
+
+        match Regex::new(&pattern) {
+            Ok(regex) => Some(Decomposition {
+                synonym: regex.capture_names().position(|n| n == Some(SYNONYM_GROUP)),
+                regex,
+                words,
+            }),
+            Err(_) => {
+                error!("Invalid decompostion rule: '{}'", decomposition);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    //Returns the text of each of the rule's captures as it appeared in the original phrase
+    fn captures<'a>(&self, phrase: &Phrase, original: &'a str, cap: &Captures<'_>) -> Vec<&'a str> {
+        let mut captures = phrase.captures(original, cap);
+        if let Some(i) = self.synonym {
+            captures.remove(i);
+        }
+        captures
+    }
+
+    //Returns the synonym or equivalent, as written in the script, that the phrase matched
+    fn word(&self, cap: &Captures<'_>, normalizer: Normalizer) -> Option<String> {
+        let matched = cap.get(self.synonym?)?.as_str();
+        let word = self
+            .words
+            .iter()
+            .find(|w| normalizer.normalize(w) == matched)
+            .map_or(matched, String::as_str);
+        Some(word.to_string())
+    }
+}
+
+//Returns the name of the synonym in a decomposition rule, once its inline alternations are compiled
+fn synonym_name<'a>(decomposition: &'a str, word_chars: &CharClass) -> Option<&'a str> {
+    decomposition
+        .find('@')
+        .map(|i| word_chars.take(&decomposition[i + 1..]))
+}
+
+//Swaps each '$@name' in a reassembly rule for the word that matched the decomposition rule's
+//synonym, e.g. 'Tell me about your $@family' becomes 'Tell me about your
+//father'
+fn substitute_synonym(
+    rule: &str,
+    decomposition: &str,
+    word: Option<&str>,
+    word_chars: &CharClass,
+    normalizer: Normalizer,
+) -> Option<String> {
+    let decomposition = inline_alternations(decomposition);
+    let synonym = synonym_name(&decomposition, word_chars).map(|s| normalizer.normalize(s));
+    let mut substituted = String::new();
+    let mut rest = rule;
+    while let Some(i) = rest.find("$@") {
+        substituted.push_str(&rest[..i]);
+        let name = word_chars.take(&rest[i + 2..]);
+        rest = &rest[i + 2 + name.len()..];
+        match word {
+            Some(word) if synonym.as_deref() == Some(normalizer.normalize(name).as_ref()) => {
+                substituted.push_str(word)
+            }
+            _ => {
+                error!(
+                    "Refers to a synonym the decomposition rule lacks: '{}'",
+                    rule
+                );
+                return None;
+            }
+        }
+    }
+
+    substituted.push_str(rest);
+    Some(substituted)
+}
+
+fn assemble(
+    rule: &str,
+    captures: &[&str],
+    reflections: &[Reflection],
+    normalizer: Normalizer,
+    limits: Limits,
+) -> Option<String> {
+    let mut assembled = String::with_capacity(rule.len() + captures.first().map_or(0, |c| c.len()));
+    let mut rest = rule;
+
+    //Swap each capture id for its capture
+    while let Some(i) = rest.find('$') {
+        assembled.push_str(&rest[..i]);
+
+        //Format example 'What makes you think I am $2?' which
+        //uses the second capture group of the regex
+        let id = CharClass::Alphanumeric.take(&rest[i + 1..]);
+        rest = &rest[i + 1 + id.len()..];
+        if let Ok(n) = id.parse::<usize>() {
+            if let Some(capture) = captures.get(n) {
+                //indexing starts at 1
+                //Perform reflection on the capture before subsitution
+                let reflected = reflect(capture, reflections, normalizer);
+                match limits.capture {
+                    Some(max) => assembled.push_str(&truncate(&reflected, max)),
+                    None => assembled.push_str(&reflected),
+                }
+            } else {
+                error!("{} is outside capture range in: '{}'", n, rule);
+                return None;
+            }
+        } else {
+            error!("Contains invalid capture id: '{}'", rule);
+            return None;
+        }
+    }
+
+    assembled.push_str(rest);
+    if let Some(max) = limits.response {
+        //Only a response that is too long is copied
+        if let Cow::Owned(truncated) = truncate(&assembled, max) {
+            assembled = truncated;
+        }
+    }
+    Some(assembled)
+}
+
+//Names a reassembly rule for `rule_usage()`, given its keyword and the text of the rule
+fn usage_name(key: &str, rule: &str) -> String {
+    format!("{} {}", key, rule)
+}
+
+//Describes a reassembly rule or follow-up that couldn't be assembled
+fn unassembled(rule: &str) -> String {
+    format!(
+        "'{}' refers to a capture or synonym that doesn't exist",
+        rule
+    )
+}
+
+fn greeting(greet: Option<&String>) -> String {
+    match greet {
+        Some(greet) => greet.to_string(),
+        None => {
+            warn!("Eliza has no greetings to use");
+            String::from("Hello, I am Eliza.") //If greetings are empty, have default
+        }
+    }
+}
+
+fn farewell(farewell: Option<&String>) -> String {
+    match farewell {
+        Some(farewell) => farewell.to_string(),
+        None => {
+            warn!("Eliza has no farewells to use");
+            String::from("Goodbye.") //If farewells are empty, have default
+        }
+    }
+}
+
+//Returns the n-th option, counting round again from the first once n passes the last
+fn nth<T>(options: &[T], n: usize) -> Option<&T> {
+    match options.len() {
+        0 => None,
+        len => options.get(n % len),
+    }
+}
+
+//Swaps any placeholder in an assembled response for ELIZA's previous response. A response that
+//needs one can't be given before ELIZA has said anything.
+fn recall(text: String, last_response: Option<&str>) -> Option<String> {
+    if !text.contains(LAST_RESPONSE) {
+        return Some(text);
+    }
+
+    last_response.map(|last| text.replace(LAST_RESPONSE, last))
+}
+
+//Shortens the text to at most `max` characters, ellipsis included, breaking it between words
+//where that doesn't lose too much of it
+fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    const ELLIPSIS: &str = "...";
+    if text.chars().count() <= max {
+        return Cow::Borrowed(text);
+    } else if max <= ELLIPSIS.len() {
+        return Cow::Owned(text.chars().take(max).collect());
+    }
+
+    let keep = max - ELLIPSIS.len();
+    let end = text.char_indices().nth(keep).map_or(text.len(), |(i, _)| i);
+    let cut = match text[..end].rfind(char::is_whitespace) {
+        Some(i) if i > end / 2 => &text[..i],
+        _ => &text[..end],
+    };
+
+    Cow::Owned(format!("{}{}", cut.trim_end(), ELLIPSIS))
+}
+
+fn reflect(input: &str, reflections: &[Reflection], normalizer: Normalizer) -> String {
+    //we don't want to accidently re-reflect word pairs that have two-way reflection
+    let mut reflected_phrase = String::with_capacity(input.len());
+
+    for w in input.split_whitespace() {
+        let normalized = normalizer.normalize(w);
+        let is_word = |r: &Reflection| normalizer.normalize(&r.word) == normalized;
+        let is_inverse = |r: &Reflection| normalizer.normalize(&r.inverse) == normalized;
+
+        //Find reflection pairs that are applicable to this word
+        if let Some(reflect) = reflections
+            .iter()
+            .find(|r| is_word(r) || (r.twoway && is_inverse(r)))
+        {
+            if is_word(reflect) {
+                reflected_phrase.push_str(&reflect.inverse);
+            } else if reflect.twoway && is_inverse(reflect) {
+                reflected_phrase.push_str(&reflect.word);
+            } else {
+                //Unlikely to happen, but print message just incase
+                error!("Invalid reflection for pair {:?} in: '{}'", reflect, input);
+            }
+        } else {
+            //No reflection required
+            reflected_phrase.push_str(w);
+        }
+
+        reflected_phrase.push(' '); //put a space after each word
+    }
+
+    //Trim in place, rather than copying the phrase
+    let end = reflected_phrase.trim_end().len();
+    reflected_phrase.truncate(end);
+    let start = reflected_phrase.len() - reflected_phrase.trim_start().len();
+    reflected_phrase.drain(..start);
+    reflected_phrase
+}
+
+//Scores a response from a keyword, from 0.5 for a catch-all rule of the lowest ranked keyword,
+//towards 1.0 for a highly ranked keyword whose rule matched the phrase word for word
+fn confidence(rank: i32, phrase: &str, captures: &Captures<'_>, synonym: Option<usize>) -> f64 {
+    //The specificity of the rule is the share of the phrase it matched with its own words, which
+    //include its synonym
+    let mut captured = vec![false; phrase.len()];
+    let groups = captures.iter().enumerate().skip(1);
+    let matches = groups
+        .filter(|(i, _)| Some(*i) != synonym)
+        .filter_map(|(_, m)| m);
+    for m in matches {
+        captured[m.range()].iter_mut().for_each(|c| *c = true);
+    }
+    let literal = captured.iter().filter(|&&c| !c).count();
+    let specificity = match phrase.len() {
+        0 => 0.0,
+        len => literal as f64 / len as f64,
+    };
+
+    //Ranks are open ended, so their weight approaches one as they grow. Negative ranks weigh
+    //nothing, like a rank of zero.
+    let rank = f64::from(rank.max(0));
+    let rank = rank / (rank + 10.0);
+
+    0.5 + 0.25 * specificity + 0.25 * rank
+}
+
+fn get_phrases(input: &str, delimiters: &[&str]) -> Vec<String> {
+    let mut phrases = vec![input.to_string()];
+    for d in delimiters {
+        phrases = phrases
+            .iter()
+            .flat_map(|p| p.split(d))
+            .map(|s| s.to_string())
+            .collect();
+    }
+
+    phrases.iter().map(|s| s.trim().to_string()).collect()
+}
+
+//Common words that are a single edit from a likely keyword, such as 'other' from 'mother', and
+//so must not be corrected to it
+const COMMON_WORDS: &[&str] = &[
+    "another", "brother", "either", "neither", "other", "others", "rather", "there", "these",
+    "thing", "things", "think", "where", "whether",
+];
+
+//Gathers the words that fuzzy matching leaves alone, as the script already knows them: those of
+//its transforms, synonyms and reflections, along with some common words
+fn known_words(script: &Script) -> HashSet<String> {
+    let normalizer = script.normalizer();
+    let transforms = script
+        .transforms
+        .iter()
+        .flat_map(|t| iter::once(&t.word).chain(&t.equivalents));
+    let synonyms = script
+        .synonyms
+        .iter()
+        .flat_map(|s| iter::once(&s.word).chain(&s.equivalents));
+    let reflections = script
+        .reflections
+        .iter()
+        .flat_map(|r| [&r.word, &r.inverse]);
+
+    transforms
+        .chain(synonyms)
+        .chain(reflections)
+        .map(String::as_str)
+        .chain(COMMON_WORDS.iter().copied())
+        .map(|w| normalizer.word(w).into_owned())
+        .collect()
+}
+
+//Finds the keyword nearest in spelling to a word, within a tolerance that grows with its length
+fn closest_keyword(word: &str, keywords: &[Keyword], normalizer: Normalizer) -> Option<usize> {
+    keywords
+        .iter()
+        .enumerate()
+        .filter(|(_, k)| !k.is_none_keyword())
+        .filter_map(|(i, k)| {
+            let key = normalizer.word(&k.key);
+            let tolerance = match key.chars().count() {
+                0..=3 => return None,
+                4..=7 => 1,
+                _ => 2,
+            };
+            let distance = edit_distance(word, &key);
+            (distance <= tolerance).then_some((distance, i))
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, i)| i)
+}
+
+//Counts the insertions, deletions, substitutions and transpositions of adjacent characters that
+//turn one word into the other, so that 'mohter' is a single edit from 'mother'
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    //Rows of the distance matrix, from two rows back to the current row
+    let mut previous2 = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(previous2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut previous2, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+//Returns the byte offset of a word, taken from the phrase, within the phrase
+fn word_offset(phrase: &str, word: &str) -> usize {
+    word.as_ptr() as usize - phrase.as_ptr() as usize
+}
+
+//Breaks a phrase wherever the conjunction appears as a whole word (or words)
+fn split_conjunction(phrase: &str, conjunction: &str, normalizer: Normalizer) -> Vec<String> {
+    let conjunction = get_words(&normalizer.normalize(conjunction));
+    if conjunction.is_empty() {
+        return vec![phrase.to_string()];
+    }
+
+    //The byte range of each word in the phrase, so that parts keep their original spacing
+    let words: Vec<(usize, usize)> = phrase
+        .split_whitespace()
+        .map(|w| {
+            let start = word_offset(phrase, w);
+            (start, start + w.len())
+        })
+        .collect();
+    let is_match = |i: usize| {
+        conjunction.iter().enumerate().all(|(j, c)| {
+            words.get(i + j).is_some_and(|&(start, end)| {
+                trim_punctuation(&normalizer.normalize(&phrase[start..end])) == c
+            })
+        })
+    };
+
+    let mut parts = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while i < words.len() {
+        if is_match(i) {
+            parts.push(phrase[start..words[i].0].trim().to_string());
+            i += conjunction.len();
+            start = words.get(i).map_or(phrase.len(), |w| w.0);
+        } else {
+            i += 1;
+        }
+    }
+    parts.push(phrase[start..].trim().to_string());
+
+    parts
+}
+
+//Removes punctuation from either end of a word, leaving any within it (as in "don't")
+fn trim_punctuation(word: &str) -> &str {
+    word.trim_matches(|c: char| {
+        c.is_ascii_punctuation()
+            || matches!(
+                c,
+                '¡' | '¿' | '«' | '»' | '‘' | '’' | '“' | '”' | '„' | '…' | '–' | '—'
+            )
+    })
+}
+
+fn get_words(phrase: &str) -> Vec<String> {
+    phrase.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::DEFAULT_DELIMITERS;
+
+    #[test]
+    fn decomposition_valid() {
+        let synonyms: Vec<Synonym> = vec![Synonym {
+            word: "family".to_string(),
+            equivalents: vec!["brother".to_string(), "mother".to_string()],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)my (.* @family)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "(.*)my (.* (?P<syn>family|brother|mother))",
+            d.regex.as_str()
+        );
+        assert_eq!(Some(3), d.synonym);
+
+        let phrase = Normalizer::default().phrase("Well my dear Mother");
+        let cap = d.regex.captures(&phrase.text).unwrap();
+        assert_eq!(
+            Some("mother"),
+            d.word(&cap, Normalizer::default()).as_deref()
+        );
+        assert_eq!(
+            vec!["Well my dear Mother", "Well ", "dear Mother"],
+            d.captures(&phrase, "Well my dear Mother", &cap)
+        );
+    }
+
+    #[test]
+    fn decomposition_invalid() {
+        let synonyms: Vec<Synonym> = vec![Synonym {
+            word: "family".to_string(),
+            equivalents: vec!["brother".to_string(), "mother".to_string()],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)my (.* @family @fail)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        );
+        assert!(d.is_none());
+    }
+
+    #[test]
+    fn decomposition_accented() {
+        let synonyms: Vec<Synonym> = vec![Synonym {
+            word: "glücklich".to_string(),
+            equivalents: vec!["froh".to_string()],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)ich bin (.*)(@glücklich)(.*)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "(.*)ich bin (.*)((?P<syn>glücklich|froh))(.*)",
+            d.regex.as_str()
+        );
+    }
+
+    #[test]
+    fn decomposition_hyphenated() {
+        let synonyms: Vec<Synonym> = vec![Synonym {
+            word: "well-being".to_string(),
+            equivalents: vec!["health".to_string()],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)my (@well-being)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        )
+        .unwrap();
+        assert_eq!("(.*)my ((?P<syn>well-being|health))", d.regex.as_str());
+    }
+
+    #[test]
+    fn decomposition_alternation() {
+        let synonyms = vec![Synonym {
+            word: String::from("feeling"),
+            equivalents: vec![String::from("sad"), String::from("happy")],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)my @{Mother|father} is (.*@feeling)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "(.*)my (?:mother|father) is (.*(?P<syn>feeling|sad|happy))",
+            d.regex.as_str()
+        );
+
+        let cap = d.regex.captures("i think my father is sad").unwrap();
+        assert_eq!(4, cap.len());
+        assert_eq!("sad", &cap[2]);
+        assert_eq!(Some("sad"), d.word(&cap, Normalizer::default()).as_deref());
+        assert_eq!("(.*)@{a|b", inline_alternations("(.*)@{a|b"));
+    }
+
+    #[test]
+    fn decomposition_simple() {
+        let synonyms: Vec<Synonym> = vec![Synonym {
+            word: "family".to_string(),
+            equivalents: vec!["brother".to_string(), "mother".to_string()],
+        }];
+
+        let d = Decomposition::compile(
+            "(.*)my (.* dog)",
+            &synonyms,
+            &CharClass::word(),
+            Normalizer::default(),
+        )
+        .unwrap();
+        assert_eq!("(.*)my (.* dog)", d.regex.as_str());
+        assert_eq!(None, d.synonym);
+    }
+
+    #[test]
+    fn synonym_substitution() {
+        let substitute = |rule, decomposition, word| {
+            substitute_synonym(
+                rule,
+                decomposition,
+                word,
+                &CharClass::word(),
+                Normalizer::default(),
+            )
+        };
+
+        assert_eq!(
+            Some(String::from("Tell me about your father, $2.")),
+            substitute(
+                "Tell me about your $@Family, $2.",
+                "(.*)my @family (.*)",
+                Some("father")
+            )
+        );
+        assert_eq!(
+            Some(String::from("Is it your father?")),
+            substitute(
+                "Is it your $@family?",
+                "(.*)@{his|her} @family(.*)",
+                Some("father")
+            )
+        );
+        assert_eq!(
+            None,
+            substitute("Your $@parents?", "(.*)my @family", Some("father"))
+        );
+        assert_eq!(None, substitute("Your $@family?", "(.*)my (.*)", None));
+    }
+
+    #[test]
+    fn assemble_rule_equal() {
+        let mut e: Eliza = Default::default();
+
+        //Create a fake rule usage HashMap
+        let mut usages: HashMap<RuleId, usize> = HashMap::new();
+        usages.insert((0, 0, 0, 0), 1);
+        usages.insert((0, 0, 0, 1), 1);
+        usages.insert((0, 0, 0, 2), 1);
+        usages.insert((0, 0, 0, 3), 1);
+
+        //All equal precedence, should just return the first
+        e.rule_usage = usages;
+        assert_eq!(Some(0), e.get_reassembly((0, 0, 0), &[0, 1, 2, 3]));
+        assert_eq!(2, e.rule_usage[&(0, 0, 0, 0)]);
+    }
+
+    #[test]
+    fn assemble_rule_smaller() {
+        let mut e: Eliza = Default::default();
+
+        //Create a fake rule usage HashMap
+        let mut usages: HashMap<RuleId, usize> = HashMap::new();
+        usages.insert((0, 0, 0, 0), 7);
+        usages.insert((0, 0, 0, 1), 3);
+        usages.insert((0, 0, 0, 2), 2);
+        usages.insert((0, 0, 0, 3), 10);
+
+        //One has been used less than the rest
+        e.rule_usage = usages;
+        assert_eq!(Some(2), e.get_reassembly((0, 0, 0), &[0, 1, 2, 3]));
+        assert_eq!(3, e.rule_usage[&(0, 0, 0, 2)]);
+    }
+
+    #[test]
+    fn assemble_rule_unknown() {
+        let mut e: Eliza = Default::default();
+
+        //Create a fake rule usage HashMap
+        let mut usages: HashMap<RuleId, usize> = HashMap::new();
+        usages.insert((0, 0, 0, 0), 7);
+        usages.insert((0, 0, 0, 1), 3);
+        usages.insert((0, 0, 0, 2), 2);
+
+        //One has never been used
+        e.rule_usage = usages;
+        assert_eq!(Some(3), e.get_reassembly((0, 0, 0), &[0, 1, 2, 3]));
+        assert_eq!(1, e.rule_usage[&(0, 0, 0, 3)]);
+    }
+
+    #[test]
+    fn assemble_ok() {
+        let reflections: Vec<Reflection> = Vec::new();
+        let re = Regex::new(r"(.*) you are (.*)").unwrap();
+        let phrase = "I think that you are so stupid";
+        let cap = Normalizer::default().phrase(phrase);
+        let cap = cap.captures(phrase, &re.captures(phrase).unwrap());
+
+        let res = assemble(
+            "What makes you think I am $2?",
+            &cap,
+            &reflections,
+            Normalizer::default(),
+            Limits::default(),
+        );
+        assert_eq!(res.unwrap(), "What makes you think I am so stupid?");
+    }
+
+    #[test]
+    fn assemble_invalid_index() {
+        let reflections: Vec<Reflection> = Vec::new();
+        let re = Regex::new(r"(.*) you are (.*)").unwrap();
+        let phrase = "I think that you are so stupid";
+        let cap = Normalizer::default().phrase(phrase);
+        let cap = cap.captures(phrase, &re.captures(phrase).unwrap());
+
+        let res = assemble(
+            "What makes you think I am $5 ?",
+            &cap,
+            &reflections,
+            Normalizer::default(),
+            Limits::default(),
+        );
+        assert!(res.is_none());
+    }
+
+    #[test]
+    fn assemble_invalid_id() {
+        let reflections: Vec<Reflection> = Vec::new();
+        let re = Regex::new(r"(.*) you are (.*)").unwrap();
+        let phrase = "I think that you are so stupid";
+        let cap = Normalizer::default().phrase(phrase);
+        let cap = cap.captures(phrase, &re.captures(phrase).unwrap());
+
+        let res = assemble(
+            "What makes you think I am $a ?",
+            &cap,
+            &reflections,
+            Normalizer::default(),
+            Limits::default(),
+        );
+        assert!(res.is_none());
+    }
+
+    #[test]
+    fn assemble_truncated() {
+        let reflections: Vec<Reflection> = Vec::new();
+        let re = Regex::new(r"(.*)i said (.*)").unwrap();
+        let phrase = "i said that the weather has been terribly wet all week";
+        let cap = Normalizer::default().phrase(phrase);
+        let cap = cap.captures(phrase, &re.captures(phrase).unwrap());
+
+        let limits = Limits {
+            capture: Some(20),
+            response: None,
+            ..Limits::default()
+        };
+        let res = assemble("Why $2?", &cap, &reflections, Normalizer::default(), limits);
+        assert_eq!("Why that the weather...?", res.unwrap());
+
+        let limits = Limits {
+            capture: None,
+            response: Some(16),
+            ..Limits::default()
+        };
+        let res = assemble("Why $2?", &cap, &reflections, Normalizer::default(), limits);
+        assert_eq!("Why that the...", res.unwrap());
+
+        assert_eq!("ab...", truncate("abcdefgh", 5));
+        assert_eq!("ab", truncate("abcdefgh", 2));
+        assert_eq!("short", truncate("short", 5));
+    }
+
+    #[test]
+    fn builder_options() {
+        let script = |fallback: &str| Script {
+            fallbacks: vec![String::from(fallback)],
+            keywords: vec![Keyword {
+                key: String::from("remember"),
+                rank: 0,
+                tags: Vec::new(),
+                rules: vec![
+                    Rule {
+                        memorise: true,
+                        decomposition_rule: String::from("(.*)remember (.*)"),
+                        reassembly_rules: vec![String::from("Earlier you said $2.")],
+                        ..Default::default()
+                    },
+                    Rule {
+                        memorise: false,
+                        decomposition_rule: String::from("(.*)"),
+                        reassembly_rules: vec![String::from("Do go on.")],
+                        ..Default::default()
+                    },
+                ],
+            }],
+            ..Default::default()
+        };
+
+        assert!(Eliza::builder().build().is_err());
+
+        let mut e = Eliza::builder()
+            .script(script("Go on."))
+            .shared_script(Arc::new(script("Unused.")))
+            .memory_capacity(2)
+            .selection(Strategy::Random)
+            .seed(1)
+            .build()
+            .unwrap();
+        assert_eq!(1, e.fallback_scripts.len());
+        assert_eq!(Strategy::Random, e.selection);
+        for input in ["remember one", "remember two", "remember three"] {
+            assert_eq!("Do go on.", e.respond(input));
+        }
+        assert_eq!("Earlier you said two.", e.respond("Hmm."));
+        assert_eq!("Earlier you said three.", e.respond("Hmm."));
+        assert_eq!("Go on.", e.respond("Hmm."));
+
+        let mut e = Eliza::builder()
+            .script(script("Go on."))
+            .memorise(false)
+            .build()
+            .unwrap();
+        assert_eq!("Do go on.", e.respond("remember one"));
+        assert_eq!("Go on.", e.respond("Hmm."));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn clone_send_sync() {
+        fn assert_clone_send_sync<T: Clone + Send + Sync>() {}
+        assert_clone_send_sync::<Eliza>();
+        assert_clone_send_sync::<Script>();
+
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.seed(3);
+        e.respond("I remember my mother.");
+        let mut fork = e.clone();
+        assert!(Arc::ptr_eq(&e.script, &fork.script));
+        for input in ["Hmm.", "I remember my mother.", "You are like my father."] {
+            assert_eq!(e.respond(input), fork.respond(input));
+        }
+        assert_eq!(e.greet(), fork.greet());
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn forget_user_data() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.respond("My mother was kind.");
+        e.respond("I remember my mother.");
+        e.affect.insert(String::from("anger"), 2);
+        let usage = e.rule_usage().clone();
+
+        e.forget_user_data();
+        assert_eq!(
+            Snapshot {
+                rule_usage: usage,
+                turns: 2,
+                ..Snapshot::default()
+            },
+            e.snapshot()
+        );
+        assert_eq!(None, e.undo());
+    }
+
+    #[test]
+    fn banned_input() {
+        let script = crate::builder::ScriptBuilder::new()
+            .banned("darn")
+            .banned("Heck Off")
+            .banned_response("Let's keep it civil.")
+            .keyword("my", 2, |k| k.rule("(.*)my(.*)", ["Your $2?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder()
+            .script(script)
+            .content_filter(|input| input.contains("spam"))
+            .build()
+            .unwrap();
+
+        let res = e.respond_detailed("My DARN neighbour");
+        assert_eq!("Let's keep it civil.", res.text);
+        assert_eq!(ResponseSource::Filtered, res.source);
+        assert_eq!("Let's keep it civil.", e.respond("my neighbour, heck off!"));
+        assert_eq!("Let's keep it civil.", e.respond("my spam"));
+
+        //Only whole words are banned
+        assert_eq!("Your darning?", e.respond("my darning"));
+
+        //Control characters can't split a banned term to slip it past the check
+        assert_eq!("Let's keep it civil.", e.respond("my d\u{7}arn neighbour"));
+        assert_eq!(
+            "Let's keep it civil.",
+            e.respond("my \u{1b}[1mdarn\u{1b}[0m neighbour")
+        );
+        //The content filter is given the sanitized input too
+        assert_eq!("Let's keep it civil.", e.respond("my sp\u{7}am"));
+
+        //A fallback script's banned responses are used when the primary script has none
+        let primary = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .build()
+            .unwrap();
+        let fallback = crate::builder::ScriptBuilder::new()
+            .banned("darn")
+            .banned_response("Mind your language.")
+            .build()
+            .unwrap();
+        let mut e = Eliza::from_scripts(vec![primary, fallback]).unwrap();
+        assert_eq!("Mind your language.", e.respond("darn it"));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn sanitized_captures() {
+        let input = "I remember my \u{1b}[2Jmother\u{7}";
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.first_choice(true);
+        let res = e.respond(input);
+        assert!(res.contains("your mother"), "{}", res);
+        assert!(!res.contains(char::is_control), "{}", res);
+
+        let mut e = Eliza::builder()
+            .script(Script::from_file("scripts/doctor.json").unwrap())
+            .sanitization(Sanitization::Escape)
+            .first_choice(true)
+            .build()
+            .unwrap();
+        let res = e.respond(input);
+        assert!(res.contains("your \\x1b[2jmother\\x07"), "{}", res);
+    }
+
+    #[test]
+    fn phrase_and_keystack_limits() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("mother", 2, |k| k.rule("(.*)", ["Your mother?"]))
+            .keyword("dog", 1, |k| k.rule("(.*)", ["Your dog?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder()
+            .script(script)
+            .max_phrases(2)
+            .max_keystack(1)
+            .build()
+            .unwrap();
+
+        assert_eq!("Go on.", e.respond("well, um, my mother"));
+        assert_eq!("Your mother?", e.respond("well, my mother"));
+
+        let (_, keystack) = e.keystack(0, "my dog and my mother and my dog");
+        assert_eq!(VecDeque::from([0]), keystack);
+        let many = ", ".repeat(10_000) + "my dog";
+        assert_eq!("Go on.", e.respond(&many));
+    }
+
+    #[test]
+    fn rule_warnings() {
+        let mut e = Eliza::from_str(
+            r#"{"greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [], "keywords": [
+                {"key": "cat", "rank": 2, "rules": [
+                    {"memorise": false, "decomposition_rule": "(.*)cat(.*)",
+                     "reassembly_rules": ["Your $5?"]}
+                ]},
+                {"key": "dog", "rank": 1, "rules": [
+                    {"memorise": false, "decomposition_rule": "(.*)dog(.*)",
+                     "reassembly_rules": ["Dogs$2."]}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let err = e.try_respond("my cat and dog").unwrap_err();
+        assert_eq!(
+            "Rule '(.*)cat(.*)' of key 'cat': 'Your $5?' refers to a capture or synonym that \
+             doesn't exist",
+            err.to_string()
+        );
+
+        //The conversation carried on regardless
+        assert_eq!(1, e.history().len());
+        let res = e.respond_detailed("my cat and dog");
+        assert_eq!("Dogs.", res.text);
+        assert_eq!(1, res.warnings.len());
+        assert_eq!("Dogs.", e.try_respond("my dog").unwrap().text);
+    }
+
+    #[test]
+    fn greeting_order() {
+        let script = crate::builder::ScriptBuilder::new()
+            .greeting("Hi.")
+            .greeting("Hello.")
+            .farewell("Bye.")
+            .farewell("Farewell.")
+            .build()
+            .unwrap();
+        let e = Eliza::builder()
+            .script(script.clone())
+            .greeting_order(GreetingOrder::Sequential)
+            .build()
+            .unwrap();
+
+        assert_eq!("Hi.", e.greet());
+        assert_eq!("Hello.", e.greet());
+        assert_eq!("Hi.", e.greet());
+        assert_eq!("Bye.", e.farewell());
+        assert_eq!("Hello.", e.clone().greet());
+
+        let e = Eliza::builder().script(script).build().unwrap();
+        assert_eq!("Hello.", e.greet_nth(1));
+        assert_eq!("Hi.", e.greet_nth(2));
+        assert_eq!("Farewell.", e.farewell_nth(1));
+        assert_eq!("Hello, I am Eliza.", Eliza::default().greet_nth(0));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn runtime_keywords() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        let shared = Arc::clone(&e.script);
+        e.respond("I remember my mother.");
+        e.respond("Perhaps I could.");
+        let usage = e.rule_usage().clone();
+
+        let keyword = |key: &str, decomposition: &str, reassembly: &str| Keyword {
+            key: key.to_string(),
+            rank: 20,
+            tags: Vec::new(),
+            rules: vec![Rule {
+                memorise: false,
+                decomposition_rule: decomposition.to_string(),
+                reassembly_rules: vec![reassembly.to_string()],
+                ..Rule::default()
+            }],
+        };
+        e.add_keyword(keyword("pizza", "(.*)pizza(.*)", "Tell me about pizza $2."))
+            .unwrap();
+        assert_eq!(
+            "Tell me about pizza with anchovies.",
+            e.respond("I like pizza with anchovies")
+        );
+        assert!(!Arc::ptr_eq(&shared, &e.script));
+        assert!(shared.goto("pizza").is_none());
+
+        //Keywords with mistakes are refused, leaving the script as it was
+        assert!(e.add_keyword(keyword("cake", "(.*", "Cake?")).is_err());
+        assert!(e.add_keyword(keyword("cake", "(.*)", "Cake $2?")).is_err());
+        assert!(e
+            .add_keyword(keyword("cake", "(.*)", "GOTO biscuit"))
+            .is_err());
+        assert!(e.script.goto("cake").is_none());
+
+        //Replacing a keyword keeps only the new rules
+        e.add_keyword(keyword("pizza", "(.*)", "Pizza again?"))
+            .unwrap();
+        assert_eq!("Pizza again?", e.respond("pizza"));
+
+        assert_eq!("pizza", e.remove_keyword("pizza").unwrap().key);
+        assert!(e.remove_keyword("pizza").is_err());
+        assert!(e.remove_keyword("alike").is_err()); //Named by a GOTO
+
+        //The usage of the rules of the pizza keyword is forgotten, while the usage of every
+        //other rule survives the keywords after the one removed moving up
+        assert_eq!(0, e.script.goto_index("sorry").unwrap());
+        e.remove_keyword("sorry").unwrap();
+        assert_eq!(&usage, e.rule_usage());
+    }
+
+    #[test]
+    fn boosted_keywords() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("mother", 1, |k| k.rule("(.*)", ["Your mother?"]))
+            .keyword("dream", 3, |k| k.rule("(.*)", ["A dream?"]))
+            .keyword("cat", -2, |k| k.rule("(.*)", ["A cat?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder().script(script).build().unwrap();
+        let input = "I dream of my mother";
+        assert_eq!("A dream?", e.respond(input));
+
+        //The boost fades from +10 to +5, then is gone
+        assert!(e.boost_keyword("Mother", 10, 2));
+        assert_eq!("Your mother?", e.respond(input));
+        assert_eq!("Your mother?", e.respond(input));
+        assert_eq!("A dream?", e.respond(input));
+
+        e.boost_keyword("dream", -5, 1);
+        assert_eq!("Your mother?", e.respond(input));
+        e.boost_keyword("mother", -5, 3);
+        e.boost_keyword("mother", 0, 0);
+        assert_eq!("A dream?", e.respond(input));
+
+        //Negative ranks come last
+        assert_eq!("Your mother?", e.respond("My cat and my mother"));
+        assert!(!e.boost_keyword("father", 10, 2));
+
+        //Taking back a response leaves boosts as they were, and snapshots keep them
+        e.boost_keyword("mother", 10, 1);
+        let snapshot = e.snapshot();
+        e.respond(input);
+        e.undo();
+        assert_eq!(snapshot, e.snapshot());
+        let mut restored = e.clone();
+        restored.boost_keyword("mother", 0, 0);
+        restored.restore(snapshot).unwrap();
+        assert_eq!("Your mother?", restored.respond(input));
+    }
+
+    #[test]
+    fn tagged_keywords() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("my", 2, |k| {
+                k.rule("(.*)my (#family)(.*)", ["Who else in your family $2?"])
+                    .rule("(.*)my #pet(.*)", ["Do you have other pets?"])
+                    .rule("(.*)my(.*)", ["Your $2?"])
+            })
+            .keyword("mother", 1, |k| k.tag("family").rule("(.*)", ["A mother?"]))
+            .keyword("Father", 1, |k| {
+                k.tag("Family").tag("person").rule("(.*)", ["Go on."])
+            })
+            .keyword("friend", 1, |k| k.tag("person").rule("(.*)", ["A friend?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder().script(script).build().unwrap();
+
+        assert_eq!(
+            "Who else in your family mother?",
+            e.respond("my mother is kind")
+        );
+        assert_eq!(
+            "Who else in your family father?",
+            e.respond("my father is kind")
+        );
+        assert_eq!("Your friend is kind?", e.respond("my friend is kind"));
+
+        //A '#' that names no tag is left for the regex to match
+        assert_eq!("Do you have other pets?", e.respond("my #pet hamster"));
+    }
+
+    #[test]
+    fn none_keyword() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("NONE", 0, |k| {
+                k.rule("(.*)you(.*)", ["We were talking about you, not me."])
+            })
+            .keyword("none", 1, |k| k.rule("(.*)", ["None at all?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder().script(script).build().unwrap();
+
+        let res = e.respond_detailed("Do you like cats");
+        assert_eq!("We were talking about you, not me.", res.text);
+        assert_eq!(ResponseSource::Fallback, res.source);
+        assert_eq!(Some("NONE"), res.keyword.as_deref());
+        assert_eq!(0.0, res.confidence);
+
+        //The fallbacks are left for input the NONE keyword has no rule for
+        assert_eq!("Go on.", e.respond("I like cats"));
+        assert_eq!(2, e.snapshot().fallback_streak);
+
+        //The NONE keyword is never found in the input itself
+        assert_eq!("None at all?", e.respond("NONE of them"));
+    }
+
+    #[test]
+    fn recall_policy() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("mom", 1, |k| {
+                k.memorised_rule("(.*)my mom(.*)", ["Earlier you mentioned your mom."])
+            })
+            .build()
+            .unwrap();
+        let eliza = |recall| {
+            Eliza::builder()
+                .script(script.clone())
+                .recall(recall)
+                .build()
+                .unwrap()
+        };
+
+        //By default, a response may be recalled as soon as it is saved
+        let mut e = eliza(Recall::default());
+        assert_eq!("Earlier you mentioned your mom.", e.respond("my mom"));
+
+        let mut e = eliza(Recall {
+            min_turns: 2,
+            ..Recall::default()
+        });
+        assert_eq!("Go on.", e.respond("my mom"));
+        assert_eq!(vec![1], e.snapshot().memory_turns);
+        assert_eq!("Are you still there?", e.nudge());
+        assert_eq!("Go on.", e.respond("Hmm"));
+        assert_eq!("Earlier you mentioned your mom.", e.respond("Hmm"));
+        assert!(e.snapshot().memory_turns.is_empty());
+
+        let mut e = eliza(Recall {
+            after_fallbacks: 1,
+            ..Recall::default()
+        });
+        assert_eq!("Go on.", e.respond("my mom"));
+        assert_eq!("Earlier you mentioned your mom.", e.respond("Hmm"));
+
+        let mut e = eliza(Recall {
+            probability: 0.0,
+            ..Recall::default()
+        });
+        assert_eq!("Go on.", e.respond("my mom"));
+        assert_eq!("Go on.", e.respond("Hmm"));
+        assert_eq!(1, e.memory().len());
+        assert_eq!("Earlier you mentioned your mom.", e.nudge());
+
+        //Snapshots from before turns were recorded leave their memory free to recall
+        let mut e = eliza(Recall {
+            min_turns: 2,
+            ..Recall::default()
+        });
+        e.restore(Snapshot {
+            memory: vec!["Earlier you mentioned your mom.".to_string()],
+            turns: 1,
+            ..Snapshot::default()
+        })
+        .unwrap();
+        assert_eq!("Earlier you mentioned your mom.", e.respond("Hmm"));
+    }
+
+    #[test]
+    fn escalating_fallbacks() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .fallback("Please tell me more.")
+            .fallback("Tell me about your family.")
+            .fallback_order(FallbackOrder::Escalating)
+            .keyword("mother", 1, |k| k.rule("(.*)", ["Your mother?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder().script(script).build().unwrap();
+
+        assert_eq!("Go on.", e.respond("Hmm"));
+        assert_eq!("Please tell me more.", e.respond("Hmm"));
+        assert_eq!("Tell me about your family.", e.respond("Hmm"));
+        assert_eq!("Tell me about your family.", e.respond("Hmm"));
+        assert_eq!(4, e.snapshot().fallback_streak);
+
+        e.respond("Hmm");
+        e.undo();
+        assert_eq!(4, e.snapshot().fallback_streak);
+
+        assert_eq!("Your mother?", e.respond("My mother"));
+        assert_eq!("Go on.", e.respond("Hmm"));
+    }
+
+    #[test]
+    fn echoed_fallbacks() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("What makes you say $input?")
+            .fallback("Go on.")
+            .fallback_order(FallbackOrder::Escalating)
+            .reflection("i", "you", false)
+            .banned("darn")
+            .banned_response("Let's keep it civil.")
+            .keyword("mother", 1, |k| k.rule("(.*)", ["Your mother?"]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder()
+            .script(script)
+            .max_capture_len(20)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "What makes you say you hate mondays?",
+            e.respond("I hate Mondays. Always.")
+        );
+        assert_eq!("Go on.", e.respond("Hmm"));
+        e.respond("My mother");
+        assert_eq!(
+            "What makes you say you hate every...?",
+            e.respond("I hate every single Monday")
+        );
+
+        //With nothing to echo, only the other fallbacks are chosen from
+        e.respond("My mother");
+        assert_eq!("Go on.", e.respond("..."));
+
+        //Banned terms aren't echoed, even when split by control characters
+        e.respond("My mother");
+        assert_eq!(
+            "Let's keep it civil.",
+            e.respond("I hate d\u{7}arn Mondays")
+        );
+    }
 
-        //For whatever rule we use (if any), increment its usage count
-        if best_rule.is_some() {
-            let key = String::from(id) + &best_rule.clone().unwrap();
-            if let Some(usage) = self.rule_usage.get_mut(&key) {
-                *usage += 1;
-            }
-        }
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn fork_conversation() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.event_log(Vec::new());
+        e.seed(5);
+        e.respond("My mother was kind.");
 
-        best_rule
+        //Each branch carries on from the same point, without touching the original
+        let mut yes = e.fork();
+        let mut no = e.fork();
+        assert!(Arc::ptr_eq(&e.script, &yes.script));
+        assert_eq!(e.snapshot(), yes.snapshot());
+        yes.respond("Yes, I suppose.");
+        no.respond("No, never.");
+        assert_ne!(yes.snapshot(), no.snapshot());
+        assert_eq!(1, e.history().len());
+        assert!(yes.event_log.is_none());
+
+        assert_eq!(e.fork().respond("Hmm"), e.respond("Hmm"));
     }
-}
 
-fn transform(input: &str, transforms: &[Transform]) -> String {
-    let mut transformed = String::from(input);
-    for t in transforms {
-        let replacement = &t.word;
-        for equivalent in &t.equivalents {
-            transformed = transformed.replace(equivalent, replacement);
-        }
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn usage_reset() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        let first = e.respond("I remember my mother.");
+        assert_eq!(
+            Some(&1),
+            e.rule_usage()
+                .get("remember (.*)i remember(.+)Do you often think of $2?")
+        );
+        assert_ne!(first, e.respond("I remember my mother."));
+
+        e.reset_usage();
+        assert!(e.rule_usage().is_empty());
+        assert_eq!(first, e.respond("I remember my mother."));
     }
 
-    transformed
-}
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn stream_response() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        let mut fork = e.clone();
+        e.seed(1);
+        fork.seed(1);
 
-fn populate_keystack(
-    phrases: Vec<String>,
-    keywords: &[Keyword],
-) -> (Option<String>, VecDeque<Keyword>) {
-    let mut keystack: Vec<Keyword> = Vec::new();
-    let mut active_phrase: Option<String> = None;
+        let words: Vec<String> = e.respond_stream("I remember my mother.").collect();
+        assert!(words.len() > 1);
+        assert!(words[0].ends_with(' '));
+        assert_eq!(fork.respond("I remember my mother."), words.concat());
+    }
 
-    for phrase in phrases {
-        if active_phrase.is_some() {
-            //A phrase with keywords was found, break as we don't care about other phrases
-            break;
-        }
+    #[test]
+    fn history_window() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .keyword("said", 5, |k| {
+                k.rule("(.*)you said(.*)", ["I said '{last_response}'"])
+            })
+            .keyword("hello", 0, |k| k.rule("(.*) hello", ["Hello $1."]))
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder()
+            .script(script)
+            .history_capacity(2)
+            .build()
+            .unwrap();
 
-        let words = get_words(&phrase);
+        //Nothing has been said to quote yet
+        assert_eq!("Go on.", e.respond("What you said"));
+        assert_eq!("Hello there.", e.respond("there hello"));
+        assert_eq!("I said 'Hello there.'", e.respond("you said what?"));
 
-        for word in words {
-            if let Some(k) = keywords.iter().find(|k| k.key == word) {
-                keystack.push(k.clone());
-                active_phrase = Some(phrase.clone());
-            }
-        }
+        let history = e.history();
+        assert_eq!(2, history.len());
+        assert_eq!("there hello", history[0].input);
+        assert_eq!("I said 'Hello there.'", history[1].response);
     }
 
-    //sort the keystack with highest rank first
-    keystack.sort_by(|a, b| b.rank.cmp(&a.rank));
-
-    (active_phrase, VecDeque::from(keystack))
-}
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn undo_turn() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        assert_eq!(None, e.undo());
 
-fn permutations(decomposition: &str, synonyms: &[Synonym]) -> Vec<Regex> {
-    let mut permutations: Vec<String> = Vec::new();
-    let mut re_perms: Vec<Regex> = Vec::new();
-    let words = get_words(decomposition);
+        e.respond("I remember my mother.");
+        e.respond("My mother was kind."); //Saved to memory
+        let before = e.snapshot();
+        let usage = e.rule_usage.clone();
+        assert_eq!(1, e.memory.len());
 
-    if decomposition.matches('@').count() > 1 {
-        error!(
-            "Decomposition rules are limited to one synonym conversion: '{}'",
-            decomposition
+        let response = e.respond("Hmm");
+        assert_eq!(
+            Some(Exchange {
+                input: "Hmm".to_string(),
+                response,
+            }),
+            e.undo()
         );
-        return re_perms;
-    }
+        assert_eq!(before, e.snapshot());
+        assert_eq!(usage, e.rule_usage);
+        assert_eq!(None, e.undo());
 
-    //If no '@' symbol then just add to permutations
-    if decomposition.matches('@').count() == 0 {
-        permutations.push(decomposition.to_string());
-    } else {
-        //remember to add the base word without the @
-        permutations.push(decomposition.replace('@', ""));
+        //A nudge can't be taken back, so nor can the response before it
+        e.respond("I remember my mother.");
+        e.nudge();
+        assert_eq!(None, e.undo());
     }
 
-    for w in &words {
-        if w.contains('@') {
-            //Format example: '(.*) my (.* @family)'
-            let scrubbed = alphabet::STANDARD.scrub(w);
-            if let Some(synonym) = synonyms.iter().find(|s| s.word == scrubbed) {
-                for equivalent in &synonym.equivalents {
-                    permutations.push(
-                        decomposition
-                            .replace(&scrubbed, equivalent)
-                            .replace('@', ""),
-                    );
-                }
-            }
-        }
+    #[test]
+    fn undo_changes() {
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "hate", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": [{"text": "Hate?", "then": "Why?"}],
+                        "affect": {"anger": 1}
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        //A turn that changes nothing else copies nothing for undo
+        e.respond("Hello");
+        assert!(e.undo.as_ref().unwrap().saved.affect.is_none());
+
+        let before = e.snapshot();
+        assert_eq!("Hate?", e.respond("I hate it"));
+        assert_eq!(1, e.affect("anger"));
+        e.undo();
+        assert_eq!(before, e.snapshot());
+
+        //The follow-up question is asked again once its turn is taken back
+        e.respond("I hate it");
+        assert_eq!("Why?", e.respond("Hmm"));
+        e.undo();
+        assert_eq!("Why?", e.respond("Hmm"));
+        assert_eq!(1, e.affect("anger"));
     }
 
-    for p in permutations {
-        if let Ok(re) = Regex::new(&p) {
-            re_perms.push(re)
-        } else {
-            error!("Invalid decompostion rule: '{}'", decomposition);
-        }
-    }
-
-    re_perms
-}
-
-fn assemble(rule: &str, captures: &Captures<'_>, reflections: &[Reflection]) -> Option<String> {
-    let mut temp = String::from(rule);
-    let mut ok = true;
-    let words = get_words(rule);
-
-    //For each word, see if we need to swap anything out for a capture
-    for w in &words {
-        if w.contains('$') {
-            //Format example 'What makes you think I am $2 ?' which
-            //uses the second capture group of the regex
-            let scrubbed = alphabet::ALPHANUMERIC.scrub(w);
-            if let Ok(n) = scrubbed.parse::<usize>() {
-                if n < captures.len() + 1 {
-                    //indexing starts at 1
-                    //Perform reflection on the capture before subsitution
-                    temp = temp
-                        .replace(&scrubbed, &reflect(&captures[n], reflections))
-                        .replace('$', "");
-                } else {
-                    ok = false;
-                    error!("{} is outside capture range in: '{}'", n, rule);
-                }
-            } else {
-                ok = false;
-                error!("Contains invalid capture id: '{}'", rule);
-            }
-        }
+    #[test]
+    fn undo_without_history() {
+        let script = crate::builder::ScriptBuilder::new()
+            .fallback("Go on.")
+            .build()
+            .unwrap();
+        let mut e = Eliza::builder()
+            .script(script)
+            .history_capacity(0)
+            .build()
+            .unwrap();
 
-        if !ok {
-            break;
-        }
+        e.respond("Hello");
+        assert!(e.history().is_empty());
+        assert!(e.undo().is_some());
+        assert!(e.history().is_empty());
     }
 
-    if ok {
-        Some(temp)
-    } else {
-        None
+    #[test]
+    fn usage_per_keyword() {
+        let keyword = |key: &str| Keyword {
+            key: key.to_string(),
+            rank: 0,
+            tags: Vec::new(),
+            rules: vec![Rule {
+                decomposition_rule: String::from("(.*)"),
+                reassembly_rules: vec![String::from("One."), String::from("Two.")],
+                ..Default::default()
+            }],
+        };
+        let mut e = Eliza::from_scripts(vec![Script {
+            keywords: vec![keyword("cats"), keyword("dogs")],
+            ..Default::default()
+        }])
+        .unwrap();
+
+        //Rules that read alike in different keywords are counted apart
+        assert_eq!("One.", e.respond("cats"));
+        assert_eq!("One.", e.respond("dogs"));
+        assert_eq!("Two.", e.respond("cats"));
+        assert_eq!(Some(&1), e.rule_usage().get("cats (.*)One."));
+        assert_eq!(Some(&1), e.rule_usage().get("dogs (.*)One."));
+        assert_eq!(None, e.rule_usage().get("dogs (.*)Two."));
+
+        //The counts of each keyword survive a snapshot
+        let mut restored = e.clone();
+        restored.reset_usage();
+        restored.restore(e.snapshot()).unwrap();
+        assert_eq!(e.rule_usage, restored.rule_usage);
+        assert_eq!("Two.", restored.respond("dogs"));
+
+        //Counts saved without their keyword are given to every rule that reads alike
+        let mut snapshot = Snapshot::default();
+        snapshot.rule_usage.insert(String::from("(.*)One."), 1);
+        restored.restore(snapshot).unwrap();
+        assert_eq!(2, restored.rule_usage.len());
+        assert_eq!("Two.", restored.respond("cats"));
+        assert_eq!("Two.", restored.respond("dogs"));
     }
-}
 
-fn reflect(input: &str, reflections: &[Reflection]) -> String {
-    //we don't want to accidently re-reflect word pairs that have two-way reflection
-    let mut reflected_phrase = String::new();
-    let words = get_words(input);
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn snapshot_restore() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.respond("I remember my mother.");
+        e.memory.push_back(String::from("remember me"));
+        e.affect.insert(String::from("anger"), 2);
 
-    for w in words {
-        //Find reflection pairs that are applicable to this word
-        if let Some(reflect) = reflections
-            .iter()
-            .find(|r| r.word == w || return if r.twoway { r.inverse == w } else { false })
-        {
-            if reflect.word == w {
-                reflected_phrase.push_str(&reflect.inverse);
-            } else if reflect.twoway && reflect.inverse == w {
-                reflected_phrase.push_str(&reflect.word);
-            } else {
-                //Unlikely to happen, but print message just incase
-                error!("Invalid reflection for pair {:?} in: '{}'", reflect, input);
-            }
-        } else {
-            //No reflection required
-            reflected_phrase.push_str(&w);
-        }
+        let mut snapshot = e.snapshot();
+        snapshot.rule_usage.insert(String::from("(.*)gone"), 1);
+        let mut json = Vec::new();
+        snapshot.to_writer(&mut json).unwrap();
 
-        reflected_phrase.push(' '); //put a space after each word
+        let mut restored = Eliza::from_file("scripts/doctor.json").unwrap();
+        restored
+            .restore(Snapshot::from_reader(&json[..]).unwrap())
+            .unwrap();
+        assert_eq!(e.snapshot(), restored.snapshot());
+        assert_eq!(
+            e.respond("I remember my mother."),
+            restored.respond("I remember my mother.")
+        );
+
+        //Every element of a snapshot is optional
+        assert_eq!(
+            Snapshot::default(),
+            Snapshot::from_reader(&b"{}"[..]).unwrap()
+        );
     }
 
-    reflected_phrase.trim().to_string()
-}
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn snapshot_versions() {
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        let mut json = Vec::new();
+        e.snapshot().to_writer(&mut json).unwrap();
+        assert!(String::from_utf8(json).unwrap().contains("\"version\":3"));
 
-fn get_phrases(input: &str) -> Vec<String> {
-    input
-        .split(" but ")
-        .flat_map(|s| s.split(|c| c == '.' || c == ',' || c == '?'))
-        .map(|s| s.trim().to_string())
-        .collect()
-}
+        //Snapshots saved before the format was versioned are still restored
+        let v1 = br#"{"memory":["remember me"],"rule_usage":{"(.*)gone":1},"affect":{"anger":2}}"#;
+        let snapshot = Snapshot::from_reader(&v1[..]).unwrap();
+        assert_eq!(snapshot::VERSION, snapshot.version);
+        assert_eq!(vec![String::from("remember me")], snapshot.memory);
+        assert_eq!(Some(&2), snapshot.affect.get("anger"));
 
-fn get_words(phrase: &str) -> Vec<String> {
-    phrase.split_whitespace().map(|s| s.to_string()).collect()
-}
+        //Elements added by newer releases are ignored, unless the format has changed meaning
+        let newer = br#"{"version":3,"turns":3,"mood":"cheerful"}"#;
+        assert_eq!(3, Snapshot::from_reader(&newer[..]).unwrap().turns);
+        assert!(Snapshot::from_reader(&br#"{"version":4}"#[..]).is_err());
 
-//Returns NONE if not a goto, otherwise reutrns goto id
-fn is_goto(statement: &str) -> Option<String> {
-    match statement.contains("GOTO") {
-        true => Some(
-            statement
-                .replace("GOTO", "")
-                .replace(char::is_whitespace, ""),
-        ),
-        false => None,
+        //Snapshots deserialized by other means are checked as they are restored
+        e.respond("I remember my mother.");
+        let before = e.snapshot();
+        let newer: Snapshot = serde_json::from_slice(br#"{"version":4,"turns":3}"#).unwrap();
+        assert!(e.restore(newer).is_err());
+        assert_eq!(before, e.snapshot());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::script::Rule;
+    #[test]
+    fn confidence_heuristic() {
+        let score = |rank: i32, rule: &str, phrase: &str| {
+            confidence(
+                rank,
+                phrase,
+                &Regex::new(rule).unwrap().captures(phrase).unwrap(),
+                None,
+            )
+        };
+
+        assert_eq!(0.5, score(0, "(.*)", "i am sad"));
+        assert_eq!(0.75, score(0, "i am sad", "i am sad"));
+        assert!(score(0, "(.*)i am (.*)", "i am sad") > score(0, "(.*)am (.*)", "i am sad"));
+        assert!(score(10, "(.*)", "i am sad") > score(5, "(.*)", "i am sad"));
+        assert!(score(255, "i am sad", "i am sad") < 1.0);
+    }
 
     #[test]
-    fn perm_valid() {
-        let synonyms: Vec<Synonym> = vec![Synonym {
-            word: "family".to_string(),
-            equivalents: vec!["brother".to_string(), "mother".to_string()],
-        }];
+    fn goto_loop() {
+        let goto = |key: &str, target: &str| Keyword {
+            key: String::from(key),
+            rank: 0,
+            tags: Vec::new(),
+            rules: vec![Rule {
+                decomposition_rule: String::from("(.*)"),
+                reassembly_rules: vec![format!("GOTO {}", target)],
+                ..Default::default()
+            }],
+        };
+
+        let mut e: Eliza = Default::default();
+        e.set_script(Script {
+            fallbacks: vec![String::from("Go on.")],
+            keywords: vec![goto("hello", "hi"), goto("hi", "hello"), goto("hey", "hey")],
+            ..Default::default()
+        })
+        .unwrap();
 
-        let re_perms = permutations("(.*)my (.* @family)", &synonyms);
-        assert_eq!("(.*)my (.* family)", re_perms[0].as_str());
-        assert_eq!("(.*)my (.* brother)", re_perms[1].as_str());
-        assert_eq!("(.*)my (.* mother)", re_perms[2].as_str());
+        assert_eq!("Go on.", e.respond("hello"));
+        assert_eq!("Go on.", e.respond("hey"));
     }
 
     #[test]
-    fn perm_invalid() {
-        let synonyms: Vec<Synonym> = vec![Synonym {
-            word: "family".to_string(),
-            equivalents: vec!["brother".to_string(), "mother".to_string()],
-        }];
+    fn set_script_keeps_usage() {
+        let script = |reassembly_rules: &[&str]| Script {
+            keywords: vec![Keyword {
+                key: String::from("hello"),
+                rank: 0,
+                tags: Vec::new(),
+                rules: vec![Rule {
+                    memorise: false,
+                    decomposition_rule: String::from("(.*)"),
+                    reassembly_rules: reassembly_rules.iter().map(|a| a.to_string()).collect(),
+                    ..Default::default()
+                }],
+            }],
+            ..Default::default()
+        };
+        let mut e = Eliza::from_scripts(vec![script(&["dropped", "kept"])]).unwrap();
+        e.memory.push_back("remember me".to_string());
+        e.rule_usage.insert((0, 0, 0, 0), 5);
+        e.rule_usage.insert((0, 0, 0, 1), 3);
+
+        //The kept rule moves, but its usage follows it
+        e.set_script(script(&["kept"])).unwrap();
 
-        let re_perms = permutations("(.*)my (.* @family @fail)", &synonyms);
-        assert!(re_perms.is_empty());
+        assert_eq!(1, e.rule_usage.len());
+        assert_eq!(3, e.rule_usage[&(0, 0, 0, 0)]);
+        assert_eq!(3, e.rule_usage()["hello (.*)kept"]);
+        assert_eq!("remember me", e.memory[0]);
+        assert_eq!("hello", e.script.keywords[0].key);
     }
 
     #[test]
-    fn perm_simple() {
-        let synonyms: Vec<Synonym> = vec![Synonym {
-            word: "family".to_string(),
-            equivalents: vec!["brother".to_string(), "mother".to_string()],
-        }];
+    fn affect_conditions() {
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "hello", "rank": 0,
+                    "rules": [
+                        {
+                            "memorise": false, "decomposition_rule": "(.*)",
+                            "reassembly_rules": ["Go away."],
+                            "conditions": [{"variable": "anger", "min": 2}]
+                        },
+                        {
+                            "memorise": false, "decomposition_rule": "(.*)",
+                            "reassembly_rules": ["Hi."], "affect": {"anger": 1}
+                        }
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
 
-        let re_perms = permutations("(.*)my (.* dog)", &synonyms);
-        assert_eq!(1, re_perms.len());
-        assert_eq!("(.*)my (.* dog)", re_perms[0].as_str());
+        assert_eq!("Hi.", e.respond("hello"));
+        assert_eq!("Hi.", e.respond("hello"));
+        assert_eq!(2, e.affect("anger"));
+        assert_eq!("Go away.", e.respond("hello"));
+        assert_eq!(0, e.affect("fear"));
     }
 
     #[test]
-    fn assemble_rule_equal() {
-        let mut e: Eliza = Default::default();
+    fn reassembly_context() {
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "mother", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": [
+                            {"text": "Your mother again?", "if_last_keyword": "mother"},
+                            {"text": "We've talked a while.", "if_turn_gt": 5},
+                            {"text": "Calm down.", "if_var_set": "anger"},
+                            "Tell me about her."
+                        ]
+                    }]
+                }, {
+                    "key": "hate", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": ["Hate?"], "affect": {"anger": 1}
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        e.first_choice(true);
 
-        //Create a fake rule usage HashMap
-        let mut usages: HashMap<String, usize> = HashMap::new();
-        usages.insert("first".to_string(), 1);
-        usages.insert("second".to_string(), 1);
-        usages.insert("third".to_string(), 1);
-        usages.insert("fourth".to_string(), 1);
+        assert_eq!("Tell me about her.", e.respond("my mother"));
+        assert_eq!("Your mother again?", e.respond("my mother"));
+        assert_eq!("Hate?", e.respond("i hate it"));
+        assert_eq!("Calm down.", e.respond("my mother"));
+        assert_eq!("Go on.", e.respond("hmm"));
+        assert_eq!("We've talked a while.", e.respond("my mother"));
 
-        //All equal precedence, should just return the first
-        e.rule_usage = usages;
+        let restored = Eliza::from_str(&serde_json::to_string(&*e.script).unwrap()).unwrap();
         assert_eq!(
-            "first",
-            e.get_reassembly(
-                "",
-                &vec!(
-                    "first".to_string(),
-                    "second".to_string(),
-                    "third".to_string(),
-                    "fourth".to_string()
-                )
-            )
-            .unwrap()
+            e.script.keywords[0].rules[0].reassembly_context,
+            restored.script.keywords[0].rules[0].reassembly_context
         );
-        assert_eq!(2, e.rule_usage["first"]);
+        assert_eq!(
+            3,
+            restored.script.keywords[0].rules[0]
+                .reassembly_context
+                .iter()
+                .flatten()
+                .count()
+        );
+
+        //The same response may be given in different contexts
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "mother", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": [
+                            {"text": "Tell me more.", "if_turn_gt": 2},
+                            {"text": "Tell me more.", "if_last_keyword": "mother"},
+                            "Your mother?"
+                        ]
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        e.first_choice(true);
+
+        assert_eq!("Your mother?", e.respond("my mother"));
+        assert_eq!("Tell me more.", e.respond("my mother"));
+        assert_eq!("Go on.", e.respond("hmm"));
+        assert_eq!("Tell me more.", e.respond("my mother"));
     }
 
     #[test]
-    fn assemble_rule_smaller() {
-        let mut e: Eliza = Default::default();
+    fn follow_ups() {
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [{"word": "my", "inverse": "your", "twoway": false}],
+                "keywords": [{
+                    "key": "my", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)my (.*)",
+                        "reassembly_rules": [
+                            {"text": "Your $2?", "then": "How does your $2 make you feel?"}
+                        ]
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
 
-        //Create a fake rule usage HashMap
-        let mut usages: HashMap<String, usize> = HashMap::new();
-        usages.insert("first".to_string(), 7);
-        usages.insert("second".to_string(), 3);
-        usages.insert("third".to_string(), 2);
-        usages.insert("fourth".to_string(), 10);
+        assert_eq!("Your dog?", e.respond("my dog"));
+        let res = e.respond_detailed("my cat");
+        assert_eq!("How does your dog make you feel?", res.text);
+        assert_eq!(ResponseSource::FollowUp, res.source);
 
-        //One has been used less than the rest
-        e.rule_usage = usages;
+        e.respond("my cat");
+        let mut restored = Eliza::from_str(&serde_json::to_string(&*e.script).unwrap()).unwrap();
+        restored.restore(e.snapshot()).unwrap();
+        assert_eq!("How does your cat make you feel?", restored.respond("hmm"));
+        assert_eq!("Go on.", restored.respond("hmm"));
+
+        //The same response may be followed up differently, or not at all
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "dog", "rank": 0,
+                    "rules": [{
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": ["Dogs?", {"text": "Dogs?", "then": "Do you have one?"}]
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!("Dogs?", e.respond("dog"));
+        assert_eq!("Dogs?", e.respond("dog"));
+        assert_eq!("Do you have one?", e.respond("hmm"));
+        let restored = Eliza::from_str(&serde_json::to_string(&*e.script).unwrap()).unwrap();
         assert_eq!(
-            "third",
-            e.get_reassembly(
-                "",
-                &vec!(
-                    "first".to_string(),
-                    "second".to_string(),
-                    "third".to_string(),
-                    "fourth".to_string()
-                )
-            )
-            .unwrap()
+            e.script.keywords[0].rules[0],
+            restored.script.keywords[0].rules[0]
         );
-        assert_eq!(3, e.rule_usage["third"]);
     }
 
     #[test]
-    fn assemble_rule_unknown() {
-        let mut e: Eliza = Default::default();
+    fn nudges() {
+        let mut e = Eliza::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                "nudges": ["Still there?"], "synonyms": [], "reflections": [],
+                "keywords": [{
+                    "key": "dream", "rank": 0,
+                    "rules": [{
+                        "memorise": true, "decomposition_rule": "(.*)dream(.*)",
+                        "reassembly_rules": ["Tell me more about that dream."]
+                    }, {
+                        "memorise": false, "decomposition_rule": "(.*)",
+                        "reassembly_rules": ["Go on."]
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
 
-        //Create a fake rule usage HashMap
-        let mut usages: HashMap<String, usize> = HashMap::new();
-        usages.insert("first".to_string(), 7);
-        usages.insert("second".to_string(), 3);
-        usages.insert("third".to_string(), 2);
+        assert_eq!("Still there?", e.nudge());
+        assert_eq!("Go on.", e.respond("i had a dream"));
+        assert_eq!("Tell me more about that dream.", e.nudge());
+        assert_eq!("Still there?", e.nudge());
+        assert_eq!(1, e.snapshot().turns);
 
-        //One has never been used
-        e.rule_usage = usages;
-        assert_eq!(
-            "fourth",
-            e.get_reassembly(
-                "",
-                &vec!(
-                    "first".to_string(),
-                    "second".to_string(),
-                    "third".to_string(),
-                    "fourth".to_string()
-                )
-            )
-            .unwrap()
-        );
-        assert_eq!(1, e.rule_usage["fourth"]);
+        let mut e = Eliza::from_str(
+            r#"{"greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                "synonyms": [], "reflections": [], "keywords": []}"#,
+        )
+        .unwrap();
+        assert_eq!("Are you still there?", e.nudge());
     }
 
     #[test]
-    fn assemble_ok() {
-        let reflections: Vec<Reflection> = Vec::new();
-        let re = Regex::new(r"(.*) you are (.*)").unwrap();
-        let phrase = "I think that you are so stupid";
-        let cap = re.captures(phrase).unwrap();
+    fn case_folded_keywords() {
+        let script = r#"{
+            "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+            "synonyms": [], "reflections": [{"word": "IHR", "inverse": "unser", "twoway": false}],
+            "keywords": [{
+                "key": "STRAßE", "rank": 0,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)straße (.*)",
+                    "reassembly_rules": ["Was ist mit $2?"]
+                }]
+            }, {
+                "key": "ılık", "rank": 0,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)",
+                    "reassembly_rules": ["Ilık mı?"]
+                }]
+            }]%s
+        }"#;
 
-        let res = assemble("What makes you think I am $2?", &cap, &reflections);
-        assert_eq!(res.unwrap(), "What makes you think I am so stupid?");
+        let mut e = Eliza::from_str(&script.replace("%s", "")).unwrap();
+        assert_eq!("Was ist mit unser haus?", e.respond("Die STRASSE ihr Haus"));
+
+        //Only a Turkic folding takes 'ILIK' to 'ılık'
+        assert_ne!("Ilık mı?", e.respond("ILIK"));
+        let mut e =
+            Eliza::from_str(&script.replace("%s", r#", "case_folding": "turkic""#)).unwrap();
+        assert_eq!("Ilık mı?", e.respond("ILIK"));
     }
 
     #[test]
-    fn assemble_invalid_index() {
-        let reflections: Vec<Reflection> = Vec::new();
-        let re = Regex::new(r"(.*) you are (.*)").unwrap();
-        let phrase = "I think that you are so stupid";
-        let cap = re.captures(phrase).unwrap();
+    fn apostrophes() {
+        let script = r#"{
+            "greetings": [], "farewells": [], "fallbacks": [],
+            "transforms": [{"word": "i'm", "equivalents": ["im"]}],
+            "synonyms": [], "reflections": [{"word": "i’m", "inverse": "you're", "twoway": true}],
+            "keywords": [{
+                "key": "I’M", "rank": 0,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)i'm (.*)",
+                    "reassembly_rules": ["Why do you say $2?"]
+                }]
+            }]
+        }"#;
 
-        let res = assemble("What makes you think I am $5 ?", &cap, &reflections);
-        assert!(res.is_none());
+        let mut e = Eliza::from_str(script).unwrap();
+        assert_eq!(
+            "Why do you say sure i’m sad?",
+            e.respond("I’m sure you’re sad")
+        );
+        assert_eq!(
+            "Why do you say sure i’m tired?",
+            e.respond("Im sure youʼre tired")
+        );
+        assert_eq!("Why do you say sad?", e.respond("You’re sure I'm sad"));
     }
 
     #[test]
-    fn assemble_invalid_id() {
-        let reflections: Vec<Reflection> = Vec::new();
-        let re = Regex::new(r"(.*) you are (.*)").unwrap();
-        let phrase = "I think that you are so stupid";
-        let cap = re.captures(phrase).unwrap();
+    fn synonym_responses() {
+        let mut e = Eliza::from_str(
+            r#"{
+            "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+            "synonyms": [{"word": "family", "equivalents": ["mother", "father"]}],
+            "reflections": [],
+            "keywords": [{
+                "key": "my", "rank": 0,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)my (.*)@family(.*)",
+                    "reassembly_rules": ["Tell me more about your $@family."]
+                }]
+            }]
+        }"#,
+        )
+        .unwrap();
 
-        let res = assemble("What makes you think I am $a ?", &cap, &reflections);
-        assert!(res.is_none());
+        assert_eq!(
+            "Tell me more about your father.",
+            e.respond("My FATHER hates me")
+        );
+        assert_eq!(
+            "Tell me more about your family.",
+            e.respond("My family hates me")
+        );
+        assert_eq!(
+            "Tell me more about your mother.",
+            e.respond_candidates("I love my mother")[0].text
+        );
+    }
+
+    #[test]
+    fn set_script_invalid() {
+        let mut e: Eliza = Default::default();
+        e.rule_usage.insert((0, 0, 0, 0), 3);
+
+        let result = e.set_script(Script {
+            keywords: vec![Keyword {
+                key: String::from("hello"),
+                rank: 0,
+                tags: Vec::new(),
+                rules: vec![Rule {
+                    memorise: false,
+                    decomposition_rule: String::from("(.*"),
+                    reassembly_rules: vec![String::from("dropped")],
+                    ..Default::default()
+                }],
+            }],
+            ..Default::default()
+        });
+
+        assert!(result.is_err());
+        assert!(e.script.keywords.is_empty());
+        assert_eq!(3, e.rule_usage[&(0, 0, 0, 0)]);
     }
 
     #[test]
@@ -588,10 +3954,12 @@ mod tests {
             Transform {
                 word: String::from("computer"),
                 equivalents: vec![String::from("machine"), String::from("computers")],
+                ..Default::default()
             },
             Transform {
                 word: String::from("remember"),
                 equivalents: vec![String::from("recollect")],
+                ..Default::default()
             },
         ];
 
@@ -599,13 +3967,46 @@ mod tests {
             "computer will one day be the superior computer.",
             transform(
                 "computers will one day be the superior machine.",
-                &transforms
+                &transforms,
+                CaseFolding::Unicode
             )
         );
 
         assert_eq!(
             "I cant remember.",
-            transform("I cant recollect.", &transforms)
+            transform("I cant recollect.", &transforms, CaseFolding::Unicode)
+        );
+    }
+
+    #[test]
+    fn transform_overlapping() {
+        let transform_ = |word: &str, equivalents: &[&str], priority| Transform {
+            word: word.to_string(),
+            equivalents: equivalents.iter().map(|e| e.to_string()).collect(),
+            priority,
+        };
+        let mut transforms = vec![
+            transform_("me", &["i"], 0),
+            transform_("i am", &["i'm", "im"], 0),
+            transform_("you", &["me"], 0),
+            transform_("we", &["you and me"], 0),
+        ];
+
+        //The longest equivalent wins, and replacements aren't transformed again
+        assert_eq!(
+            "i am sure me told we, him",
+            transform(
+                "i'm sure i told you and me, him",
+                &transforms,
+                CaseFolding::Unicode
+            )
+        );
+
+        //Unless a higher priority transform overlaps it
+        transforms.push(transform_("thou", &["you"], 1));
+        assert_eq!(
+            "thou and you",
+            transform("you and me", &transforms, CaseFolding::Unicode)
         );
     }
 
@@ -615,39 +4016,48 @@ mod tests {
             Keyword {
                 key: String::from("hello"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("how"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("i"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
         ];
 
-        let phrases = get_phrases("hello how are you? i was feeling good today, but now i'm not.");
-        let (phrase, keystack) = populate_keystack(phrases, &keywords);
+        let phrases = get_phrases(
+            "hello how are you? i was feeling good today, but now i'm not.",
+            &DEFAULT_DELIMITERS,
+        );
+        let (phrase, keystack) = populate_keystack(phrases, &keywords, Normalizer::default(), None);
 
         assert_eq!("hello how are you", phrase.unwrap());
         assert_eq!(2, keystack.len());
-        assert_eq!("hello", keystack[0].key);
-        assert_eq!("how", keystack[1].key);
+        assert_eq!("hello", keywords[keystack[0]].key);
+        assert_eq!("how", keywords[keystack[1]].key);
     }
 
     #[test]
@@ -656,39 +4066,48 @@ mod tests {
             Keyword {
                 key: String::from("was"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("how"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("i"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
         ];
 
-        let phrases = get_phrases("spagetti meatballs? i was feeling good today, but now...");
-        let (phrase, keystack) = populate_keystack(phrases, &keywords);
+        let phrases = get_phrases(
+            "spagetti meatballs? i was feeling good today, but now...",
+            &DEFAULT_DELIMITERS,
+        );
+        let (phrase, keystack) = populate_keystack(phrases, &keywords, Normalizer::default(), None);
 
         assert_eq!("i was feeling good today", phrase.unwrap());
         assert_eq!(2, keystack.len());
-        assert_eq!("i", keystack[0].key);
-        assert_eq!("was", keystack[1].key);
+        assert_eq!("i", keywords[keystack[0]].key);
+        assert_eq!("was", keywords[keystack[1]].key);
     }
 
     #[test]
@@ -697,56 +4116,99 @@ mod tests {
             Keyword {
                 key: String::from("i"),
                 rank: 1,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("my"),
                 rank: 2,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("are"),
                 rank: 0,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
             Keyword {
                 key: String::from("alike"),
                 rank: 3,
+                tags: Vec::new(),
                 rules: vec![Rule {
                     memorise: false,
                     decomposition_rule: String::new(),
                     reassembly_rules: Vec::new(),
+                    ..Default::default()
                 }],
             },
         ];
 
-        let phrases = get_phrases("i love my dog - people think we are alike");
-        let (phrase, keystack) = populate_keystack(phrases, &keywords);
+        let phrases = get_phrases(
+            "i love my dog - people think we are alike",
+            &DEFAULT_DELIMITERS,
+        );
+        let (phrase, keystack) = populate_keystack(phrases, &keywords, Normalizer::default(), None);
 
         assert_eq!("i love my dog - people think we are alike", phrase.unwrap());
         assert_eq!(4, keystack.len());
-        assert_eq!("alike", keystack[0].key);
-        assert_eq!("my", keystack[1].key);
-        assert_eq!("i", keystack[2].key);
-        assert_eq!("are", keystack[3].key);
+        assert_eq!("alike", keywords[keystack[0]].key);
+        assert_eq!("my", keywords[keystack[1]].key);
+        assert_eq!("i", keywords[keystack[2]].key);
+        assert_eq!("are", keywords[keystack[3]].key);
+    }
+
+    #[test]
+    fn keystack_punctuation() {
+        let keywords: Vec<Keyword> = vec![Keyword {
+            key: String::from("mother"),
+            rank: 0,
+            tags: Vec::new(),
+            rules: vec![Rule {
+                memorise: false,
+                decomposition_rule: String::new(),
+                reassembly_rules: Vec::new(),
+                ..Default::default()
+            }],
+        }];
+
+        for input in &[
+            "i hate my mother!",
+            "my (mother) says so",
+            "\"mother\"; she said…",
+        ] {
+            let phrases = get_phrases(input, &DEFAULT_DELIMITERS);
+            let (phrase, keystack) =
+                populate_keystack(phrases, &keywords, Normalizer::default(), None);
+
+            //The punctuation remains in the phrase, for the captures
+            assert_eq!(*input, phrase.unwrap());
+            assert_eq!(1, keystack.len());
+        }
+        assert_eq!("can't", trim_punctuation("«can't»"));
     }
 
     #[test]
     fn phrase_spliting() {
-        let phrases =
-            get_phrases("Hello how are you, you look good. Let    me know what you think,of me?");
+        let phrases = get_phrases(
+            "Hello how are you, you look good. Let    me know what you think,of me?",
+            &DEFAULT_DELIMITERS,
+        );
 
         //check phrases are correct
         assert_eq!("Hello how are you", phrases[0]);
@@ -755,6 +4217,148 @@ mod tests {
         assert_eq!("of me", phrases[3]);
     }
 
+    #[test]
+    fn phrase_spliting_delimiters() {
+        let phrases = get_phrases(
+            "¿por qué no puedo dormir? estoy cansada pero feliz",
+            &["¿", "?", " pero "],
+        );
+        assert_eq!(
+            vec!("", "por qué no puedo dormir", "estoy cansada", "feliz"),
+            phrases
+        );
+    }
+
+    #[test]
+    fn keystack_fuzzy() {
+        let keywords: Vec<Keyword> = ["mother", "dream", "computer", "you"]
+            .iter()
+            .map(|key| Keyword {
+                key: key.to_string(),
+                rank: 0,
+                tags: Vec::new(),
+                rules: Vec::new(),
+            })
+            .collect();
+        let mut script = Script::default();
+        script.synonyms.push(Synonym {
+            word: "family".to_string(),
+            equivalents: vec!["brothers".to_string(), "mothers".to_string()],
+        });
+        let known = known_words(&script);
+        let keystack = |input: &str, fuzzy: bool| {
+            let phrases = get_phrases(input, &DEFAULT_DELIMITERS);
+            let (phrase, keystack) = populate_keystack(
+                phrases,
+                &keywords,
+                Normalizer::default(),
+                fuzzy.then_some(&known),
+            );
+            (
+                phrase,
+                keystack
+                    .iter()
+                    .map(|&k| keywords[k].key.clone())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!((None, vec!()), keystack("my mohter hates me", false));
+        assert_eq!(
+            (
+                Some("my mother hates me".to_string()),
+                vec!("mother".to_string())
+            ),
+            keystack("my mohter hates me", true)
+        );
+        assert_eq!(
+            (
+                Some("my mother's computer!".to_string()),
+                vec!("computer".to_string())
+            ),
+            keystack("my mother's compuetr!", true)
+        );
+        assert_eq!(
+            (
+                Some("did you dream".to_string()),
+                vec!("you".to_string(), "dream".to_string())
+            ),
+            keystack("did you dream", true)
+        );
+        assert_eq!((None, vec!()), keystack("yuo dreamily brother", true));
+
+        //Words the script or language already knows aren't taken for misspellings
+        assert_eq!((None, vec!()), keystack("the other one", true));
+        assert_eq!((None, vec!()), keystack("our mothers", true));
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(0, edit_distance("mother", "mother"));
+        assert_eq!(1, edit_distance("mohter", "mother"));
+        assert_eq!(1, edit_distance("mothr", "mother"));
+        assert_eq!(2, edit_distance("father", "mother"));
+        assert_eq!(3, edit_distance("", "you"));
+        assert_eq!(1, edit_distance("sueño", "sueno"));
+    }
+
+    #[test]
+    fn conjunction_splitting() {
+        let normalizer = Normalizer::default();
+        assert_eq!(
+            vec!("i feel sad", "my mother  left", "i was young"),
+            split_conjunction(
+                "i feel sad BECAUSE my mother  left because i was young",
+                "because",
+                normalizer
+            )
+        );
+        assert_eq!(
+            vec!("", "i said so", ""),
+            split_conjunction("so that i said so so that", "so that", normalizer)
+        );
+        assert_eq!(
+            vec!("my brand new android"),
+            split_conjunction("my brand new android", "and", normalizer)
+        );
+    }
+
+    #[test]
+    fn conjunction_priority() {
+        let script = r#"{
+            "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+            "synonyms": [], "reflections": [],
+            "keywords": [{
+                "key": "mother", "rank": 0,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)",
+                    "reassembly_rules": ["Mother: $1"]
+                }]
+            }, {
+                "key": "dream", "rank": 5,
+                "rules": [{
+                    "memorise": false, "decomposition_rule": "(.*)",
+                    "reassembly_rules": ["Dream: $1"]
+                }]
+            }],
+            "conjunctions": ["but", "and"]
+        }"#;
+
+        let mut e = Eliza::from_str(script).unwrap();
+        assert_eq!(
+            "Mother: i love my mother",
+            e.respond("I love my mother and I had a dream, but I am fine")
+        );
+        assert_eq!(
+            "Dream: my dream",
+            e.respond("I am fine but my dream and my mother")
+        );
+        assert_eq!(
+            "Dream: my dream is odd",
+            e.respond("Yes and my dream is odd")
+        );
+    }
+
     #[test]
     fn word_splitting() {
         let words = get_words("Hello how are you");
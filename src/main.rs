@@ -11,6 +11,166 @@
 //! ...
 //! ```
 //!
+//! Further scripts may follow the first; they are consulted in order whenever the scripts before
+//! them can't find a response:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run scripts/pirate.json scripts/doctor.json
+//! ...
+//! ```
+//!
+//! A script location of `-` reads the script from stdin instead, with the conversation following
+//! it. This suits sandboxes without a file system, such as the `wasm32-wasip1` build:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo build --release --target wasm32-wasip1 --bin eliza
+//! user@foo(eliza) ~> cat scripts/doctor.json - | wasmtime target/wasm32-wasip1/release/eliza.wasm -
+//! ...
+//! ```
+//!
+//! ## Editing scripts
+//!
+//! When built with the `watch` feature, the `--watch` flag will reload the script whenever it
+//! changes on disk, keeping the conversation going:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run --features watch -- --watch scripts/doctor.json
+//! ...
+//! ```
+//!
+//! ## Nudging a quiet user
+//!
+//! The `--idle-secs` flag has ELIZA prompt the user whenever they have been quiet for that many
+//! seconds, with one of the script's `nudges` or something she remembers:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run -- --idle-secs 60 scripts/doctor.json
+//! ...
+//! ```
+//!
+//! ## Typing
+//!
+//! The `--typing` flag has ELIZA print each response as though she were typing it, pausing after
+//! punctuation. `--typing-speed N` sets how many characters she types each second (30 by
+//! default):
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run -- --typing-speed 60 scripts/doctor.json
+//! ...
+//! ```
+//!
+//! ## Driving ELIZA from another program
+//!
+//! The `--json` flag switches to a line-based protocol that is simple to drive from a subprocess.
+//! Each line of input is a `json` object, either `{"type": "message", "text": "..."}` or
+//! `{"type": "quit"}`, and each line of output is a `json` object describing ELIZA's reply:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run run --json scripts/doctor.json
+//! {"text":"Is something troubling you?","type":"greeting"}
+//! {"type": "message", "text": "Well, my boyfriend made me come here."}
+//! {"confidence":0.5583333333333333,"from_memory":false,"keyword":"my","rule":"(.*)my(.+)","source":"keyword","text":"Your boyfriend made you come here?","type":"response"}
+//! {"type": "quit"}
+//! {"text":"Goodbye.  It was nice talking to you.","type":"farewell"}
+//! ```
+//!
+//! ## Serving ELIZA over HTTP
+//!
+//! When built with the `server` feature, the `serve` subcommand hosts a script behind a small
+//! REST API (see the `server` module for its endpoints):
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run --features server -- serve --script scripts/doctor.json --port 8080
+//! ...
+//! ```
+//!
+//! Built with the `store-sqlite` feature as well, `--sessions-db sessions.db` keeps every
+//! conversation in a SQLite database, so that a restarted server carries on where it left off.
+//!
+//! The `serve-tcp` subcommand, built with the default `tcp` feature, instead hosts a script for
+//! telnet clients, each connection having its own conversation:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run serve-tcp --script scripts/doctor.json --port 2323
+//! ...
+//! user@foo(~) ~> telnet localhost 2323
+//! ```
+//!
+//! ## Watching ELIZA talk to herself
+//!
+//! The `duel` subcommand has two scripts converse with one another, printing the transcript. A
+//! seed makes the transcript repeatable, and `--json` prints each line as a `json` object:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run duel scripts/doctor.json scripts/pirate.json --turns 50 --seed 1
+//! ...
+//! ```
+//!
+//! ## Inspecting a script
+//!
+//! The `info` subcommand summarises a script at a glance, or as a `json` object with `--json`:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run info scripts/doctor.json
+//! Keywords             32
+//! ...
+//! ```
+//!
+//! The `diff` subcommand compares two versions of a script by their keywords, rules and
+//! synonyms, rather than line by line:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run diff old.json scripts/doctor.json
+//! + keyword 'dream'
+//! ~ keyword 'my' (rank 2 -> 3)
+//!     ~ rule '(.*)my(.+)'
+//! ```
+//!
+//! The `fmt` subcommand rewrites scripts in a canonical format, with keywords sorted by rank and
+//! then key. `--check` only reports the scripts that aren't formatted, failing if there are any,
+//! and `--minify` writes each script on a single line:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run fmt --check scripts/*.json
+//! ...
+//! ```
+//!
+//! The `validate` subcommand checks scripts for every mistake that would otherwise only be
+//! found mid-conversation, such as an invalid regex or a `GOTO` to a missing keyword, failing if
+//! there are any:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run validate scripts/doctor.json
+//! scripts/doctor.json is valid
+//! ```
+//!
+//! The `convert` subcommand converts a script between formats: `json`; `yaml` and `toml`, with
+//! the features of the same name; the scripts of other ELIZAs, `hayden`, `elizabot` and `slip`
+//! (see the `formats` module); and `dir`, the layout of early versions of this crate with each
+//! part of the script in a file of its own. The formats default to the layout or extension of
+//! each location, and a script that the output format can't hold all of is refused:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run convert --from dir --to json old_script/ new.json
+//! user@foo(eliza) ~> cargo run convert --from hayden doctor.script doctor.json
+//! user@foo(eliza) ~> cargo run convert --from slip --to yaml DOCTOR doctor.yaml
+//! ...
+//! ```
+//!
+//! ## Testing a script
+//!
+//! The `test` subcommand runs the tests embedded in a script, and plays any golden transcripts
+//! that follow it (see the `transcript` module for their format). With `--junit`, a JUnit XML
+//! report is also written for CI:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> cargo run test scripts/doctor.json tests/transcripts/doctor.txt
+//! test tests[0] ... ok
+//! test doctor.txt:2 ... ok
+//! ...
+//! test result: ok. 12 passed; 0 failed
+//! ```
+//!
 //! ## Stopping ELIZA
 //!
 //! To stop ELIZA, simply supply the `/quit` command:
@@ -27,43 +187,737 @@
 //!
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+use eliza::formats::Format;
+#[cfg(feature = "server")]
+use eliza::pool::ElizaPool;
+use eliza::script::Script;
+#[cfg(feature = "server")]
+use eliza::server::Server;
+#[cfg(feature = "tcp")]
+use eliza::tcp::TcpServer;
+use eliza::transcript::Transcript;
+use eliza::typing::Typing;
+#[cfg(feature = "watch")]
+use eliza::watch::ScriptWatcher;
+use eliza::{Eliza, ResponseSource};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use std::{env, io, process, thread};
 
-use eliza::Eliza;
-use std::io::Write;
-use std::{env, io, thread, time};
+//A line of input when running with '--json'
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Request {
+    Message { text: String },
+    Quit,
+}
+
+//What happened next in an interactive session
+enum Event {
+    Line(String),
+    Idle,
+    End,
+}
+
+//Reads the user's input, on a thread of its own when the user should be nudged while idle
+struct Input {
+    idle: Option<(Duration, Receiver<io::Result<String>>)>,
+}
+
+impl Input {
+    fn new(idle: Option<Duration>) -> Input {
+        let idle = idle.map(|timeout| {
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || loop {
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if tx.send(Ok(line)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        break;
+                    }
+                }
+            });
+            (timeout, rx)
+        });
+
+        Input { idle }
+    }
+
+    fn next(&self) -> io::Result<Event> {
+        match &self.idle {
+            Some((timeout, rx)) => match rx.recv_timeout(*timeout) {
+                Ok(line) => Ok(Event::Line(line?)),
+                Err(RecvTimeoutError::Timeout) => Ok(Event::Idle),
+                Err(RecvTimeoutError::Disconnected) => Ok(Event::End),
+            },
+            None => {
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line)? {
+                    0 => Ok(Event::End),
+                    _ => Ok(Event::Line(line)),
+                }
+            }
+        }
+    }
+}
 
 fn main() {
     env_logger::init();
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        error!("Usage of eliza is: ./eliza [SCRIPT]");
-        panic!("Not enough arguments");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|a| a.as_str()) {
+        Some("run") => chat(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        Some("serve-tcp") => serve_tcp(&args[1..]),
+        Some("duel") => duel(&args[1..]),
+        Some("info") => info(&args[1..]),
+        Some("diff") => diff(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("test") => test(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        Some("validate") => validate(&args[1..]),
+        _ => chat(&args),
+    };
+
+    //Report failures rather than panicking, as some runtimes (e.g. WASI) handle panics poorly
+    if let Err(e) = result {
+        error!("{}", e);
+        process::exit(1);
+    }
+}
+
+//Returns the value following a flag, e.g. '--port 8080'
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|a| a.as_str())
+}
+
+//Loads a script from the file system, or from stdin if the location is '-'
+fn load_script(location: &str) -> Result<Script, Box<dyn Error>> {
+    if location != "-" {
+        info!("Loading {}", location);
+        return Script::from_file(location);
+    }
+
+    //Only the script itself is consumed, leaving the rest of stdin for the conversation
+    info!("Loading script from stdin...");
+    let stdin = io::stdin();
+    let mut lock = stdin.lock();
+    let script = serde_json::Deserializer::from_reader(&mut lock)
+        .into_iter::<Script>()
+        .next()
+        .ok_or("Expected a script on stdin")??;
+    lock.read_line(&mut String::new())?; //Skip the remainder of the script's last line
+
+    Ok(script)
+}
+
+#[cfg(feature = "server")]
+fn serve(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = flag_value(args, "--script")
+        .ok_or("Usage of eliza is: ./eliza serve --script [SCRIPT] [--host HOST] [--port PORT]")?;
+    let host = flag_value(args, "--host").unwrap_or("127.0.0.1");
+    let port = flag_value(args, "--port").unwrap_or("8080");
+    let idle = match flag_value(args, "--idle-secs") {
+        Some(s) => s.parse().map_err(|_| "Invalid idle timeout")?,
+        None => 30 * 60,
+    };
+
+    let pool =
+        ElizaPool::new(load_script(path)?).idle_timeout(std::time::Duration::from_secs(idle));
+    let mut server = Server::with_pool(format!("{}:{}", host, port), pool)?;
+    if let Some(db) = flag_value(args, "--sessions-db") {
+        server = sessions_db(server, db)?;
     }
+    println!("Serving {} on http://{}:{}", path, host, port);
+    server.run();
+    Ok(())
+}
+
+#[cfg(all(feature = "server", feature = "store-sqlite"))]
+fn sessions_db(server: Server, path: &str) -> Result<Server, Box<dyn Error>> {
+    let store = eliza::store::SqliteSessionStore::open(path)?;
+    Ok(server.session_store(store))
+}
+
+#[cfg(all(feature = "server", not(feature = "store-sqlite")))]
+fn sessions_db(_server: Server, _path: &str) -> Result<Server, Box<dyn Error>> {
+    Err("eliza was built without the 'store-sqlite' feature".into())
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_args: &[String]) -> Result<(), Box<dyn Error>> {
+    Err("eliza was built without the 'server' feature".into())
+}
 
-    let mut eliza = Eliza::from_file(&args[1]).expect("Eliza failed to load");
-    println!("\nEnter '/quit' to leave the session.\n");
-    println!("{}\n", eliza.greet()); //eliza greets the user
+#[cfg(feature = "tcp")]
+fn serve_tcp(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = flag_value(args, "--script").ok_or(
+        "Usage of eliza is: ./eliza serve-tcp --script [SCRIPT] [--host HOST] [--port PORT]",
+    )?;
+    let host = flag_value(args, "--host").unwrap_or("127.0.0.1");
+    let port = flag_value(args, "--port").unwrap_or("2323");
 
+    let server = TcpServer::bind(format!("{}:{}", host, port), load_script(path)?)?;
+    println!("Serving {} on telnet://{}:{}", path, host, port);
+    server.run();
+    Ok(())
+}
+
+#[cfg(not(feature = "tcp"))]
+fn serve_tcp(_args: &[String]) -> Result<(), Box<dyn Error>> {
+    Err("eliza was built without the 'tcp' feature".into())
+}
+
+fn duel(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let json = args.iter().any(|a| a == "--json");
+    let turns = match flag_value(args, "--turns") {
+        Some(t) => t.parse().map_err(|_| "Invalid number of turns")?,
+        None => 20,
+    };
+    let seed: Option<u64> = match flag_value(args, "--seed") {
+        Some(s) => Some(s.parse().map_err(|_| "Invalid seed")?),
+        None => None,
+    };
+
+    //The scripts are whatever remains once the flags and their values are removed
+    let mut locations = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--turns" | "--seed" => {
+                rest.next();
+            }
+            a if a.starts_with("--") => {}
+            a => locations.push(a),
+        }
+    }
+    if locations.len() != 2 {
+        return Err(
+            "Usage of eliza is: ./eliza duel [SCRIPT_A] [SCRIPT_B] [--turns N] [--seed N] [--json]"
+                .into(),
+        );
+    }
+
+    let mut first = Eliza::from_scripts(vec![load_script(locations[0])?])?;
+    let mut second = Eliza::from_scripts(vec![load_script(locations[1])?])?;
+    if let Some(seed) = seed {
+        first.seed(seed);
+        second.seed(seed.wrapping_add(1));
+    }
+
+    //Name each speaker after their script, e.g. 'doctor'
+    let names: Vec<String> = locations
+        .iter()
+        .map(|l| {
+            Path::new(l)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_uppercase())
+                .unwrap_or_else(|| l.to_string())
+        })
+        .collect();
+
+    for line in eliza::duel::duel(&mut first, &mut second, turns) {
+        if json {
+            print_json(json!({
+                "speaker": names[line.speaker],
+                "text": line.text,
+                "source": line.source,
+            }))?;
+        } else {
+            println!("{}: {}\n", names[line.speaker], line.text);
+        }
+    }
+
+    Ok(())
+}
+
+fn info(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let json = args.iter().any(|a| a == "--json");
+    let location = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .ok_or("Usage of eliza is: ./eliza info [SCRIPT] [--json]")?;
+
+    let stats = load_script(location)?.stats();
+    if json {
+        print_json(json!(stats))?;
+        return Ok(());
+    }
+
+    let max_rank = stats.max_rank.map_or("-".to_string(), |r| r.to_string());
+    let lines = [
+        ("Keywords", stats.keywords.to_string()),
+        ("Highest rank", max_rank),
+        ("Decomposition rules", stats.rules.to_string()),
+        ("Rules per keyword", format!("{:.1}", stats.average_rules)),
+        ("Memorised rules", stats.memorised_rules.to_string()),
+        ("Reassembly rules", stats.reassembly_rules.to_string()),
+        ("GOTOs", stats.gotos.to_string()),
+        ("Synonyms", stats.synonyms.to_string()),
+        ("Reflections", stats.reflections.to_string()),
+        ("Transforms", stats.transforms.to_string()),
+        ("Greetings", stats.greetings.to_string()),
+        ("Farewells", stats.farewells.to_string()),
+        ("Fallbacks", stats.fallbacks.to_string()),
+    ];
+    for (label, value) in &lines {
+        println!("{:<21}{}", label, value);
+    }
+
+    Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let json = args.iter().any(|a| a == "--json");
+    let locations: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    if locations.len() != 2 {
+        return Err("Usage of eliza is: ./eliza diff [OLD_SCRIPT] [NEW_SCRIPT] [--json]".into());
+    }
+
+    let diff = load_script(locations[0])?.diff(&load_script(locations[1])?);
+    if json {
+        print_json(json!(diff))?;
+    } else if diff.is_empty() {
+        println!("The scripts are equivalent");
+    } else {
+        print!("{}", diff);
+    }
+
+    Ok(())
+}
+
+fn fmt(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let check = args.iter().any(|a| a == "--check");
+    let minify = args.iter().any(|a| a == "--minify");
+    let locations: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    if locations.is_empty() {
+        return Err("Usage of eliza is: ./eliza fmt [--check] [--minify] [SCRIPT...]".into());
+    }
+
+    let mut unformatted = 0;
+    for location in locations {
+        //A script from stdin is written to stdout
+        if location == "-" {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            print!("{}", Script::format(&bytes, minify)?);
+            continue;
+        }
+
+        let bytes = std::fs::read(location)?;
+        let formatted =
+            Script::format(&bytes, minify).map_err(|e| format!("{}: {}", location, e))?;
+        if formatted.as_bytes() == bytes.as_slice() {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", location);
+            unformatted += 1;
+        } else {
+            std::fs::write(location, formatted)?;
+            println!("Formatted {}", location);
+        }
+    }
+
+    match unformatted {
+        0 => Ok(()),
+        n => Err(format!("{} script(s) are not formatted", n).into()),
+    }
+}
+
+fn validate(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let locations: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    if locations.is_empty() {
+        return Err("Usage of eliza is: ./eliza validate [SCRIPT...]".into());
+    }
+
+    let mut invalid = 0;
+    for location in locations {
+        let bytes = match location.as_str() {
+            "-" => {
+                let mut bytes = Vec::new();
+                io::stdin().read_to_end(&mut bytes)?;
+                bytes
+            }
+            _ => std::fs::read(location).map_err(|e| format!("{}: {}", location, e))?,
+        };
+
+        //A script that can't be parsed at all is reported like any other problem
+        let problems = Script::problems_in(&bytes).unwrap_or_else(|e| vec![e.to_string()]);
+        if problems.is_empty() {
+            println!("{} is valid", location);
+            continue;
+        }
+
+        invalid += 1;
+        for problem in &problems {
+            println!("{}: {}", location, problem);
+        }
+    }
+
+    match invalid {
+        0 => Ok(()),
+        n => Err(format!("{} script(s) are invalid", n).into()),
+    }
+}
+
+fn convert(args: &[String]) -> Result<(), Box<dyn Error>> {
+    const USAGE: &str = "Usage of eliza is: ./eliza convert [--from FORMAT] [--to FORMAT] [INPUT] \
+                         [OUTPUT], where FORMAT is json, yaml, toml, hayden, elizabot, slip or dir";
+
+    let mut locations = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--from" | "--to" => {
+                rest.next();
+            }
+            a if a.starts_with("--") => {}
+            a => locations.push(a),
+        }
+    }
+    let (input, output) = match locations.as_slice() {
+        [input, output] => (*input, *output),
+        _ => return Err(USAGE.into()),
+    };
+
+    //Unless given, a format is guessed from whether its location is a directory, or else from its
+    //extension, where a directory is None
+    let format = |flag: &str, location: &str| match flag_value(args, flag) {
+        Some("dir") => Ok(None),
+        Some(f) => f.parse::<Format>().map(Some),
+        None if Path::new(location).is_dir() || location.ends_with('/') => Ok(None),
+        None => Ok(Some(
+            Format::from_extension(location).unwrap_or(Format::Json),
+        )),
+    };
+
+    let script = match format("--from", input)? {
+        None => Script::from_dir(input)?,
+        Some(Format::Json) => load_script(input)?,
+        Some(f) => {
+            let text = match input {
+                "-" => io::read_to_string(io::stdin())?,
+                _ => std::fs::read_to_string(input)?,
+            };
+            f.parse(&text).map_err(|e| format!("{}: {}", input, e))?
+        }
+    };
+    match format("--to", output)? {
+        None => script.to_dir(output)?,
+        Some(f) => {
+            let text = f.write(&script)?;
+            match output {
+                "-" => print!("{}", text),
+                _ => std::fs::write(output, text)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//A test case run by the 'test' subcommand, from a script or from one of its transcripts
+struct Case {
+    suite: String,
+    name: String,
+    failures: Vec<String>,
+}
+
+fn test(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let junit = flag_value(args, "--junit");
+
+    //The script and transcripts are whatever remains once the flags and their values are removed
+    let mut locations = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--junit" => {
+                rest.next();
+            }
+            a if a.starts_with("--") => {}
+            a => locations.push(a),
+        }
+    }
+    let (location, transcripts) = locations
+        .split_first()
+        .ok_or("Usage of eliza is: ./eliza test [SCRIPT] [TRANSCRIPT...] [--junit REPORT]")?;
+
+    let script = load_script(location)?;
+    let mut cases: Vec<Case> = script
+        .run_embedded_tests()
+        .into_iter()
+        .enumerate()
+        .map(|(i, result)| Case {
+            suite: location.to_string(),
+            name: format!("tests[{}]", i),
+            failures: result.failures,
+        })
+        .collect();
+
+    for path in transcripts {
+        let transcript = Transcript::load(path).map_err(|e| format!("{}: {}", path, e))?;
+        let name = Path::new(path)
+            .file_name()
+            .map_or(path.to_string(), |n| n.to_string_lossy().to_string());
+        cases.extend(transcript.run(&script).into_iter().map(|turn| Case {
+            suite: path.to_string(),
+            name: format!("{}:{}", name, turn.line),
+            failures: match turn.passed() {
+                true => Vec::new(),
+                false => vec![format!(
+                    "Expected '{}' in reply to '{}', but ELIZA said '{}'",
+                    turn.expected, turn.input, turn.response
+                )],
+            },
+        }));
+    }
+
+    for case in &cases {
+        let outcome = if case.failures.is_empty() {
+            "ok"
+        } else {
+            "FAILED"
+        };
+        println!("test {} ... {}", case.name, outcome);
+    }
+
+    let failed: Vec<&Case> = cases.iter().filter(|c| !c.failures.is_empty()).collect();
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for case in &failed {
+            for failure in &case.failures {
+                println!("    {}: {}", case.name, failure);
+            }
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        cases.len() - failed.len(),
+        failed.len()
+    );
+
+    if let Some(report) = junit {
+        std::fs::write(report, junit_report(&cases))?;
+    }
+
+    match failed.len() {
+        0 => Ok(()),
+        n => Err(format!("{} test(s) failed", n).into()),
+    }
+}
+
+//Formats test cases as a JUnit XML report, with a test suite for the script and each transcript
+fn junit_report(cases: &[Case]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    let mut suites: Vec<&str> = cases.iter().map(|c| c.suite.as_str()).collect();
+    suites.dedup();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for suite in suites {
+        let cases: Vec<&Case> = cases.iter().filter(|c| c.suite == suite).collect();
+        let failures = cases.iter().filter(|c| !c.failures.is_empty()).count();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            escape(suite),
+            cases.len(),
+            failures
+        ));
+        for case in cases {
+            let name = escape(&case.name);
+            if case.failures.is_empty() {
+                xml.push_str(&format!("    <testcase name=\"{}\"/>\n", name));
+                continue;
+            }
+
+            xml.push_str(&format!("    <testcase name=\"{}\">\n", name));
+            for failure in &case.failures {
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\"/>\n",
+                    escape(failure)
+                ));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+
+    xml
+}
+
+fn chat(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let watch = args.iter().any(|a| a == "--watch");
+    let json = args.iter().any(|a| a == "--json");
+    let speed = match flag_value(args, "--typing-speed") {
+        Some(s) => Some(s.parse().map_err(|_| "Invalid typing speed")?),
+        None => None,
+    };
+    let typing = speed.map_or(Typing::default(), Typing::new);
+    let typed = speed.is_some() || args.iter().any(|a| a == "--typing");
+    let mut idle = match flag_value(args, "--idle-secs") {
+        Some(s) => match s.parse() {
+            Ok(0) => return Err("The idle timeout must be at least one second".into()),
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => return Err("Invalid idle timeout".into()),
+        },
+        None => None,
+    };
+
+    //The scripts are whatever remains once the flags and their values are removed
+    let mut locations = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--idle-secs" | "--typing-speed" => {
+                rest.next();
+            }
+            a if a.starts_with("--") => {}
+            a => locations.push(a),
+        }
+    }
+    if locations.is_empty() {
+        return Err(
+            "Usage of eliza is: ./eliza [run] [--watch] [--json] [--typing] [--typing-speed N] \
+                    [--idle-secs N] [SCRIPT] [FALLBACK_SCRIPT...]"
+                .into(),
+        );
+    }
+
+    //WASI has no threads, so can't wait for input with a timeout
+    if cfg!(target_os = "wasi") && idle.is_some() {
+        warn!("Ignoring '--idle-secs', which is unsupported by WASI");
+        idle = None;
+    }
+
+    let mut scripts = Vec::new();
+    for location in &locations {
+        scripts.push(load_script(location)?);
+    }
+    let mut eliza = Eliza::from_scripts(scripts)?;
+
+    #[cfg(feature = "watch")]
+    let watcher = match watch {
+        true => Some(ScriptWatcher::new(locations[0])?),
+        false => None,
+    };
+    #[cfg(not(feature = "watch"))]
+    {
+        if watch {
+            warn!("Ignoring '--watch', eliza was built without the 'watch' feature");
+        }
+    }
+
+    if json {
+        print_json(json!({ "type": "greeting", "text": eliza.greet() }))?;
+    } else {
+        println!("\nEnter '/quit' to leave the session.\n");
+        println!("{}\n", eliza.greet()); //eliza greets the user
+    }
+
+    let reader = Input::new(idle);
     loop {
-        print!("> ");
-        io::stdout().flush().expect("Failed to read line.");
-
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line.");
-
-        match input.as_ref() {
-            "/quit\n" => break,
-            //Based on the rules in the script, eliza responds to the given input
-            _ => {
-                //Insert short delay to make eliza seem like she's thinking
-                thread::sleep(time::Duration::from_millis(300));
-                println!("{}\n", eliza.respond(&input));
+        if !json {
+            print!("> ");
+            io::stdout().flush()?;
+        }
+
+        //The session also ends when there is no more input
+        let input = match reader.next()? {
+            Event::Line(input) => input,
+            Event::Idle => {
+                if json {
+                    print_json(json!({ "type": "nudge", "text": eliza.nudge() }))?;
+                } else {
+                    println!("\n{}\n", eliza.nudge());
+                }
+                continue;
+            }
+            Event::End => break,
+        };
+
+        let text = if json {
+            match serde_json::from_str(&input) {
+                Ok(Request::Message { text }) => text,
+                Ok(Request::Quit) => break,
+                Err(e) => {
+                    print_json(json!({ "type": "error", "text": e.to_string() }))?;
+                    continue;
+                }
+            }
+        } else if input.trim_end() == "/quit" {
+            break;
+        } else {
+            input
+        };
+
+        //Pick up any changes made to the script since the last turn
+        #[cfg(feature = "watch")]
+        {
+            if let Some(w) = &watcher {
+                eliza.reload(w);
+            }
+        }
+
+        //Based on the rules in the script, eliza responds to the given input
+        if json {
+            let res = eliza.respond_detailed(&text);
+            print_json(json!({
+                "type": "response",
+                "text": res.text,
+                "keyword": res.keyword,
+                "rule": res.rule,
+                "source": res.source,
+                "from_memory": res.source == ResponseSource::Memory,
+                "confidence": res.confidence,
+            }))?;
+        } else {
+            if typed {
+                typing.write_stream(eliza.respond_stream(&text), io::stdout())?;
+                println!("\n");
+            } else {
+                //Insert short delay to make eliza seem like she's thinking, where threads can sleep
+                #[cfg(not(target_os = "wasi"))]
+                std::thread::sleep(typing.thinking);
+                println!("{}\n", eliza.respond(&text));
             }
         }
     }
 
-    println!("\n{}", eliza.farewell()); //eliza farewells the user
+    if json {
+        print_json(json!({ "type": "farewell", "text": eliza.farewell() }))?;
+    } else {
+        println!("\n{}", eliza.farewell()); //eliza farewells the user
+    }
+
+    Ok(())
+}
+
+fn print_json(value: Value) -> io::Result<()> {
+    println!("{}", value);
+    io::stdout().flush()
 }
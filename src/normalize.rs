@@ -0,0 +1,216 @@
+//! Prepares text for matching, so that ELIZA can ignore case and, optionally, diacritics.
+//!
+//! The user's phrases and the script's keywords, synonyms, reflections and decomposition rules
+//! all pass through the same `Normalizer`, so that they may be compared directly. Diacritics are
+//! only stripped when the crate is built with the `normalize` feature and the script opts in with
+//! `strip_diacritics`, in which case `sueño` and `sueno` are treated alike.
+//!
+//! When searching for keywords, words may also be reduced to their stems, if the crate is built
+//! with the `stemming` feature and the script names a language with `stemming`.
+//!
+use crate::script::{CaseFolding, Stemming};
+use regex::Captures;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Normalizer {
+    folding: CaseFolding,
+    #[cfg_attr(not(feature = "normalize"), allow(dead_code))]
+    strip_diacritics: bool,
+    #[cfg_attr(not(feature = "stemming"), allow(dead_code))]
+    stemming: Option<Stemming>,
+}
+
+impl Normalizer {
+    pub(crate) fn new(
+        folding: CaseFolding,
+        strip_diacritics: bool,
+        stemming: Option<Stemming>,
+    ) -> Normalizer {
+        Normalizer {
+            folding,
+            strip_diacritics: strip_diacritics && cfg!(feature = "normalize"),
+            stemming: stemming.filter(|_| cfg!(feature = "stemming")),
+        }
+    }
+
+    /// Returns the form in which a single word is compared with keywords, which is stemmed if the
+    /// script asks for it.
+    pub(crate) fn word(self, word: &str) -> Cow<'_, str> {
+        let normalized = self.normalize(word);
+
+        #[cfg(feature = "stemming")]
+        if let Some(language) = self.stemming {
+            return Cow::Owned(
+                rust_stemmers::Stemmer::create(algorithm(language))
+                    .stem(&normalized)
+                    .into_owned(),
+            );
+        }
+
+        normalized
+    }
+
+    /// Returns the normalized form of a piece of script, such as a keyword. Text that is already
+    /// normalized is borrowed rather than copied.
+    pub(crate) fn normalize(self, text: &str) -> Cow<'_, str> {
+        //ASCII has no diacritics to strip, so only its case matters
+        if text.is_ascii() {
+            return self.folding.folded(text);
+        }
+
+        let mut normalized = String::with_capacity(text.len());
+        for c in text.chars() {
+            self.push(c, &mut normalized);
+        }
+        Cow::Owned(normalized)
+    }
+
+    /// Normalizes a decomposition rule, leaving escape sequences such as '\W' and '\p{Lu}' intact.
+    pub(crate) fn pattern(self, pattern: &str) -> String {
+        let mut normalized = String::with_capacity(pattern.len());
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                self.push(c, &mut normalized);
+                continue;
+            }
+
+            normalized.push(c);
+            if let Some(escaped) = chars.next() {
+                normalized.push(escaped);
+                if (escaped == 'p' || escaped == 'P') && chars.peek() == Some(&'{') {
+                    for c in chars.by_ref() {
+                        normalized.push(c);
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        normalized
+    }
+
+    /// Normalizes one of the user's phrases, remembering where each part of it came from.
+    pub(crate) fn phrase(self, phrase: &str) -> Phrase {
+        let mut text = String::with_capacity(phrase.len());
+        let mut offsets = Vec::with_capacity(phrase.len() + 1);
+        for (i, c) in phrase.char_indices() {
+            self.push(c, &mut text);
+            offsets.resize(text.len(), i);
+        }
+        offsets.push(phrase.len());
+
+        Phrase { text, offsets }
+    }
+
+    fn push(self, c: char, normalized: &mut String) {
+        #[cfg(feature = "normalize")]
+        if self.strip_diacritics {
+            use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+            decompose_compatible(c, |d| {
+                if !is_combining_mark(d) {
+                    self.folding.fold_char(d, normalized);
+                }
+            });
+            return;
+        }
+
+        self.folding.fold_char(c, normalized);
+    }
+}
+
+#[cfg(feature = "stemming")]
+fn algorithm(language: Stemming) -> rust_stemmers::Algorithm {
+    use rust_stemmers::Algorithm;
+    match language {
+        Stemming::Arabic => Algorithm::Arabic,
+        Stemming::Danish => Algorithm::Danish,
+        Stemming::Dutch => Algorithm::Dutch,
+        Stemming::English => Algorithm::English,
+        Stemming::Finnish => Algorithm::Finnish,
+        Stemming::French => Algorithm::French,
+        Stemming::German => Algorithm::German,
+        Stemming::Greek => Algorithm::Greek,
+        Stemming::Hungarian => Algorithm::Hungarian,
+        Stemming::Italian => Algorithm::Italian,
+        Stemming::Norwegian => Algorithm::Norwegian,
+        Stemming::Portuguese => Algorithm::Portuguese,
+        Stemming::Romanian => Algorithm::Romanian,
+        Stemming::Russian => Algorithm::Russian,
+        Stemming::Spanish => Algorithm::Spanish,
+        Stemming::Swedish => Algorithm::Swedish,
+        Stemming::Tamil => Algorithm::Tamil,
+        Stemming::Turkish => Algorithm::Turkish,
+    }
+}
+
+/// A normalized phrase, which can recover the original form of anything captured from it.
+pub(crate) struct Phrase {
+    pub(crate) text: String,
+    //The offset in the original phrase of each byte of the normalized text, plus its end
+    offsets: Vec<usize>,
+}
+
+impl Phrase {
+    /// Returns the text of each capture group as it appeared in the original phrase. Groups that
+    /// did not participate in the match are empty.
+    pub(crate) fn captures<'a>(&self, original: &'a str, captures: &Captures<'_>) -> Vec<&'a str> {
+        captures
+            .iter()
+            .map(|m| match m {
+                Some(m) => &original[self.offsets[m.start()]..self.offsets[m.end()]],
+                None => "",
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn phrase_captures() {
+        let normalizer = Normalizer::new(CaseFolding::Unicode, false, None);
+        let phrase = normalizer.phrase("die STRAßE ist lang");
+        assert_eq!("die strasse ist lang", phrase.text);
+
+        let re = Regex::new("die (.*) ist (.*)").unwrap();
+        let cap = re.captures(&phrase.text).unwrap();
+        assert_eq!(
+            vec!("die STRAßE ist lang", "STRAßE", "lang"),
+            phrase.captures("die STRAßE ist lang", &cap)
+        );
+    }
+
+    #[cfg(feature = "normalize")]
+    #[test]
+    fn strip_diacritics() {
+        let normalizer = Normalizer::new(CaseFolding::Unicode, true, None);
+        assert_eq!("sueno", normalizer.normalize("sueño"));
+        assert_eq!("creme brulee", normalizer.normalize("Crème Brûlée"));
+        assert_eq!("(.*)\\Wsueno", normalizer.pattern("(.*)\\Wsueño"));
+
+        let phrase = normalizer.phrase("tuve un sueño extraño");
+        let re = Regex::new("tuve un sueno (.*)").unwrap();
+        let cap = re.captures(&phrase.text).unwrap();
+        assert_eq!("extraño", phrase.captures("tuve un sueño extraño", &cap)[1]);
+    }
+
+    #[cfg(feature = "stemming")]
+    #[test]
+    fn stemmed_words() {
+        let normalizer = Normalizer::new(CaseFolding::Unicode, false, Some(Stemming::English));
+        assert_eq!("dream", normalizer.word("Dreaming"));
+        assert_eq!("dream", normalizer.word("dreams"));
+        assert_eq!("dreaming", normalizer.normalize("Dreaming"));
+        assert_eq!(
+            "dreaming",
+            Normalizer::new(CaseFolding::Unicode, false, None).word("dreaming")
+        );
+    }
+}
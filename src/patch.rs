@@ -0,0 +1,236 @@
+//! Customises a shared script without copying it, by describing only what changes.
+//!
+//! A deployment that wants the DOCTOR with a few keywords of its own, or with a different
+//! greeting, can keep its changes in a patch rather than a fork of the whole script, and pick up
+//! improvements to the upstream script as they come:
+//!
+//! ```json,no_run
+//! {
+//!     "add_keywords": [
+//!         {"key": "pizza", "rank": 5, "rules": [
+//!             {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["Do you like anchovies?"]}
+//!         ]}
+//!     ],
+//!     "remove_keywords": ["computer"],
+//!     "override_greetings": ["Welcome to the clinic. What brings you here?"]
+//! }
+//! ```
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::patch::ScriptPatch;
+//! use eliza::script::Script;
+//! use eliza::Eliza;
+//!
+//! let mut script = Script::from_file("scripts/doctor.json").unwrap();
+//! script.apply_patch(&ScriptPatch::load("clinic.json").unwrap()).unwrap();
+//! let mut e = Eliza::from_scripts(vec![script]).unwrap();
+//! # }
+//! ```
+//!
+use crate::script::{Keyword, Script};
+use std::error::Error;
+use std::io::Read;
+
+/// Changes to make to a script, saved as `json`.
+///
+/// * **add_keywords**: Keywords to add to the script. Each replaces any keyword in the script
+///   with the same key.
+/// * **remove_keywords**: The keys of keywords to remove from the script, which are removed
+///   before any are added.
+/// * **override_greetings**, **override_farewells**, **override_fallbacks**: Optionally, the
+///   greetings, farewells or fallbacks to use in place of the script's own.
+///
+/// Every element is optional, so a patch need only mention what it changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScriptPatch {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_keywords: Vec<Keyword>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_keywords: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_greetings: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_farewells: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_fallbacks: Option<Vec<String>>,
+}
+
+impl ScriptPatch {
+    /// Will load a patch from a string.
+    ///
+    /// Will return `Err` if the patch is invalid.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(val: &str) -> Result<ScriptPatch, Box<dyn Error>> {
+        Ok(serde_json::from_str(val)?)
+    }
+
+    /// Will load a patch from any reader.
+    ///
+    /// Will return `Err` if the patch could not be read, or is invalid.
+    pub fn from_reader<R: Read>(reader: R) -> Result<ScriptPatch, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Will load a patch from the file system.
+    ///
+    /// Will return `Err` if the patch at the specified location is invalid or non-existant. Not
+    /// available without the `std-fs` feature, or when targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<ScriptPatch, Box<dyn Error>> {
+        let file = std::fs::File::open(path)?;
+        ScriptPatch::from_reader(std::io::BufReader::new(file))
+    }
+}
+
+impl ScriptPatch {
+    //Builds a script from the patch to put in front of the base script, which holds only the
+    //patch's keywords and any greetings, farewells or fallbacks it overrides. ELIZA reads the
+    //input with the base script's settings, so nothing of the base is kept, and each overlay
+    //costs only as much as the patch.
+    pub(crate) fn overlay(&self, base: &Script) -> Result<Script, Box<dyn Error>> {
+        if let Some(key) = self.remove_keywords.first() {
+            return Err(format!(
+                "An overlay can't remove keywords from its script: '{}'",
+                key
+            )
+            .into());
+        }
+
+        //The keywords are checked and compiled with the base's synonyms, as they'll be matched
+        let mut rules = Script {
+            keywords: self.add_keywords.clone(),
+            ..base.rule_settings()
+        };
+        let indices: Vec<usize> = (0..rules.keywords.len()).collect();
+        if let Some(problem) = rules.keyword_problems(&indices).into_iter().next() {
+            return Err(problem.into());
+        }
+        rules.resolve_gotos();
+        rules.compile_rules();
+
+        Ok(Script {
+            greetings: self.override_greetings.clone().unwrap_or_default(),
+            farewells: self.override_farewells.clone().unwrap_or_default(),
+            fallbacks: self.override_fallbacks.clone().unwrap_or_default(),
+            keywords: rules.keywords,
+            gotos: rules.gotos,
+            compiled: rules.compiled,
+            ..Script::default()
+        })
+    }
+}
+
+impl Script {
+    /// Applies a patch to the script (see `patch::ScriptPatch`).
+    ///
+    /// Will return `Err` (and leave the script as it was) if the patch removes a keyword that
+    /// doesn't exist or that a `GOTO` still names, or adds a keyword with any of the mistakes
+    /// that `validate()` checks for. Mistakes the script had already are not reported.
+    pub fn apply_patch(&mut self, patch: &ScriptPatch) -> Result<(), Box<dyn Error>> {
+        let mut script = self.clone();
+        for key in &patch.remove_keywords {
+            let i = script
+                .keywords
+                .iter()
+                .position(|k| k.key == *key)
+                .ok_or_else(|| format!("Patch removes a keyword that doesn't exist: '{}'", key))?;
+            script.keywords.remove(i);
+        }
+
+        for keyword in &patch.add_keywords {
+            script.keywords.retain(|k| k.key != keyword.key);
+            script.keywords.push(keyword.clone());
+        }
+
+        for key in &patch.remove_keywords {
+            if script.goto(key).is_none() && script.is_goto_target(key) {
+                return Err(format!("Patch removes a keyword that a GOTO names: '{}'", key).into());
+            }
+        }
+
+        let added: Vec<usize> = script
+            .keywords
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| patch.add_keywords.iter().any(|a| a.key == k.key))
+            .map(|(i, _)| i)
+            .collect();
+        if let Some(problem) = script.keyword_problems(&added).into_iter().next() {
+            return Err(problem.into());
+        }
+
+        if let Some(greetings) = &patch.override_greetings {
+            script.greetings = greetings.clone();
+        }
+        if let Some(farewells) = &patch.override_farewells {
+            script.farewells = farewells.clone();
+        }
+        if let Some(fallbacks) = &patch.override_fallbacks {
+            script.fallbacks = fallbacks.clone();
+        }
+
+        script.resolve_gotos();
+        script.compile_rules();
+        *self = script;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_patch() {
+        let upstream = Script::from_file("scripts/doctor.json").unwrap();
+        let patch = ScriptPatch::from_str(
+            r#"{
+                "add_keywords": [
+                    {"key": "pizza", "rank": 5, "rules": [{"memorise": false,
+                        "decomposition_rule": "(.*)", "reassembly_rules": ["Anchovies?"]}]},
+                    {"key": "sorry", "rank": 0, "rules": [{"memorise": false,
+                        "decomposition_rule": "(.*)", "reassembly_rules": ["No need."]}]}
+                ],
+                "remove_keywords": ["computer"],
+                "override_greetings": ["Welcome to the clinic."]
+            }"#,
+        )
+        .unwrap();
+
+        let mut script = upstream.clone();
+        script.apply_patch(&patch).unwrap();
+        assert_eq!(vec!["Welcome to the clinic."], script.greetings);
+        assert_eq!(upstream.fallbacks, script.fallbacks);
+        assert!(script.goto("computer").is_none());
+        assert_eq!(
+            1,
+            script.keywords.iter().filter(|k| k.key == "sorry").count()
+        );
+        //One keyword was removed and one added, while 'sorry' was replaced
+        assert_eq!(upstream.keywords.len(), script.keywords.len());
+
+        let mut e = crate::Eliza::from_scripts(vec![script]).unwrap();
+        assert_eq!("Anchovies?", e.respond("pizza"));
+        assert_eq!("No need.", e.respond("sorry"));
+
+        //A patch with mistakes leaves the script alone
+        let mut script = upstream.clone();
+        for bad in [
+            r#"{"remove_keywords": ["pizza"]}"#,
+            r#"{"remove_keywords": ["alike"]}"#,
+            r#"{"add_keywords": [{"key": "pizza", "rank": 5, "rules": [{"memorise": false,
+                "decomposition_rule": "(.*", "reassembly_rules": ["Anchovies?"]}]}]}"#,
+        ]
+        .iter()
+        {
+            let patch = ScriptPatch::from_str(bad).unwrap();
+            assert!(script.apply_patch(&patch).is_err(), "{}", bad);
+        }
+        assert_eq!(upstream.keywords.len(), script.keywords.len());
+        assert!(ScriptPatch::from_str(r#"{"remove_keywords": "computer"}"#).is_err());
+    }
+}
@@ -0,0 +1,651 @@
+//! Hosts many ELIZA conversations at once, all sharing a single script.
+//!
+//! Bots and servers tend to need the same session bookkeeping: a conversation per user, which is
+//! started on first contact and discarded once the user goes quiet. An `ElizaPool` takes care of
+//! this, so integrations only need to decide what identifies a session.
+//!
+//! Whenever a conversation leaves the pool, whether it was ended, replaced or expired, the
+//! `on_session_end()` hook is given a last look at it. Hosts may use the hook to save the
+//! conversation before it is discarded, or to send ELIZA's farewell where the transport allows.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::pool::ElizaPool;
+//! use eliza::script::Script;
+//! use std::time::Duration;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let mut pool = ElizaPool::new(script)
+//!     .idle_timeout(Duration::from_secs(30 * 60))
+//!     .max_sessions(1000)
+//!     .rate_limit(5, Duration::from_secs(2))
+//!     .on_session_end(|end| println!("{}: {}", end.id, end.farewell));
+//!
+//! println!("{}", pool.respond("alice", "Men are all alike.").unwrap());
+//! println!("{}", pool.respond("bob", "I need some help.").unwrap());
+//! # }
+//! ```
+//!
+use crate::patch::ScriptPatch;
+use crate::script::Script;
+use crate::snapshot::Snapshot;
+use crate::{Eliza, Response};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Identifies a conversation within a pool, e.g. a user or channel id.
+pub type SessionId = String;
+
+/// The reasons a pool may refuse to respond.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    /// A new session was needed, but the pool already holds the maximum number of sessions.
+    Full,
+    /// The conversation has sent more input than its rate limit allows. Further input will be
+    /// accepted after `retry_after`.
+    RateLimited { retry_after: Duration },
+    /// A snapshot couldn't be resumed, as it was saved in a newer `version` of the format than
+    /// this release understands.
+    UnsupportedSnapshot { version: u32 },
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Full => write!(f, "The maximum number of sessions has been reached"),
+            PoolError::RateLimited { retry_after } => write!(
+                f,
+                "Too much input, try again in {:.1} seconds",
+                retry_after.as_secs_f64()
+            ),
+            PoolError::UnsupportedSnapshot { version } => write!(
+                f,
+                "Snapshot is version {}, but only versions up to {} are supported",
+                version,
+                crate::snapshot::VERSION
+            ),
+        }
+    }
+}
+
+impl Error for PoolError {}
+
+/// Why a conversation left the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    /// The conversation was ended with `end()`.
+    Ended,
+    /// The conversation was replaced by a new one with the same id, through `start()`.
+    Replaced,
+    /// The conversation was idle for longer than the pool's idle timeout.
+    Expired,
+}
+
+/// A conversation leaving the pool, as given to the `on_session_end()` hook.
+pub struct SessionEnd<'a> {
+    /// The id of the conversation.
+    pub id: &'a str,
+    /// The conversation itself, e.g. to save with `Eliza::snapshot()`.
+    pub eliza: &'a Eliza,
+    /// Why the conversation is leaving the pool.
+    pub reason: EndReason,
+    /// ELIZA's farewell, which `end()` also returns. Transports able to message the user
+    /// unprompted may send it when a conversation expires.
+    pub farewell: &'a str,
+}
+
+//A function called whenever a conversation leaves the pool
+type SessionEndHook = Box<dyn FnMut(&SessionEnd<'_>) + Send>;
+
+struct Session {
+    eliza: Eliza,
+    last_active: Instant,
+    bucket: Bucket,
+}
+
+//A token bucket, which holds up to `burst` tokens and gains one every `interval`
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    burst: u32,
+    interval: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimit {
+    //Takes a token from the bucket, or returns how long until one will be available
+    fn take(&self, bucket: &mut Bucket) -> Result<(), Duration> {
+        let now = Instant::now();
+        let gained = now.duration_since(bucket.updated).as_secs_f64() / self.interval.as_secs_f64();
+        bucket.tokens = (bucket.tokens + gained).min(f64::from(self.burst));
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.interval.mul_f64(1.0 - bucket.tokens))
+        }
+    }
+}
+
+/// A collection of ELIZA conversations sharing one script.
+///
+/// This struct is created by the `new()` method. See its documentation for more.
+pub struct ElizaPool {
+    script: Arc<Script>,
+    sessions: HashMap<SessionId, Session>,
+    idle_timeout: Option<Duration>,
+    max_sessions: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    on_session_end: Option<SessionEndHook>,
+}
+
+impl ElizaPool {
+    /// Creates an empty pool, where every session will converse using the script.
+    ///
+    /// By default sessions never expire and there is no limit on their number.
+    pub fn new(script: Script) -> ElizaPool {
+        ElizaPool::from_shared(Arc::new(script))
+    }
+
+    /// Creates an empty pool from a script that is shared elsewhere.
+    pub fn from_shared(script: Arc<Script>) -> ElizaPool {
+        ElizaPool {
+            script,
+            sessions: HashMap::new(),
+            idle_timeout: None,
+            max_sessions: None,
+            rate_limit: None,
+            on_session_end: None,
+        }
+    }
+
+    /// Discards sessions that have been idle for longer than the timeout.
+    pub fn idle_timeout(mut self, timeout: Duration) -> ElizaPool {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    //Returns how long a session may be idle before it expires, if it ever does
+    #[cfg(feature = "server")]
+    pub(crate) fn idle_timeout_value(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Limits the number of sessions the pool will hold at once.
+    pub fn max_sessions(mut self, max: usize) -> ElizaPool {
+        self.max_sessions = Some(max);
+        self
+    }
+
+    /// Limits how quickly each conversation may send input. A conversation may send up to
+    /// `burst` messages at once, after which it is allowed one more message every `interval`.
+    /// Input beyond the limit is refused with `PoolError::RateLimited`, and does not reach ELIZA,
+    /// nor does it keep the conversation from expiring.
+    ///
+    /// Panics if `burst` or `interval` is zero, which would refuse every message.
+    pub fn rate_limit(mut self, burst: u32, interval: Duration) -> ElizaPool {
+        assert!(
+            burst > 0,
+            "A rate limit's burst must be at least one message"
+        );
+        assert!(
+            !interval.is_zero(),
+            "A rate limit's interval must be longer than zero"
+        );
+        self.rate_limit = Some(RateLimit { burst, interval });
+        self
+    }
+
+    /// Calls the hook whenever a conversation leaves the pool, before it is discarded.
+    ///
+    /// Expired conversations are only discarded when the pool next needs room or `expire()` is
+    /// called, so hosts wanting a timely farewell should call `expire()` periodically.
+    pub fn on_session_end<F>(mut self, hook: F) -> ElizaPool
+    where
+        F: FnMut(&SessionEnd<'_>) + Send + 'static,
+    {
+        self.on_session_end = Some(Box::new(hook));
+        self
+    }
+
+    /// Starts a new conversation, returning ELIZA's greeting.
+    ///
+    /// Any existing conversation with the same id is replaced. Will return `Err` if the pool is
+    /// full.
+    pub fn start(&mut self, id: &str) -> Result<String, PoolError> {
+        if let Some(session) = self.sessions.remove(id) {
+            self.farewell(id, session, EndReason::Replaced);
+        }
+        let session = self.session(id)?;
+        Ok(session.eliza.greet())
+    }
+
+    /// Personalises a conversation, starting it if need be, with a patch of its own: keywords
+    /// for this user alone, say, or greetings that use their name.
+    ///
+    /// The patch's keywords are consulted before the shared script's, which is otherwise used as
+    /// before, and is not copied for the conversation. An override that is empty leaves the
+    /// shared script's greetings, farewells or fallbacks in place. The patch replaces any the
+    /// conversation had already, and lasts as long as the conversation does.
+    ///
+    /// A patch is not part of a `Snapshot`, so a conversation restored from one, e.g. by
+    /// `resume()` after a restart, must be personalized again.
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "std-fs")] {
+    /// use eliza::patch::ScriptPatch;
+    /// use eliza::pool::ElizaPool;
+    /// use eliza::script::Script;
+    ///
+    /// let mut pool = ElizaPool::new(Script::from_file("scripts/doctor.json").unwrap());
+    ///
+    /// let patch = ScriptPatch {
+    ///     override_greetings: Some(vec!["Hello Alice, how are you feeling today?".to_string()]),
+    ///     ..ScriptPatch::default()
+    /// };
+    /// pool.personalize("alice", &patch).unwrap();
+    /// println!("{}", pool.get("alice").unwrap().greet());
+    /// # }
+    /// ```
+    ///
+    /// Will return `Err` if a new conversation was needed but the pool is full, or the patch has
+    /// a keyword with mistakes (see `Script::apply_patch()`). As the shared script is left alone,
+    /// a patch may not remove keywords, and its `GOTO`s may only name its own keywords.
+    pub fn personalize(&mut self, id: &str, patch: &ScriptPatch) -> Result<(), Box<dyn Error>> {
+        let overlay = patch.overlay(&self.script)?;
+        let shared = self.script.clone();
+        self.session(id)?.eliza.overlay(overlay, shared);
+        Ok(())
+    }
+
+    /// Carries on a conversation from a snapshot, e.g. one kept in a `store::SessionStore`,
+    /// starting it if need be. Any state the conversation already had is replaced (see
+    /// `Eliza::restore()`).
+    ///
+    /// Will return `Err` if the snapshot was saved in a newer version of the format than this
+    /// release understands, or a new conversation was needed but the pool is full.
+    pub fn resume(&mut self, id: &str, snapshot: Snapshot) -> Result<(), PoolError> {
+        let version = snapshot.version;
+        let snapshot = snapshot
+            .migrate()
+            .map_err(|_| PoolError::UnsupportedSnapshot { version })?;
+        self.session(id)?.eliza.restore_migrated(snapshot);
+        Ok(())
+    }
+
+    /// Responds to the input within a conversation, starting the conversation if need be.
+    ///
+    /// Will return `Err` if a new conversation was needed but the pool is full, or if the
+    /// conversation has exceeded its rate limit.
+    pub fn respond(&mut self, id: &str, input: &str) -> Result<String, PoolError> {
+        self.respond_detailed(id, input).map(|r| r.text)
+    }
+
+    /// Responds to the input within a conversation, reporting how the response was found (see
+    /// `Eliza::respond_detailed()`).
+    ///
+    /// Will return `Err` for the same reasons as `respond()`.
+    pub fn respond_detailed(&mut self, id: &str, input: &str) -> Result<Response, PoolError> {
+        let rate_limit = self.rate_limit;
+        let session = self.live_session(id)?;
+        if let Some(limit) = rate_limit {
+            if let Err(retry_after) = limit.take(&mut session.bucket) {
+                debug!("Session {} is rate limited", id);
+                return Err(PoolError::RateLimited { retry_after });
+            }
+        }
+
+        session.last_active = Instant::now();
+        Ok(session.eliza.respond_detailed(input))
+    }
+
+    /// Ends a conversation, returning ELIZA's farewell.
+    ///
+    /// Will return None if there is no such conversation.
+    pub fn end(&mut self, id: &str) -> Option<String> {
+        self.expire();
+        let session = self.sessions.remove(id)?;
+        Some(self.farewell(id, session, EndReason::Ended))
+    }
+
+    /// Returns true if the conversation exists and has not expired.
+    pub fn contains(&self, id: &str) -> bool {
+        match self.sessions.get(id) {
+            Some(session) => !self.is_expired(session),
+            None => false,
+        }
+    }
+
+    /// Returns the conversation, if it exists and has not expired.
+    pub fn get(&self, id: &str) -> Option<&Eliza> {
+        self.sessions
+            .get(id)
+            .filter(|s| !self.is_expired(s))
+            .map(|s| &s.eliza)
+    }
+
+    /// Returns the number of conversations held by the pool, including any that have expired
+    /// but are yet to be discarded.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Returns true if the pool holds no conversations.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Discards any conversations that have been idle for longer than the timeout, returning
+    /// their ids.
+    ///
+    /// Expired conversations are also discarded whenever a new one needs room, so calling this
+    /// is only necessary to reclaim memory promptly.
+    pub fn expire(&mut self) -> Vec<SessionId> {
+        let expired: Vec<SessionId> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| self.is_expired(s))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &expired {
+            info!("Session {} has expired", id);
+            if let Some(session) = self.sessions.remove(id) {
+                self.farewell(id, session, EndReason::Expired);
+            }
+        }
+
+        expired
+    }
+
+    //Returns the live session for the id, creating it if need be, and marks it as active
+    fn session(&mut self, id: &str) -> Result<&mut Session, PoolError> {
+        let session = self.live_session(id)?;
+        session.last_active = Instant::now();
+        Ok(session)
+    }
+
+    //Returns the live session for the id, creating it if need be, without marking it as active
+    fn live_session(&mut self, id: &str) -> Result<&mut Session, PoolError> {
+        if !self.contains(id) {
+            self.expire();
+            if let Some(max) = self.max_sessions {
+                if self.sessions.len() >= max {
+                    warn!("Refusing session {}, the pool is full", id);
+                    return Err(PoolError::Full);
+                }
+            }
+
+            let session = Session {
+                eliza: Eliza::from_shared(self.script.clone()),
+                last_active: Instant::now(),
+                bucket: Bucket {
+                    tokens: self.rate_limit.map_or(0.0, |l| f64::from(l.burst)),
+                    updated: Instant::now(),
+                },
+            };
+            self.sessions.insert(id.to_string(), session);
+        }
+
+        Ok(self.sessions.get_mut(id).unwrap()) //safe as it was inserted above
+    }
+
+    //Returns ELIZA's farewell to a conversation leaving the pool, once the hook has seen it
+    fn farewell(&mut self, id: &str, session: Session, reason: EndReason) -> String {
+        let farewell = session.eliza.farewell();
+        if let Some(hook) = &mut self.on_session_end {
+            hook(&SessionEnd {
+                id,
+                eliza: &session.eliza,
+                reason,
+                farewell: &farewell,
+            });
+        }
+
+        farewell
+    }
+
+    fn is_expired(&self, session: &Session) -> bool {
+        match self.idle_timeout {
+            Some(timeout) => session.last_active.elapsed() >= timeout,
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+
+    fn doctor() -> ElizaPool {
+        ElizaPool::new(Script::from_file("scripts/doctor.json").unwrap())
+    }
+
+    #[test]
+    fn sessions_are_separate() {
+        let mut pool = doctor();
+
+        assert_eq!(
+            "Your boyfriend made you come here?",
+            pool.respond("alice", "Well, my boyfriend made me come here.")
+                .unwrap()
+        );
+        pool.respond("bob", "Hello").unwrap();
+        assert_eq!(2, pool.len());
+
+        //Only alice has something in memory
+        assert!(pool
+            .respond("alice", "Bullies.")
+            .unwrap()
+            .contains("boyfriend"));
+        assert!(!pool
+            .respond("bob", "Bullies.")
+            .unwrap()
+            .contains("boyfriend"));
+        assert!(pool.end("alice").is_some());
+        assert!(pool.end("alice").is_none());
+        assert!(!pool.contains("alice"));
+        assert!(pool.contains("bob"));
+    }
+
+    #[test]
+    fn personalized_sessions() {
+        let mut pool = doctor();
+        pool.respond("alice", "I remember my mother.").unwrap();
+        let usage = pool.get("alice").unwrap().rule_usage().clone();
+
+        let patch = ScriptPatch::from_str(
+            r#"{
+                "add_keywords": [{"key": "mother", "rank": 0, "rules": [{"memorise": false,
+                    "decomposition_rule": "(.*)my mother is(.*)",
+                    "reassembly_rules": ["Is Margaret $2?"]}]}],
+                "override_greetings": ["Hello Alice."]
+            }"#,
+        )
+        .unwrap();
+        pool.personalize("alice", &patch).unwrap();
+
+        let alice = pool.get("alice").unwrap();
+        assert!(Arc::ptr_eq(&pool.script, &alice.fallback_scripts[0]));
+        assert_eq!(&usage, alice.rule_usage());
+        assert_eq!("Hello Alice.", alice.greet());
+
+        //Only the patch is held by the overlay, and the rest is read from the shared script
+        assert!(alice.script.reflections.is_empty() && alice.script.synonyms.is_empty());
+        assert!(alice.script.farewells.is_empty());
+        assert_eq!(pool.script.farewells[0], alice.farewell_nth(0));
+
+        //The overlay is consulted first, and reflects the input as the shared script does
+        assert_eq!(
+            "Is Margaret always nagging you?",
+            pool.respond("alice", "My mother is always nagging me")
+                .unwrap()
+        );
+        assert_eq!(
+            "In what way?",
+            pool.respond("alice", "Men are all alike").unwrap()
+        );
+        assert_ne!(
+            "Is Margaret always nagging you?",
+            pool.respond("bob", "My mother is always nagging me")
+                .unwrap()
+        );
+
+        let remove = ScriptPatch::from_str(r#"{"remove_keywords": ["mother"]}"#).unwrap();
+        assert!(pool.personalize("alice", &remove).is_err());
+        let goto = ScriptPatch::from_str(
+            r#"{"add_keywords": [{"key": "mum", "rank": 0, "rules": [{"memorise": false,
+                "decomposition_rule": "(.*)", "reassembly_rules": ["GOTO mother"]}]}]}"#,
+        )
+        .unwrap();
+        assert!(pool.personalize("alice", &goto).is_err());
+    }
+
+    #[test]
+    fn resumed_sessions() {
+        let mut pool = doctor();
+        pool.respond("alice", "I remember my mother.").unwrap();
+        let snapshot = pool.get("alice").unwrap().snapshot();
+        pool.end("alice");
+
+        pool.resume("alice", snapshot.clone()).unwrap();
+        assert_eq!(snapshot, pool.get("alice").unwrap().snapshot());
+        assert!(doctor()
+            .max_sessions(0)
+            .resume("bob", snapshot.clone())
+            .is_err());
+
+        let newer = Snapshot {
+            version: crate::snapshot::VERSION + 1,
+            ..snapshot
+        };
+        assert_eq!(
+            Err(PoolError::UnsupportedSnapshot {
+                version: crate::snapshot::VERSION + 1
+            }),
+            pool.resume("alice", newer)
+        );
+    }
+
+    #[test]
+    fn full_pool() {
+        let mut pool = doctor().max_sessions(1);
+
+        assert!(pool.start("alice").is_ok());
+        assert_eq!(Err(PoolError::Full), pool.respond("bob", "Hello"));
+        assert!(pool.respond("alice", "Hello").is_ok());
+    }
+
+    #[test]
+    fn session_end_hook() {
+        use std::sync::Mutex;
+
+        let ended = Arc::new(Mutex::new(Vec::new()));
+        let log = ended.clone();
+        let mut pool = doctor().on_session_end(move |end| {
+            let memory = end.eliza.memory().len();
+            log.lock().unwrap().push((
+                end.id.to_string(),
+                end.reason,
+                end.farewell.to_string(),
+                memory,
+            ));
+        });
+
+        pool.respond("alice", "Well, my boyfriend made me come here.")
+            .unwrap();
+        let farewell = pool.end("alice").unwrap();
+        pool.start("bob").unwrap();
+        pool.start("bob").unwrap();
+
+        let ended = ended.lock().unwrap();
+        assert_eq!(
+            ("alice".to_string(), EndReason::Ended, farewell, 1),
+            ended[0]
+        );
+        assert_eq!(
+            ("bob".to_string(), EndReason::Replaced),
+            (ended[1].0.clone(), ended[1].1)
+        );
+        assert_eq!(2, ended.len());
+    }
+
+    #[test]
+    fn expired_sessions_end() {
+        let expired = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = expired.clone();
+        let mut pool = doctor()
+            .idle_timeout(Duration::from_millis(0))
+            .on_session_end(move |end| log.lock().unwrap().push((end.id.to_string(), end.reason)));
+
+        pool.start("alice").unwrap();
+        pool.expire();
+        assert_eq!(
+            vec![("alice".to_string(), EndReason::Expired)],
+            *expired.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn rate_limited_sessions() {
+        let mut pool = doctor().rate_limit(2, Duration::from_secs(60));
+
+        assert!(pool.respond("alice", "Hello").is_ok());
+        assert!(pool.respond("alice", "Hello").is_ok());
+        match pool.respond("alice", "Hello") {
+            Err(PoolError::RateLimited { retry_after }) => {
+                assert!(retry_after > Duration::from_secs(0));
+                assert!(retry_after <= Duration::from_secs(60));
+            }
+            other => panic!("Expected to be rate limited, not {:?}", other),
+        }
+        assert!(pool.respond("bob", "Hello").is_ok());
+
+        //The bucket refills over time
+        let mut pool = doctor().rate_limit(1, Duration::from_millis(10));
+        assert!(pool.respond("alice", "Hello").is_ok());
+        assert!(pool.respond("alice", "Hello").is_err());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(pool.respond("alice", "Hello").is_ok());
+
+        //Refused input doesn't keep a conversation alive
+        let mut pool = doctor()
+            .rate_limit(1, Duration::from_secs(60))
+            .idle_timeout(Duration::from_millis(200));
+        assert!(pool.respond("alice", "Hello").is_ok());
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(pool.respond("alice", "Hello").is_err());
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(!pool.contains("alice"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_rate_limit() {
+        doctor().rate_limit(0, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let mut pool = doctor()
+            .idle_timeout(Duration::from_millis(0))
+            .max_sessions(1);
+
+        pool.start("alice").unwrap();
+        assert!(!pool.contains("alice"));
+
+        //Expired sessions make room for new ones
+        assert!(pool.start("bob").is_ok());
+        assert_eq!(1, pool.len());
+        assert_eq!(vec!["bob".to_string()], pool.expire());
+        assert!(pool.is_empty());
+    }
+}
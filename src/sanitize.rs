@@ -0,0 +1,116 @@
+//! Cleans control characters and terminal escape sequences out of the user's input.
+//!
+//! Input from a network transport may carry anything, and whatever ELIZA captures she echoes
+//! back. A capture holding an ANSI escape sequence can recolour or clear the terminal it is
+//! printed to, and a stray carriage return can forge lines in a log. ELIZA therefore sanitizes
+//! the input before transforming it, in one of three ways:
+//!
+//! ```rust
+//! use eliza::sanitize::Sanitization;
+//!
+//! let input = "I feel \u{1b}[31mangry\u{1b}[0m\r\ntoday\u{7}";
+//! assert_eq!("I feel angry  today", Sanitization::Strip.apply(input));
+//! assert_eq!(
+//!     "I feel \\x1b[31mangry\\x1b[0m  today\\x07",
+//!     Sanitization::Escape.apply(input)
+//! );
+//! assert_eq!(input, Sanitization::Off.apply(input));
+//! ```
+//!
+//! Tabs and line breaks become spaces in either case, so the words on either side of them stay
+//! apart.
+//!
+use std::borrow::Cow;
+
+/// How ELIZA treats control characters and escape sequences in the user's input (see
+/// `builder::ElizaBuilder::sanitization()`).
+///
+/// * **strip**: Removes escape sequences entirely, along with any other control characters. The
+///   default.
+/// * **escape**: Writes each control character as a visible escape, such as `\x1b`, so that the
+///   rest of an escape sequence is harmless text.
+/// * **off**: Leaves the input as it is, for hosts that sanitize it themselves.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sanitization {
+    #[default]
+    Strip,
+    Escape,
+    Off,
+}
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+//The single character form of the control sequence introducer, from the C1 controls
+const CSI: char = '\u{9b}';
+
+impl Sanitization {
+    /// Sanitizes the input. Input without any control characters is borrowed rather than copied.
+    pub fn apply(self, input: &str) -> Cow<'_, str> {
+        if self == Sanitization::Off || !input.chars().any(char::is_control) {
+            return Cow::Borrowed(input);
+        }
+
+        let mut sanitized = String::with_capacity(input.len());
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            if !c.is_control() {
+                sanitized.push(c);
+            } else if matches!(c, '\t' | '\n' | '\r') {
+                sanitized.push(' ');
+            } else if self == Sanitization::Escape {
+                sanitized.push_str(&format!("\\x{:02x}", u32::from(c)));
+            } else if c == CSI {
+                skip_csi(&mut chars);
+            } else if c == ESC {
+                match chars.next() {
+                    Some('[') => skip_csi(&mut chars),
+                    //An operating system command, e.g. setting the window title, runs until BEL
+                    //or the string terminator 'ESC \'
+                    Some(']') => {
+                        while let Some(c) = chars.next() {
+                            if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                                break;
+                            }
+                        }
+                    }
+                    //Any other escape is followed by a single character
+                    _ => {}
+                }
+            }
+        }
+
+        Cow::Owned(sanitized)
+    }
+}
+
+//Skips the parameters of a control sequence, up to and including its final character
+fn skip_csi<I: Iterator<Item = char>>(chars: &mut I) {
+    for c in chars {
+        if ('\u{40}'..='\u{7e}').contains(&c) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_sequences() {
+        let strip = |input| Sanitization::Strip.apply(input).into_owned();
+        assert_eq!("plain text", strip("plain text"));
+        assert!(matches!(
+            Sanitization::Strip.apply("plain"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!("red", strip("\u{1b}[1;31mred\u{1b}[0m"));
+        assert_eq!("title", strip("\u{1b}]0;hacked\u{7}title"));
+        assert_eq!("title", strip("\u{1b}]0;hacked\u{1b}\\title"));
+        assert_eq!("clear", strip("\u{9b}2Jclear"));
+        assert_eq!("ab", strip("a\u{0}\u{8}\u{7f}b"));
+        assert_eq!("a b", strip("a\tb"));
+        assert_eq!("unterminated", strip("unterminated\u{1b}["));
+    }
+}
@@ -14,6 +14,13 @@
 //!     "greetings" : ["", ...],
 //!     "farewells" : ["", ...],
 //!     "fallbacks" : ["", ...],
+//!     "nudges" : ["", ...],
+//!     "banned" : ["", ...],
+//!     "banned_responses" : ["", ...],
+//!     "tests" : [
+//!         {"input": "", "expect_contains": "", "expect_keyword": ""},
+//!         ...
+//!     ],
 //!     "transforms" : [
 //!         {"word": "", "equivalents": ["", ...]},
 //!         ...
@@ -28,31 +35,54 @@
 //!     ],
 //!     "keywords" : [
 //!         {
-//!             "key": "", "rank": number,
+//!             "key": "", "rank": number, "tags": ["", ...],
 //!             "rules": [
 //!                 {
 //!                     "memorise": bool, "decomposition_rule": rust_regex,
-//!                     "reassembly_rules": ["", ...]
+//!                     "reassembly_rules": ["" | {"text": "", "then": "", "if_turn_gt": number, ...}, ...],
+//!                     "conditions": [{"variable": "", "min": number, "max": number}, ...],
+//!                     "affect": {"": number, ...}
 //!                 },
 //!                 ...
 //!             ]
 //!         },
 //!         ...
-//!     ]
+//!     ],
+//!     "delimiters" : ["", ...],
+//!     "splitter" : "smart" | "simple",
+//!     "conjunctions" : ["", ...],
+//!     "fuzzy_keywords" : bool,
+//!     "case_folding" : "unicode" | "turkic",
+//!     "strip_diacritics" : bool,
+//!     "stemming" : "english" | "german" | "spanish" | ...,
+//!     "emoji" : "keep" | "strip" | {"map" : {"": "", ...}},
+//!     "word_chars" : "letters" | "digits" | "alphanumeric" | {"chars" : ""} | {"any" : [...]},
+//!     "fallback_order" : "random" | "escalating"
 //! }
 //! ```
 //!
-//! See struct documentation for more information on each element.
+//! See struct documentation for more information on each element. The `conditions`, `affect`,
+//! `delimiters`, `splitter`, `conjunctions`, `fuzzy_keywords`, `case_folding`,
+//! `strip_diacritics`, `stemming`, `emoji`, `word_chars` and `fallback_order` elements are
+//! optional.
 //!
 use rand;
-use serde;
 use serde_json;
 
-use self::serde::de::Deserialize;
+use crate::alphabet::CharClass;
+use crate::error::ElizaError;
+use crate::normalize::Normalizer;
+use crate::splitter::PhraseSplitter;
+use crate::Decomposition;
 use rand::seq::SliceRandom;
+use regex::{Regex, RegexSet};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
-use std::fs::File;
-use std::path::Path;
+use std::io::Read;
+use std::sync::{Arc, OnceLock};
 
 ///  A rule to transform a user's input prior to processing.
 ///
@@ -65,10 +95,21 @@ use std::path::Path;
 /// Then the text `"I can't recollect, or even recall nowdays"` would be transformed to
 /// `"I can't remember, or even remember nowdays"` before performing a keyword search.
 ///
-#[derive(Serialize, Deserialize, Debug)]
+/// Equivalents are only replaced where they appear as whole words, and the input is transformed
+/// in a single pass, so a replacement is never transformed again. Where equivalents of different
+/// transforms overlap, the transform with the highest `priority` (which is optional, and defaults
+/// to 0) wins, and then the longest equivalent - so `i'm` is replaced before `i`.
+///
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct Transform {
     pub word: String,
     pub equivalents: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
 }
 
 /// A rule to aid the playwright in constructing simple decomposition rules.
@@ -79,18 +120,22 @@ pub struct Transform {
 /// ```json,no_run
 /// { "word" : "family", "equivalents" : ["mother","father","sister","brother"]}
 /// ```
-/// Then the decomposition rule `"(.*)my (.*@family)(.*)"`, would be tried with the following
-/// perumtations:
+/// Then the decomposition rule `"(.*)my (.*@family)(.*)"` would be compiled into a single regex,
+/// in which `@family` is replaced by an alternation of the synonym and its equivalents:
 ///
-/// * `"(.*)my (.*family)(.*)"`
-/// * `"(.*)my (.*mother)(.*)"`
-/// * `"(.*)my (.*father)(.*)"`
-/// * `"(.*)my (.*sister)(.*)"`
-/// * `"(.*)my (.*brother)(.*)"`
+/// * `"(.*)my (.*(?P<syn>family|mother|father|sister|brother))(.*)"`
 ///
-/// Note the special `@` symbol denotes that the word should be permutated.
+/// Note the special `@` symbol denotes that the word should be replaced by its synonyms. The
+/// alternation's `syn` group isn't one of the rule's captures, so `$2` above is still the text
+/// from `(.*@family)`. A decomposition rule may name only one synonym.
 ///
-#[derive(Serialize, Deserialize, Debug)]
+/// An equivalent may also name another synonym, such as `"@parents"`, to include all of its
+/// equivalents. References are expanded when the script is loaded, so that
+/// `{ "word" : "family", "equivalents" : ["@parents", "@siblings"]}` builds a group from two
+/// others. A reference to a synonym that doesn't exist, or that leads back to the synonym itself,
+/// is ignored.
+///
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Synonym {
     pub word: String,
     pub equivalents: Vec<String>,
@@ -111,7 +156,7 @@ pub struct Synonym {
 ///
 /// Then the assembled response would look like `"Really, you think about your life?"`
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Reflection {
     pub word: String,
     pub inverse: String,
@@ -121,11 +166,11 @@ pub struct Reflection {
 ///  A rule to decompose a user's input then assemble a response based on that input.
 ///
 /// * **memorise**: Used to indicate whether the response should be used now, or saved to
-/// internal memory for later use (true).
+///   internal memory for later use (true).
 /// * **decomposition_rule**: A rust regex used to match and extract contextual information from
-/// user input.
+///   user input.
 /// * **reassembly_rules**: A list of strings that are to be used for ELIZA's reponse if the
-/// associated `decomposition_rule` matched.
+///   associated `decomposition_rule` matched.
 ///
 /// # Example
 /// For example, if we had the `Rule`:
@@ -140,69 +185,1467 @@ pub struct Reflection {
 /// Note the special `$[num]` symbol denotes that a replacement with a regex capture group should
 /// occur.
 ///
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A reassembly rule may also quote ELIZA's previous response with `{last_response}`, for rules
+/// such as `"(.*)you said(.*)"` - `"I said '{last_response}'"`. Such a rule is passed over until
+/// ELIZA has said something.
+///
+/// Where the decomposition rule has a synonym, the reassembly rules may refer to it too, as in
+/// `"Tell me more about your $@family."`, to echo the word that took its place - `father`, say.
+///
+/// A decomposition rule may also list alternative words inline, as in
+/// `"(.*)my @{mother|father|dog}(.*)"`, where a named `Synonym` would be overkill. The alternation
+/// is not a capture group, so it doesn't change the numbering of the captures around it.
+///
+/// Much as Weizenbaum's DLIST did, a decomposition rule may also name a tag of the script's
+/// keywords, as in `"(.*)my (#family)(.*)"`, to match any keyword with that tag - `mother`, say,
+/// if it is tagged `family`. Like a synonym, the tag's own name matches too, and a rule may have
+/// either a synonym or a tag but not both. A `#` that doesn't name a tag is matched as it is.
+///
+/// * **conditions**: Optional conditions on ELIZA's affect variables, all of which must hold for
+///   the rule to be tried (see `Condition`).
+/// * **affect**: Optional adjustments to make to ELIZA's affect variables whenever this rule is
+///   used, e.g. `{"anger": 2, "fear": -1}`.
+/// * **reassembly_context**: The conditions on the conversation so far that each reassembly rule
+///   must meet before it is used, by the index of the reassembly rule (see `ReassemblyContext`).
+///   A rule without an entry may always be used. In a script, they are written with the reassembly rule itself, e.g.
+///   `{"text": "You mentioned your family before.", "if_last_keyword": "family"}`.
+/// * **follow_ups**: The question that ELIZA asks on the next turn after using each reassembly
+///   rule, whatever the user says in the meantime, by the index of the reassembly rule. In a script, a
+///   follow-up is written with the reassembly rule itself, e.g.
+///   `{"text": "Your $2 did that?", "then": "And how did that make you feel?"}`. A follow-up may
+///   refer to captures just as its reassembly rule does.
+///
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "RuleDef", into = "RuleDef")]
 pub struct Rule {
     pub memorise: bool,
     pub decomposition_rule: String,
     pub reassembly_rules: Vec<String>,
+    pub conditions: Vec<Condition>,
+    pub affect: BTreeMap<String, i32>,
+    pub reassembly_context: Vec<Option<ReassemblyContext>>,
+    pub follow_ups: Vec<Option<String>>,
+}
+
+//The form of a rule within a script, where a reassembly rule may carry its context
+#[derive(Serialize, Deserialize)]
+struct RuleDef {
+    memorise: bool,
+    decomposition_rule: String,
+    reassembly_rules: Vec<ReassemblyDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conditions: Vec<Condition>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    affect: BTreeMap<String, i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ReassemblyDef {
+    Text(String),
+    Detailed {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        then: Option<String>,
+        #[serde(flatten)]
+        context: ReassemblyContext,
+    },
+}
+
+impl From<RuleDef> for Rule {
+    fn from(def: RuleDef) -> Rule {
+        let mut reassembly_context = Vec::new();
+        let mut follow_ups = Vec::new();
+        let reassembly_rules = def
+            .reassembly_rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, assem)| match assem {
+                ReassemblyDef::Text(text) => text,
+                ReassemblyDef::Detailed {
+                    text,
+                    then,
+                    context,
+                } => {
+                    if context != ReassemblyContext::default() {
+                        reassembly_context.resize(i + 1, None);
+                        reassembly_context[i] = Some(context);
+                    }
+                    if let Some(then) = then {
+                        follow_ups.resize(i + 1, None);
+                        follow_ups[i] = Some(then);
+                    }
+                    text
+                }
+            })
+            .collect();
+
+        Rule {
+            memorise: def.memorise,
+            decomposition_rule: def.decomposition_rule,
+            reassembly_rules,
+            conditions: def.conditions,
+            affect: def.affect,
+            reassembly_context,
+            follow_ups,
+        }
+    }
+}
+
+impl From<Rule> for RuleDef {
+    fn from(rule: Rule) -> RuleDef {
+        let context = rule.reassembly_context;
+        let follow_ups = rule.follow_ups;
+        let reassembly_rules = rule
+            .reassembly_rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let context = context.get(i).cloned().flatten();
+                match (context, follow_ups.get(i).cloned().flatten()) {
+                    (None, None) => ReassemblyDef::Text(text),
+                    (context, then) => ReassemblyDef::Detailed {
+                        text,
+                        then,
+                        context: context.unwrap_or_default(),
+                    },
+                }
+            })
+            .collect();
+
+        RuleDef {
+            memorise: rule.memorise,
+            decomposition_rule: rule.decomposition_rule,
+            reassembly_rules,
+            conditions: rule.conditions,
+            affect: rule.affect,
+        }
+    }
+}
+
+/// Requirements on the conversation so far, all of which must be met before a reassembly rule
+/// may be used. They let a script grow more familiar as the conversation goes on.
+///
+/// * **if_last_keyword**: The keyword that produced ELIZA's previous response.
+/// * **if_turn_gt**: The number of inputs, this one included, that the conversation must have
+///   gone beyond.
+/// * **if_var_set**: An affect variable that a rule must have adjusted (see `Condition`).
+///
+/// # Example
+/// For example, the reassembly rules:
+///
+/// ```json,no_run
+/// ["Tell me about your $2.", {"text": "We keep coming back to your $2.", "if_turn_gt": 10}]
+/// ```
+/// Would only consider the second response once the user has said more than ten things. A rule
+/// whose reassembly rules are all out of context is passed over, as if it hadn't matched.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReassemblyContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_last_keyword: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_turn_gt: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_var_set: Option<String>,
+}
+
+impl ReassemblyContext {
+    /// Returns true if the conversation meets every requirement, given the keyword of the
+    /// previous response, the number of the current turn (counting from one) and the affect
+    /// variables.
+    pub fn holds(
+        &self,
+        last_keyword: Option<&str>,
+        turn: usize,
+        affect: &HashMap<String, i32>,
+    ) -> bool {
+        self.if_last_keyword
+            .as_deref()
+            .is_none_or(|k| last_keyword == Some(k))
+            && self.if_turn_gt.is_none_or(|n| turn > n)
+            && self
+                .if_var_set
+                .as_ref()
+                .is_none_or(|v| affect.contains_key(v))
+    }
+}
+
+/// A requirement on one of ELIZA's affect variables, which must be met before a rule may be
+/// tried.
+///
+/// Affect variables (such as `anger` or `fear`) give a script a simple emotional state, in the
+/// manner of Colby's PARRY. Every variable starts at zero, and is adjusted by the `affect` of any
+/// rule that is used. Both bounds are optional and inclusive.
+///
+/// # Example
+/// For example, if a rule had the `Condition`:
+///
+/// ```json,no_run
+/// { "variable" : "anger", "min" : 5 }
+/// ```
+/// Then it would only be tried once ELIZA's anger had reached five. Placing such a rule ahead of
+/// a keyword's other rules lets ELIZA's mood change how she responds to the same input.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub variable: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i32>,
+}
+
+impl Condition {
+    /// Returns true if the value of the variable satisfies this condition.
+    pub fn holds(&self, value: i32) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+/// How text is case folded before it is compared, so that matching ignores case.
+///
+/// The user's input is folded, as are the script's keywords, transforms, synonyms, reflections and
+/// decomposition rules - playwrights need not worry about case at all.
+///
+/// * **unicode**: Full Unicode case folding, so that `STRASSE` and `straße` are equal. The default.
+/// * **turkic**: As above, but `I` folds to the dotless `ı` and `İ` to `i`, as in Turkish and
+///   Azerbaijani.
+///
+/// Either way, typographic apostrophes such as `’` fold to `'`, so that a keyword or reflection
+/// like `i'm` matches however the user's keyboard writes it.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseFolding {
+    #[default]
+    Unicode,
+    Turkic,
+}
+
+impl CaseFolding {
+    /// Returns the case folded form of the text.
+    pub fn fold(self, text: &str) -> String {
+        let mut folded = String::with_capacity(text.len());
+        for c in text.chars() {
+            self.fold_char(c, &mut folded);
+        }
+
+        folded
+    }
+
+    //Like `fold()`, but borrows text that folding wouldn't change, as most of a script's text is
+    //already lower case
+    pub(crate) fn folded(self, text: &str) -> Cow<'_, str> {
+        //Only ASCII is checked, as other characters may fold in surprising ways
+        match text
+            .bytes()
+            .all(|b| b.is_ascii() && !b.is_ascii_uppercase() && b != b'`')
+        {
+            true => Cow::Borrowed(text),
+            false => Cow::Owned(self.fold(text)),
+        }
+    }
+
+    pub(crate) fn fold_char(self, c: char, folded: &mut String) {
+        match (self, c) {
+            (CaseFolding::Turkic, 'I') => folded.push('ı'),
+            (_, 'İ') => folded.push('i'),
+            (_, 'ß') | (_, 'ẞ') => folded.push_str("ss"),
+            (_, 'ς') => folded.push('σ'),
+            (_, 'ſ') => folded.push('s'),
+            (_, '’') | (_, 'ʼ') | (_, '‛') | (_, '′') | (_, '`') | (_, '´') => {
+                folded.push('\'')
+            }
+            _ => folded.extend(c.to_lowercase()),
+        }
+    }
+}
+
+/// The language whose rules reduce words to their stems, so that a keyword matches the other forms
+/// of its word.
+///
+/// With `"stemming": "english"`, the keyword `dream` also matches `dreams` and `dreaming`, and
+/// `remember` matches `remembered`. Only the search for keywords is affected - decomposition rules
+/// are matched against the user's words as they were written, and so captures and echoes keep
+/// their original form. Irregular forms such as `dreamt` still need keywords of their own.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stemming {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+/// How ELIZA chooses between the fallbacks of a script.
+///
+/// * **random**: A fallback at random each time. The default.
+/// * **escalating**: The fallbacks in the order they are written, one for each input in a row
+///   that ELIZA couldn't find a keyword for, staying with the last once all have been used. Once
+///   a keyword is found again, she starts from the first. This suits a ladder of fallbacks that
+///   press harder the longer the conversation stalls, e.g. `"Go on."`, `"Please tell me more."`,
+///   then `"Perhaps we should talk about something else - tell me about your family."`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FallbackOrder {
+    #[default]
+    Random,
+    Escalating,
+}
+
+/// What to do with emoji (and similar pictographic symbols) in the user's input, before it is
+/// transformed.
+///
+/// * **keep**: Leave emoji untouched. The default.
+/// * **strip**: Remove every emoji, so they are never echoed back in a response.
+/// * **map**: Replace emoji with words from the given table, so that scripts can respond to them
+///   like any other word. Emoji missing from the table are removed.
+///
+/// # Example
+/// ```json,no_run
+/// "emoji" : { "map" : { "😢" : "sad", "😀" : "happy", "❤️" : "love" } }
+/// ```
+/// Skin tones and variation selectors are ignored when looking an emoji up, so `👍🏽` is found
+/// under `👍`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiPolicy {
+    #[default]
+    Keep,
+    Strip,
+    Map(BTreeMap<String, String>),
+}
+
+impl EmojiPolicy {
+    /// Applies the policy to the text.
+    pub fn apply(&self, text: &str) -> String {
+        let table = match self {
+            EmojiPolicy::Keep => return text.to_string(),
+            EmojiPolicy::Strip => None,
+            EmojiPolicy::Map(table) => Some(table),
+        };
+
+        //An emoji may be a sequence of several characters, such as a family or a flag
+        static EMOJI: OnceLock<Regex> = OnceLock::new();
+        let emoji = EMOJI.get_or_init(|| {
+            Regex::new(concat!(
+                r"[\x{1F1E6}-\x{1F1FF}]{2}",
+                r"|[0-9#*]\x{FE0F}?\x{20E3}",
+                r"|\p{Extended_Pictographic}[\x{FE0F}\x{1F3FB}-\x{1F3FF}]*",
+                r"(\x{200D}\p{Extended_Pictographic}[\x{FE0F}\x{1F3FB}-\x{1F3FF}]*)*"
+            ))
+            .unwrap() //safe as the pattern is known to be valid
+        });
+
+        let replaced = emoji.replace_all(text, |cap: &regex::Captures<'_>| {
+            let lookup = |e: &str| table.and_then(|t| t.get(e));
+            let bare: String = cap[0]
+                .chars()
+                .filter(|c| !matches!(c, '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}'))
+                .collect();
+
+            match lookup(&cap[0]).or_else(|| lookup(&bare)) {
+                Some(word) => format!(" {} ", word),
+                None => String::from(" "),
+            }
+        });
+
+        //Tidy the spaces that were left behind
+        replaced.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
 }
 
 ///  A keyword and it's associated decompositon and reassembly rules.
 ///
 /// * **key**: The keyword to look for in the input text.
-/// * **rank**: Denotes it's importance over other keywords. Higher rank = Higher priority.
+/// * **rank**: Denotes it's importance over other keywords. Higher rank = Higher priority. Ranks
+///   may be negative, for keywords that should only be used when nothing else is found.
+/// * **tags**: Optional classes that the keyword belongs to, such as `["family", "person"]`, so
+///   that decomposition rules can match any keyword of a class (see `Rule`). Defaults to none.
 /// * **rules**: The associated decompositon and reassembly rules
 ///
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A keyword whose key is `NONE`, in capitals, is never looked for in the input. Its rules are
+/// instead used when no other keyword matches and ELIZA has nothing in memory to use, as in
+/// Weizenbaum's scripts, so that fallbacks can draw on the input with decomposition and
+/// reassembly rules of their own:
+///
+/// ```json,no_run
+/// {
+///     "key": "NONE",
+///     "rank": 0,
+///     "rules": [
+///         { "memorise": false, "decomposition_rule": "(.*)\\?", "reassembly_rules": ["Why do you ask?"] },
+///         { "memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["Please go on."] }
+///     ]
+/// }
+/// ```
+///
+/// The `fallbacks` of the script are only used if none of its rules match.
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct Keyword {
     pub key: String,
-    pub rank: u8,
+    pub rank: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub rules: Vec<Rule>,
 }
 
+/// The key of the keyword whose rules are used when no other keyword matches.
+pub const NONE_KEYWORD: &str = "NONE";
+
+impl Keyword {
+    /// Returns true if this is the `NONE` keyword, which is never looked for in the input.
+    pub fn is_none_keyword(&self) -> bool {
+        self.key == NONE_KEYWORD
+    }
+}
+
 /// A collection of ELIZA directives.
 ///
 /// * **greetings**: A set of strings that are used to greet the user upon program start
 /// * **farewells**: A set of strings that are used to farewell the user upon program termination
 /// * **fallbacks**: A set of strings that are used when ELIZA can't match any
-/// keywords/decompositon rules against user input
+///   keywords/decompositon rules against user input. A fallback may include `$input`, which
+///   becomes the first phrase of the input, reflected and shortened as a capture would be, e.g.
+///   "What makes you say $input?". When there is no phrase to echo, such fallbacks are passed over
+/// * **nudges**: Optional strings, such as "Are you still there?", that are used to prompt a
+///   user who has been quiet for a while (see `Eliza::nudge()`). Defaults to none.
+/// * **banned**: Optional words or phrases that ELIZA refuses to engage with, such as slurs, so
+///   that she never echoes them back. Input containing any of them as whole words is answered
+///   with one of the `banned_responses` instead, before any keyword is searched for. Defaults to
+///   none.
+/// * **banned_responses**: Optional strings, such as "Let's keep this civil.", that answer input
+///   containing a banned term, or input refused by a content filter (see
+///   `Eliza::content_filter()`). Defaults to none.
+/// * **tests**: Optional tests of how the script should respond, which document its intended
+///   behaviour next to the rules (see `ScriptTest`). Defaults to none.
 /// * **transforms**: A set of rules to transform a user's input prior to processing.
 /// * **synonyms**: A set of synonyms to aid the playwright in constructing simple decomposition
-/// rules
+///   rules
 /// * **reflections**: A set of string pairs, that are used to post process any contextual
-/// information in an ELIZA response.
+///   information in an ELIZA response.
 /// * **keywords**: A set of keywords and their associated decompositon and reassembly rules.
+/// * **delimiters**: Optional strings that separate the phrases of the user's input, for scripts
+///   written in languages other than English (e.g. `["¿", "?", ".", ",", " pero "]`). Defaults to
+///   `DEFAULT_DELIMITERS`.
+/// * **splitter**: Optionally, how the input is split at the delimiters (see
+///   `splitter::PhraseSplitter`). Defaults to `smart`, which also splits at `!` and `;`.
+/// * **conjunctions**: Optional words, such as `["but", "because", "and"]`, at which a phrase
+///   with keywords is broken further, strongest first. The phrase is narrowed to the earliest part
+///   with keywords for each conjunction in turn, so a weak conjunction like `and` only divides
+///   what the stronger ones left. A conjunction is dropped along with the break, so one that is
+///   also a keyword will only be found if no part has other keywords. Defaults to none.
+/// * **fuzzy_keywords**: Optionally, whether to tolerate misspelt keywords, so that `mohter`
+///   matches `mother`. Keys of four to seven characters allow one edit (an insertion, deletion,
+///   substitution or swap of neighbouring characters), longer keys two and shorter keys none. A
+///   misspelt keyword is corrected in the phrase before decomposition. Words of the script's
+///   transforms, synonyms and reflections, and common words such as `other`, are never taken for
+///   misspellings. Defaults to `false`.
+/// * **case_folding**: Optionally, how to ignore case when matching (see `CaseFolding`). Defaults
+///   to `unicode`.
+/// * **strip_diacritics**: Optionally, whether to ignore accents and other diacritics when
+///   matching, so that `sueño` matches `sueno`. Captures still keep their accents when they are
+///   echoed in a response. Requires the `normalize` feature, and defaults to `false`.
+/// * **stemming**: Optionally, the language in which to reduce words to their stems when searching
+///   for keywords (see `Stemming`). Requires the `stemming` feature, and defaults to none.
+/// * **emoji**: Optionally, what to do with emoji in the user's input (see `EmojiPolicy`).
+///   Defaults to `keep`.
+/// * **word_chars**: Optionally, the characters that synonym names in decomposition rules are made
+///   of (see `alphabet::CharClass`). Defaults to `CharClass::word()`.
+/// * **fallback_order**: Optionally, how ELIZA chooses between the fallbacks (see
+///   `FallbackOrder`). Defaults to `random`.
 ///
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct Script {
     pub greetings: Vec<String>,
     pub farewells: Vec<String>,
     pub fallbacks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nudges: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned_responses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<ScriptTest>,
     pub transforms: Vec<Transform>,
     pub synonyms: Vec<Synonym>,
     pub reflections: Vec<Reflection>,
     pub keywords: Vec<Keyword>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiters: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub splitter: Option<PhraseSplitter>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conjunctions: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fuzzy_keywords: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_folding: Option<CaseFolding>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_diacritics: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stemming: Option<Stemming>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<EmojiPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_chars: Option<CharClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_order: Option<FallbackOrder>,
+    /// The keyword that each `GOTO` names, as resolved by `resolve_gotos()`.
+    #[serde(skip)]
+    pub gotos: GotoIndex,
+    /// Each decomposition rule, as compiled by `compile_rules()`.
+    #[serde(skip)]
+    pub compiled: CompiledRules,
 }
 
+/// The index of the keyword that each `GOTO` in a script names.
+///
+/// The loaders fill this in, so that ELIZA can follow a `GOTO` mid-conversation without searching
+/// for its keyword. Scripts built in code start with an empty index, which `Eliza` resolves when
+/// given the script.
+#[derive(Default, Debug, Clone)]
+pub struct GotoIndex(HashMap<String, usize>);
+
+/// The compiled regex of each decomposition rule in a script, keyed by the rule.
+///
+/// A rule with a synonym is compiled into a single regex, in which the synonym is an alternation
+/// of it and its equivalents. The loaders fill this in, so that ELIZA needn't compile any regex
+/// mid-conversation. A rule that is missing, such as one added since the script was loaded, is
+/// compiled whenever it is tried.
+///
+/// Each keyword with many rules also has a `RegexSet` of its rules, with which ELIZA finds the
+/// rules that match a phrase in a single pass before trying any of them in full.
+#[derive(Default, Debug, Clone)]
+pub struct CompiledRules {
+    rules: HashMap<String, Option<Arc<Decomposition>>>,
+    keywords: HashMap<String, Arc<RuleSet>>,
+}
+
+//The fewest rules a keyword must have for them to be compiled together
+const RULE_SET_MIN: usize = 8;
+
+//The decomposition rules of a keyword, compiled together
+#[derive(Debug)]
+struct RuleSet {
+    //The keyword's decomposition rules when the set was compiled
+    rules: Vec<String>,
+    set: RegexSet,
+    //The index of the rule that each regex of the set belongs to, as invalid rules are left out
+    members: Vec<usize>,
+}
+
+/// A test of how a script responds to some input, kept in the script itself.
+///
+/// * **input**: What the user says.
+/// * **expect_contains**: Optionally, text that ELIZA's response must contain.
+/// * **expect_keyword**: Optionally, the keyword that must produce the response.
+///
+/// # Example
+/// For example, the `ScriptTest`:
+///
+/// ```json,no_run
+/// { "input" : "I remember my mother", "expect_contains" : "mother", "expect_keyword" : "remember"}
+/// ```
+/// Would check that ELIZA answers a memory by mentioning it. Every test starts a fresh
+/// conversation, so tests don't depend on one another (see `Script::run_embedded_tests()`).
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScriptTest {
+    pub input: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_contains: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_keyword: Option<String>,
+}
+
+/// The outcome of a `ScriptTest`, as returned by `Script::run_embedded_tests()`.
+///
+/// * **test**: The test that was run.
+/// * **response**: ELIZA's response to the test's input.
+/// * **failures**: Each expectation that wasn't met, described. Empty if the test passed.
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub test: ScriptTest,
+    pub response: crate::Response,
+    pub failures: Vec<String>,
+}
+
+impl TestResult {
+    /// Returns true if every expectation of the test was met.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A summary of a script, as returned by `Script::stats()`.
+///
+/// * **keywords**, **rules**, **reassembly_rules**: How many keywords the script has, how many
+///   decomposition rules they have between them, and how many reassembly rules those have.
+/// * **gotos**: How many of the reassembly rules are a `GOTO`.
+/// * **memorised_rules**: How many decomposition rules save their response to memory.
+/// * **synonyms**, **reflections**, **transforms**: How many of each the script has.
+/// * **greetings**, **farewells**, **fallbacks**: How many of each the script has.
+/// * **max_rank**: The highest rank of any keyword, or None if there are no keywords.
+/// * **average_rules**: The average number of decomposition rules per keyword.
+///
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct ScriptStats {
+    pub keywords: usize,
+    pub rules: usize,
+    pub reassembly_rules: usize,
+    pub gotos: usize,
+    pub memorised_rules: usize,
+    pub synonyms: usize,
+    pub reflections: usize,
+    pub transforms: usize,
+    pub greetings: usize,
+    pub farewells: usize,
+    pub fallbacks: usize,
+    pub max_rank: Option<i32>,
+    pub average_rules: f64,
+}
+
+/// The phrase delimiters used by scripts that don't specify their own, which suit English.
+pub const DEFAULT_DELIMITERS: [&str; 4] = [" but ", ".", ",", "?"];
+
 impl Script {
     /// Will load an ELIZA json script from the file system.
     ///
-    /// Will return `Err` if the script at the specified location is invalid or non-existant.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Script, Box<dyn Error>>
-    where
-        for<'de> Script: Deserialize<'de>,
-    {
-        //Attempt to open file and parse the script
-        let file = File::open(path)?;
-        let script: Script = serde_json::from_reader(file)?;
+    /// Will return `Err` if the script at the specified location is invalid or non-existant. Only
+    /// available with the `std-fs` feature, which is enabled by default, and not when targeting
+    /// `wasm32-unknown-unknown`, which has no file system. Without it, scripts can still be
+    /// loaded with `from_str()` or `from_slice()`.
+    ///
+    /// Like the other lenient loaders, keywords that are defined more than once have their rules
+    /// merged into the first definition, and any `GOTO` naming a keyword that doesn't exist is
+    /// reported, with a warning.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Script, Box<dyn Error>> {
+        let mut script = read_file(path)?;
+        script.tidy();
         Ok(script)
     }
 
-    pub fn from_str(val: &str) -> Result<Script, Box<dyn Error>> {
-        let script: Script = serde_json::from_str(val)?;
+    /// Will load an ELIZA json script from the file system, refusing any script with mistakes
+    /// that would otherwise only be discovered mid-conversation.
+    ///
+    /// As well as for the reasons given by `from_file()`, will return `Err` if the script fails
+    /// `validate()`. Not available without the `std-fs` feature, or when targeting
+    /// `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn from_file_strict<P: AsRef<std::path::Path>>(path: P) -> Result<Script, Box<dyn Error>> {
+        let mut script = read_file(path)?;
+        script.validate()?;
+        script.expand_synonyms();
+        script.resolve_gotos();
+        script.compile_rules();
         Ok(script)
     }
 
+    /// Will load an ELIZA script from a directory laid out as scripts were by early versions of
+    /// this crate, with each part of the script in a file of its own: `greetings.json`,
+    /// `farewells.json`, `fallbacks.json`, `transforms.json`, `synonyms.json`, `reflections.json`
+    /// and `keywords.json`.
+    ///
+    /// Each file holds the json array for its part of the script. A missing file leaves that part
+    /// empty, except for `keywords.json`, which is required. Will return `Err` if a file could not
+    /// be read or is invalid. Not available without the `std-fs` feature, or when targeting
+    /// `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn from_dir<P: AsRef<std::path::Path>>(path: P) -> Result<Script, Box<dyn Error>> {
+        let dir = path.as_ref();
+        if !dir.is_dir() {
+            return Err(format!("{} is not a directory", dir.display()).into());
+        }
+
+        let mut script = Script {
+            greetings: read_part(dir, "greetings", strings)?.unwrap_or_default(),
+            farewells: read_part(dir, "farewells", strings)?.unwrap_or_default(),
+            fallbacks: read_part(dir, "fallbacks", strings)?.unwrap_or_default(),
+            transforms: read_part(dir, "transforms", |v, p| {
+                each::<Transform>(v, p, locate_transform)
+            })?
+            .unwrap_or_default(),
+            synonyms: read_part(dir, "synonyms", |v, p| {
+                each::<Synonym>(v, p, locate_equivalents)
+            })?
+            .unwrap_or_default(),
+            reflections: read_part(dir, "reflections", |v, p| {
+                each::<Reflection>(v, p, locate_reflection)
+            })?
+            .unwrap_or_default(),
+            keywords: read_part(dir, "keywords", |v, p| {
+                each::<Keyword>(v, p, locate_keyword)
+            })?
+            .ok_or_else(|| format!("{} has no keywords.json", dir.display()))?,
+            ..Script::default()
+        };
+
+        script.tidy();
+        Ok(script)
+    }
+
+    /// Will save the script to a directory laid out as `from_dir()` expects, creating the
+    /// directory if needed and replacing any files of the same names within it.
+    ///
+    /// Will return `Err` if a file could not be written, or if the script sets anything that
+    /// early versions of this crate didn't support (such as `delimiters` or `nudges`), which a
+    /// directory has nowhere to keep. Not available without the `std-fs` feature, or when
+    /// targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn to_dir<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let parts = match serde_json::to_value(self)? {
+            Value::Object(parts) => parts,
+            _ => return Err("A script must serialize to a json object".into()),
+        };
+
+        let unsupported: Vec<&str> = parts
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !DIR_PARTS.contains(k))
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "A directory can't hold the script's {}",
+                unsupported.join(", ")
+            )
+            .into());
+        }
+
+        let dir = path.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (name, part) in &parts {
+            let mut json = serde_json::to_string_pretty(part)?;
+            json.push('\n');
+            std::fs::write(dir.join(format!("{}.json", name)), json)?;
+        }
+
+        Ok(())
+    }
+
+    /// Will load an ELIZA json script from the file system without blocking the async runtime.
+    ///
+    /// Only available with the `async` feature. Will return `Err` if the script at the specified
+    /// location is invalid or non-existant.
+    #[cfg(feature = "async")]
+    pub async fn from_file_async<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Script, Box<dyn Error + Send + Sync>> {
+        let bytes = tokio::fs::read(path).await?;
+        Script::from_slice(&bytes).map_err(|e| e.to_string().into())
+    }
+
+    /// Will load an ELIZA json script from any reader, such as a network stream or an archive
+    /// entry.
+    ///
+    /// Will return `Err` if the script could not be read, or is invalid (see `from_slice()`).
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Script, Box<dyn Error>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Script::from_slice(&bytes)
+    }
+
+    /// Will load an ELIZA json script directly from bytes, such as those embedded in a binary
+    /// with `include_bytes!`.
+    ///
+    /// Will return `Err` if the script is invalid. The error is an `ElizaError::Parse`, which
+    /// reports the position of the problem and the path to the offending element of the script
+    /// (e.g. `keywords[42].rules[3].reassembly_rules`).
+    pub fn from_slice(bytes: &[u8]) -> Result<Script, Box<dyn Error>> {
+        let mut script: Script = parse(bytes, "", locate_script)?;
+        script.tidy();
+        Ok(script)
+    }
+
+    /// Will load an ELIZA json script from a string.
+    ///
+    /// Will return `Err` if the script is invalid (see `from_slice()`).
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(val: &str) -> Result<Script, Box<dyn Error>> {
+        Script::from_slice(val.as_bytes())
+    }
+
+    /// Formats the `json` of a script canonically, as the `fmt` subcommand does.
+    ///
+    /// The parts of the script, and of each keyword and rule, are written in a fixed order, and
+    /// keywords are sorted by rank, highest first, then by key. Unlike the loaders, nothing else
+    /// is changed: duplicate keywords are not merged, and synonym references are not expanded.
+    /// The script is pretty printed with two spaces of indentation, unless `minify` is set.
+    ///
+    /// Will return `Err` if the script is invalid (see `from_slice()`).
+    pub fn format(bytes: &[u8], minify: bool) -> Result<String, Box<dyn Error>> {
+        let mut script: Script = parse(bytes, "", locate_script)?;
+        script
+            .keywords
+            .sort_by(|a, b| b.rank.cmp(&a.rank).then_with(|| a.key.cmp(&b.key)));
+
+        let mut json = match minify {
+            true => serde_json::to_string(&script)?,
+            false => serde_json::to_string_pretty(&script)?,
+        };
+        json.push('\n');
+        Ok(json)
+    }
+
+    /// Returns the famous DOCTOR script, which is bundled with the crate.
+    ///
+    /// Only available with the `builtin-doctor` feature, which embeds `scripts/doctor.json` at
+    /// compile time so no file system access is needed.
+    #[cfg(feature = "builtin-doctor")]
+    pub fn doctor() -> Script {
+        Script::from_str(include_str!("../scripts/doctor.json"))
+            .expect("The bundled DOCTOR script is invalid")
+    }
+
+    /// Checks that every decomposition rule in the script is a valid regex.
+    ///
+    /// Will return `Err` describing the first rule that fails to compile.
+    pub(crate) fn check(&self) -> Result<(), Box<dyn Error>> {
+        for k in &self.keywords {
+            for r in &k.rules {
+                if let Err(e) = crate::syntax::decomposition_regex(&r.decomposition_rule) {
+                    return Err(
+                        format!("Invalid decomposition rule for key '{}': {}", k.key, e).into(),
+                    );
+                }
+            }
+        }
+
+        self.warn_disabled_features();
+        Ok(())
+    }
+
+    //Warns of any settings that do nothing, as the feature they rely upon is disabled
+    fn warn_disabled_features(&self) {
+        if self.strip_diacritics && !cfg!(feature = "normalize") {
+            warn!("Diacritics will not be stripped, as the 'normalize' feature is disabled");
+        }
+        if self.stemming.is_some() && !cfg!(feature = "stemming") {
+            warn!("Keywords will not be stemmed, as the 'stemming' feature is disabled");
+        }
+    }
+
+    /// Checks the script for mistakes that a lenient load lets through, where they are only
+    /// logged once the faulty rule is tried mid-conversation.
+    ///
+    /// Will return `Err` describing the first of the following that is found:
+    /// * A decomposition rule that is not a valid regex.
+    /// * A reassembly rule that refers to a capture (e.g. `$3`) or synonym (e.g. `$@family`) its
+    ///   decomposition rule lacks.
+    /// * A `GOTO` naming a keyword that does not exist.
+    /// * A keyword that is defined more than once, as only the first definition would be used.
+    /// * A synonym that refers to a synonym that does not exist, or to itself.
+    ///
+    /// Use `problems()` to find every mistake at once.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.warn_disabled_features();
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Describes every mistake that `validate()` checks for, along with where it is in the
+    /// script, in the order that `validate()` checks for them.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (i, k) in self.keywords.iter().enumerate() {
+            for (j, r) in k.rules.iter().enumerate() {
+                if let Err(e) = crate::syntax::decomposition_regex(&r.decomposition_rule) {
+                    problems.push(format!(
+                        "Invalid decomposition rule for key '{}' (keywords[{}].rules[{}]): {}",
+                        k.key, i, j, e
+                    ));
+                }
+            }
+        }
+
+        let normalizer = self.normalizer();
+        let mut keys = HashSet::new();
+        for (i, k) in self.keywords.iter().enumerate() {
+            let key = match k.is_none_keyword() {
+                true => Cow::Borrowed(NONE_KEYWORD),
+                false => normalizer.normalize(&k.key),
+            };
+            if !keys.insert(key) {
+                problems.push(format!(
+                    "Keyword '{}' (keywords[{}]) is defined more than once",
+                    k.key, i
+                ));
+            }
+        }
+
+        problems.extend(self.expanded_synonyms().1);
+
+        for (i, k) in self.keywords.iter().enumerate() {
+            for (j, r) in k.rules.iter().enumerate() {
+                for goto in r
+                    .reassembly_rules
+                    .iter()
+                    .filter_map(|a| crate::syntax::is_goto(a))
+                {
+                    if self.goto(&goto).is_none() {
+                        problems.push(format!(
+                            "GOTO for key '{}' (keywords[{}].rules[{}]) names a keyword that \
+                             doesn't exist: '{}'",
+                            k.key, i, j, goto
+                        ));
+                    }
+                }
+            }
+        }
+
+        problems.extend(self.capture_mismatches());
+        problems
+    }
+
+    //Returns a script without any of this one's keywords, whose rules are compiled and checked
+    //just as this one's are. Only the synonyms and what decides how words compare are copied.
+    pub(crate) fn rule_settings(&self) -> Script {
+        Script {
+            synonyms: self.synonyms.clone(),
+            case_folding: self.case_folding,
+            strip_diacritics: self.strip_diacritics,
+            stemming: self.stemming,
+            word_chars: self.word_chars.clone(),
+            ..Script::default()
+        }
+    }
+
+    //Describes the mistakes that `problems()` finds in the keywords at the given indices
+    pub(crate) fn keyword_problems(&self, indices: &[usize]) -> Vec<String> {
+        let at: Vec<String> = indices
+            .iter()
+            .map(|i| format!("(keywords[{}]", i))
+            .collect();
+        let mut problems = self.problems();
+        problems.retain(|p| at.iter().any(|at| p.contains(at)));
+        problems
+    }
+
+    //Returns true if a reassembly rule of any keyword is a `GOTO` naming the key
+    pub(crate) fn is_goto_target(&self, key: &str) -> bool {
+        let rules = self.keywords.iter().flat_map(|k| &k.rules);
+        let mut gotos = rules
+            .flat_map(|r| &r.reassembly_rules)
+            .filter_map(|a| crate::syntax::is_goto(a));
+        gotos.any(|goto| goto == key)
+    }
+
+    /// Parses a script, as `from_slice()` does, but without tidying it first, so that mistakes
+    /// such as duplicate keywords are reported by `problems()` rather than mended.
+    ///
+    /// Will return `Err` if the script is invalid (see `from_slice()`), and otherwise the
+    /// problems found in the script, which is empty if it is valid.
+    pub fn problems_in(bytes: &[u8]) -> Result<Vec<String>, Box<dyn Error>> {
+        let script: Script = parse(bytes, "", locate_script)?;
+        Ok(script.problems())
+    }
+
+    /// Describes each reassembly rule that refers to a capture (e.g. `$3`) or synonym (e.g.
+    /// `$@family`) that its decomposition rule doesn't have, along with where the rule is in the script.
+    ///
+    /// Rules with an invalid decomposition rule are skipped, as `validate()` reports those.
+    pub fn capture_mismatches(&self) -> Vec<String> {
+        let normalizer = self.normalizer();
+        let mut mismatches = Vec::new();
+        for (i, k) in self.keywords.iter().enumerate() {
+            for (j, r) in k.rules.iter().enumerate() {
+                let captures = match crate::syntax::decomposition_regex(&r.decomposition_rule) {
+                    Ok(re) => re.captures_len(),
+                    Err(_) => continue,
+                };
+
+                for assem in r
+                    .reassembly_rules
+                    .iter()
+                    .chain(r.follow_ups.iter().flatten())
+                {
+                    if crate::syntax::is_goto(assem).is_some() {
+                        continue;
+                    }
+
+                    for id in assem.split('$').skip(1) {
+                        //Format example: 'Tell me more about your $@family'
+                        if let Some(name) = id.strip_prefix('@') {
+                            let name = self.word_chars().take(name);
+                            let decomposition =
+                                crate::syntax::inline_alternations(&r.decomposition_rule);
+                            if crate::synonym_name(&decomposition, &self.word_chars()).is_none_or(
+                                |s| normalizer.normalize(s) != normalizer.normalize(name),
+                            ) {
+                                mismatches.push(format!(
+                                    "Reassembly rule for key '{}' (keywords[{}].rules[{}]) refers \
+                                     to synonym '$@{}', which '{}' doesn't have: '{}'",
+                                    k.key, i, j, name, r.decomposition_rule, assem
+                                ));
+                            }
+                            continue;
+                        }
+
+                        let id = CharClass::Alphanumeric.take(id);
+                        if !id.parse::<usize>().is_ok_and(|n| n < captures) {
+                            mismatches.push(format!(
+                                "Reassembly rule for key '{}' (keywords[{}].rules[{}]) refers to \
+                                 capture '${}', which '{}' doesn't have: '{}'",
+                                k.key, i, j, id, r.decomposition_rule, assem
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    //Prepares a leniently loaded script for use, warning of any mistakes that remain
+    pub(crate) fn tidy(&mut self) {
+        self.merge_duplicate_keywords();
+        self.expand_synonyms();
+        self.resolve_gotos();
+        self.compile_rules();
+        for mismatch in self.capture_mismatches() {
+            warn!("{}", mismatch);
+        }
+    }
+
+    //Keywords are found by the first definition of their key, so any further definitions are
+    //appended to it
+    fn merge_duplicate_keywords(&mut self) {
+        let normalizer = self.normalizer();
+        let mut first: HashMap<String, usize> = HashMap::new();
+        let mut keywords: Vec<Keyword> = Vec::with_capacity(self.keywords.len());
+        for k in self.keywords.drain(..) {
+            //The NONE keyword is kept apart from any keyword for the word 'none'
+            let key = match k.is_none_keyword() {
+                true => NONE_KEYWORD.to_string(),
+                false => normalizer.normalize(&k.key).into_owned(),
+            };
+            match first.get(&key) {
+                Some(&i) => {
+                    warn!(
+                        "Keyword '{}' is defined more than once, so its rules have been merged",
+                        k.key
+                    );
+                    keywords[i].rules.extend(k.rules);
+                }
+                None => {
+                    first.insert(key, keywords.len());
+                    keywords.push(k);
+                }
+            }
+        }
+
+        self.keywords = keywords;
+    }
+
+    /// Replaces each reference to another synonym (such as `@parents`) in the equivalents of the
+    /// script's synonyms with that synonym's equivalents, warning of any that can't be expanded.
+    ///
+    /// The loaders do this already, so it is only needed for scripts built in code.
+    pub fn expand_synonyms(&mut self) {
+        let (expanded, mistakes) = self.expanded_synonyms();
+        for mistake in mistakes {
+            warn!("{}", mistake);
+        }
+
+        for (synonym, equivalents) in self.synonyms.iter_mut().zip(expanded) {
+            synonym.equivalents = equivalents;
+        }
+    }
+
+    //Returns the equivalents of each synonym with any references expanded, along with a
+    //description of each reference that couldn't be
+    fn expanded_synonyms(&self) -> (Vec<Vec<String>>, Vec<String>) {
+        let normalizer = self.normalizer();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (i, s) in self.synonyms.iter().enumerate() {
+            index
+                .entry(normalizer.normalize(&s.word).into_owned())
+                .or_insert(i);
+        }
+
+        let mut expanded = vec![None; self.synonyms.len()];
+        let mut mistakes = Vec::new();
+        let mut equivalents = Vec::with_capacity(self.synonyms.len());
+        for i in 0..self.synonyms.len() {
+            let (found, _) =
+                self.expand_synonym(i, &index, &mut expanded, &mut Vec::new(), &mut mistakes);
+            equivalents.push(found);
+        }
+
+        (equivalents, mistakes)
+    }
+
+    //Expands a synonym depth first, where `path` holds the synonyms being expanded so that a
+    //cycle can be broken, returning its equivalents and whether a cycle was broken to find them.
+    //Only expansions that broke no cycle are kept in `expanded`, as the others depend on where
+    //the cycle was entered, and so on the order of the synonyms.
+    fn expand_synonym(
+        &self,
+        i: usize,
+        index: &HashMap<String, usize>,
+        expanded: &mut Vec<Option<Vec<String>>>,
+        path: &mut Vec<usize>,
+        mistakes: &mut Vec<String>,
+    ) -> (Vec<String>, bool) {
+        if let Some(equivalents) = &expanded[i] {
+            return (equivalents.clone(), false);
+        }
+
+        let synonym = &self.synonyms[i];
+        let mut equivalents: Vec<String> = Vec::new();
+        let mut cut = false;
+        path.push(i);
+        for e in &synonym.equivalents {
+            let found = match e.strip_prefix('@') {
+                None => vec![e.clone()],
+                Some(name) => match index.get(self.normalizer().normalize(name).as_ref()) {
+                    Some(&j) if path.contains(&j) => {
+                        let mistake = format!(
+                            "Synonym '{}' refers to itself through '{}', which has been ignored",
+                            synonym.word, e
+                        );
+                        if !mistakes.contains(&mistake) {
+                            mistakes.push(mistake);
+                        }
+                        cut = true;
+                        continue;
+                    }
+                    Some(&j) => {
+                        let (found, cut_below) =
+                            self.expand_synonym(j, index, expanded, path, mistakes);
+                        cut |= cut_below;
+                        found
+                    }
+                    None => {
+                        let mistake = format!(
+                            "Synonym '{}' refers to a synonym that doesn't exist: '{}'",
+                            synonym.word, e
+                        );
+                        if !mistakes.contains(&mistake) {
+                            mistakes.push(mistake);
+                        }
+                        continue;
+                    }
+                },
+            };
+
+            for f in found {
+                if !equivalents.contains(&f) {
+                    equivalents.push(f);
+                }
+            }
+        }
+        path.pop();
+
+        if !cut {
+            expanded[i] = Some(equivalents.clone());
+        }
+        (equivalents, cut)
+    }
+
+    /// Resolves every `GOTO` to the index of the keyword it names (see `GotoIndex`), warning of
+    /// any that name a keyword that doesn't exist.
+    pub fn resolve_gotos(&mut self) {
+        self.gotos.0.clear();
+        for k in &self.keywords {
+            for assem in k.rules.iter().flat_map(|r| &r.reassembly_rules) {
+                if let Some(goto) = crate::syntax::is_goto(assem) {
+                    match crate::syntax::goto_index(self.keys(), &goto) {
+                        Some(i) => {
+                            self.gotos.0.insert(goto, i);
+                        }
+                        None => warn!(
+                            "GOTO for key '{}' names a keyword that doesn't exist: '{}'",
+                            k.key, goto
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compiles every decomposition rule (see `CompiledRules`). A rule is compiled along with the
+    /// equivalents of its synonym, or the keywords with its tag, so this should be called again
+    /// if the synonyms or tags change.
+    pub fn compile_rules(&mut self) {
+        let mut compiled = CompiledRules::default();
+        for r in self.keywords.iter().flat_map(|k| &k.rules) {
+            if !compiled.rules.contains_key(&r.decomposition_rule) {
+                let decomposition = self.compile(&r.decomposition_rule).map(Arc::new);
+                compiled
+                    .rules
+                    .insert(r.decomposition_rule.clone(), decomposition);
+            }
+        }
+
+        //A set only pays for itself when it rules out enough rules
+        for k in self
+            .keywords
+            .iter()
+            .filter(|k| k.rules.len() >= RULE_SET_MIN)
+        {
+            let mut patterns = Vec::new();
+            let mut members = Vec::new();
+            for (i, r) in k.rules.iter().enumerate() {
+                if let Some(Some(d)) = compiled.rules.get(&r.decomposition_rule) {
+                    patterns.push(d.pattern());
+                    members.push(i);
+                }
+            }
+
+            if let Ok(set) = RegexSet::new(patterns) {
+                let rules = k
+                    .rules
+                    .iter()
+                    .map(|r| r.decomposition_rule.clone())
+                    .collect();
+                let set = RuleSet {
+                    rules,
+                    set,
+                    members,
+                };
+                compiled.keywords.insert(k.key.clone(), Arc::new(set));
+            }
+        }
+
+        self.compiled = compiled;
+    }
+
+    //Returns whether each of a keyword's rules may match the phrase. Only the rules of keywords
+    //whose rules have been compiled together are ruled out.
+    pub(crate) fn possible_rules(&self, keyword: &Keyword, phrase: &str) -> Vec<bool> {
+        let set = self.compiled.keywords.get(&keyword.key).filter(|s| {
+            //The keyword's rules may have changed since they were compiled
+            s.rules.len() == keyword.rules.len()
+                && s.rules
+                    .iter()
+                    .zip(&keyword.rules)
+                    .all(|(old, r)| *old == r.decomposition_rule)
+        });
+
+        match set {
+            Some(s) => {
+                //Invalid rules aren't in the set, and are left to report themselves
+                let mut possible = vec![true; keyword.rules.len()];
+                for &i in &s.members {
+                    possible[i] = false;
+                }
+                for m in s.set.matches(phrase).iter() {
+                    possible[s.members[m]] = true;
+                }
+                possible
+            }
+            None => vec![true; keyword.rules.len()],
+        }
+    }
+
+    //Returns the compiled form of a decomposition rule, compiling it if that hasn't been done
+    pub(crate) fn decomposition(&self, rule: &str) -> Option<Arc<Decomposition>> {
+        match self.compiled.rules.get(rule) {
+            Some(decomposition) => decomposition.clone(),
+            None => self.compile(rule).map(Arc::new),
+        }
+    }
+
+    fn compile(&self, rule: &str) -> Option<Decomposition> {
+        let word_chars = self.word_chars();
+        match self.tag_reference(rule, &word_chars) {
+            //The tag is compiled as a synonym of the keywords that have it
+            Some((i, tag)) => {
+                let keys = self.keywords.iter().filter(|k| self.has_tag(k, tag));
+                let synonym = Synonym {
+                    word: tag.to_string(),
+                    equivalents: keys.map(|k| k.key.clone()).collect(),
+                };
+                let rule = format!("{}@{}", &rule[..i], &rule[i + 1..]);
+                Decomposition::compile(&rule, &[synonym], &word_chars, self.normalizer())
+            }
+            None => Decomposition::compile(rule, &self.synonyms, &word_chars, self.normalizer()),
+        }
+    }
+
+    //Returns the first tag that a decomposition rule names, e.g. '#family', along with where it is
+    fn tag_reference<'a>(&self, rule: &'a str, word_chars: &CharClass) -> Option<(usize, &'a str)> {
+        if self.keywords.iter().all(|k| k.tags.is_empty()) {
+            return None;
+        }
+
+        rule.match_indices('#')
+            .map(|(i, _)| (i, word_chars.take(&rule[i + 1..])))
+            .find(|&(_, tag)| !tag.is_empty() && self.keywords.iter().any(|k| self.has_tag(k, tag)))
+    }
+
+    fn has_tag(&self, keyword: &Keyword, tag: &str) -> bool {
+        let normalizer = self.normalizer();
+        let tag = normalizer.normalize(tag);
+        keyword.tags.iter().any(|t| normalizer.normalize(t) == tag)
+    }
+
+    /// Returns the keyword that a `GOTO` names, if it exists.
+    pub fn goto(&self, key: &str) -> Option<&Keyword> {
+        self.goto_index(key).map(|i| &self.keywords[i])
+    }
+
+    //Returns the index of the keyword that a `GOTO` names, if it exists
+    pub(crate) fn goto_index(&self, key: &str) -> Option<usize> {
+        match self.gotos.0.get(key) {
+            Some(&i) if self.keywords.get(i).is_some_and(|k| k.key == key) => Some(i),
+            //The keywords have been changed since the GOTOs were resolved
+            _ => crate::syntax::goto_index(self.keys(), key),
+        }
+    }
+
+    //Returns the key of each keyword, in order
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keywords.iter().map(|k| k.key.as_str())
+    }
+
+    //Returns the index of the NONE keyword, if the script has one
+    pub(crate) fn none_keyword(&self) -> Option<usize> {
+        self.keywords.iter().position(Keyword::is_none_keyword)
+    }
+
+    /// Returns the strings that separate the phrases of the user's input.
+    pub fn delimiters(&self) -> Vec<&str> {
+        match &self.delimiters {
+            Some(delimiters) => delimiters.iter().map(|d| d.as_str()).collect(),
+            None => DEFAULT_DELIMITERS.to_vec(),
+        }
+    }
+
+    /// Returns how the script splits the user's input into phrases.
+    pub fn splitter(&self) -> PhraseSplitter {
+        self.splitter.unwrap_or_default()
+    }
+
+    /// Returns how ELIZA chooses between the script's fallbacks.
+    pub fn fallback_order(&self) -> FallbackOrder {
+        self.fallback_order.unwrap_or_default()
+    }
+
+    /// Returns how the script ignores case when matching.
+    pub fn case_folding(&self) -> CaseFolding {
+        self.case_folding.unwrap_or_default()
+    }
+
+    /// Returns the characters that synonym names are made of.
+    pub fn word_chars(&self) -> CharClass {
+        self.word_chars.clone().unwrap_or_else(CharClass::word)
+    }
+
+    //Returns the normalizer that prepares text for matching against this script
+    pub(crate) fn normalizer(&self) -> Normalizer {
+        Normalizer::new(self.case_folding(), self.strip_diacritics, self.stemming)
+    }
+
+    /// Runs the tests embedded in the script, returning the outcome of each in order.
+    ///
+    /// Each test starts a fresh conversation with ELIZA, seeded so that any random choices are
+    /// the same on every run.
+    pub fn run_embedded_tests(&self) -> Vec<TestResult> {
+        let script = Arc::new(self.clone());
+        self.tests
+            .iter()
+            .map(|test| {
+                let mut e = crate::Eliza::from_shared(script.clone());
+                e.seed(0);
+                let response = e.respond_detailed(&test.input);
+
+                let mut failures = Vec::new();
+                if let Some(text) = &test.expect_contains {
+                    if !response.text.contains(text.as_str()) {
+                        failures.push(format!(
+                            "Expected the response to contain '{}', but it was '{}'",
+                            text, response.text
+                        ));
+                    }
+                }
+                if let Some(key) = &test.expect_keyword {
+                    if response.keyword.as_ref() != Some(key) {
+                        failures.push(format!(
+                            "Expected keyword '{}', but the response came from {}",
+                            key,
+                            match &response.keyword {
+                                Some(k) => format!("keyword '{}'", k),
+                                None => format!("{:?}", response.source).to_lowercase(),
+                            }
+                        ));
+                    }
+                }
+
+                TestResult {
+                    test: test.clone(),
+                    response,
+                    failures,
+                }
+            })
+            .collect()
+    }
+
+    /// Summarises the script at a glance, for tooling such as the `info` subcommand.
+    pub fn stats(&self) -> ScriptStats {
+        let rules = self.keywords.iter().flat_map(|k| &k.rules);
+        let reassembly_rules = rules.clone().flat_map(|r| &r.reassembly_rules);
+        let count = self.keywords.iter().map(|k| k.rules.len()).sum::<usize>();
+
+        ScriptStats {
+            keywords: self.keywords.len(),
+            rules: count,
+            reassembly_rules: reassembly_rules.clone().count(),
+            gotos: reassembly_rules
+                .filter(|a| crate::syntax::is_goto(a).is_some())
+                .count(),
+            memorised_rules: rules.filter(|r| r.memorise).count(),
+            synonyms: self.synonyms.len(),
+            reflections: self.reflections.len(),
+            transforms: self.transforms.len(),
+            greetings: self.greetings.len(),
+            farewells: self.farewells.len(),
+            fallbacks: self.fallbacks.len(),
+            max_rank: self.keywords.iter().map(|k| k.rank).max(),
+            average_rules: match self.keywords.len() {
+                0 => 0.0,
+                n => count as f64 / n as f64,
+            },
+        }
+    }
+
     /// Returns a random string from the `greetings` vector.
     ///
     /// Will return None if the vector is empty.
@@ -225,3 +1668,668 @@ impl Script {
         self.fallbacks.choose(&mut rand::thread_rng())
     }
 }
+
+//Parses a script, or part of one at the given path, and if it is invalid, finds the element at
+//fault
+fn parse<T: DeserializeOwned>(bytes: &[u8], path: &str, locate: Locate) -> Result<T, ElizaError> {
+    let e = match serde_json::from_slice(bytes) {
+        Ok(script) => return Ok(script),
+        Err(e) => e,
+    };
+
+    //Only well formed json can be searched for the element at fault
+    let path = match serde_json::from_slice::<Value>(bytes) {
+        Ok(value) => check::<T>(&value, path.to_string(), locate).unwrap_or_default(),
+        Err(_) => String::new(),
+    };
+
+    let message = e.to_string();
+    let position = format!(" at line {} column {}", e.line(), e.column());
+    Err(ElizaError::Parse {
+        path,
+        message: message
+            .strip_suffix(&position)
+            .unwrap_or(&message)
+            .to_string(),
+        line: e.line(),
+        column: e.column(),
+        offset: byte_offset(bytes, e.line(), e.column()),
+    })
+}
+
+//Reads a script from the file system, without tidying it as the lenient loaders do
+#[cfg(all(
+    feature = "std-fs",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn read_file<P: AsRef<std::path::Path>>(path: P) -> Result<Script, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(parse(&bytes, "", locate_script)?)
+}
+
+//The parts of a script that `from_dir()` reads, each from a file of its own
+#[cfg(all(
+    feature = "std-fs",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+const DIR_PARTS: [&str; 7] = [
+    "greetings",
+    "farewells",
+    "fallbacks",
+    "transforms",
+    "synonyms",
+    "reflections",
+    "keywords",
+];
+
+//Reads the part of a legacy script held in '<dir>/<name>.json', if the file exists
+#[cfg(all(
+    feature = "std-fs",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn read_part<T: DeserializeOwned>(
+    dir: &std::path::Path,
+    name: &str,
+    locate: Locate,
+) -> Result<Option<T>, Box<dyn Error>> {
+    let file = dir.join(format!("{}.json", name));
+    match std::fs::read(&file) {
+        Ok(bytes) => Ok(Some(parse(&bytes, name, locate)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", file.display(), e).into()),
+    }
+}
+
+//Each part of a script has a function to find the element at fault within it, given its path
+type Locate = fn(&Value, &str) -> Option<String>;
+
+//Returns the path to the element at fault, if the value is not a valid T
+fn check<T: DeserializeOwned>(value: &Value, path: String, locate: Locate) -> Option<String> {
+    match T::deserialize(value) {
+        Ok(_) => None,
+        Err(_) => Some(locate(value, &path).unwrap_or(path)),
+    }
+}
+
+fn field<T: DeserializeOwned>(
+    value: &Value,
+    path: &str,
+    name: &str,
+    locate: Locate,
+) -> Option<String> {
+    let path = match path {
+        "" => name.to_string(),
+        _ => format!("{}.{}", path, name),
+    };
+
+    check::<T>(value.get(name)?, path, locate)
+}
+
+fn each<T: DeserializeOwned>(value: &Value, path: &str, locate: Locate) -> Option<String> {
+    value
+        .as_array()?
+        .iter()
+        .enumerate()
+        .find_map(|(i, v)| check::<T>(v, format!("{}[{}]", path, i), locate))
+}
+
+fn entries<T: DeserializeOwned>(value: &Value, path: &str, locate: Locate) -> Option<String> {
+    value
+        .as_object()?
+        .iter()
+        .find_map(|(k, v)| check::<T>(v, format!("{}.{}", path, k), locate))
+}
+
+fn leaf(_: &Value, _: &str) -> Option<String> {
+    None
+}
+
+fn strings(value: &Value, path: &str) -> Option<String> {
+    each::<String>(value, path, leaf)
+}
+
+fn locate_script(value: &Value, path: &str) -> Option<String> {
+    field::<Vec<String>>(value, path, "greetings", strings)
+        .or_else(|| field::<Vec<String>>(value, path, "farewells", strings))
+        .or_else(|| field::<Vec<String>>(value, path, "fallbacks", strings))
+        .or_else(|| field::<Vec<String>>(value, path, "nudges", strings))
+        .or_else(|| field::<Vec<String>>(value, path, "banned", strings))
+        .or_else(|| field::<Vec<String>>(value, path, "banned_responses", strings))
+        .or_else(|| {
+            field::<Vec<ScriptTest>>(value, path, "tests", |v, p| {
+                each::<ScriptTest>(v, p, locate_test)
+            })
+        })
+        .or_else(|| {
+            field::<Vec<Transform>>(value, path, "transforms", |v, p| {
+                each::<Transform>(v, p, locate_transform)
+            })
+        })
+        .or_else(|| {
+            field::<Vec<Synonym>>(value, path, "synonyms", |v, p| {
+                each::<Synonym>(v, p, locate_equivalents)
+            })
+        })
+        .or_else(|| {
+            field::<Vec<Reflection>>(value, path, "reflections", |v, p| {
+                each::<Reflection>(v, p, locate_reflection)
+            })
+        })
+        .or_else(|| {
+            field::<Vec<Keyword>>(value, path, "keywords", |v, p| {
+                each::<Keyword>(v, p, locate_keyword)
+            })
+        })
+        .or_else(|| field::<Option<Vec<String>>>(value, path, "delimiters", strings))
+        .or_else(|| field::<Option<PhraseSplitter>>(value, path, "splitter", leaf))
+        .or_else(|| field::<Vec<String>>(value, path, "conjunctions", strings))
+        .or_else(|| field::<bool>(value, path, "fuzzy_keywords", leaf))
+        .or_else(|| field::<Option<CaseFolding>>(value, path, "case_folding", leaf))
+        .or_else(|| field::<bool>(value, path, "strip_diacritics", leaf))
+        .or_else(|| field::<Option<Stemming>>(value, path, "stemming", leaf))
+        .or_else(|| {
+            field::<Option<EmojiPolicy>>(value, path, "emoji", |v, p| {
+                field::<BTreeMap<String, String>>(v, p, "map", |v, p| entries::<String>(v, p, leaf))
+            })
+        })
+        .or_else(|| field::<Option<CharClass>>(value, path, "word_chars", leaf))
+        .or_else(|| field::<Option<FallbackOrder>>(value, path, "fallback_order", leaf))
+}
+
+fn locate_test(value: &Value, path: &str) -> Option<String> {
+    field::<String>(value, path, "input", leaf)
+        .or_else(|| field::<Option<String>>(value, path, "expect_contains", leaf))
+        .or_else(|| field::<Option<String>>(value, path, "expect_keyword", leaf))
+}
+
+fn locate_transform(value: &Value, path: &str) -> Option<String> {
+    locate_equivalents(value, path).or_else(|| field::<i32>(value, path, "priority", leaf))
+}
+
+fn locate_equivalents(value: &Value, path: &str) -> Option<String> {
+    field::<String>(value, path, "word", leaf)
+        .or_else(|| field::<Vec<String>>(value, path, "equivalents", strings))
+}
+
+fn locate_reflection(value: &Value, path: &str) -> Option<String> {
+    field::<String>(value, path, "word", leaf)
+        .or_else(|| field::<String>(value, path, "inverse", leaf))
+        .or_else(|| field::<bool>(value, path, "twoway", leaf))
+}
+
+fn locate_keyword(value: &Value, path: &str) -> Option<String> {
+    field::<String>(value, path, "key", leaf)
+        .or_else(|| field::<i32>(value, path, "rank", leaf))
+        .or_else(|| field::<Vec<String>>(value, path, "tags", strings))
+        .or_else(|| {
+            field::<Vec<Rule>>(value, path, "rules", |v, p| each::<Rule>(v, p, locate_rule))
+        })
+}
+
+fn locate_rule(value: &Value, path: &str) -> Option<String> {
+    field::<bool>(value, path, "memorise", leaf)
+        .or_else(|| field::<String>(value, path, "decomposition_rule", leaf))
+        .or_else(|| {
+            field::<Vec<ReassemblyDef>>(value, path, "reassembly_rules", |v, p| {
+                each::<ReassemblyDef>(v, p, locate_reassembly)
+            })
+        })
+        .or_else(|| {
+            field::<Vec<Condition>>(value, path, "conditions", |v, p| {
+                each::<Condition>(v, p, locate_condition)
+            })
+        })
+        .or_else(|| {
+            field::<BTreeMap<String, i32>>(value, path, "affect", |v, p| entries::<i32>(v, p, leaf))
+        })
+}
+
+fn locate_reassembly(value: &Value, path: &str) -> Option<String> {
+    value.as_object()?;
+    field::<String>(value, path, "text", leaf)
+        .or_else(|| field::<Option<String>>(value, path, "then", leaf))
+        .or_else(|| field::<Option<String>>(value, path, "if_last_keyword", leaf))
+        .or_else(|| field::<Option<usize>>(value, path, "if_turn_gt", leaf))
+        .or_else(|| field::<Option<String>>(value, path, "if_var_set", leaf))
+}
+
+fn locate_condition(value: &Value, path: &str) -> Option<String> {
+    field::<String>(value, path, "variable", leaf)
+        .or_else(|| field::<Option<i32>>(value, path, "min", leaf))
+        .or_else(|| field::<Option<i32>>(value, path, "max", leaf))
+}
+
+//Converts a one-based line/column position into a byte offset within the slice
+fn byte_offset(bytes: &[u8], line: usize, column: usize) -> usize {
+    let line_start: usize = bytes
+        .split(|&b| b == b'\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum();
+
+    (line_start + column.saturating_sub(1)).min(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_ok() {
+        let script = Script::from_slice(
+            br#"{"greetings": ["Hi"], "farewells": [], "fallbacks": [],
+            "transforms": [], "synonyms": [], "reflections": [], "keywords": []}"#,
+        )
+        .unwrap();
+        assert_eq!("Hi", script.greetings[0]);
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!("strasse", CaseFolding::Unicode.fold("Straße"));
+        assert_eq!("strasse", CaseFolding::Unicode.fold("STRASSE"));
+        assert_eq!("σοφοσ", CaseFolding::Unicode.fold("ΣΟΦΟΣ"));
+        assert_eq!("istanbul", CaseFolding::Unicode.fold("İSTANBUL"));
+        assert_eq!("ıstanbul", CaseFolding::Turkic.fold("ISTANBUL"));
+        assert_eq!("istanbul", CaseFolding::Turkic.fold("İstanbul"));
+    }
+
+    #[test]
+    fn emoji_policy() {
+        let text = "i feel 😢 about my 👨‍👩‍👧 🇳🇿 👍🏽";
+        assert_eq!(text, EmojiPolicy::Keep.apply(text));
+        assert_eq!("i feel about my", EmojiPolicy::Strip.apply(text));
+
+        let mut table = BTreeMap::new();
+        table.insert("😢".to_string(), "sad".to_string());
+        table.insert("👍".to_string(), "yes".to_string());
+        assert_eq!(
+            "i feel sad about my yes",
+            EmojiPolicy::Map(table).apply(text)
+        );
+    }
+
+    #[test]
+    fn validate_mistakes() {
+        let script = |decomposition: &str, reassembly: &str| {
+            Script::from_str(&format!(
+                r#"{{
+                    "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                    "synonyms": [], "reflections": [],
+                    "keywords": [{{
+                        "key": "hello", "rank": 0,
+                        "rules": [{{
+                            "memorise": false, "decomposition_rule": "{}",
+                            "reassembly_rules": ["{}"]
+                        }}]
+                    }}]
+                }}"#,
+                decomposition, reassembly
+            ))
+            .unwrap()
+        };
+
+        assert!(script("(.*)hello (.*)", "Hi $2.").validate().is_ok());
+        assert!(script("(.*)hello (.*)", "GOTO hello").validate().is_ok());
+        assert!(script("(.*)hello (.*", "Hi.").validate().is_err());
+        assert!(script("(.*)hello (.*)", "Hi $3.").validate().is_err());
+        assert!(script("(.*)hello (.*)", "Hi $a.").validate().is_err());
+        assert!(script("(.*)hello (.*)", "GOTO goodbye").validate().is_err());
+    }
+
+    #[test]
+    fn every_problem() {
+        let problems = Script::problems_in(
+            br#"{
+                "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                "synonyms": [{"word": "family", "equivalents": ["@pets"]}], "reflections": [],
+                "keywords": [
+                    {"key": "hello", "rank": 0, "rules": [
+                        {"memorise": false, "decomposition_rule": "(.*", "reassembly_rules": ["Hi."]},
+                        {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["GOTO bye", "$2"]}
+                    ]},
+                    {"key": "Hello", "rank": 0, "rules": []}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(5, problems.len(), "{:?}", problems);
+        assert!(problems[0]
+            .starts_with("Invalid decomposition rule for key 'hello' (keywords[0].rules[0])"));
+        assert_eq!(
+            "Keyword 'Hello' (keywords[1]) is defined more than once",
+            problems[1]
+        );
+        assert!(problems[2].contains("'@pets'"));
+        assert!(problems[3]
+            .contains("(keywords[0].rules[1]) names a keyword that doesn't exist: 'bye'"));
+        assert!(problems[4].contains("'$2'"));
+
+        assert!(Script::problems_in(b"{}").is_err());
+        let doctor = std::fs::read("scripts/doctor.json").unwrap();
+        assert!(Script::problems_in(&doctor).unwrap().is_empty());
+    }
+
+    #[test]
+    fn capture_mismatches() {
+        let mut script = Script::default();
+        script.keywords.push(Keyword {
+            key: String::from("my"),
+            rank: 0,
+            tags: Vec::new(),
+            rules: vec![
+                Rule {
+                    decomposition_rule: String::from("(.*)my (.*)"),
+                    reassembly_rules: vec![String::from("Your $2.")],
+                    ..Rule::default()
+                },
+                Rule {
+                    decomposition_rule: String::from("(.*)my (.*@family)"),
+                    reassembly_rules: vec![String::from("Your $2, $3 and $x.")],
+                    ..Rule::default()
+                },
+                Rule {
+                    decomposition_rule: String::from("(.*)your (.*@Family)"),
+                    reassembly_rules: vec![String::from("My $@family, not my $@pets.")],
+                    ..Rule::default()
+                },
+            ],
+        });
+
+        let mismatches = script.capture_mismatches();
+        assert_eq!(3, mismatches.len());
+        assert!(mismatches[2].contains("'$@pets'"));
+        assert!(mismatches[0].contains("(keywords[0].rules[1])"));
+        assert!(mismatches[0].contains("'$3'"));
+        assert!(mismatches[1].contains("'$x'"));
+        assert_eq!(
+            Some(mismatches[0].clone()),
+            script.validate().err().map(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn duplicate_keywords() {
+        let mut script = Script::default();
+        for (key, decomposition) in [("sorry", "(.*)"), ("hello", "(.*)"), ("Sorry", "(.*)me")] {
+            script.keywords.push(Keyword {
+                key: key.to_string(),
+                rank: 0,
+                tags: Vec::new(),
+                rules: vec![Rule {
+                    decomposition_rule: decomposition.to_string(),
+                    ..Rule::default()
+                }],
+            });
+        }
+        assert!(script.validate().is_err());
+
+        script.merge_duplicate_keywords();
+        assert!(script.validate().is_ok());
+        assert_eq!(2, script.keywords.len());
+        assert_eq!("sorry", script.keywords[0].key);
+        assert_eq!("(.*)me", script.keywords[0].rules[1].decomposition_rule);
+    }
+
+    #[test]
+    fn synonym_references() {
+        let synonym = |word: &str, equivalents: &[&str]| Synonym {
+            word: word.to_string(),
+            equivalents: equivalents.iter().map(|e| e.to_string()).collect(),
+        };
+        let mut script = Script {
+            synonyms: vec![
+                synonym("family", &["@Parents", "@siblings", "wife"]),
+                synonym("parents", &["mother", "father"]),
+                synonym("siblings", &["sister", "brother", "@family", "mother"]),
+            ],
+            ..Script::default()
+        };
+        assert!(script.validate().is_err());
+
+        let mut reversed = script.clone();
+        reversed.synonyms.reverse();
+        script.expand_synonyms();
+        assert!(script.validate().is_ok());
+        assert_eq!(
+            vec!("mother", "father", "sister", "brother", "wife"),
+            script.synonyms[0].equivalents
+        );
+        assert_eq!(
+            vec!("sister", "brother", "mother", "father", "wife"),
+            script.synonyms[2].equivalents
+        );
+
+        //Synonyms in a cycle expand alike whichever comes first
+        reversed.expand_synonyms();
+        reversed.synonyms.reverse();
+        for (s, r) in script.synonyms.iter().zip(&reversed.synonyms) {
+            assert_eq!(s.equivalents, r.equivalents);
+        }
+
+        script.synonyms.push(synonym("pets", &["@animals"]));
+        assert!(script.validate().is_err());
+        script.expand_synonyms();
+        assert!(script.synonyms[3].equivalents.is_empty());
+    }
+
+    #[test]
+    fn goto_resolution() {
+        let mut script = Script::from_str(
+            r#"{
+                "greetings": [], "farewells": [], "fallbacks": [], "transforms": [],
+                "synonyms": [], "reflections": [],
+                "keywords": [
+                    {"key": "mine", "rank": 0, "rules": [
+                        {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["GOTO my"]}
+                    ]},
+                    {"key": "yours", "rank": 0, "rules": [
+                        {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["GOTO your"]}
+                    ]},
+                    {"key": "my", "rank": 0, "rules": []}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(&2), script.gotos.0.get("my"));
+        assert!(!script.gotos.0.contains_key("your"));
+        assert_eq!("my", script.goto("my").unwrap().key);
+        assert!(script.goto("your").is_none());
+
+        //Changes to the keywords after loading are still followed
+        script.keywords.remove(0);
+        assert_eq!("my", script.goto("my").unwrap().key);
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn rule_sets() {
+        let mut script = Script::from_file("scripts/doctor.json").unwrap();
+        let i = script.keywords.iter().position(|k| k.key == "i").unwrap();
+        let possible = script.possible_rules(&script.keywords[i], "i am unhappy with you");
+        assert_eq!(12, possible.len());
+        assert!(possible[2]); //'(.*)i am (.*@happy)'
+        assert!(!possible[3]); //'(.*)i was(.+)'
+        assert!(possible[11]); //'(.+)'
+
+        //Rules changed since loading are all tried
+        script.keywords[i].rules.pop();
+        let possible = script.possible_rules(&script.keywords[i], "i am unhappy with you");
+        assert_eq!(vec![true; 11], possible);
+    }
+
+    #[test]
+    fn script_stats() {
+        let script = Script::from_str(
+            r#"{
+                "greetings": ["Hi"], "farewells": [], "fallbacks": ["Go on."], "transforms": [],
+                "synonyms": [{"word": "family", "equivalents": ["mother"]}], "reflections": [],
+                "keywords": [
+                    {"key": "mine", "rank": 3, "rules": [
+                        {"memorise": true, "decomposition_rule": "(.*)", "reassembly_rules": ["A", "B"]},
+                        {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": ["GOTO my"]}
+                    ]},
+                    {"key": "my", "rank": 1, "rules": []}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let stats = script.stats();
+        assert_eq!(
+            ScriptStats {
+                keywords: 2,
+                rules: 2,
+                reassembly_rules: 3,
+                gotos: 1,
+                memorised_rules: 1,
+                synonyms: 1,
+                greetings: 1,
+                fallbacks: 1,
+                max_rank: Some(3),
+                average_rules: 1.0,
+                ..ScriptStats::default()
+            },
+            stats
+        );
+        assert_eq!(None, Script::default().stats().max_rank);
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn embedded_tests() {
+        let mut script = Script::from_file("scripts/doctor.json").unwrap();
+        script.tests = vec![
+            ScriptTest {
+                input: "I remember my mother".to_string(),
+                expect_contains: Some("mother".to_string()),
+                expect_keyword: Some("remember".to_string()),
+            },
+            ScriptTest {
+                input: "Sorry".to_string(),
+                expect_keyword: Some("remember".to_string()),
+                ..ScriptTest::default()
+            },
+            ScriptTest {
+                input: "Hmm".to_string(),
+                expect_contains: Some("mother".to_string()),
+                ..ScriptTest::default()
+            },
+        ];
+
+        let results = script.run_embedded_tests();
+        assert_eq!(3, results.len());
+        assert!(results[0].passed(), "{:?}", results[0].failures);
+        assert_eq!(
+            vec!["Expected keyword 'remember', but the response came from keyword 'sorry'"],
+            results[1].failures
+        );
+        //Each test starts a fresh conversation, so nothing is remembered from the first
+        assert!(results[2].failures[0].starts_with("Expected the response to contain 'mother'"));
+    }
+
+    #[test]
+    fn canonical_format() {
+        let json = br#"{"keywords": [
+                {"key": "b", "rank": 0, "rules": []}, {"key": "a", "rank": 0, "rules": []},
+                {"key": "c", "rank": 1, "rules": []}, {"key": "a", "rank": 0, "rules": []}
+            ],
+            "fallbacks": [], "farewells": [], "greetings": ["Hi"], "transforms": [],
+            "synonyms": [{"word": "all", "equivalents": ["@family"]}], "reflections": []}"#;
+
+        let formatted = Script::format(json, false).unwrap();
+        let keys: Vec<String> = Script::from_str(&formatted)
+            .unwrap()
+            .keywords
+            .into_iter()
+            .map(|k| k.key)
+            .collect();
+        assert_eq!(vec!["c", "a", "b"], keys);
+        assert!(formatted.starts_with("{\n  \"greetings\": [\n    \"Hi\"\n  ],\n"));
+        assert!(formatted.contains("\"@family\""));
+        assert_eq!(
+            formatted,
+            Script::format(formatted.as_bytes(), false).unwrap()
+        );
+
+        let minified = Script::format(json, true).unwrap();
+        assert_eq!(1, minified.lines().count());
+        assert_eq!(
+            minified,
+            Script::format(formatted.as_bytes(), true).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_err_path() {
+        let path = |json: &str| match *Script::from_str(json)
+            .unwrap_err()
+            .downcast::<ElizaError>()
+            .unwrap()
+        {
+            ElizaError::Parse { path, .. } => path,
+            e => panic!("Expected a parse error, got {:?}", e),
+        };
+
+        assert_eq!("", path(r#"{"greetings": ["#));
+        assert_eq!("", path(r#"{"greetings": []}"#));
+        assert_eq!("greetings[1]", path(r#"{"greetings": ["Hi", 2]}"#));
+        assert_eq!(
+            "keywords[1].rules[0].reassembly_rules",
+            path(
+                r#"{
+                    "keywords": [
+                        {"key": "a", "rank": 0, "rules": []},
+                        {"key": "b", "rank": 0, "rules": [
+                            {"memorise": false, "decomposition_rule": "(.*)", "reassembly_rules": "Hi"}
+                        ]}
+                    ]
+                }"#
+            )
+        );
+        assert_eq!(
+            "keywords[0].rules[0]",
+            path(r#"{"keywords": [{"key": "a", "rank": 0, "rules": [{"memorise": false}]}]}"#)
+        );
+        assert_eq!(
+            "keywords[0].rules[0].reassembly_rules[1].if_turn_gt",
+            path(
+                r#"{"keywords": [{"key": "a", "rank": 0, "rules": [{
+                    "memorise": false, "decomposition_rule": "",
+                    "reassembly_rules": ["Hi", {"text": "Hello", "if_turn_gt": "soon"}]
+                }]}]}"#
+            )
+        );
+        assert_eq!(
+            "keywords[0].rules[0].affect.anger",
+            path(
+                r#"{"keywords": [{"key": "a", "rank": 0, "rules": [{
+                    "memorise": false, "decomposition_rule": "", "reassembly_rules": [],
+                    "affect": {"anger": "very"}
+                }]}]}"#
+            )
+        );
+        assert_eq!(
+            "keywords[0].rank",
+            path(r#"{"keywords": [{"key": "a", "rank": 3000000000}]}"#)
+        );
+    }
+
+    #[test]
+    fn slice_err_offset() {
+        let err = Script::from_slice(b"{\n  \"greetings\": [1]}").unwrap_err();
+        assert!(err.to_string().ends_with("(byte offset 18)"), "{}", err);
+    }
+
+    #[test]
+    fn offset_from_position() {
+        let bytes = b"ab\ncd\nef";
+        assert_eq!(0, byte_offset(bytes, 1, 1));
+        assert_eq!(4, byte_offset(bytes, 2, 2));
+        assert_eq!(6, byte_offset(bytes, 3, 1));
+        assert_eq!(8, byte_offset(bytes, 9, 9));
+    }
+}
@@ -0,0 +1,961 @@
+//! A small HTTP API for hosting many ELIZA conversations at once, so that ELIZA can be fronted
+//! by any web UI.
+//!
+//! This module is only available when the crate is built with the `server` feature.
+//!
+//! ## Endpoints
+//!
+//! All request and response bodies are `json`.
+//!
+//! * `POST /sessions`: Starts a new conversation, replying with the session id and a greeting:
+//!   `{"id": "...", "greeting": "..."}`.
+//! * `POST /sessions/{id}/messages`: Sends `{"text": "..."}` to ELIZA, replying with
+//!   `{"response": "..."}`.
+//! * `DELETE /sessions/{id}`: Ends a conversation, replying with `{"farewell": "..."}`.
+//! * `GET /metrics`: Reports the server's metrics in the Prometheus text format (see below).
+//!
+//! Errors are reported with an appropriate status code and a body of `{"error": "..."}`.
+//!
+//! ## WebSockets
+//!
+//! Browser chat widgets may prefer to hold a WebSocket open instead:
+//!
+//! * `GET /ws`: Starts a new conversation. The first frame sent by the server is
+//!   `{"type": "session", "id": "...", "greeting": "..."}`.
+//! * `GET /sessions/{id}/ws`: Continues an existing conversation.
+//!
+//! The client sends frames of `{"text": "..."}`, and ELIZA replies with
+//! `{"type": "response", "text": "..."}`. Adding `?stream=words` to the route will have each word
+//! of the reply sent first as `{"type": "chunk", "text": "..."}` for a typing effect, optionally
+//! paced with `&delay_ms=N`; the full `response` frame always follows the last chunk.
+//!
+//! ## Sessions
+//!
+//! Sessions are held in an `ElizaPool`, so an idle timeout, a limit on the number of sessions
+//! and a rate limit may be configured by binding with `Server::with_pool()`. Once the limit on
+//! sessions is reached, starting a session fails with a `503` status. Messages beyond a session's
+//! rate limit fail with a `429` status, or an `error` frame over a WebSocket. A WebSocket whose
+//! client sends nothing for longer than the pool's idle timeout is closed, as its session has
+//! expired.
+//!
+//! Sessions live only as long as the server does, unless it is given a `store::SessionStore` with
+//! `Server::session_store()`. The store is then sent a snapshot of each session whenever it
+//! changes, and a session the pool doesn't hold, because the server has restarted or the session
+//! went idle, is resumed from the store. Ending a session discards it from the store too.
+//!
+//! ## Metrics
+//!
+//! So that operators can watch the health of a script in production, `/metrics` exports:
+//!
+//! * `eliza_http_requests_total`: HTTP requests served, by `status`.
+//! * `eliza_responses_total`: Responses given, by `source` (`keyword`, `memory`, `followup`,
+//!   `fallback` or `filtered`, as in `ResponseSource`). A rising share of fallbacks suggests the
+//!   script is missing keywords.
+//! * `eliza_keyword_hits_total`: Responses produced by each `keyword`.
+//! * `eliza_response_seconds`: How long ELIZA took to respond.
+//! * `eliza_session_memory`: How many responses a session held in memory after each response.
+//! * `eliza_sessions`: The number of sessions in the pool.
+//!
+use crate::pool::{ElizaPool, PoolError};
+use crate::script::Script;
+use crate::snapshot::Snapshot;
+use crate::store::SessionStore;
+use crate::Eliza;
+use prometheus::{
+    Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder, TEXT_FORMAT,
+};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::protocol::{Role, WebSocket};
+
+#[derive(Deserialize)]
+struct Message {
+    text: String,
+}
+
+//The state shared between the HTTP handler and any WebSocket connections
+struct State {
+    pool: Mutex<ElizaPool>,
+    store: Mutex<Option<Box<dyn SessionStore + Send>>>,
+    metrics: Metrics,
+    read_timeout: Option<Duration>,
+}
+
+//The metrics exported at '/metrics'
+struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    responses: IntCounterVec,
+    keywords: IntCounterVec,
+    latency: Histogram,
+    memory: Histogram,
+    sessions: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        //The metrics are well formed and uniquely named, so can't fail to be created or registered
+        let counter = |name: &str, help: &str, label: &str| {
+            IntCounterVec::new(Opts::new(name, help), &[label]).unwrap()
+        };
+        let histogram = |name: &str, help: &str, buckets: Vec<f64>| {
+            Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets)).unwrap()
+        };
+
+        let metrics = Metrics {
+            registry: Registry::new(),
+            requests: counter(
+                "eliza_http_requests_total",
+                "HTTP requests served",
+                "status",
+            ),
+            responses: counter("eliza_responses_total", "Responses given", "source"),
+            keywords: counter(
+                "eliza_keyword_hits_total",
+                "Responses produced by each keyword",
+                "keyword",
+            ),
+            latency: histogram(
+                "eliza_response_seconds",
+                "Time taken to respond",
+                prometheus::exponential_buckets(0.0001, 4.0, 8).unwrap(),
+            ),
+            memory: histogram(
+                "eliza_session_memory",
+                "Responses held in a session's memory",
+                vec![0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0],
+            ),
+            sessions: IntGauge::new("eliza_sessions", "Sessions in the pool").unwrap(),
+        };
+
+        let registry = &metrics.registry;
+        registry
+            .register(Box::new(metrics.requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.responses.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.keywords.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.latency.clone()))
+            .unwrap();
+        registry.register(Box::new(metrics.memory.clone())).unwrap();
+        registry
+            .register(Box::new(metrics.sessions.clone()))
+            .unwrap();
+
+        metrics
+    }
+}
+
+impl State {
+    //Returns the new session's id and greeting
+    fn start(&self) -> Result<(String, String), PoolError> {
+        let id = format!("{:016x}", rand::random::<u64>());
+        let mut pool = self.pool.lock().unwrap();
+        let greeting = pool.start(&id)?;
+        let snapshot = self.snapshot(&pool, &id);
+        drop(pool);
+
+        self.save(&id, snapshot);
+        Ok((id, greeting))
+    }
+
+    //Returns None if there is no such session
+    fn respond(&self, id: &str, input: &str) -> Option<Result<String, PoolError>> {
+        match self.resume(id) {
+            Ok(true) => (),
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
+        //The session may have expired since it was resumed
+        let mut pool = self.pool.lock().unwrap();
+        if !pool.contains(id) {
+            return None;
+        }
+
+        let start = Instant::now();
+        let response = match pool.respond_detailed(id, input) {
+            Ok(response) => response,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let metrics = &self.metrics;
+        metrics.latency.observe(start.elapsed().as_secs_f64());
+        let source = json!(response.source);
+        metrics
+            .responses
+            .with_label_values(&[source.as_str().unwrap_or_default()])
+            .inc();
+        if let Some(keyword) = &response.keyword {
+            metrics.keywords.with_label_values(&[keyword]).inc();
+        }
+        if let Some(eliza) = pool.get(id) {
+            metrics.memory.observe(eliza.memory().len() as f64);
+        }
+        let snapshot = self.snapshot(&pool, id);
+        drop(pool);
+
+        self.save(id, snapshot);
+        Some(Ok(response.text))
+    }
+
+    //Returns true if the pool holds the session, bringing it back from the store if need be. The
+    //store is read without holding the pool, so that other sessions needn't wait for it.
+    fn resume(&self, id: &str) -> Result<bool, PoolError> {
+        if self.pool.lock().unwrap().contains(id) {
+            return Ok(true);
+        }
+
+        let snapshot = match self.store.lock().unwrap().as_mut().map(|s| s.load(id)) {
+            Some(Ok(Some(snapshot))) => snapshot,
+            Some(Ok(None)) | None => return Ok(false),
+            Some(Err(e)) => {
+                warn!("Failed to load session {}: {}", id, e);
+                return Ok(false);
+            }
+        };
+
+        //Another request may have resumed the session while it was loaded
+        let mut pool = self.pool.lock().unwrap();
+        if !pool.contains(id) {
+            pool.resume(id, snapshot)?;
+            info!("Session {} was resumed from the store", id);
+        }
+        Ok(true)
+    }
+
+    //Captures the session for the store while the pool is held, if there is a store to save it
+    fn snapshot(&self, pool: &ElizaPool, id: &str) -> Option<Snapshot> {
+        match self.store.lock().unwrap().is_some() {
+            true => pool.get(id).map(Eliza::snapshot),
+            false => None,
+        }
+    }
+
+    //Saves a snapshot of the session to the store, once the pool has been released. Failures
+    //are only logged, as the session carries on in the pool regardless
+    fn save(&self, id: &str, snapshot: Option<Snapshot>) {
+        if let (Some(store), Some(snapshot)) = (self.store.lock().unwrap().as_mut(), snapshot) {
+            if let Err(e) = store.save(id, &snapshot) {
+                warn!("Failed to save session {}: {}", id, e);
+            }
+        }
+    }
+
+    //Returns the metrics in the Prometheus text format
+    fn metrics(&self) -> String {
+        let sessions = self.pool.lock().unwrap().len();
+        self.metrics.sessions.set(sessions as i64);
+
+        let families = self.metrics.registry.gather();
+        TextEncoder::new()
+            .encode_to_string(&families)
+            .unwrap_or_else(|e| {
+                warn!("Failed to encode metrics: {}", e);
+                String::new()
+            })
+    }
+
+    //Returns None if there is no such session
+    fn end(&self, id: &str) -> Option<String> {
+        if let Err(e) = self.resume(id) {
+            warn!("Failed to resume session {} to end it: {}", id, e);
+        }
+        let farewell = self.pool.lock().unwrap().end(id);
+
+        if let Some(store) = self.store.lock().unwrap().as_mut() {
+            if let Err(e) = store.expire(id) {
+                warn!("Failed to discard session {}: {}", id, e);
+            }
+        }
+        farewell
+    }
+}
+
+/// An ELIZA HTTP server.
+///
+/// This struct is created by the `bind()` method. See its documentation for more.
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+impl Server {
+    /// Binds a server to the given address, where each session will converse using the script.
+    ///
+    /// Will return `Err` if the address can't be bound.
+    pub fn bind<A: ToSocketAddrs>(addr: A, script: Script) -> Result<Server, Box<dyn Error>> {
+        Server::with_pool(addr, ElizaPool::new(script))
+    }
+
+    /// Binds a server to the given address, hosting its sessions in the pool.
+    ///
+    /// This allows the pool's idle timeout and session limit to be configured. Will return `Err`
+    /// if the address can't be bound.
+    pub fn with_pool<A: ToSocketAddrs>(addr: A, pool: ElizaPool) -> Result<Server, Box<dyn Error>> {
+        let listener = TcpListener::bind(addr)?;
+        //A zero timeout can't be set on a socket, and would close every connection at once anyway
+        let read_timeout = pool.idle_timeout_value().filter(|t| !t.is_zero());
+
+        Ok(Server {
+            listener,
+            state: Arc::new(State {
+                pool: Mutex::new(pool),
+                store: Mutex::new(None),
+                metrics: Metrics::new(),
+                read_timeout,
+            }),
+        })
+    }
+
+    /// Keeps every session in the store as well as the pool, so that conversations survive the
+    /// server being restarted (see `store::SessionStore`).
+    pub fn session_store<S: SessionStore + Send + 'static>(self, store: S) -> Server {
+        *self.state.store.lock().unwrap() = Some(Box::new(store));
+        self
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Serves requests until the process is stopped.
+    pub fn run(&self) {
+        if let Some(addr) = self.addr() {
+            info!("Listening on http://{}", addr);
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+
+            //WebSockets are long-lived, so give each connection a thread of its own
+            let state = self.state.clone();
+            thread::spawn(move || {
+                if let Err(e) = serve(&state, stream) {
+                    warn!("Connection closed: {}", e);
+                }
+            });
+        }
+    }
+}
+
+//The methods the server routes on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Get,
+    Post,
+    Delete,
+    Other,
+}
+
+impl Method {
+    fn parse(method: &str) -> Method {
+        match method {
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            "DELETE" => Method::Delete,
+            _ => Method::Other,
+        }
+    }
+}
+
+impl State {
+    //Counts a request served with the status
+    fn count(&self, status: u16) {
+        self.metrics
+            .requests
+            .with_label_values(&[&status.to_string()])
+            .inc();
+    }
+
+    fn route(&self, method: &Method, url: &str, body: &str) -> (u16, Value) {
+        let segments = segments(url);
+
+        match (method, segments.as_slice()) {
+            (Method::Post, ["sessions"]) => match self.start() {
+                Ok((id, greeting)) => (201, json!({ "id": id, "greeting": greeting })),
+                Err(e) => (503, json!({ "error": e.to_string() })),
+            },
+            (Method::Post, ["sessions", id, "messages"]) => {
+                let message: Message = match serde_json::from_str(body) {
+                    Ok(message) => message,
+                    Err(e) => return (400, json!({ "error": e.to_string() })),
+                };
+
+                match self.respond(id, &message.text) {
+                    Some(Ok(response)) => (200, json!({ "response": response })),
+                    Some(Err(e @ PoolError::Full)) => (503, json!({ "error": e.to_string() })),
+                    Some(Err(e @ PoolError::RateLimited { .. })) => {
+                        (429, json!({ "error": e.to_string() }))
+                    }
+                    Some(Err(e)) => (500, json!({ "error": e.to_string() })),
+                    None => (404, json!({ "error": "No such session" })),
+                }
+            }
+            (Method::Delete, ["sessions", id]) => match self.end(id) {
+                Some(farewell) => (200, json!({ "farewell": farewell })),
+                None => (404, json!({ "error": "No such session" })),
+            },
+            _ => (404, json!({ "error": "Not found" })),
+        }
+    }
+}
+
+//Serves one connection: a single HTTP request, or a WebSocket until it is closed
+fn serve(state: &State, mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    //A client that never finishes its request would otherwise keep the thread serving it forever,
+    //whether or not sessions are ever discarded
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let (head, rest) = read_head(&mut stream)?;
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut headers);
+    if let Err(e) = request.parse(&head) {
+        return respond(state, &mut stream, 400, &json!({ "error": e.to_string() }));
+    }
+
+    let method = request.method.unwrap_or_default();
+    let url = request.path.unwrap_or_default().to_string();
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .and_then(|h| std::str::from_utf8(h.value).ok())
+    };
+
+    let upgrade = header("Upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket"));
+    if let Some(key) = header("Sec-WebSocket-Key").filter(|_| upgrade) {
+        //A socket that goes quiet for longer than the pool's idle timeout has lost its session, so
+        //there's no reason to keep its connection, or the thread serving it
+        stream.set_read_timeout(state.read_timeout)?;
+        let key = key.to_string();
+        return upgrade_websocket(state, stream, Method::parse(method), &url, &key, rest);
+    }
+
+    //Metrics are plain text rather than json
+    if Method::parse(method) == Method::Get && segments(&url) == ["metrics"] {
+        state.count(200);
+        return write_response(&mut stream, 200, TEXT_FORMAT, &state.metrics());
+    }
+
+    let length = match header("Content-Length").map(str::parse::<usize>) {
+        Some(Ok(length)) if length > MAX_BODY => {
+            return respond(
+                state,
+                &mut stream,
+                413,
+                &json!({ "error": "Body is too large" }),
+            )
+        }
+        Some(Ok(length)) => length,
+        Some(Err(e)) => {
+            return respond(state, &mut stream, 400, &json!({ "error": e.to_string() }))
+        }
+        None => 0,
+    };
+
+    let mut body = rest;
+    body.truncate(length);
+    if body.len() < length {
+        if header("Expect").is_some_and(|e| e.eq_ignore_ascii_case("100-continue")) {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+        let mut remainder = vec![0; length - body.len()];
+        stream.read_exact(&mut remainder)?;
+        body.extend(remainder);
+    }
+
+    let (status, reply) = match String::from_utf8(body) {
+        Ok(body) => state.route(&Method::parse(method), &url, &body),
+        Err(e) => (400, json!({ "error": e.to_string() })),
+    };
+    info!("{} {} -> {}", method, url, status);
+    respond(state, &mut stream, status, &reply)
+}
+
+//The largest request head and body the server will read
+const MAX_HEAD: usize = 16 * 1024;
+const MAX_BODY: usize = 64 * 1024;
+
+//How long the server waits for each read of a request before giving up on the client
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+//Reads the head of an HTTP request, returning it along with any bytes read beyond it
+fn read_head<R: Read>(reader: &mut R) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    let mut head = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        if let Some(end) = head.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = head.split_off(end + 4);
+            return Ok((head, rest));
+        }
+        if head.len() > MAX_HEAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request head is too large",
+            ));
+        }
+
+        match reader.read(&mut chunk)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => head.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+//Sends a json reply, counting it towards the metrics
+fn respond(
+    state: &State,
+    stream: &mut TcpStream,
+    status: u16,
+    reply: &Value,
+) -> Result<(), Box<dyn Error>> {
+    state.count(status);
+    write_response(stream, status, "application/json", &reply.to_string())
+}
+
+fn write_response<W: Write>(
+    writer: &mut W,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> Result<(), Box<dyn Error>> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(writer.flush()?)
+}
+
+fn upgrade_websocket(
+    state: &State,
+    mut stream: TcpStream,
+    method: Method,
+    url: &str,
+    key: &str,
+    rest: Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    //Work out which session the socket belongs to before accepting it
+    let session = match (method, segments(url).as_slice()) {
+        (Method::Get, ["ws"]) => None,
+        (Method::Get, ["sessions", id, "ws"]) => Some(id.to_string()),
+        _ => return write_response(&mut stream, 404, "text/plain", "Not found"),
+    };
+
+    info!("GET {} -> 101", url);
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        tungstenite::handshake::derive_accept_key(key.as_bytes())
+    )?;
+
+    let ws = WebSocket::from_partially_read(stream, rest, Role::Server, None);
+    converse(state, ws, session, url)
+}
+
+//Holds a conversation over a WebSocket until it is closed
+fn converse<S: Read + Write>(
+    state: &State,
+    mut ws: WebSocket<S>,
+    session: Option<String>,
+    url: &str,
+) -> Result<(), Box<dyn Error>> {
+    let stream = query(url, "stream") == Some("words");
+    let delay = query(url, "delay_ms")
+        .and_then(|d| d.parse().ok())
+        .map(Duration::from_millis);
+
+    let id = match session {
+        Some(id) => id,
+        None => {
+            let (id, greeting) = state.start()?;
+            let frame = json!({ "type": "session", "id": id, "greeting": greeting });
+            ws.send(tungstenite::Message::text(frame.to_string()))?;
+            id
+        }
+    };
+
+    loop {
+        let text = match ws.read() {
+            Ok(tungstenite::Message::Text(text)) => text,
+            Ok(tungstenite::Message::Close(_)) => return Ok(()),
+            Ok(_) => continue, //Pings are answered by tungstenite itself
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                info!("Closing the WebSocket of idle session {}", id);
+                ws.close(None)?;
+                return Ok(ws.flush()?);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let message: Message = match serde_json::from_str(&text) {
+            Ok(message) => message,
+            Err(e) => {
+                let frame = json!({ "type": "error", "text": e.to_string() });
+                ws.send(tungstenite::Message::text(frame.to_string()))?;
+                continue;
+            }
+        };
+
+        let response = match state.respond(&id, &message.text) {
+            Some(Ok(response)) => response,
+            Some(Err(e)) => {
+                let frame = json!({ "type": "error", "text": e.to_string() });
+                ws.send(tungstenite::Message::text(frame.to_string()))?;
+                continue;
+            }
+            None => {
+                let frame = json!({ "type": "error", "text": "No such session" });
+                ws.send(tungstenite::Message::text(frame.to_string()))?;
+                ws.close(None)?;
+                return Ok(());
+            }
+        };
+
+        if stream {
+            for word in response.split_inclusive(' ') {
+                let frame = json!({ "type": "chunk", "text": word });
+                ws.send(tungstenite::Message::text(frame.to_string()))?;
+                if let Some(delay) = delay {
+                    thread::sleep(delay);
+                }
+            }
+        }
+
+        let frame = json!({ "type": "response", "text": response });
+        ws.send(tungstenite::Message::text(frame.to_string()))?;
+    }
+}
+
+fn segments(url: &str) -> Vec<&str> {
+    let path = url.split('?').next().unwrap_or_default();
+    path.trim_matches('/').split('/').collect()
+}
+
+fn query<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    url.split_once('?')?
+        .1
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    fn doctor() -> ElizaPool {
+        ElizaPool::new(Script::from_file("scripts/doctor.json").unwrap())
+    }
+
+    #[test]
+    fn conversation() {
+        let server = Server::with_pool("127.0.0.1:0", doctor()).unwrap();
+
+        let (status, session) = server.state.route(&Method::Post, "/sessions", "");
+        assert_eq!(201, status);
+        let id = session["id"].as_str().unwrap();
+        let url = format!("/sessions/{}/messages", id);
+
+        let (status, reply) =
+            server
+                .state
+                .route(&Method::Post, &url, r#"{"text": "Men are all alike."}"#);
+        assert_eq!(200, status);
+        assert_eq!("In what way?", reply["response"]);
+
+        let (status, _) = server
+            .state
+            .route(&Method::Delete, &format!("/sessions/{}", id), "");
+        assert_eq!(200, status);
+        let (status, _) = server
+            .state
+            .route(&Method::Post, &url, r#"{"text": "Hello?"}"#);
+        assert_eq!(404, status);
+    }
+
+    //A store shared between servers, standing in for a database that outlives them
+    #[derive(Clone, Default)]
+    struct SharedStore(Arc<Mutex<std::collections::HashMap<String, Snapshot>>>);
+
+    impl SessionStore for SharedStore {
+        fn load(&mut self, id: &str) -> Result<Option<Snapshot>, Box<dyn Error>> {
+            Ok(self.0.lock().unwrap().get(id).cloned())
+        }
+
+        fn save(&mut self, id: &str, snapshot: &Snapshot) -> Result<(), Box<dyn Error>> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), snapshot.clone());
+            Ok(())
+        }
+
+        fn expire(&mut self, id: &str) -> Result<bool, Box<dyn Error>> {
+            Ok(self.0.lock().unwrap().remove(id).is_some())
+        }
+    }
+
+    #[test]
+    fn stored_sessions() {
+        let store = SharedStore::default();
+        let server = Server::with_pool("127.0.0.1:0", doctor())
+            .unwrap()
+            .session_store(store.clone());
+        let (_, session) = server.state.route(&Method::Post, "/sessions", "");
+        let id = session["id"].as_str().unwrap();
+        let url = format!("/sessions/{}/messages", id);
+        server
+            .state
+            .route(&Method::Post, &url, r#"{"text": "I remember my mother."}"#);
+        let before = server
+            .state
+            .pool
+            .lock()
+            .unwrap()
+            .get(id)
+            .unwrap()
+            .snapshot();
+        drop(server);
+
+        //A restarted server carries on the conversation where it was left
+        let server = Server::with_pool("127.0.0.1:0", doctor())
+            .unwrap()
+            .session_store(store.clone());
+        let (status, _) =
+            server
+                .state
+                .route(&Method::Post, &url, r#"{"text": "Men are all alike."}"#);
+        assert_eq!(200, status);
+        let after = server
+            .state
+            .pool
+            .lock()
+            .unwrap()
+            .get(id)
+            .unwrap()
+            .snapshot();
+        assert_eq!(before.memory, after.memory);
+        assert_eq!(before.turns + 1, after.turns);
+
+        let (status, _) = server
+            .state
+            .route(&Method::Delete, &format!("/sessions/{}", id), "");
+        assert_eq!(200, status);
+        assert!(store.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_requests() {
+        let server = Server::with_pool("127.0.0.1:0", doctor()).unwrap();
+        let (_, session) = server.state.route(&Method::Post, "/sessions", "");
+        let url = format!("/sessions/{}/messages", session["id"].as_str().unwrap());
+
+        assert_eq!(400, server.state.route(&Method::Post, &url, "not json").0);
+        assert_eq!(404, server.state.route(&Method::Get, "/sessions", "").0);
+        assert_eq!(
+            404,
+            server
+                .state
+                .route(
+                    &Method::Post,
+                    "/sessions/nope/messages",
+                    r#"{"text": "hi"}"#
+                )
+                .0
+        );
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let pool = doctor().idle_timeout(Duration::from_millis(0));
+        let server = Server::with_pool("127.0.0.1:0", pool).unwrap();
+        let (_, session) = server.state.route(&Method::Post, "/sessions", "");
+        let url = format!("/sessions/{}/messages", session["id"].as_str().unwrap());
+
+        assert_eq!(
+            404,
+            server
+                .state
+                .route(&Method::Post, &url, r#"{"text": "hi"}"#)
+                .0
+        );
+    }
+
+    #[test]
+    fn full_pool() {
+        let server = Server::with_pool("127.0.0.1:0", doctor().max_sessions(1)).unwrap();
+
+        assert_eq!(201, server.state.route(&Method::Post, "/sessions", "").0);
+        assert_eq!(503, server.state.route(&Method::Post, "/sessions", "").0);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let pool = doctor().rate_limit(1, Duration::from_secs(60));
+        let server = Server::with_pool("127.0.0.1:0", pool).unwrap();
+        let (_, session) = server.state.route(&Method::Post, "/sessions", "");
+        let url = format!("/sessions/{}/messages", session["id"].as_str().unwrap());
+
+        let message = r#"{"text": "hi"}"#;
+        assert_eq!(200, server.state.route(&Method::Post, &url, message).0);
+        assert_eq!(429, server.state.route(&Method::Post, &url, message).0);
+    }
+
+    #[test]
+    fn metrics() {
+        let server = Server::with_pool("127.0.0.1:0", doctor()).unwrap();
+        let (_, session) = server.state.route(&Method::Post, "/sessions", "");
+        let url = format!("/sessions/{}/messages", session["id"].as_str().unwrap());
+        server
+            .state
+            .route(&Method::Post, &url, r#"{"text": "Men are all alike."}"#);
+        server
+            .state
+            .route(&Method::Post, &url, r#"{"text": "Bullies."}"#);
+
+        let metrics = server.state.metrics();
+        assert!(metrics.contains(r#"eliza_responses_total{source="keyword"} 1"#));
+        assert!(metrics.contains(r#"eliza_responses_total{source="fallback"} 1"#));
+        assert!(metrics.contains(r#"eliza_keyword_hits_total{keyword="alike"} 1"#));
+        assert!(metrics.contains("eliza_response_seconds_count 2"));
+        assert!(metrics.contains("eliza_session_memory_count 2"));
+        assert!(metrics.contains("eliza_sessions 1"));
+    }
+
+    #[test]
+    fn websocket_stream() {
+        let server = Server::with_pool("127.0.0.1:0", doctor()).unwrap();
+        let addr = server.addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let url = format!("ws://{}/ws?stream=words", addr);
+        let (mut ws, _) = tungstenite::client(url, stream).unwrap();
+
+        let read = |ws: &mut WebSocket<TcpStream>| -> Value {
+            serde_json::from_str(ws.read().unwrap().to_text().unwrap()).unwrap()
+        };
+
+        assert_eq!("session", read(&mut ws)["type"]);
+        ws.send(tungstenite::Message::text(
+            r#"{"text": "Men are all alike."}"#,
+        ))
+        .unwrap();
+
+        assert_eq!(json!({"type": "chunk", "text": "In "}), read(&mut ws));
+        assert_eq!(json!({"type": "chunk", "text": "what "}), read(&mut ws));
+        assert_eq!(json!({"type": "chunk", "text": "way?"}), read(&mut ws));
+        assert_eq!(
+            json!({"type": "response", "text": "In what way?"}),
+            read(&mut ws)
+        );
+    }
+
+    #[test]
+    fn http_requests() {
+        let server = Server::with_pool("127.0.0.1:0", doctor()).unwrap();
+        let addr = server.addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let send = |request: &str| -> (String, Value) {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).unwrap();
+            let (head, body) = reply.split_once("\r\n\r\n").unwrap();
+            (head.to_string(), serde_json::from_str(body).unwrap())
+        };
+
+        let (head, session) = send("POST /sessions HTTP/1.1\r\nHost: eliza\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 201 Created\r\n"), "{}", head);
+        assert!(head.contains("Content-Type: application/json"));
+
+        let body = r#"{"text": "Men are all alike."}"#;
+        let (head, reply) = send(&format!(
+            "POST /sessions/{}/messages HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            session["id"].as_str().unwrap(),
+            body.len(),
+            body
+        ));
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert_eq!("In what way?", reply["response"]);
+
+        let (head, _) = send("GET /nowhere HTTP/1.1\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", head);
+
+        let mut reply = Vec::new();
+        write_response(&mut reply, 500, "application/json", "{}").unwrap();
+        assert!(reply.starts_with(b"HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+
+    #[test]
+    fn idle_websockets_close() {
+        let pool = doctor().idle_timeout(Duration::from_millis(200));
+        let server = Server::with_pool("127.0.0.1:0", pool).unwrap();
+        let addr = server.addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let (mut ws, _) = tungstenite::client(format!("ws://{}/ws", addr), stream).unwrap();
+        assert!(ws.read().unwrap().is_text());
+
+        //The server gives up on a client that says nothing for longer than the idle timeout
+        assert!(ws.read().unwrap().is_close());
+    }
+
+    #[test]
+    fn query_values() {
+        assert_eq!(
+            Some("words"),
+            query("/ws?stream=words&delay_ms=5", "stream")
+        );
+        assert_eq!(Some("5"), query("/ws?stream=words&delay_ms=5", "delay_ms"));
+        assert_eq!(None, query("/ws", "stream"));
+    }
+}
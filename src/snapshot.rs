@@ -0,0 +1,181 @@
+//! Saves the state of a conversation, so that it can survive a restart.
+//!
+//! A `Snapshot` holds everything ELIZA has learnt during a conversation, but not her script. It
+//! includes the usage of each reassembly rule, so that a bot which is restored after a deploy
+//! carries on varying its responses rather than repeating its first lines.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::Eliza;
+//!
+//! let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+//! e.respond("I remember my mother.");
+//! e.snapshot().save("session.json").unwrap();
+//!
+//! let mut restored = Eliza::from_file("scripts/doctor.json").unwrap();
+//! restored.restore(eliza::snapshot::Snapshot::load("session.json").unwrap()).unwrap();
+//! # }
+//! ```
+//!
+//! Each snapshot records the `VERSION` of the format it was saved in, so that conversations saved
+//! by older releases of the crate can still be restored after an upgrade. Snapshots without a
+//! version are from before versions were recorded, and are read as version 1. Elements that this
+//! release doesn't know about are ignored, so a snapshot saved by a newer release that only added
+//! to the format can still be loaded; the version changes only when an element changes meaning.
+//!
+use crate::Exchange;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+
+/// The version of the snapshot format that this release saves.
+pub const VERSION: u32 = 3;
+
+/// The state of a conversation, saved as `json`.
+///
+/// * **version**: The version of the format the snapshot was saved in (see `VERSION`).
+/// * **memory**: The responses ELIZA has saved for later, oldest first.
+/// * **memory_turns**: The turn on which each response in memory was saved (see `Recall`).
+/// * **follow_ups**: The follow-up questions ELIZA will ask next, in order.
+/// * **rule_usage**: How many times each reassembly rule has been used (see
+///   `Eliza::rule_usage()`).
+/// * **affect**: The values of ELIZA's affect variables.
+/// * **turns**: How many inputs ELIZA has responded to.
+/// * **last_keyword**: The keyword that produced ELIZA's last response, if one did.
+/// * **history**: The most recent exchanges of the conversation, oldest first.
+/// * **fallback_streak**: How many inputs in a row ELIZA has answered with a fallback (see
+///   `script::FallbackOrder`).
+/// * **boosts**: The keywords whose rank is boosted, by key (see `Eliza::boost_keyword()`).
+///
+/// Every element is optional when loading, so a snapshot holding only `rule_usage` may be used
+/// to carry response variety over to new conversations.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    #[serde(default)]
+    pub memory: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memory_turns: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub follow_ups: Vec<String>,
+    #[serde(default)]
+    pub rule_usage: HashMap<String, usize>,
+    #[serde(default)]
+    pub affect: HashMap<String, i32>,
+    #[serde(default)]
+    pub turns: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_keyword: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Exchange>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub fallback_streak: usize,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub boosts: HashMap<String, Boost>,
+}
+
+/// A temporary change to the rank of a keyword, which fades to nothing by the given turn.
+///
+/// * **delta**: The change to the keyword's rank when the boost was given.
+/// * **turns**: How many responses the boost lasts for.
+/// * **until**: The number of responses ELIZA will have given when the boost is gone.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boost {
+    pub delta: i32,
+    pub turns: usize,
+    pub until: usize,
+}
+
+impl Default for Snapshot {
+    fn default() -> Snapshot {
+        Snapshot {
+            version: VERSION,
+            memory: Vec::new(),
+            memory_turns: Vec::new(),
+            follow_ups: Vec::new(),
+            rule_usage: HashMap::new(),
+            affect: HashMap::new(),
+            turns: 0,
+            last_keyword: None,
+            history: Vec::new(),
+            fallback_streak: 0,
+            boosts: HashMap::new(),
+        }
+    }
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl Snapshot {
+    /// Will load a snapshot from any reader, migrating it from the version it was saved in.
+    ///
+    /// Will return `Err` if the snapshot could not be read, is invalid, or was saved in a newer
+    /// version of the format than this release understands.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Snapshot, Box<dyn Error>> {
+        let snapshot: Snapshot = serde_json::from_reader(reader)?;
+        snapshot.migrate()
+    }
+
+    /// Brings a snapshot that was deserialized by other means, such as part of a larger document,
+    /// up to the current `VERSION`. `from_reader()` and `load()` do this already.
+    ///
+    /// Will return `Err` if the snapshot was saved in a newer version of the format than this
+    /// release understands.
+    pub fn migrate(mut self) -> Result<Snapshot, Box<dyn Error>> {
+        if self.version > VERSION {
+            return Err(format!(
+                "Snapshot is version {}, but only versions up to {} are supported",
+                self.version, VERSION
+            )
+            .into());
+        }
+
+        //Everything added to the format since version 1 has a default. Version 3 names each rule
+        //of `rule_usage` by its keyword too, but the names can't be converted without the script,
+        //so `Eliza::restore()` accepts both. Later versions will convert the elements they change
+        //here, one version at a time
+        self.version = VERSION;
+        Ok(self)
+    }
+
+    /// Will save the snapshot to any writer.
+    ///
+    /// Will return `Err` if the snapshot could not be written.
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    /// Will load a snapshot from the file system.
+    ///
+    /// Will return `Err` if the snapshot at the specified location is invalid or non-existant.
+    /// Not available without the `std-fs` feature, or when targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Snapshot, Box<dyn Error>> {
+        let file = std::fs::File::open(path)?;
+        Snapshot::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// Will save the snapshot to the file system, replacing any file at the location.
+    ///
+    /// Will return `Err` if the snapshot could not be written. Not available without the `std-fs`
+    /// feature, or when targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.to_writer(&mut file)?;
+        Ok(file.flush()?)
+    }
+}
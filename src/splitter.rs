@@ -0,0 +1,164 @@
+//! Splits the user's input into phrases, before ELIZA searches them for keywords.
+//!
+//! Splitting blindly on every `.` breaks `Dr. Smith` in two, and leaves nothing but noise of an
+//! ellipsis. The `smart` splitter, which scripts use unless they choose otherwise, looks at the
+//! text around each full stop first:
+//!
+//! * A full stop ends a sentence only when followed by a space or the end of the input, so `e.g`,
+//!   `3.5` and `example.com` are left whole.
+//! * Common abbreviations such as `dr.`, `mrs.` and `e.g.` never end a sentence.
+//! * A run of terminators such as `...`, `…` or `?!` is a single boundary.
+//! * `!` and `;` end sentences, as well as `.` and `?`.
+//!
+//! The script's other delimiters, such as `,` and ` but `, then split each sentence as before.
+//!
+//! ```rust
+//! use eliza::splitter::PhraseSplitter;
+//!
+//! let phrases = PhraseSplitter::Smart.split("i saw dr. smith... he said no!", &[".", ","]);
+//! assert_eq!(vec!("i saw dr. smith", "he said no", ""), phrases);
+//!
+//! let phrases = PhraseSplitter::Simple.split("i saw dr. smith", &[".", ","]);
+//! assert_eq!(vec!("i saw dr", "smith"), phrases);
+//! ```
+//!
+
+/// How a script splits the user's input into phrases.
+///
+/// In a script, a `PhraseSplitter` is written as `"smart"` or `"simple"`.
+///
+/// * **smart**: Finds the ends of sentences first, taking care over abbreviations, decimal
+///   numbers and ellipses, then splits each sentence at the script's other delimiters. The
+///   default.
+/// * **simple**: Splits wherever any of the script's delimiters appear, as ELIZA always used to.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PhraseSplitter {
+    #[default]
+    Smart,
+    Simple,
+}
+
+//The characters that end a sentence, and which the smart splitter handles itself
+const TERMINATORS: [char; 5] = ['.', '!', '?', ';', '…'];
+
+//Words that are followed by a full stop without ending a sentence
+const ABBREVIATIONS: [&str; 13] = [
+    "dr", "mr", "mrs", "ms", "mx", "prof", "st", "jr", "sr", "vs", "e.g", "i.e", "approx",
+];
+
+impl PhraseSplitter {
+    /// Splits the input into trimmed phrases, at the given delimiters.
+    pub fn split(self, input: &str, delimiters: &[&str]) -> Vec<String> {
+        match self {
+            PhraseSplitter::Simple => crate::get_phrases(input, delimiters),
+            PhraseSplitter::Smart => {
+                let delimiters: Vec<&str> = delimiters
+                    .iter()
+                    .filter(|d| !is_terminator(d))
+                    .cloned()
+                    .collect();
+
+                sentences(input)
+                    .iter()
+                    .flat_map(|s| crate::get_phrases(s, &delimiters))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn is_terminator(delimiter: &str) -> bool {
+    let mut chars = delimiter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => TERMINATORS.contains(&c),
+        _ => false,
+    }
+}
+
+fn sentences(input: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !TERMINATORS.contains(&c) {
+            continue;
+        }
+
+        let next = chars.peek().map(|&(_, n)| n);
+        if c == '.' && !ends_sentence(&input[start..i], next) {
+            continue;
+        }
+
+        //Treat a run of terminators, such as an ellipsis or '?!', as a single boundary
+        sentences.push(&input[start..i]);
+        start = i + c.len_utf8();
+        while let Some(&(j, n)) = chars.peek() {
+            if !TERMINATORS.contains(&n) {
+                break;
+            }
+            start = j + n.len_utf8();
+            chars.next();
+        }
+    }
+    sentences.push(&input[start..]);
+
+    sentences
+}
+
+//Decides whether a full stop, following the text of the sentence so far, ends the sentence
+fn ends_sentence(before: &str, next: Option<char>) -> bool {
+    if next.is_some_and(|n| !n.is_whitespace() && !TERMINATORS.contains(&n)) {
+        return false;
+    }
+
+    let word = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or("")
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    !ABBREVIATIONS.contains(&word.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::DEFAULT_DELIMITERS;
+
+    #[test]
+    fn smart_sentences() {
+        let split = |input| PhraseSplitter::Smart.split(input, &DEFAULT_DELIMITERS);
+        assert_eq!(
+            vec!("I told Mrs. Jones", "she said no"),
+            split("I told Mrs. Jones; she said no")
+        );
+        assert_eq!(
+            vec!("it costs 3.50 e.g. at the shop", "really", "yes"),
+            split("it costs 3.50 e.g. at the shop?! really... yes")
+        );
+        assert_eq!(
+            vec!("see example.com", "now", ""),
+            split("see example.com. now!")
+        );
+        assert_eq!(
+            vec!("well", "maybe", "i", "don't know"),
+            split("well… maybe, i but don't know")
+        );
+    }
+
+    #[test]
+    fn simple_split() {
+        assert_eq!(
+            vec!("dr", "smith is here!"),
+            PhraseSplitter::Simple.split("dr. smith is here!", &DEFAULT_DELIMITERS)
+        );
+    }
+
+    #[test]
+    fn script_splitter() {
+        let splitter: PhraseSplitter = serde_json::from_str(r#""simple""#).unwrap();
+        assert_eq!(PhraseSplitter::Simple, splitter);
+    }
+}
@@ -0,0 +1,171 @@
+//! Keeps conversations somewhere more durable than the process hosting them.
+//!
+//! A `SessionStore` saves a `Snapshot` of each conversation under its session id, so that a
+//! server can be restarted or redeployed without its users' conversations starting over. Stores
+//! are given snapshots rather than whole conversations, so anything they hold can be restored
+//! by later releases of the crate (see `snapshot::VERSION`).
+//!
+//! With the `store-sqlite` feature, `SqliteSessionStore` keeps the snapshots in a SQLite
+//! database. Other stores, such as Redis or a hosted database, need only implement the trait.
+//!
+//! ```rust,ignore
+//! use eliza::server::Server;
+//! use eliza::store::SqliteSessionStore;
+//!
+//! let store = SqliteSessionStore::open("sessions.db")?;
+//! let server = Server::bind("127.0.0.1:8080", script)?.session_store(store);
+//! server.run();
+//! ```
+//!
+use crate::snapshot::Snapshot;
+use std::error::Error;
+
+/// Somewhere to keep the state of conversations between restarts, by session id.
+pub trait SessionStore {
+    /// Returns the snapshot saved for the session, migrated to the current version of the
+    /// format, or None if nothing has been saved for it.
+    ///
+    /// Will return `Err` if the store can't be read, or the snapshot is invalid.
+    fn load(&mut self, id: &str) -> Result<Option<Snapshot>, Box<dyn Error>>;
+
+    /// Saves the snapshot for the session, replacing anything saved for it before.
+    ///
+    /// Will return `Err` if the store can't be written.
+    fn save(&mut self, id: &str, snapshot: &Snapshot) -> Result<(), Box<dyn Error>>;
+
+    /// Discards whatever has been saved for the session, returning true if there was anything.
+    ///
+    /// Will return `Err` if the store can't be written.
+    fn expire(&mut self, id: &str) -> Result<bool, Box<dyn Error>>;
+}
+
+/// A `SessionStore` backed by a SQLite database.
+///
+/// Snapshots are kept as `json` in the `eliza_sessions` table, which is created if need be,
+/// alongside the time each was last saved in seconds since the Unix epoch:
+///
+/// ```sql
+/// CREATE TABLE eliza_sessions (id TEXT PRIMARY KEY, snapshot TEXT NOT NULL, saved INTEGER NOT NULL)
+/// ```
+///
+/// Only available with the `store-sqlite` feature. Stores never discard sessions on their own,
+/// so long-lived deployments may want to prune the table by `saved` from time to time.
+#[cfg(feature = "store-sqlite")]
+pub struct SqliteSessionStore {
+    conn: rusqlite::Connection,
+}
+
+#[cfg(feature = "store-sqlite")]
+impl SqliteSessionStore {
+    /// Opens the database at the location, creating it if need be.
+    ///
+    /// Will return `Err` if the database can't be opened, or the table can't be created. Not
+    /// available without the `std-fs` feature.
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<SqliteSessionStore, Box<dyn Error>> {
+        SqliteSessionStore::from_connection(rusqlite::Connection::open(path)?)
+    }
+
+    /// Opens a database that lives only as long as the store, e.g. for tests.
+    ///
+    /// Will return `Err` if the database can't be created.
+    pub fn open_in_memory() -> Result<SqliteSessionStore, Box<dyn Error>> {
+        SqliteSessionStore::from_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    /// Keeps sessions in a database the host has already opened, creating the table if need be.
+    ///
+    /// Will return `Err` if the table can't be created.
+    pub fn from_connection(
+        conn: rusqlite::Connection,
+    ) -> Result<SqliteSessionStore, Box<dyn Error>> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS eliza_sessions (
+                id TEXT PRIMARY KEY,
+                snapshot TEXT NOT NULL,
+                saved INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(SqliteSessionStore { conn })
+    }
+}
+
+#[cfg(feature = "store-sqlite")]
+impl SessionStore for SqliteSessionStore {
+    fn load(&mut self, id: &str) -> Result<Option<Snapshot>, Box<dyn Error>> {
+        use rusqlite::OptionalExtension;
+
+        let json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT snapshot FROM eliza_sessions WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match json {
+            Some(json) => Ok(Some(Snapshot::from_reader(json.as_bytes())?)),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&mut self, id: &str, snapshot: &Snapshot) -> Result<(), Box<dyn Error>> {
+        let mut json = Vec::new();
+        snapshot.to_writer(&mut json)?;
+        let saved = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        self.conn.execute(
+            "INSERT INTO eliza_sessions (id, snapshot, saved) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET snapshot = excluded.snapshot, saved = excluded.saved",
+            rusqlite::params![id, String::from_utf8(json)?, saved],
+        )?;
+        Ok(())
+    }
+
+    fn expire(&mut self, id: &str) -> Result<bool, Box<dyn Error>> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM eliza_sessions WHERE id = ?1", [id])?;
+        Ok(removed > 0)
+    }
+}
+
+#[cfg(all(test, feature = "store-sqlite", feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::Eliza;
+
+    #[test]
+    fn sqlite_sessions() {
+        let mut store = SqliteSessionStore::open_in_memory().unwrap();
+        assert!(store.load("alice").unwrap().is_none());
+
+        let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+        e.respond("I remember my mother.");
+        store.save("alice", &e.snapshot()).unwrap();
+        e.respond("Men are all alike.");
+        store.save("alice", &e.snapshot()).unwrap();
+        assert_eq!(Some(e.snapshot()), store.load("alice").unwrap());
+
+        //Snapshots saved by older releases are migrated as they are loaded
+        store
+            .conn
+            .execute(
+                "INSERT INTO eliza_sessions VALUES ('bob', '{\"memory\":[\"hi\"]}', 0)",
+                [],
+            )
+            .unwrap();
+        let bob = store.load("bob").unwrap().unwrap();
+        assert_eq!(crate::snapshot::VERSION, bob.version);
+
+        assert!(store.expire("alice").unwrap());
+        assert!(!store.expire("alice").unwrap());
+        assert!(store.load("alice").unwrap().is_none());
+        assert!(store.load("bob").unwrap().is_some());
+    }
+}
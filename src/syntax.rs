@@ -0,0 +1,52 @@
+//! Reads the syntax of a script's rules: `GOTO`s and inline alternations.
+//!
+//! The `eliza-macros` crate includes this file too, so that `eliza_script!` checks a script at
+//! compile time just as ELIZA reads it at runtime. It must therefore only depend on `std` and
+//! `regex`.
+//!
+use regex::Regex;
+
+//Returns NONE if not a goto, otherwise returns goto id
+pub(crate) fn is_goto(statement: &str) -> Option<String> {
+    match statement.contains("GOTO") {
+        true => Some(
+            statement
+                .replace("GOTO", "")
+                .replace(char::is_whitespace, ""),
+        ),
+        false => None,
+    }
+}
+
+//Returns the index of the key that a GOTO names, which must be spelt exactly as the keyword is
+pub(crate) fn goto_index<'a>(keys: impl IntoIterator<Item = &'a str>, goto: &str) -> Option<usize> {
+    keys.into_iter().position(|k| k == goto)
+}
+
+//Compiles each inline alternation, such as '@{mother|father}', into a group that doesn't capture,
+//leaving any '@' that remains to name a synonym
+pub(crate) fn inline_alternations(decomposition: &str) -> String {
+    let mut compiled = String::with_capacity(decomposition.len());
+    let mut rest = decomposition;
+    while let Some(i) = rest.find("@{") {
+        let end = match rest[i..].find('}') {
+            Some(end) => i + end,
+            None => break,
+        };
+
+        compiled.push_str(&rest[..i]);
+        compiled.push_str("(?:");
+        compiled.push_str(&rest[i + 2..end]);
+        compiled.push(')');
+        rest = &rest[end + 1..];
+    }
+    compiled.push_str(rest);
+
+    compiled
+}
+
+//Compiles a decomposition rule as validation does, with its inline alternations but without
+//expanding its synonym, so that the regex is the same whatever the script's synonyms
+pub(crate) fn decomposition_regex(decomposition: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&inline_alternations(decomposition).replace('@', ""))
+}
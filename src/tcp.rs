@@ -0,0 +1,122 @@
+//! A telnet-style chat server, for retro fun and easy demos.
+//!
+//! Every connection is given its own conversation, which begins with a greeting and ends with a
+//! farewell when the client sends `/quit` or disconnects.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::script::Script;
+//! use eliza::tcp::TcpServer;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let server = TcpServer::bind("127.0.0.1:2323", script).unwrap();
+//! server.run(); //Now try 'telnet 127.0.0.1 2323'
+//! # }
+//! ```
+//!
+use crate::script::Script;
+use crate::Eliza;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+/// An ELIZA telnet server.
+///
+/// This struct is created by the `bind()` method. See its documentation for more.
+pub struct TcpServer {
+    listener: TcpListener,
+    script: Arc<Script>,
+}
+
+impl TcpServer {
+    /// Binds a server to the given address, where each connection will converse using the script.
+    ///
+    /// Will return `Err` if the address can't be bound.
+    pub fn bind<A: ToSocketAddrs>(addr: A, script: Script) -> Result<TcpServer, Box<dyn Error>> {
+        Ok(TcpServer {
+            listener: TcpListener::bind(addr)?,
+            script: Arc::new(script),
+        })
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until the process is stopped, conversing with each on its own thread.
+    pub fn run(&self) {
+        if let Ok(addr) = self.addr() {
+            info!("Listening on {}", addr);
+        }
+
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let script = self.script.clone();
+                    thread::spawn(move || {
+                        let peer = stream.peer_addr().ok();
+                        info!("{:?} connected", peer);
+                        if let Err(e) = converse(stream, Eliza::from_shared(script)) {
+                            warn!("Connection to {:?} failed: {}", peer, e);
+                        }
+                        info!("{:?} disconnected", peer);
+                    });
+                }
+                Err(e) => warn!("Failed to accept connection: {}", e),
+            }
+        }
+    }
+}
+
+//Holds a conversation with a single client until it leaves
+fn converse(stream: TcpStream, mut eliza: Eliza) -> Result<(), Box<dyn Error>> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    write!(writer, "{}\r\n\r\n> ", eliza.greet())?;
+
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break; //The client has disconnected
+        }
+
+        //Telnet clients aren't guaranteed to send valid UTF-8
+        let input = String::from_utf8_lossy(&line);
+        if input.trim() == "/quit" {
+            break;
+        }
+
+        write!(writer, "{}\r\n\r\n> ", eliza.respond(&input))?;
+    }
+
+    //The client may have already gone, in which case there is nobody to say goodbye to
+    let _ = write!(writer, "\r\n{}\r\n", eliza.farewell());
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversation() {
+        let script = Script::from_file("scripts/doctor.json").unwrap();
+        let server = TcpServer::bind("127.0.0.1:0", script).unwrap();
+        let addr = server.addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"Men are all alike.\r\n/quit\r\n")
+            .unwrap();
+
+        let lines: Vec<String> = BufReader::new(client).lines().map(|l| l.unwrap()).collect();
+        assert_eq!("> In what way?", lines[2]);
+        assert!(lines[5].contains("Goodbye"));
+    }
+}
@@ -0,0 +1,159 @@
+//! Random generation of scripts for property tests, through the `arbitrary` crate.
+//!
+//! Only available with the `testing` feature. The generated scripts are small, and are drawn from
+//! a fixed vocabulary so that user input made of the same words will exercise their rules. Every
+//! decomposition rule is a valid regex, and every reassembly rule only refers to captures that
+//! its decomposition rule has - though a `GOTO` may name a keyword that doesn't exist, as a
+//! leniently loaded script may.
+//!
+//! ```rust
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use eliza::script::Script;
+//! use eliza::Eliza;
+//!
+//! let bytes: Vec<u8> = (0..=255).collect();
+//! let script = Script::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+//! let mut e = Eliza::from_scripts(vec![script]).unwrap();
+//! assert!(!e.respond("i remember my mother").contains('$'));
+//! ```
+//!
+use crate::script::{Condition, Keyword, Reflection, Rule, Script, Synonym, Transform};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// The words that generated scripts are made of.
+pub const WORDS: [&str; 16] = [
+    "i", "you", "my", "your", "am", "are", "mother", "father", "dream", "remember", "sorry",
+    "computer", "why", "yes", "no", "always",
+];
+
+//The names of the synonyms that generated scripts may have, and that their rules may refer to
+const SYNONYMS: [&str; 2] = ["family", "feeling"];
+
+//The affect variables that generated rules may adjust or depend upon
+const VARIABLES: [&str; 2] = ["anger", "fear"];
+
+fn word(u: &mut Unstructured<'_>) -> Result<String> {
+    Ok(u.choose(&WORDS)?.to_string())
+}
+
+fn words(u: &mut Unstructured<'_>, min: u32, max: u32) -> Result<Vec<String>> {
+    (0..u.int_in_range(min..=max)?).map(|_| word(u)).collect()
+}
+
+fn sentence(u: &mut Unstructured<'_>) -> Result<String> {
+    Ok(words(u, 1, 4)?.join(" "))
+}
+
+fn sentences(u: &mut Unstructured<'_>) -> Result<Vec<String>> {
+    (0..u.int_in_range(0..=3)?).map(|_| sentence(u)).collect()
+}
+
+impl<'a> Arbitrary<'a> for Rule {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        //Format example: '(.*)my (.*@family)(.*)', with a capture either side of every word
+        let mut decomposition_rule = String::from("(.*)");
+        for w in words(u, 1, 3)? {
+            decomposition_rule.push_str(&w);
+            if u.ratio(1, 4)? {
+                decomposition_rule.push_str(&format!(" (.*@{})", u.choose(&SYNONYMS)?));
+            } else {
+                decomposition_rule.push_str(" (.*)");
+            }
+        }
+        let captures = decomposition_rule.matches('(').count();
+
+        let mut reassembly_rules = Vec::new();
+        for _ in 0..u.int_in_range(1..=3)? {
+            if u.ratio(1, 8)? {
+                reassembly_rules.push(format!("GOTO {}", word(u)?));
+                continue;
+            }
+
+            let mut assem = sentence(u)?;
+            for _ in 0..u.int_in_range(0..=2)? {
+                assem.push_str(&format!(" ${}", u.int_in_range(0..=captures)?));
+            }
+            reassembly_rules.push(assem);
+        }
+
+        let mut conditions = Vec::new();
+        let mut affect = std::collections::BTreeMap::new();
+        if u.ratio(1, 4)? {
+            conditions.push(Condition {
+                variable: u.choose(&VARIABLES)?.to_string(),
+                min: Option::<i8>::arbitrary(u)?.map(i32::from),
+                max: Option::<i8>::arbitrary(u)?.map(i32::from),
+            });
+        }
+        if u.ratio(1, 4)? {
+            affect.insert(
+                u.choose(&VARIABLES)?.to_string(),
+                i32::from(i8::arbitrary(u)?),
+            );
+        }
+
+        Ok(Rule {
+            memorise: u.ratio(1, 4)?,
+            decomposition_rule,
+            reassembly_rules,
+            conditions,
+            affect,
+            reassembly_context: Vec::new(),
+            follow_ups: Vec::new(),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Keyword {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Keyword {
+            key: word(u)?,
+            rank: i32::arbitrary(u)?,
+            tags: Vec::new(),
+            rules: (0..u.int_in_range(1..=3)?)
+                .map(|_| Rule::arbitrary(u))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Script {
+    /// Generates a script, which is then tidied as though it had been leniently loaded.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut script = Script {
+            greetings: sentences(u)?,
+            farewells: sentences(u)?,
+            fallbacks: sentences(u)?,
+            ..Script::default()
+        };
+
+        for _ in 0..u.int_in_range(0..=2)? {
+            script.transforms.push(Transform {
+                word: word(u)?,
+                equivalents: words(u, 1, 2)?,
+                priority: i32::from(i8::arbitrary(u)?),
+            });
+        }
+        for name in SYNONYMS {
+            if u.arbitrary()? {
+                script.synonyms.push(Synonym {
+                    word: name.to_string(),
+                    equivalents: words(u, 1, 3)?,
+                });
+            }
+        }
+        for _ in 0..u.int_in_range(0..=3)? {
+            script.reflections.push(Reflection {
+                word: word(u)?,
+                inverse: word(u)?,
+                twoway: u.arbitrary()?,
+            });
+        }
+        for _ in 0..u.int_in_range(0..=8)? {
+            script.keywords.push(Keyword::arbitrary(u)?);
+        }
+
+        script.tidy();
+        Ok(script)
+    }
+}
@@ -0,0 +1,183 @@
+//! Golden transcripts: recorded conversations that a script should reproduce word for word.
+//!
+//! A transcript is plain text, written in the same way as an interactive session. Each line
+//! beginning with `> ` is something the user says, and the line that follows is ELIZA's expected
+//! response. Blank lines, and lines beginning with `#`, are ignored:
+//!
+//! ```text
+//! # The opening of Weizenbaum's 1966 paper
+//! > Men are all alike.
+//! In what way?
+//!
+//! > They're always bugging us about something or other.
+//! Can you think of a specific example?
+//! ```
+//!
+//! The transcript is played as a single conversation, with ELIZA's random choices seeded as they
+//! are for `Script::run_embedded_tests()`. Every turn is played even after a mismatch, so a
+//! change to one response may also be reported against the turns that depend upon it.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::script::Script;
+//! use eliza::transcript::Transcript;
+//!
+//! let script = Script::from_file("scripts/doctor.json").unwrap();
+//! let transcript = Transcript::load("tests/transcripts/doctor.txt").unwrap();
+//! assert!(transcript.run(&script).iter().all(|t| t.passed()));
+//! # }
+//! ```
+//!
+use crate::script::Script;
+use crate::Eliza;
+use std::error::Error;
+use std::sync::Arc;
+
+/// A recorded conversation (see the module documentation for its format).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Transcript {
+    pub turns: Vec<Turn>,
+}
+
+/// A single turn of a transcript.
+///
+/// * **line**: The line of the transcript that the input is on, counting from one.
+/// * **input**: What the user says.
+/// * **response**: What ELIZA is expected to say in return.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+    pub line: usize,
+    pub input: String,
+    pub response: String,
+}
+
+/// The outcome of playing a turn of a transcript, as returned by `Transcript::run()`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TurnResult {
+    pub line: usize,
+    pub input: String,
+    pub expected: String,
+    pub response: String,
+}
+
+impl TurnResult {
+    /// Returns true if ELIZA responded as the transcript expected.
+    pub fn passed(&self) -> bool {
+        self.expected == self.response
+    }
+}
+
+impl Transcript {
+    /// Reads a transcript from its text.
+    ///
+    /// Will return `Err` if an input has no response, or a response has no input.
+    pub fn parse(text: &str) -> Result<Transcript, Box<dyn Error>> {
+        let mut turns = Vec::new();
+        let mut input: Option<(usize, &str)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match (line.strip_prefix("> "), input.take()) {
+                (Some(_), Some((n, _))) => {
+                    return Err(format!("The input on line {} has no response", n).into())
+                }
+                (Some(said), None) => input = Some((i + 1, said)),
+                (None, Some((n, said))) => turns.push(Turn {
+                    line: n,
+                    input: said.to_string(),
+                    response: line.to_string(),
+                }),
+                (None, None) => {
+                    return Err(format!("The response on line {} has no input", i + 1).into())
+                }
+            }
+        }
+
+        match input {
+            Some((n, _)) => Err(format!("The input on line {} has no response", n).into()),
+            None => Ok(Transcript { turns }),
+        }
+    }
+
+    /// Will load a transcript from the file system.
+    ///
+    /// Will return `Err` if the transcript at the specified location is invalid or non-existant.
+    /// Not available without the `std-fs` feature, or when targeting `wasm32-unknown-unknown`.
+    #[cfg(all(
+        feature = "std-fs",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Transcript, Box<dyn Error>> {
+        Transcript::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Plays the transcript against a script, returning the outcome of each turn in order.
+    pub fn run(&self, script: &Script) -> Vec<TurnResult> {
+        let mut e = Eliza::from_shared(Arc::new(script.clone()));
+        e.seed(0);
+
+        self.turns
+            .iter()
+            .map(|turn| TurnResult {
+                line: turn.line,
+                input: turn.input.clone(),
+                expected: turn.response.clone(),
+                response: e.respond(&turn.input),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transcript() {
+        let transcript =
+            Transcript::parse("# A comment\n> Men are all alike.\nIn what way?\n\n> Hmm\nGo on.\n")
+                .unwrap();
+        assert_eq!(
+            vec![
+                Turn {
+                    line: 2,
+                    input: "Men are all alike.".to_string(),
+                    response: "In what way?".to_string(),
+                },
+                Turn {
+                    line: 5,
+                    input: "Hmm".to_string(),
+                    response: "Go on.".to_string(),
+                },
+            ],
+            transcript.turns
+        );
+
+        let err = Transcript::parse("> Hello\n> Anyone?\nYes").unwrap_err();
+        assert_eq!("The input on line 1 has no response", err.to_string());
+        let err = Transcript::parse("Hello\n").unwrap_err();
+        assert_eq!("The response on line 1 has no input", err.to_string());
+        assert!(Transcript::parse("> Hello").is_err());
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn play_transcript() {
+        let script = Script::from_file("scripts/doctor.json").unwrap();
+        let transcript = Transcript::parse(
+            "> Well, my boyfriend made me come here.\nYour boyfriend made you come here?\n\
+             > Men are all alike.\nIn every way?\n",
+        )
+        .unwrap();
+
+        let results = transcript.run(&script);
+        assert!(results[0].passed());
+        assert!(!results[1].passed());
+        assert_eq!(3, results[1].line);
+    }
+}
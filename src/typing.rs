@@ -0,0 +1,175 @@
+//! Paces ELIZA's responses as though she were typing them.
+//!
+//! A `Typing` decides how long to wait before each character of a response: a moment's thought
+//! before the first, then a delay per character, so that long responses take longer, and a
+//! longer pause after punctuation. Frontends may use the delays to animate a response, or have
+//! `Typing::write()` print it. `Typing::write_stream()` types the words of
+//! `Eliza::respond_stream()` as they come, pacing them as one response:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "std-fs")] {
+//! use eliza::typing::Typing;
+//! use eliza::Eliza;
+//!
+//! let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+//! let typing = Typing::new(40);
+//! let words = e.respond_stream("Men are all alike.");
+//! typing.write_stream(words, std::io::stdout()).unwrap();
+//! # }
+//! ```
+//!
+//! Integrations that only show an indicator while ELIZA is "typing" can instead wait for
+//! `Typing::duration()` before sending the whole response.
+//!
+use std::time::Duration;
+
+/// How quickly ELIZA types.
+///
+/// * **chars_per_second**: How many characters ELIZA types each second. Zero types instantly.
+/// * **pause**: How long ELIZA pauses after punctuation that ends a sentence or clause.
+/// * **thinking**: How long ELIZA thinks before typing the first character of a response.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Typing {
+    pub chars_per_second: u32,
+    pub pause: Duration,
+    pub thinking: Duration,
+}
+
+impl Default for Typing {
+    fn default() -> Typing {
+        Typing {
+            chars_per_second: 30,
+            pause: Duration::from_millis(250),
+            thinking: Duration::from_millis(300),
+        }
+    }
+}
+
+impl Typing {
+    /// Types at the given speed, in characters per second, pausing and thinking as the default
+    /// does.
+    pub fn new(chars_per_second: u32) -> Typing {
+        Typing {
+            chars_per_second,
+            ..Typing::default()
+        }
+    }
+
+    /// Returns each character of the text, along with how long to wait before showing it.
+    pub fn delays(&self, text: &str) -> Vec<(char, Duration)> {
+        let mut delays = Vec::with_capacity(text.len());
+        let mut previous: Option<char> = None;
+        for c in text.chars() {
+            delays.push((c, self.delay(previous, c)));
+            previous = Some(c);
+        }
+
+        delays
+    }
+
+    //Returns how long to wait before typing a character, given the one typed before it
+    fn delay(&self, previous: Option<char>, c: char) -> Duration {
+        let per_char = match self.chars_per_second {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        };
+
+        match previous {
+            None => self.thinking,
+            Some(p) if is_pause(p) && c.is_whitespace() => per_char + self.pause,
+            Some(_) => per_char,
+        }
+    }
+
+    /// Returns how long typing the whole text takes.
+    pub fn duration(&self, text: &str) -> Duration {
+        self.delays(text).iter().map(|(_, d)| *d).sum()
+    }
+
+    /// Writes the text a character at a time, waiting before each as `delays()` describes.
+    ///
+    /// Not available when targeting `wasm32-unknown-unknown`, which can't sleep. Under WASI, which
+    /// has no threads, the text is written without waiting.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn write<W: std::io::Write>(&self, text: &str, writer: W) -> std::io::Result<()> {
+        self.write_stream(std::iter::once(text), writer)
+    }
+
+    /// Writes chunks of a response, such as the words of `Eliza::respond_stream()`, a character
+    /// at a time as each chunk arrives. The chunks are paced as though they were one text, so
+    /// ELIZA only thinks before the first, and still pauses after punctuation that ends a chunk.
+    ///
+    /// Not available when targeting `wasm32-unknown-unknown`, which can't sleep. Under WASI, which
+    /// has no threads, the chunks are written without waiting.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn write_stream<I, S, W>(&self, chunks: I, mut writer: W) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        W: std::io::Write,
+    {
+        let mut buf = [0; 4];
+        let mut previous: Option<char> = None;
+        for chunk in chunks {
+            for c in chunk.as_ref().chars() {
+                let delay = self.delay(previous, c);
+                #[cfg(not(target_os = "wasi"))]
+                std::thread::sleep(delay);
+                #[cfg(target_os = "wasi")]
+                let _ = delay;
+
+                writer.write_all(c.encode_utf8(&mut buf).as_bytes())?;
+                writer.flush()?;
+                previous = Some(c);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//Returns true if ELIZA pauses after typing the character, when a space follows it
+fn is_pause(c: char) -> bool {
+    matches!(c, '.' | ',' | '!' | '?' | ';' | ':' | '…')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_delays() {
+        let typing = Typing {
+            chars_per_second: 10,
+            pause: Duration::from_millis(500),
+            thinking: Duration::from_secs(1),
+        };
+
+        let delays = typing.delays("Hi. Why?");
+        assert_eq!(8, delays.len());
+        assert_eq!(('H', Duration::from_secs(1)), delays[0]);
+        assert_eq!(('i', Duration::from_millis(100)), delays[1]);
+        assert_eq!((' ', Duration::from_millis(600)), delays[3]);
+        assert_eq!(Duration::from_millis(2200), typing.duration("Hi. Why?"));
+
+        //Longer responses take longer to type
+        assert!(typing.duration("Go on, please.") > typing.duration("Go on."));
+        assert_eq!(Duration::ZERO, typing.duration(""));
+
+        let instant = Typing {
+            chars_per_second: 0,
+            pause: Duration::ZERO,
+            thinking: Duration::ZERO,
+        };
+        let mut out = Vec::new();
+        instant.write("Hé, you.", &mut out).unwrap();
+        assert_eq!("Hé, you.", String::from_utf8(out).unwrap());
+
+        let mut out = Vec::new();
+        instant
+            .write_stream(vec!["Hé, ", "you."], &mut out)
+            .unwrap();
+        assert_eq!("Hé, you.", String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,70 @@
+//! JavaScript bindings, so that ELIZA can run entirely in the browser without a server.
+//!
+//! This module is only available when the crate is built with the `wasm` feature. Build it with
+//! [wasm-pack](https://rustwasm.github.io/wasm-pack/) and load the script from wherever suits:
+//!
+//! ```md,no_run
+//! user@foo(eliza) ~> wasm-pack build --target web -- --features wasm
+//! ```
+//!
+//! ```js
+//! import init, { ElizaWasm } from "./pkg/eliza.js";
+//!
+//! await init();
+//! const script = await (await fetch("doctor.json")).text();
+//! const eliza = new ElizaWasm(script);
+//!
+//! console.log(eliza.greet());
+//! console.log(eliza.respond("Men are all alike."));
+//! ```
+//!
+use crate::Eliza;
+use wasm_bindgen::prelude::*;
+
+/// An ELIZA instance that may be driven from JavaScript.
+#[wasm_bindgen]
+pub struct ElizaWasm {
+    eliza: Eliza,
+}
+
+#[wasm_bindgen]
+impl ElizaWasm {
+    /// Initialise ELIZA with the `json` text of a script.
+    ///
+    /// Throws an error in JavaScript if the script is invalid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(script_json: &str) -> Result<ElizaWasm, JsValue> {
+        match Eliza::from_str(script_json) {
+            Ok(eliza) => Ok(ElizaWasm { eliza }),
+            Err(e) => Err(JsValue::from_str(&e.to_string())),
+        }
+    }
+
+    /// Returns a greeting from the script.
+    pub fn greet(&self) -> String {
+        self.eliza.greet()
+    }
+
+    /// Responds to the user's input.
+    pub fn respond(&mut self, input: &str) -> String {
+        self.eliza.respond(input)
+    }
+
+    /// Returns a farewell from the script.
+    pub fn farewell(&self) -> String {
+        self.eliza.farewell()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversation() {
+        let script = include_str!("../scripts/doctor.json");
+        let mut eliza = ElizaWasm::new(script).unwrap();
+
+        assert_eq!("In what way?", eliza.respond("Men are all alike."));
+    }
+}
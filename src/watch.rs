@@ -0,0 +1,106 @@
+//! Watches an ELIZA script on the file system, so that edits can be picked up by a running
+//! session without restarting it.
+//!
+//! This module is only available when the crate is built with the `watch` feature.
+//!
+//! ```rust,no_run
+//! use eliza::Eliza;
+//! use eliza::watch::ScriptWatcher;
+//!
+//! let mut eliza = Eliza::from_file("scripts/doctor.json").unwrap();
+//! let watcher = ScriptWatcher::new("scripts/doctor.json").unwrap();
+//!
+//! //Before each turn, pick up any changes made to the script
+//! eliza.reload(&watcher);
+//! println!("{}", eliza.respond("Hello"));
+//! ```
+//!
+use crate::script::Script;
+use crate::Eliza;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Watches a script file for changes.
+///
+/// This struct is created by the `new()` method. See its documentation for more.
+pub struct ScriptWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ScriptWatcher {
+    /// Begin watching the script at the specified location.
+    ///
+    /// Will return `Err` if the location does not exist, or can't be watched.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<ScriptWatcher, Box<dyn Error>> {
+        let path = path.as_ref().canonicalize()?;
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+
+        //Editors tend to replace the file on save, so watch the directory it lives in instead
+        let dir = path.parent().unwrap_or(&path);
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        info!("Watching {}", path.display());
+
+        Ok(ScriptWatcher {
+            path,
+            events: rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Returns the newly loaded script if the file has changed since it was last polled.
+    ///
+    /// Will return None if the file is unchanged, or if the changed script is invalid.
+    pub fn poll(&self) -> Option<Script> {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(e) => {
+                    let relevant = matches!(e.kind, EventKind::Create(_) | EventKind::Modify(_));
+
+                    if relevant
+                        && e.paths
+                            .iter()
+                            .any(|p| p.file_name() == self.path.file_name())
+                    {
+                        changed = true;
+                    }
+                }
+                Err(e) => warn!("Error while watching {}: {}", self.path.display(), e),
+            }
+        }
+
+        if !changed {
+            return None;
+        }
+
+        //Only hand back the script if it is still valid, otherwise keep using the old one
+        match Script::from_file(&self.path).and_then(|s| s.check().map(|_| s)) {
+            Ok(script) => {
+                info!("Reloaded {}", self.path.display());
+                Some(script)
+            }
+            Err(e) => {
+                error!("Ignoring change to {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+impl Eliza {
+    /// Swaps in the latest version of a watched script, if it has changed.
+    ///
+    /// Memory is preserved, as is the usage of any reassembly rules that still exist in the new
+    /// script. Will return `true` if a new script was loaded.
+    pub fn reload(&mut self, watcher: &ScriptWatcher) -> bool {
+        match watcher.poll() {
+            Some(script) => self.set_script(script).is_ok(),
+            None => false,
+        }
+    }
+}
@@ -1,5 +1,11 @@
+#[cfg(feature = "std-fs")]
+use eliza::script::Script;
 use eliza::Eliza;
+#[cfg(feature = "std-fs")]
+use eliza::ResponseSource;
+use std::fs::File;
 
+#[cfg(feature = "std-fs")]
 #[test]
 fn load_file_ok() {
     assert!(Eliza::from_file("scripts/doctor.json").is_ok());
@@ -10,11 +16,283 @@ fn load_string_ok() {
     assert!(Eliza::from_str(r#"{"greetings": [], "farewells": [], "fallbacks": [], "transforms": [], "synonyms": [], "reflections": [], "keywords": []}"#).is_ok());
 }
 
+#[test]
+fn load_reader_ok() {
+    let file = File::open("scripts/doctor.json").unwrap();
+    assert!(Eliza::from_reader(file).is_ok());
+    assert!(Eliza::from_reader(&b"{\"greetings\": []"[..]).is_err());
+}
+
+#[test]
+fn load_slice_ok() {
+    assert!(Eliza::from_slice(include_bytes!("../scripts/doctor.json")).is_ok());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn load_file_async_ok() {
+    let mut e = Eliza::from_file_async("scripts/doctor.json").await.unwrap();
+    assert_eq!("In what way?", e.respond("Men are all alike."));
+    assert!(Eliza::from_file_async("scripts/not_a_script.json")
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn load_file_strict() {
+    for location in [
+        "scripts/doctor.json",
+        "scripts/doctor_de.json",
+        "scripts/doctor_es.json",
+        "scripts/parry.json",
+        "scripts/pirate.json",
+    ] {
+        assert!(Script::from_file_strict(location).is_ok(), "{}", location);
+    }
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn load_dir_ok() {
+    //Lay the DOCTOR script out as a legacy, one-file-per-part, script directory
+    let dir = std::env::temp_dir().join(format!("eliza-legacy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let doctor: serde_json::Value =
+        serde_json::from_slice(include_bytes!("../scripts/doctor.json")).unwrap();
+    for part in [
+        "greetings",
+        "farewells",
+        "fallbacks",
+        "synonyms",
+        "reflections",
+        "keywords",
+    ] {
+        let json = serde_json::to_string(&doctor[part]).unwrap();
+        std::fs::write(dir.join(format!("{}.json", part)), json).unwrap();
+    }
+
+    let script = Script::from_dir(&dir).unwrap();
+    assert!(script.transforms.is_empty());
+    let mut e = Eliza::from_scripts(vec![script]).unwrap();
+    assert_eq!("In what way?", e.respond("Men are all alike."));
+
+    std::fs::write(
+        dir.join("keywords.json"),
+        r#"[{"key": "sorry", "rank": "high"}]"#,
+    )
+    .unwrap();
+    let err = Script::from_dir(&dir).unwrap_err().to_string();
+    assert!(err.starts_with("keywords[0].rank: "), "{}", err);
+
+    std::fs::remove_file(dir.join("keywords.json")).unwrap();
+    assert!(Script::from_dir(&dir).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn save_dir_ok() {
+    let dir = std::env::temp_dir().join(format!("eliza-saved-{}", std::process::id()));
+    let pirate = Script::from_file("scripts/pirate.json").unwrap();
+    pirate.to_dir(&dir).unwrap();
+    assert!(pirate.diff(&Script::from_dir(&dir).unwrap()).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    //The DOCTOR has nudges, which a directory has nowhere to keep
+    let doctor = Script::from_file("scripts/doctor.json").unwrap();
+    let err = doctor.to_dir(&dir).unwrap_err().to_string();
+    assert!(err.contains("nudges"), "{}", err);
+    assert!(!dir.exists());
+}
+
+#[cfg(feature = "std-fs")]
 #[test]
 fn load_file_err() {
     assert!(Eliza::from_file("scripts/not_a_script.json").is_err());
 }
 
+#[cfg(feature = "builtin-doctor")]
+#[test]
+fn load_builtin_doctor() {
+    let mut e = Eliza::doctor();
+    assert_eq!("In what way?", e.respond("Men are all alike."));
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn load_chain_err() {
+    assert!(Eliza::from_files(&[]).is_err());
+    assert!(Eliza::from_files(&["scripts/pirate.json", "scripts/not_a_script.json"]).is_err());
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_chain() {
+    //The pirate script has no keywords, so the doctor should step in - but it is still a pirate
+    //that says hello and goodbye
+    let mut e = Eliza::from_files(&["scripts/pirate.json", "scripts/doctor.json"]).unwrap();
+
+    assert_eq!("In what way?", e.respond("Men are all alike."));
+    assert!([
+        "Aaaarrrrgggghhhh!",
+        "See ya in Davy Jones' Lockerrr.",
+        "Time to walk the plank."
+    ]
+    .contains(&e.farewell().as_str()));
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_parry() {
+    //PARRY takes offence, and stays offended until he receives an apology
+    let mut e = Eliza::from_file("scripts/parry.json").unwrap();
+
+    assert_eq!("I'm not crazy!", e.respond("You are crazy."));
+    assert_eq!(4, e.affect("anger"));
+    e.respond("You seem paranoid.");
+    assert_eq!(8, e.affect("anger"));
+    assert_eq!(
+        "You're just like the rest of them.",
+        e.respond("What about you?")
+    );
+    assert_eq!(9, e.affect("anger"));
+
+    e.respond("I'm sorry.");
+    assert_eq!(6, e.affect("anger"));
+    assert_eq!(-1, e.affect("fear"));
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_german() {
+    let mut e = Eliza::from_file("scripts/doctor_de.json").unwrap();
+
+    assert_eq!("Inwiefern?", e.respond("Alle Männer sind gleich."));
+    assert_eq!(
+        "Du sagst, dein freund hat dich hergebracht?",
+        e.respond("Mein Freund hat mich hergebracht.")
+    );
+    assert_eq!(
+        "Es tut mir leid zu hören, dass du unglücklich bist.",
+        e.respond("Ich bin sehr unglücklich!")
+    );
+    assert_eq!(
+        "Machen dir Computer Sorgen?",
+        e.respond("Du bist ein Rechner, aber ich mag dich.")
+    );
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_spanish() {
+    let mut e = Eliza::from_file("scripts/doctor_es.json").unwrap();
+
+    assert_eq!(
+        "¿En qué sentido?",
+        e.respond("Todos los hombres son iguales.")
+    );
+    assert_eq!(
+        "¿Crees que deberías poder dormir?",
+        e.respond("¿Por qué no puedo dormir?")
+    );
+    assert_eq!(
+        "¿Dices que tu novio te hizo venir aquí?",
+        e.respond("Mi novio me hizo venir aquí.")
+    );
+    assert_eq!(
+        "Siento oír que estás deprimida.",
+        e.respond("¡Estoy deprimida!")
+    );
+}
+
+#[cfg(all(feature = "normalize", feature = "std-fs"))]
+#[test]
+fn convo_spanish_without_accents() {
+    let mut script = Script::from_file("scripts/doctor_es.json").unwrap();
+    script.strip_diacritics = true;
+    let mut e = Eliza::from_scripts(vec![script]).unwrap();
+
+    //'sueno' and 'sueño' are alike, but the capture keeps the user's accents
+    assert_eq!(
+        "¿Qué significa ese sueño para ti?",
+        e.respond("Tuve un sueno muy raro.")
+    );
+    assert_eq!("No pareces muy seguro.", e.respond("Quizas."));
+    assert_eq!(
+        "¿Dices que tu canción favorita es triste?",
+        e.respond("Mi canción favorita es triste")
+    );
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_emoji() {
+    let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+
+    assert_eq!("I am sorry to hear you are sad.", e.respond("I am 😭"));
+    assert_eq!("Do computers worry you?", e.respond("🤖 🤖 🤖"));
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_first_choice() {
+    let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+    e.first_choice(true);
+
+    assert_eq!("How do you do. Please tell me your problems.", e.greet());
+    assert_eq!("I'm not sure I understand you fully.", e.respond("Hmm."));
+    for _ in 0..3 {
+        assert_eq!(
+            "Do you often think of your mother?",
+            e.respond("I remember my mother.")
+        );
+    }
+    assert_eq!("Goodbye.  It was nice talking to you.", e.farewell());
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_candidates() {
+    let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+    let candidates = e.respond_candidates("I remember my mother.");
+    assert_eq!(candidates, e.respond_candidates("I remember my mother."));
+    assert_eq!("remember", candidates[0].keyword);
+    assert!(candidates
+        .iter()
+        .any(|c| c.keyword == "my" && c.text.contains("family")));
+
+    //ELIZA gives the first candidate that isn't saved to memory
+    let first = candidates.iter().find(|c| !c.memorise).unwrap();
+    assert_eq!(first.text, e.respond("I remember my mother."));
+    assert_eq!(1, e.respond_candidates("I remember my mother.")[6].usage);
+
+    assert!(e.respond_candidates("Hmm.").is_empty());
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn convo_detailed() {
+    let mut e = Eliza::from_file("scripts/doctor.json").unwrap();
+
+    let res = e.respond_detailed("Well, my boyfriend made me come here.");
+    assert_eq!("Your boyfriend made you come here?", res.text);
+    assert_eq!(ResponseSource::Keyword, res.source);
+    assert_eq!(Some("my"), res.keyword.as_deref());
+    assert_eq!(Some("(.*)my(.+)"), res.rule.as_deref());
+    assert!(res.confidence > 0.5 && res.confidence < 1.0);
+
+    let res = e.respond_detailed("Bullies.");
+    assert_eq!(ResponseSource::Memory, res.source);
+    assert_eq!(None, res.keyword);
+    assert_eq!(0.25, res.confidence);
+
+    let res = e.respond_detailed("Bullies.");
+    assert_eq!(ResponseSource::Fallback, res.source);
+    assert_eq!(0.0, res.confidence);
+}
+
+#[cfg(feature = "std-fs")]
 #[test]
 fn convo_textbook() {
     //This is the classic conversation with ELIZA as outlined in the Article:
@@ -77,3 +355,56 @@ fn convo_textbook() {
         e.respond("Bullies.")
     );
 }
+
+#[cfg(feature = "testing")]
+#[test]
+fn arbitrary_scripts() {
+    use arbitrary::{Arbitrary, Unstructured};
+    use eliza::script::Script;
+    use eliza::testing::WORDS;
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        let bytes: Vec<u8> = (0..512).map(|_| rng.gen()).collect();
+        let script = Script::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let mut e = Eliza::from_scripts(vec![script]).unwrap();
+        e.seed(0);
+
+        for _ in 0..10 {
+            let input: Vec<&str> = (0..rng.gen_range(0, 8))
+                .map(|_| WORDS[rng.gen_range(0, WORDS.len())])
+                .collect();
+            let response = e.respond(&input.join(" "));
+            assert!(!response.contains('$'), "{}", response);
+        }
+    }
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn doctor_transcript() {
+    use eliza::transcript::Transcript;
+
+    let script = Script::from_file("scripts/doctor.json").unwrap();
+    let transcript = Transcript::load("tests/transcripts/doctor.txt").unwrap();
+    for turn in transcript.run(&script) {
+        assert!(turn.passed(), "{:?}", turn);
+    }
+    assert!(script.run_embedded_tests().iter().all(|t| t.passed()));
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn embedded_script() {
+    use eliza::script::Script;
+
+    fn doctor() -> &'static Script {
+        eliza::eliza_script!("scripts/doctor.json")
+    }
+    //The script is only parsed once
+    assert!(std::ptr::eq(doctor(), doctor()));
+
+    let mut e = Eliza::from_scripts(vec![doctor().clone()]).unwrap();
+    assert_eq!("In what way?", e.respond("Men are all alike."));
+}